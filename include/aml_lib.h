@@ -0,0 +1,67 @@
+/* C interface of aml-lib (feature `ffi`), built with
+ * `cargo rustc --release --features ffi --crate-type cdylib` (or `staticlib`). */
+
+#ifndef AML_LIB_H
+#define AML_LIB_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define AML_FORMAT_AUTO       0
+#define AML_FORMAT_SMS_TEXT   1
+#define AML_FORMAT_SMS_HEX    2
+#define AML_FORMAT_SMS_BASE64 3
+#define AML_FORMAT_HTTPS      4
+
+/* Unknown values are NAN for numbers, INT64_MIN for timestamps (ms since epoch),
+ * -1 for network codes and NULL for strings. */
+typedef struct AmlRecord {
+    double latitude;
+    double longitude;
+    double accuracy;
+    double altitude;
+    double vertical_accuracy;
+    double confidence;
+    double bearing;
+    double speed;
+    int64_t beginning_of_call_ms;
+    int64_t time_of_positioning_ms;
+    int32_t home_mcc;
+    int32_t home_mnc;
+    int32_t network_mcc;
+    int32_t network_mnc;
+    char *version;
+    char *emergency_number;
+    char *source_of_activation;
+    char *floor;
+    char *positioning_method;
+    char *device_number;
+    char *model;
+    char *imsi;
+    char *imei;
+    char *iccid;
+    char *languages;
+    char *transport;
+} AmlRecord;
+
+/* JSON document, or {"error": "..."}. Release with aml_string_free(). */
+char *aml_parse_json(const char *payload, int format);
+
+/* NULL on failure. Release with aml_record_free(). */
+AmlRecord *aml_parse_record(const char *payload, int format);
+
+/* 1 if the HMAC of the HTTPS message is valid, 0 otherwise. */
+int aml_https_is_authenticated(const char *payload, const uint8_t *key, size_t key_len);
+
+void aml_string_free(char *s);
+void aml_record_free(AmlRecord *record);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* AML_LIB_H */
@@ -0,0 +1,91 @@
+use crate::AmlData;
+use std::future::Future;
+
+/// A civic (postal) address resolved from a location.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct CivicAddress {
+    /// ISO 3166-1 alpha-2 country code.
+    pub country: Option<String>,
+
+    /// State, region or county.
+    pub region: Option<String>,
+
+    /// City or municipality.
+    pub city: Option<String>,
+
+    /// Postal code.
+    pub postal_code: Option<String>,
+
+    /// Street name.
+    pub street: Option<String>,
+
+    /// House number on the street.
+    pub house_number: Option<String>,
+
+    /// The whole address as a single line, as given by the provider.
+    pub formatted: Option<String>,
+}
+
+/// A provider (Nominatim, commercial service, local database, ...) able to find
+/// the civic address of a WGS84 position.
+pub trait ReverseGeocoder {
+    type Error;
+
+    /// The address at (`latitude`, `longitude`), or `None` if the provider knows none.
+    fn reverse_geocode(&self, latitude: f64, longitude: f64) -> Result<Option<CivicAddress>, Self::Error>;
+}
+
+/// Asynchronous variant of [`ReverseGeocoder`] for providers reached over the network.
+pub trait AsyncReverseGeocoder {
+    type Error;
+
+    /// The address at (`latitude`, `longitude`), or `None` if the provider knows none.
+    fn reverse_geocode(
+        &self,
+        latitude: f64,
+        longitude: f64,
+    ) -> impl Future<Output = Result<Option<CivicAddress>, Self::Error>> + Send;
+}
+
+impl AmlData {
+    /// Resolve the civic address of the location with `geocoder` and attach it to
+    /// [`AmlData::address`]. Nothing is resolved without location.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{AmlData, CivicAddress, ReverseGeocoder};
+    ///
+    /// struct Fixed;
+    ///
+    /// impl ReverseGeocoder for Fixed {
+    ///     type Error = ();
+    ///
+    ///     fn reverse_geocode(&self, _: f64, _: f64) -> Result<Option<CivicAddress>, ()> {
+    ///         Ok(Some(CivicAddress { city: Some("Paris".to_string()), ..Default::default() }))
+    ///     }
+    /// }
+    ///
+    /// let mut aml = AmlData::from_https("location_latitude=48.8583&location_longitude=2.2945").unwrap();
+    /// aml.resolve_address(&Fixed).unwrap();
+    /// assert_eq!(aml.address.unwrap().city.as_deref(), Some("Paris"));
+    /// ```
+    pub fn resolve_address<G: ReverseGeocoder>(&mut self, geocoder: &G) -> Result<Option<&CivicAddress>, G::Error> {
+        if let Some((latitude, longitude)) = self.position() {
+            self.address = geocoder.reverse_geocode(latitude, longitude)?;
+        }
+        Ok(self.address.as_ref())
+    }
+
+    /// Asynchronous variant of [`AmlData::resolve_address`].
+    pub async fn resolve_address_async<G: AsyncReverseGeocoder>(
+        &mut self,
+        geocoder: &G,
+    ) -> Result<Option<&CivicAddress>, G::Error> {
+        if let Some((latitude, longitude)) = self.position() {
+            self.address = geocoder.reverse_geocode(latitude, longitude).await?;
+        }
+        Ok(self.address.as_ref())
+    }
+}
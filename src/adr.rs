@@ -0,0 +1,30 @@
+use crate::Timestamp;
+
+/// Advanced crash notification sent by a vehicle (`adr_*` HTTPS keys).
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct AdrData {
+    /// `adr_carcrash_time`: date and time of the crash.
+    #[cfg_attr(feature = "serde", serde(with = "crate::timestamp::rfc3339"))]
+    pub carcrash_time: Option<Timestamp>,
+
+    /// `adr_severity`: severity of the crash, on the scale of the vehicle maker.
+    pub severity: Option<String>,
+
+    /// `adr_rollover`: the vehicle has rolled over.
+    pub rollover: Option<bool>,
+
+    /// `adr_impact_direction`: direction of the impact in degrees clockwise from the
+    /// front of the vehicle.
+    pub impact_direction: Option<f64>,
+
+    /// `adr_occupants`: number of occupants.
+    pub occupants: Option<u32>,
+}
+
+impl AdrData {
+    /// True if no crash value is known.
+    pub fn is_empty(&self) -> bool {
+        *self == AdrData::default()
+    }
+}
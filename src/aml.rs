@@ -1,7 +1,9 @@
-use crate::{seconds_to_utc, millis_to_utc, AmlError, HttpsData, SmsData};
-use chrono::{DateTime, Utc, LocalResult, TimeZone,};
+use crate::{seconds_to_utc, millis_to_utc, AdrData, AmlError, CivicAddress, HttpsData, Profile, ReceiveContext, SmsData, Timestamp, VersionPolicy};
+use crate::geo::Ellipse;
+use crate::json::JsonValue;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct AmlData {
     /// See [`SmsData::header`] or [`HttpsData::v`]
     pub version: Option<String>,
@@ -13,7 +15,8 @@ pub struct AmlData {
     pub source_of_activation: Option<String>,
 
     /// See [`SmsData::beginning_of_call`] or [`HttpsData::time`]
-    pub beginning_of_call: Option<DateTime<Utc>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::timestamp::rfc3339"))]
+    pub beginning_of_call: Option<Timestamp>,
 
     /// See [`SmsData::latitude`] or [`HttpsData::location_latitude`]
     pub latitude: Option<f64>,
@@ -22,7 +25,8 @@ pub struct AmlData {
     pub longitude: Option<f64>,
 
     /// See [`SmsData::time_of_positioning`] or [`HttpsData::location_time`]
-    pub time_of_positioning: Option<DateTime<Utc>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::timestamp::rfc3339"))]
+    pub time_of_positioning: Option<Timestamp>,
 
     /// See [`SmsData::altitude`] or [`HttpsData::location_altitude`]
     pub altitude: Option<f64>,
@@ -39,6 +43,10 @@ pub struct AmlData {
     /// See [`SmsData::vertical_accuracy`] or [`HttpsData::location_vertical_accuracy`]
     pub vertical_accuracy: Option<f64>,
 
+    /// See [`HttpsData::location_semi_major_axis`], [`HttpsData::location_semi_minor_axis`]
+    /// and [`HttpsData::location_orientation`]
+    pub ellipse: Option<Ellipse>,
+
     /// See [`SmsData::level_of_confidence`] or [`HttpsData::location_confidence`]
     pub confidence: Option<f64>,
 
@@ -48,7 +56,7 @@ pub struct AmlData {
     /// See [`HttpsData::location_speed`]
     pub speed: Option<f64>,
 
-    /// See [`HttpsData::device_number`]
+    /// See [`HttpsData::device_number`] or [`SmsData::originating_address`]
     pub device_number: Option<String>,
 
     /// See [`HttpsData::device_model`]
@@ -75,11 +83,39 @@ pub struct AmlData {
     /// See [`SmsData::network_mnc`] or [`HttpsData::cell_network_mnc`]
     pub network_mnc: Option<i32>,
 
-    /// See [`SmsData::language`] or [`HttpsData::device_languages`]
+    /// See [`SmsData::languages`] or [`HttpsData::device_languages`]
     pub languages: Option<String>,
 
     /// Where the location comes from: `sms` or `https`
     pub transport: String,
+
+    /// Transport the message has been tunneled in, e.g. `https` for a SMS text posted
+    /// in a HTTPS body. See [`AmlData::from_https_tunnel`].
+    pub via: Option<String>,
+
+    /// Ground truth location (latitude, longitude) sent by test handsets.
+    #[cfg_attr(feature = "serde", serde(flatten, with = "crate::json::ground_truth"))]
+    pub ground_truth: Option<(f64, f64)>,
+
+    /// The message is flagged as a test by the operator or the vehicle.
+    pub test: bool,
+
+    /// Advanced crash notification of a vehicle (HTTPS only).
+    pub adr: Option<AdrData>,
+
+    /// Civic address of the location. See [`AmlData::resolve_address`].
+    pub address: Option<CivicAddress>,
+
+    /// Conventions the message has been parsed with. See [`SmsData::profile`] or [`HttpsData::profile`].
+    pub profile: Profile,
+
+    /// How and when the message has been received. See [`AmlData::with_context`].
+    pub context: Option<ReceiveContext>,
+
+    /// JSON members this version does not know (written by a newer version or vendor
+    /// extensions), kept in order so that they survive a round trip. See [`AmlData::from_json_value`].
+    #[cfg_attr(feature = "serde", serde(flatten, with = "crate::json::extras"))]
+    pub extras: Vec<(String, JsonValue)>,
 }
 
 impl AmlData {
@@ -89,23 +125,75 @@ impl AmlData {
         Ok(https_data.into())
     }
 
+    /// Parse a HTTPS body which may carry a SMS text instead of the HTTPS AML keys.
+    /// The SMS text is the value of the `body` or `message` parameter, or of any other
+    /// parameter starting with `A"ML=`. It is then parsed as a SMS, with `via` set to `https`.
+    /// Otherwise the body is parsed as a HTTPS AML message.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::AmlData;
+    ///
+    /// let body = "from=%2B33611223344&body=A%22ML%3D2%3Ben%3D112%3Bet%3D1593187189%3Blo%3D48.82639%2C-2.36619%2C20";
+    /// let aml = AmlData::from_https_tunnel(body).unwrap();
+    /// assert_eq!((aml.transport.as_str(), aml.via.as_deref()), ("sms", Some("https")));
+    /// assert_eq!(aml.latitude, Some(48.82639));
+    /// ```
+    pub fn from_https_tunnel(payload: &str) -> Result<Self, AmlError> {
+        let parameters: Vec<(String, String)> = url::form_urlencoded::parse(payload.as_bytes()).into_owned().collect();
+        let is_sms = |value: &str| value.trim_start().starts_with(r#"A"ML="#);
+
+        let sms = ["body", "message"]
+            .iter()
+            .find_map(|name| parameters.iter().find(|(key, value)| key == name && is_sms(value)))
+            .or_else(|| parameters.iter().find(|(_, value)| is_sms(value)));
+
+        match sms {
+            Some((_, text)) => Ok(AmlData {
+                via: Some("https".to_string()),
+                ..Self::from_text_sms(text)?
+            }),
+            None => Self::from_https(payload),
+        }
+    }
+
     /// Parse a SMS text. See [`SmsData::from_text`].
     pub fn from_text_sms<S: AsRef<str>>(text_sms: S) -> Result<Self, AmlError> {
         let sms_data = SmsData::from_text(text_sms)?;
         Ok(sms_data.into())
     }
 
+    /// Parse a SMS text, decoding an unimplemented version with the attributes of v2.
+    /// See [`SmsData::from_text_best_effort`] and [`AmlData::is_partial`].
+    pub fn from_text_sms_best_effort<S: AsRef<str>>(text_sms: S) -> Result<Self, AmlError> {
+        let sms_data = SmsData::from_text_best_effort(text_sms)?;
+        Ok(sms_data.into())
+    }
+
+    /// True for a SMS message of an unimplemented version, decoded by
+    /// [`AmlData::from_text_sms_best_effort`]: `version` is the unknown one.
+    pub fn is_partial(&self) -> bool {
+        self.transport == "sms" && self.version.as_deref().is_some_and(|version| VersionPolicy::MajorCompatible.select(version).is_none())
+    }
+
     /// Parse a SMS data. See [`SmsData::from_data`].
     pub fn from_data_sms(data_sms: &[u8]) -> Result<Self, AmlError> {
         let sms_data = SmsData::from_data(data_sms)?;
         Ok(sms_data.into())
     }
 
+    /// Parse a SMS data given as hexadecimal digits. See [`SmsData::from_hex`].
+    pub fn from_hex_sms<S: AsRef<str>>(hex_sms: S) -> Result<Self, AmlError> {
+        let sms_data = SmsData::from_hex(hex_sms)?;
+        Ok(sms_data.into())
+    }
+
     /// Parse a base64 encoded SMS data. See [`AmlData::from_data_sms`].
     pub fn from_base64_sms<S: AsRef<[u8]>>(base64_sms: S)-> Result<Self, AmlError> {
         match base64::decode(base64_sms) {
             Ok(bin_sms) => Self::from_data_sms(&bin_sms),
-            Err(_) => Err(AmlError::InvalidBase64),
+            Err(err) => Err(AmlError::Base64(err)),
         }
     }
 }
@@ -131,7 +219,9 @@ impl From<SmsData> for AmlData {
             home_mcc: sms.home_mcc,
             home_mnc: sms.home_mnc,
             languages: sms.languages,
+            device_number: sms.originating_address,
             transport: "sms".to_string(),
+            profile: sms.profile,
             ..Default::default()
         }
     }
@@ -139,6 +229,7 @@ impl From<SmsData> for AmlData {
 
 impl From<HttpsData> for AmlData {
     fn from(https_data: HttpsData) -> Self {
+        let orientation = https_data.location_orientation.unwrap_or_default();
         AmlData {
             version: https_data.v,
             emergency_number: https_data.emergency_number,
@@ -152,6 +243,10 @@ impl From<HttpsData> for AmlData {
             positioning_method: https_data.location_source,
             accuracy: https_data.location_accuracy,
             vertical_accuracy: https_data.location_vertical_accuracy,
+            ellipse: https_data
+                .location_semi_major_axis
+                .zip(https_data.location_semi_minor_axis)
+                .map(|(semi_major, semi_minor)| Ellipse { semi_major, semi_minor, orientation }),
             confidence: https_data.location_confidence,
             bearing: https_data.location_bearing,
             speed: https_data.location_speed,
@@ -165,7 +260,18 @@ impl From<HttpsData> for AmlData {
             network_mcc: https_data.cell_network_mcc,
             network_mnc: https_data.cell_network_mnc,
             languages: https_data.device_languages,
+            ground_truth: https_data.gt_location_latitude.zip(https_data.gt_location_longitude),
+            test: https_data.test == Some(true),
+            adr: Some(AdrData {
+                carcrash_time: https_data.adr_carcrash_time,
+                severity: https_data.adr_severity,
+                rollover: https_data.adr_rollover,
+                impact_direction: https_data.adr_impact_direction,
+                occupants: https_data.adr_occupants,
+            })
+            .filter(|adr| !adr.is_empty()),
             transport: "https".to_string(),
+            profile: https_data.profile,
             ..Default::default()
         }
     }
@@ -0,0 +1,198 @@
+//! Columnar export of the parsed messages, for analytics (Arrow, Parquet, DuckDB, ...).
+//!
+//! [`ColumnBatch`] transposes a batch of messages into typed columns following the
+//! stable [`schema`] (the columns of the SQL table, see [`crate::sql::COLUMNS`]), and
+//! [`ColumnBatch::to_record_batch`] into an Arrow [`RecordBatch`]. With the `parquet`
+//! feature, [`ParquetWriter`](self::parquet::ParquetWriter) writes them to files.
+//!
+//! ```
+//! use aml_lib::arrow::record_batch;
+//! use aml_lib::AmlData;
+//!
+//! let messages = [AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20"#).unwrap()];
+//! let batch = record_batch(&messages).unwrap();
+//! assert_eq!((batch.num_rows(), batch.schema().field(4).name().as_str()), (1, "latitude"));
+//! ```
+
+use crate::sql::{SqlType, SqlValue, COLUMNS, NOT_NULL};
+use crate::{timestamp, AmlData};
+use ::arrow_array::{ArrayRef, BooleanArray, Float64Array, Int32Array, RecordBatch, StringArray, TimestampMillisecondArray};
+use ::arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef, TimeUnit};
+use std::sync::Arc;
+
+#[cfg(feature = "parquet")]
+pub mod parquet;
+
+/// Type of a column, named after the Arrow data types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArrowType {
+    Utf8,
+    Float64,
+    Int32,
+    Boolean,
+
+    /// Milliseconds since the epoch, in UTC.
+    TimestampMillis,
+}
+
+impl From<SqlType> for ArrowType {
+    fn from(kind: SqlType) -> Self {
+        match kind {
+            SqlType::Text | SqlType::Json => ArrowType::Utf8,
+            SqlType::Float => ArrowType::Float64,
+            SqlType::Integer => ArrowType::Int32,
+            SqlType::Bool => ArrowType::Boolean,
+            SqlType::Timestamp => ArrowType::TimestampMillis,
+        }
+    }
+}
+
+impl From<ArrowType> for DataType {
+    fn from(kind: ArrowType) -> Self {
+        match kind {
+            ArrowType::Utf8 => DataType::Utf8,
+            ArrowType::Float64 => DataType::Float64,
+            ArrowType::Int32 => DataType::Int32,
+            ArrowType::Boolean => DataType::Boolean,
+            ArrowType::TimestampMillis => DataType::Timestamp(TimeUnit::Millisecond, Some(UTC.into())),
+        }
+    }
+}
+
+/// Time zone of the timestamps.
+const UTC: &str = "UTC";
+
+/// The schema of the batches: name, type and nullability of each column, in order.
+/// Columns are only ever added at the end.
+pub fn schema() -> Vec<(&'static str, ArrowType, bool)> {
+    COLUMNS.iter().map(|(name, kind)| (*name, ArrowType::from(*kind), !NOT_NULL.contains(name))).collect()
+}
+
+/// The [`schema`] as an Arrow schema.
+pub fn arrow_schema() -> SchemaRef {
+    let fields: Vec<Field> = schema().into_iter().map(|(name, kind, nullable)| Field::new(name, kind.into(), nullable)).collect();
+    Arc::new(Schema::new(fields))
+}
+
+/// The record batch of `messages`. See [`ColumnBatch::to_record_batch`].
+pub fn record_batch(messages: &[AmlData]) -> Result<RecordBatch, ArrowError> {
+    ColumnBatch::of(messages).to_record_batch()
+}
+
+/// The values of a column, one per message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    Text(Vec<Option<String>>),
+    Float(Vec<Option<f64>>),
+    Integer(Vec<Option<i32>>),
+    Bool(Vec<Option<bool>>),
+
+    /// Milliseconds since the epoch.
+    Timestamp(Vec<Option<i64>>),
+}
+
+impl Column {
+    fn with_capacity(kind: ArrowType, capacity: usize) -> Self {
+        match kind {
+            ArrowType::Utf8 => Column::Text(Vec::with_capacity(capacity)),
+            ArrowType::Float64 => Column::Float(Vec::with_capacity(capacity)),
+            ArrowType::Int32 => Column::Integer(Vec::with_capacity(capacity)),
+            ArrowType::Boolean => Column::Bool(Vec::with_capacity(capacity)),
+            ArrowType::TimestampMillis => Column::Timestamp(Vec::with_capacity(capacity)),
+        }
+    }
+
+    fn push(&mut self, value: SqlValue) {
+        match (self, value) {
+            (Column::Text(values), SqlValue::Text(value)) => values.push(Some(value)),
+            (Column::Float(values), SqlValue::Float(value)) => values.push(Some(value)),
+            (Column::Integer(values), SqlValue::Integer(value)) => values.push(Some(value)),
+            (Column::Bool(values), SqlValue::Bool(value)) => values.push(Some(value)),
+            (Column::Timestamp(values), SqlValue::Timestamp(value)) => values.push(Some(timestamp::unix_millis(value))),
+            (Column::Text(values), _) => values.push(None),
+            (Column::Float(values), _) => values.push(None),
+            (Column::Integer(values), _) => values.push(None),
+            (Column::Bool(values), _) => values.push(None),
+            (Column::Timestamp(values), _) => values.push(None),
+        }
+    }
+
+    /// Number of values.
+    pub fn len(&self) -> usize {
+        match self {
+            Column::Text(values) => values.len(),
+            Column::Float(values) => values.len(),
+            Column::Integer(values) => values.len(),
+            Column::Bool(values) => values.len(),
+            Column::Timestamp(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The Arrow array of the values.
+    pub fn to_array(&self) -> ArrayRef {
+        match self {
+            Column::Text(values) => Arc::new(StringArray::from(values.clone())),
+            Column::Float(values) => Arc::new(Float64Array::from(values.clone())),
+            Column::Integer(values) => Arc::new(Int32Array::from(values.clone())),
+            Column::Bool(values) => Arc::new(BooleanArray::from(values.clone())),
+            Column::Timestamp(values) => Arc::new(TimestampMillisecondArray::from(values.clone()).with_timezone(UTC)),
+        }
+    }
+}
+
+/// A batch of messages in columns, following the [`schema`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColumnBatch {
+    pub columns: Vec<(&'static str, Column)>,
+}
+
+impl ColumnBatch {
+    /// The columns of `messages`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::arrow::{Column, ColumnBatch};
+    /// use aml_lib::AmlData;
+    ///
+    /// let messages = [
+    ///     AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20"#).unwrap(),
+    ///     AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187200"#).unwrap(),
+    /// ];
+    /// let batch = ColumnBatch::of(&messages);
+    ///
+    /// assert_eq!(batch.rows(), 2);
+    /// assert_eq!(batch.column("latitude"), Some(&Column::Float(vec![Some(48.82639), None])));
+    /// assert_eq!(batch.column("beginning_of_call"), Some(&Column::Timestamp(vec![Some(1593187189000), Some(1593187200000)])));
+    /// ```
+    pub fn of(messages: &[AmlData]) -> Self {
+        let mut columns: Vec<(&'static str, Column)> = schema()
+            .into_iter()
+            .map(|(name, kind, _)| (name, Column::with_capacity(kind, messages.len())))
+            .collect();
+        for aml in messages {
+            for ((_, column), value) in columns.iter_mut().zip(aml.to_sql_values()) {
+                column.push(value);
+            }
+        }
+        ColumnBatch { columns }
+    }
+
+    /// Number of messages.
+    pub fn rows(&self) -> usize {
+        self.columns.first().map_or(0, |(_, column)| column.len())
+    }
+
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns.iter().find(|(column, _)| *column == name).map(|(_, column)| column)
+    }
+
+    /// The Arrow record batch of the columns, with the [`arrow_schema`].
+    pub fn to_record_batch(&self) -> Result<RecordBatch, ArrowError> {
+        RecordBatch::try_new(arrow_schema(), self.columns.iter().map(|(_, column)| column.to_array()).collect())
+    }
+}
@@ -0,0 +1,49 @@
+//! Parquet files of the parsed messages, with the [`arrow_schema`](super::arrow_schema).
+//!
+//! ```
+//! use aml_lib::arrow::parquet::ParquetWriter;
+//! use aml_lib::AmlData;
+//!
+//! let messages = [AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20"#).unwrap()];
+//!
+//! let mut writer = ParquetWriter::new(Vec::new()).unwrap();
+//! writer.write(&messages).unwrap();
+//! let file = writer.finish().unwrap();
+//! assert!(file.starts_with(b"PAR1"));
+//! ```
+
+use super::{arrow_schema, record_batch};
+use crate::AmlData;
+use ::parquet::arrow::ArrowWriter;
+use ::parquet::errors::ParquetError;
+use ::parquet::file::properties::WriterProperties;
+use std::io::Write;
+
+/// Writes batches of messages to a Parquet file, each call to [`ParquetWriter::write`]
+/// being buffered into the current row group.
+pub struct ParquetWriter<W: Write + Send> {
+    writer: ArrowWriter<W>,
+}
+
+impl<W: Write + Send> ParquetWriter<W> {
+    /// A writer with the default properties of the `parquet` crate.
+    pub fn new(writer: W) -> Result<Self, ParquetError> {
+        Self::with_properties(writer, WriterProperties::default())
+    }
+
+    /// A writer with `properties`, e.g. the compression.
+    pub fn with_properties(writer: W, properties: WriterProperties) -> Result<Self, ParquetError> {
+        Ok(ParquetWriter {
+            writer: ArrowWriter::try_new(writer, arrow_schema(), Some(properties))?,
+        })
+    }
+
+    pub fn write(&mut self, messages: &[AmlData]) -> Result<(), ParquetError> {
+        self.writer.write(&record_batch(messages)?)
+    }
+
+    /// Write the footer of the file and return the underlying writer.
+    pub fn finish(self) -> Result<W, ParquetError> {
+        self.writer.into_inner()
+    }
+}
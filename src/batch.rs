@@ -0,0 +1,158 @@
+//! Parsing of archived AML logs, one message per line, and NDJSON input and output.
+
+use crate::validation::clean_text;
+use crate::{json::JsonValue, AmlData, AmlError};
+use std::io::{BufRead, ErrorKind, Write};
+
+/// Parse each non-empty line of `reader` as an AML message, without loading the
+/// whole input in memory. Items are `(line number, result)`, line numbers starting at 1.
+///
+/// The format of each line is detected:
+/// - a line starting with `A"ML=` is a text SMS,
+/// - a line of hexadecimal digits is a binary SMS,
+/// - a line containing `=` is a HTTPS message, possibly tunneling a text SMS
+///   (see [`AmlData::from_https_tunnel`]),
+/// - any other line is a base64 encoded binary SMS.
+///
+/// A line which is not UTF-8 is an [`AmlError::Io`] item and the parsing goes on,
+/// any other read error is the last item.
+///
+/// # Example
+///
+/// ```
+/// use aml_lib::batch;
+///
+/// let log = "A\"ML=1;lt=48.82639;lg=-2.36619;rd=52\n\nv=1&location_latitude=55.85732&location_longitude=-4.26325\n";
+/// let parsed: Vec<_> = batch::parse_lines(log.as_bytes()).collect();
+///
+/// assert_eq!(parsed.len(), 2);
+/// assert_eq!(parsed[1].0, 3);
+/// assert_eq!(parsed[1].1.as_ref().unwrap().transport, "https");
+/// ```
+pub fn parse_lines<R: BufRead>(reader: R) -> ParseLines<R> {
+    ParseLines {
+        reader,
+        line: String::new(),
+        number: 0,
+        parse: parse_line,
+        failed: false,
+    }
+}
+
+/// Parse each non-empty line of `reader` as a NDJSON record holding a raw AML payload,
+/// such as `{"transport": "sms", "payload": "A\"ML=1;..."}`.
+/// Items are `(line number, result)`. See [`parse_record`] for the accepted transports.
+pub fn parse_ndjson<R: BufRead>(reader: R) -> ParseLines<R> {
+    ParseLines {
+        reader,
+        line: String::new(),
+        number: 0,
+        parse: parse_record,
+        failed: false,
+    }
+}
+
+/// Parse a NDJSON record. The `transport` member tells how to read the `payload` member:
+/// `sms` (text SMS), `sms_hex` (binary SMS in hexadecimal), `sms_base64` (binary SMS in base64)
+/// or `https` (urlencoded body). Without `transport`, the format is detected as in [`parse_line`].
+///
+/// # Example
+///
+/// ```
+/// use aml_lib::batch;
+///
+/// let record = r#"{"transport":"https","payload":"v=1&location_latitude=55.85732&location_longitude=-4.26325"}"#;
+/// assert_eq!(batch::parse_record(record).unwrap().latitude, Some(55.85732));
+/// ```
+pub fn parse_record(record: &str) -> Result<AmlData, AmlError> {
+    let value = JsonValue::parse(record).ok_or(AmlError::InvalidRecord)?;
+    let payload = value
+        .get("payload")
+        .and_then(JsonValue::as_str)
+        .ok_or(AmlError::InvalidRecord)?;
+
+    match value.get("transport").map(|t| t.as_str()) {
+        Some(Some(transport)) => parse_payload(Some(transport), payload),
+        None => parse_payload(None, payload),
+        Some(None) => Err(AmlError::InvalidRecord),
+    }
+}
+
+/// Parse `payload` sent over `transport` (see [`parse_record`] for the names),
+/// or of unknown format when `transport` is `None` (see [`parse_line`]).
+pub fn parse_payload(transport: Option<&str>, payload: &str) -> Result<AmlData, AmlError> {
+    match transport {
+        Some("sms") => AmlData::from_text_sms(payload),
+        Some("sms_hex") => AmlData::from_hex_sms(payload),
+        Some("sms_base64") => AmlData::from_base64_sms(payload.trim()),
+        Some("https") => AmlData::from_https(payload),
+        None => parse_line(payload),
+        Some(_) => Err(AmlError::InvalidRecord),
+    }
+}
+
+/// Write each message of `messages` as a line of JSON. See [`AmlData::to_json`].
+pub fn write_ndjson<'a, W, I>(mut writer: W, messages: I) -> std::io::Result<()>
+where
+    W: Write,
+    I: IntoIterator<Item = &'a AmlData>,
+{
+    for aml in messages {
+        writeln!(writer, "{}", aml.to_json())?;
+    }
+    writer.flush()
+}
+
+/// Parse a single message of unknown format. See [`parse_lines`] for the detection rules.
+pub fn parse_line(line: &str) -> Result<AmlData, AmlError> {
+    let (cleaned, _) = clean_text(line);
+
+    if cleaned.starts_with(r#"A"ML="#) {
+        // The SMS parser records the cleanup in its report
+        return AmlData::from_text_sms(line);
+    }
+
+    let line = cleaned;
+    if line.len().is_multiple_of(2) && line.bytes().all(|b| b.is_ascii_hexdigit()) {
+        AmlData::from_hex_sms(line)
+    } else if line.contains('&') || (line.contains('=') && !line.ends_with('=')) {
+        AmlData::from_https_tunnel(line)
+    } else {
+        AmlData::from_base64_sms(line)
+    }
+}
+
+/// Iterator returned by [`parse_lines`] and [`parse_ndjson`].
+pub struct ParseLines<R> {
+    reader: R,
+    line: String,
+    number: usize,
+    parse: fn(&str) -> Result<AmlData, AmlError>,
+    failed: bool,
+}
+
+impl<R: BufRead> Iterator for ParseLines<R> {
+    type Item = (usize, Result<AmlData, AmlError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.failed {
+            self.line.clear();
+            self.number += 1;
+
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) if self.line.trim().is_empty() => continue,
+                Ok(_) => {
+                    let line = self.line.trim_end_matches(&['\r', '\n'][..]);
+                    return Some((self.number, (self.parse)(line)));
+                }
+                // The bytes of a line which is not UTF-8 are consumed, any other error ends the input
+                Err(err) => {
+                    self.failed = err.kind() != ErrorKind::InvalidData;
+                    return Some((self.number, Err(AmlError::Io(err))));
+                }
+            }
+        }
+        None
+    }
+}
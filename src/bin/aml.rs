@@ -0,0 +1,113 @@
+//! Decode AML payloads from the command line.
+//!
+//! ```text
+//! aml [--format auto|sms|hex|base64|https] [--key KEY] [--json] [PAYLOAD...]
+//! ```
+//!
+//! Payloads are read from the arguments or, without argument, one per line from stdin.
+
+use aml_lib::{batch, AmlData, AmlError, HttpsData};
+use std::io::{BufRead, Write};
+use std::process::exit;
+
+const USAGE: &str = "Usage: aml [--format auto|sms|hex|base64|https] [--key KEY] [--json] [PAYLOAD...]
+
+Decode AML payloads given as arguments or, without argument, one per line on stdin.
+
+Options:
+  -f, --format FORMAT  Format of the payloads (default: auto)
+  -k, --key KEY        Verify the HMAC of HTTPS payloads with KEY
+  -j, --json           Print one JSON object per payload instead of a table
+  -h, --help           Print this help";
+
+#[derive(Default)]
+struct Options {
+    format: Option<String>,
+    key: Option<String>,
+    json: bool,
+    payloads: Vec<String>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-f" | "--format" => options.format = Some(args.next().ok_or("--format needs a value")?),
+            "-k" | "--key" => options.key = Some(args.next().ok_or("--key needs a value")?),
+            "-j" | "--json" => options.json = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                exit(0);
+            }
+            _ if arg.starts_with('-') && arg.len() > 1 => return Err(format!("Unknown option {}", arg)),
+            _ => options.payloads.push(arg),
+        }
+    }
+
+    Ok(options)
+}
+
+fn decode(payload: &str, options: &Options) -> Result<AmlData, String> {
+    let payload = payload.trim();
+    let result: Result<AmlData, AmlError> = match options.format.as_deref() {
+        Some("auto") | None => batch::parse_line(payload),
+        Some("sms") => AmlData::from_text_sms(payload),
+        Some("hex") => AmlData::from_hex_sms(payload),
+        Some("base64") => AmlData::from_base64_sms(payload),
+        Some("https") => AmlData::from_https(payload),
+        Some(format) => return Err(format!("Unknown format {}", format)),
+    };
+    let aml = result.map_err(|err| err.to_string())?;
+
+    // Whatever the detection picked, a message received over HTTPS (SMS tunnels included)
+    // must be authenticated
+    let is_https = aml.transport == "https" || aml.via.as_deref() == Some("https");
+    if let (true, Some(key)) = (is_https, &options.key) {
+        HttpsData::authenticate(payload, key.as_bytes()).map_err(|err| err.to_string())?;
+    }
+    Ok(aml)
+}
+
+fn main() {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            exit(2);
+        }
+    };
+
+    let payloads: Vec<String> = if options.payloads.is_empty() {
+        std::io::stdin()
+            .lock()
+            .lines()
+            .map_while(Result::ok)
+            .filter(|line| !line.trim().is_empty())
+            .collect()
+    } else {
+        options.payloads.clone()
+    };
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    let mut failed = false;
+
+    for payload in &payloads {
+        let printed = match decode(payload, &options) {
+            Ok(aml) if options.json => writeln!(out, "{}", aml.to_json()),
+            Ok(aml) => writeln!(out, "{}", aml.to_table()),
+            Err(err) => {
+                failed = true;
+                eprintln!("{}", err);
+                Ok(())
+            }
+        };
+        if printed.is_err() {
+            exit(1);
+        }
+    }
+
+    exit(if failed { 1 } else { 0 });
+}
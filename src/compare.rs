@@ -0,0 +1,80 @@
+use crate::{AmlData, Duration, Timestamp};
+
+/// Tolerances of [`AmlData::approx_eq`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// Largest difference between two floating-point values considered equal.
+    pub epsilon: f64,
+
+    /// Largest difference between two timestamps considered equal.
+    pub max_skew: Duration,
+}
+
+impl Default for Tolerance {
+    /// 1e-6 (about 0.1 m for coordinates) and no skew.
+    fn default() -> Self {
+        Tolerance {
+            epsilon: 1e-6,
+            max_skew: Duration::seconds(0),
+        }
+    }
+}
+
+impl AmlData {
+    /// Equality with floating-point values compared within `tolerance.epsilon` and
+    /// timestamps within `tolerance.max_skew`. Other fields must be equal.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{AmlData, Duration, Tolerance};
+    ///
+    /// let sms = AmlData::from_text_sms(r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;ml=62"#).unwrap();
+    /// let mut other = sms.clone();
+    /// other.latitude = Some(48.8263901);
+    /// other.time_of_positioning = sms.time_of_positioning.map(|top| top + Duration::seconds(1));
+    ///
+    /// assert!(sms != other);
+    /// assert!(sms.approx_eq(&other, Tolerance { epsilon: 1e-6, max_skew: Duration::seconds(2) }));
+    /// assert!(!sms.approx_eq(&other, Tolerance::default()));
+    /// ```
+    pub fn approx_eq(&self, other: &AmlData, tolerance: Tolerance) -> bool {
+        let number = |a: Option<f64>, b: Option<f64>| match (a, b) {
+            (Some(a), Some(b)) => (a - b).abs() <= tolerance.epsilon || a == b,
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        let timestamp = |a: Option<Timestamp>, b: Option<Timestamp>| match (a, b) {
+            (Some(a), Some(b)) => (a - b <= tolerance.max_skew) && (b - a <= tolerance.max_skew),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+
+        number(self.latitude, other.latitude)
+            && number(self.longitude, other.longitude)
+            && number(self.altitude, other.altitude)
+            && number(self.accuracy, other.accuracy)
+            && number(self.vertical_accuracy, other.vertical_accuracy)
+            && number(self.confidence, other.confidence)
+            && number(self.bearing, other.bearing)
+            && number(self.speed, other.speed)
+            && timestamp(self.beginning_of_call, other.beginning_of_call)
+            && timestamp(self.time_of_positioning, other.time_of_positioning)
+            && self.without_measures() == other.without_measures()
+    }
+
+    /// Copy without the values compared with a tolerance.
+    fn without_measures(&self) -> AmlData {
+        AmlData {
+            latitude: None,
+            longitude: None,
+            altitude: None,
+            accuracy: None,
+            vertical_accuracy: None,
+            confidence: None,
+            bearing: None,
+            speed: None,
+            beginning_of_call: None,
+            time_of_positioning: None,
+            ..self.clone()
+        }
+    }
+}
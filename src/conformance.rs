@@ -0,0 +1,420 @@
+//! Conformance checks of raw AML payloads against ETSI TS 103 625, for certification testing.
+//!
+//! Unlike [`SmsData::validation`](crate::SmsData::validation), which tells whether the
+//! decoded values can be trusted, the checks here look at each attribute as sent and
+//! report every deviation with the identifier of the rule it breaks (see [`RULES`]).
+
+use crate::json::JsonValue;
+use crate::timestamp;
+use std::borrow::Cow;
+
+/// A conformance rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rule {
+    pub id: &'static str,
+    pub description: &'static str,
+}
+
+/// All the rules checked, by identifier.
+pub const RULES: &[Rule] = &[
+    Rule { id: "SMS-01", description: "The message starts with the A\"ML header" },
+    Rule { id: "SMS-02", description: "The version is 1 or 2" },
+    Rule { id: "SMS-03", description: "Every mandatory attribute of the version is present" },
+    Rule { id: "SMS-04", description: "Every attribute is defined for the version" },
+    Rule { id: "SMS-05", description: "An attribute appears at most once" },
+    Rule { id: "SMS-06", description: "Every attribute value has the format of the attribute" },
+    Rule { id: "SMS-07", description: "Attributes are key=value pairs separated by ';'" },
+    Rule { id: "SMS-08", description: "(v1) The ml attribute is the length of the message" },
+    Rule { id: "SMS-09", description: "Attributes are in the order of the specification" },
+    Rule { id: "HTTPS-01", description: "The version v is 1" },
+    Rule { id: "HTTPS-02", description: "Every mandatory attribute is present" },
+    Rule { id: "HTTPS-03", description: "Every attribute is defined" },
+    Rule { id: "HTTPS-04", description: "An attribute appears at most once" },
+    Rule { id: "HTTPS-05", description: "Every attribute value has the format of the attribute" },
+    Rule { id: "HTTPS-06", description: "The message ends with its hmac attribute" },
+];
+
+/// The rule of identifier `id`.
+///
+/// # Example
+///
+/// ```
+/// use aml_lib::conformance;
+///
+/// assert_eq!(conformance::rule("SMS-05").unwrap().description, "An attribute appears at most once");
+/// ```
+pub fn rule(id: &str) -> Option<&'static Rule> {
+    RULES.iter().find(|rule| rule.id == id)
+}
+
+/// Whether a finding prevents conformance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The message is not conformant.
+    Error,
+
+    /// Tolerated deviation.
+    Warning,
+}
+
+/// A broken rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    /// Identifier of the rule, see [`RULES`].
+    pub rule: &'static str,
+
+    pub severity: Severity,
+
+    /// The attribute concerned, if any.
+    pub attribute: Option<String>,
+
+    /// What has been found.
+    pub detail: String,
+}
+
+/// Outcome of the conformance checks of a message.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConformanceReport {
+    /// `sms` or `https`.
+    pub transport: &'static str,
+
+    /// The version found in the message.
+    pub version: Option<String>,
+
+    /// Identifiers of all the rules checked.
+    pub checked: Vec<&'static str>,
+
+    pub findings: Vec<Finding>,
+}
+
+impl ConformanceReport {
+    /// True when no finding is an error.
+    pub fn is_conformant(&self) -> bool {
+        self.findings.iter().all(|finding| finding.severity != Severity::Error)
+    }
+
+    /// The report as a JSON object, for certification tooling.
+    pub fn to_json_value(&self) -> JsonValue {
+        let text = |s: &str| JsonValue::String(s.to_string());
+        let findings = self
+            .findings
+            .iter()
+            .map(|finding| {
+                let severity = match finding.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                };
+                JsonValue::Object(vec![
+                    ("rule".to_string(), text(finding.rule)),
+                    ("severity".to_string(), text(severity)),
+                    ("attribute".to_string(), finding.attribute.as_deref().map_or(JsonValue::Null, text)),
+                    ("detail".to_string(), text(&finding.detail)),
+                ])
+            })
+            .collect();
+
+        JsonValue::Object(vec![
+            ("transport".to_string(), text(self.transport)),
+            ("version".to_string(), self.version.as_deref().map_or(JsonValue::Null, text)),
+            ("conformant".to_string(), JsonValue::Bool(self.is_conformant())),
+            ("checked".to_string(), JsonValue::Array(self.checked.iter().map(|id| text(id)).collect())),
+            ("findings".to_string(), JsonValue::Array(findings)),
+        ])
+    }
+
+    fn push<A: Into<String>>(&mut self, rule: &'static str, severity: Severity, attribute: Option<A>, detail: String) {
+        self.findings.push(Finding {
+            rule,
+            severity,
+            attribute: attribute.map(Into::into),
+            detail,
+        });
+    }
+}
+
+const SMS_V1_MANDATORY: &[&str] = &["lt", "lg", "rd", "top", "lc", "pm", "si", "ei", "mcc", "mnc", "ml"];
+pub(crate) const SMS_V1_KNOWN: &[&str] = SMS_V1_MANDATORY;
+const SMS_V2_MANDATORY: &[&str] = &["en", "et", "lo", "lt", "lc", "ls"];
+pub(crate) const SMS_V2_KNOWN: &[&str] = &["en", "et", "lo", "lt", "lc", "lz", "ls", "ei", "nc", "hc", "lg"];
+
+pub(crate) const HTTPS_MANDATORY: &[&str] = &[
+    "v",
+    "emergency_number",
+    "time",
+    "location_latitude",
+    "location_longitude",
+    "location_time",
+    "location_accuracy",
+    "location_source",
+];
+pub(crate) const HTTPS_KNOWN: &[&str] = &[
+    "v",
+    "emergency_number",
+    "source",
+    "thunderbird_version",
+    "time",
+    "gt_location_latitude",
+    "gt_location_longitude",
+    "location_latitude",
+    "location_longitude",
+    "location_time",
+    "location_altitude",
+    "location_floor",
+    "location_source",
+    "location_accuracy",
+    "location_vertical_accuracy",
+    "location_semi_major_axis",
+    "location_semi_minor_axis",
+    "location_orientation",
+    "location_confidence",
+    "location_certainty",
+    "location_bearing",
+    "location_speed",
+    "device_number",
+    "device_model",
+    "device_imsi",
+    "device_imei",
+    "device_iccid",
+    "cell_home_mcc",
+    "cell_home_mnc",
+    "cell_network_mcc",
+    "cell_network_mnc",
+    "device_languages",
+    "adr_carcrash_time",
+    "adr_severity",
+    "adr_rollover",
+    "adr_impact_direction",
+    "adr_occupants",
+    "test",
+    "hmac",
+];
+
+/// Check a text SMS.
+///
+/// # Example
+///
+/// ```
+/// use aml_lib::conformance;
+///
+/// let sms = r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;lc=68;pm=G;si=208201771948415;ei=353472104343540;mcc=208;mnc=20;ml=126"#;
+/// assert!(conformance::check_sms(sms).is_conformant());
+///
+/// let report = conformance::check_sms(r#"A"ML=1;lt=148.82639;lg=-2.36619"#);
+/// assert!(report.findings.iter().any(|f| f.rule == "SMS-06" && f.attribute.as_deref() == Some("lt")));
+/// ```
+pub fn check_sms(text_sms: &str) -> ConformanceReport {
+    let mut report = ConformanceReport {
+        transport: "sms",
+        checked: RULES.iter().filter(|rule| rule.id.starts_with("SMS")).map(|rule| rule.id).collect(),
+        ..Default::default()
+    };
+
+    let mut attributes: Vec<(&str, &str)> = Vec::new();
+    for element in text_sms.split(';').filter(|element| !element.trim().is_empty()) {
+        match element.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => attributes.push((key.trim(), value.trim())),
+            _ => report.push("SMS-07", Severity::Error, None::<String>, format!("Malformed element `{}`", element)),
+        }
+    }
+
+    match attributes.first() {
+        Some((r#"A"ML"#, _)) => (),
+        _ => report.push("SMS-01", Severity::Error, None::<String>, "The header is not the first attribute".to_string()),
+    }
+
+    let version = attributes.iter().find(|(key, _)| *key == r#"A"ML"#).map(|(_, value)| *value);
+    report.version = version.map(str::to_string);
+    let (mandatory, known, format): (_, _, fn(&str, &str) -> bool) = match version {
+        Some("1") => (SMS_V1_MANDATORY, SMS_V1_KNOWN, sms_v1_format),
+        Some("2") => (SMS_V2_MANDATORY, SMS_V2_KNOWN, sms_v2_format),
+        _ => {
+            report.push("SMS-02", Severity::Error, Some(r#"A"ML"#), format!("Unsupported version {:?}", version));
+            return report;
+        }
+    };
+
+    check_attributes(&mut report, &attributes, r#"A"ML"#, mandatory, known, format, ["SMS-03", "SMS-04", "SMS-05", "SMS-06"]);
+    check_order(&mut report, &attributes, known, "SMS-09");
+
+    if version == Some("1") {
+        if let Some(Ok(declared)) = attributes.iter().find(|(key, _)| *key == "ml").map(|(_, value)| value.parse::<usize>()) {
+            if declared != text_sms.len() {
+                report.push(
+                    "SMS-08",
+                    Severity::Error,
+                    Some("ml"),
+                    format!("Declared length {} but the message is {} bytes long", declared, text_sms.len()),
+                );
+            }
+        }
+    }
+
+    report
+}
+
+/// Check a urlencoded HTTPS message.
+pub fn check_https(payload: &str) -> ConformanceReport {
+    let mut report = ConformanceReport {
+        transport: "https",
+        checked: RULES.iter().filter(|rule| rule.id.starts_with("HTTPS")).map(|rule| rule.id).collect(),
+        ..Default::default()
+    };
+
+    let pairs: Vec<(Cow<str>, Cow<str>)> = url::form_urlencoded::parse(payload.trim().as_bytes()).collect();
+    let attributes: Vec<(&str, &str)> = pairs.iter().map(|(key, value)| (key.as_ref(), value.trim())).collect();
+
+    let version = attributes.iter().find(|(key, _)| *key == "v").map(|(_, value)| *value);
+    report.version = version.map(str::to_string);
+    if version.is_some_and(|v| v != "1") {
+        report.push("HTTPS-01", Severity::Error, Some("v"), format!("Unsupported version {:?}", version.unwrap_or_default()));
+    }
+
+    check_attributes(&mut report, &attributes, "", HTTPS_MANDATORY, HTTPS_KNOWN, https_format, ["HTTPS-02", "HTTPS-03", "HTTPS-04", "HTTPS-05"]);
+
+    match attributes.iter().position(|(key, _)| *key == "hmac") {
+        None => report.push("HTTPS-06", Severity::Warning, Some("hmac"), "The message is not authenticated".to_string()),
+        Some(position) if position + 1 != attributes.len() => {
+            report.push("HTTPS-06", Severity::Error, Some("hmac"), "hmac is not the last attribute".to_string())
+        }
+        Some(_) => (),
+    }
+
+    report
+}
+
+/// Presence, definition, unicity and format of the attributes. `rules` are the
+/// identifiers of these four checks.
+fn check_attributes(
+    report: &mut ConformanceReport,
+    attributes: &[(&str, &str)],
+    header: &str,
+    mandatory: &[&str],
+    known: &[&str],
+    format: fn(&str, &str) -> bool,
+    rules: [&'static str; 4],
+) {
+    for key in mandatory {
+        if !attributes.iter().any(|(k, _)| k == key) {
+            report.push(rules[0], Severity::Error, Some(*key), "Missing mandatory attribute".to_string());
+        }
+    }
+
+    for (i, (key, value)) in attributes.iter().enumerate() {
+        if *key == header {
+            continue;
+        }
+        if !known.contains(key) {
+            report.push(rules[1], Severity::Warning, Some(*key), "Undefined attribute".to_string());
+            continue;
+        }
+        if attributes[..i].iter().any(|(k, _)| k == key) {
+            report.push(rules[2], Severity::Error, Some(*key), "Repeated attribute".to_string());
+        }
+        if !format(key, value) {
+            report.push(rules[3], Severity::Error, Some(*key), format!("Invalid value `{}`", value));
+        }
+    }
+}
+
+/// Known attributes in the order of `known`, which is the order of the specification.
+/// Receivers do not depend on it, so a deviation is a warning.
+fn check_order(report: &mut ConformanceReport, attributes: &[(&str, &str)], known: &[&str], rule: &'static str) {
+    let mut last: Option<(usize, &str)> = None;
+    for (key, _) in attributes {
+        let index = match known.iter().position(|k| k == key) {
+            Some(index) => index,
+            None => continue,
+        };
+        match last {
+            Some((previous, previous_key)) if index < previous => {
+                report.push(rule, Severity::Warning, Some(*key), format!("`{}` is sent after `{}`", key, previous_key))
+            }
+            _ => last = Some((index, key)),
+        }
+    }
+}
+
+fn sms_v1_format(key: &str, value: &str) -> bool {
+    match key {
+        "lt" => decimal(value, 5, -90.0, 90.0),
+        "lg" => decimal(value, 5, -180.0, 180.0),
+        "rd" => decimal(value, 1, 0.0, f64::MAX),
+        "top" => timestamp::parse_compact(value).is_some(),
+        "lc" => integer(value, 0, 100),
+        "pm" => matches!(value, "G" | "W" | "C" | "U"),
+        "si" => digits(value, 5, 15),
+        "ei" => digits(value, 14, 16),
+        "mcc" => digits(value, 3, 3),
+        "mnc" => digits(value, 2, 3),
+        "ml" => integer(value, 0, i64::MAX),
+        _ => true,
+    }
+}
+
+fn sms_v2_format(key: &str, value: &str) -> bool {
+    let numbers = |value: &str, min: usize, max: usize| {
+        let values: Vec<&str> = value.split(',').collect();
+        (min..=max).contains(&values.len()) && values.iter().all(|v| v.parse::<f64>().is_ok())
+    };
+
+    match key {
+        "en" => digits(value.trim_start_matches('+'), 1, 15),
+        "et" => integer(value, 0, i64::MAX),
+        "lo" => {
+            let values: Vec<&str> = value.split(',').collect();
+            numbers(value, 2, 3)
+                && decimal(values[0], 5, -90.0, 90.0)
+                && decimal(values[1], 5, -180.0, 180.0)
+                && values.get(2).is_none_or(|rd| decimal(rd, 1, 0.0, f64::MAX))
+        }
+        "lt" => integer(value, i64::MIN, i64::MAX),
+        "lc" => integer(value, 0, 100),
+        "lz" => numbers(value, 1, 2),
+        "ls" => matches!(value, "G" | "W" | "C" | "U" | "F"),
+        "ei" => digits(value, 14, 16),
+        "nc" | "hc" => digits(value, 5, 6),
+        "lg" => !value.is_empty() && value.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b','),
+        _ => true,
+    }
+}
+
+fn https_format(key: &str, value: &str) -> bool {
+    match key {
+        "v" => !value.is_empty(),
+        "emergency_number" => digits(value.trim_start_matches('+'), 1, 15),
+        "source" => matches!(value.to_lowercase().as_str(), "call" | "sms"),
+        "time" | "location_time" | "adr_carcrash_time" => integer(value, 0, i64::MAX),
+        "location_latitude" | "gt_location_latitude" => decimal(value, 15, -90.0, 90.0),
+        "location_longitude" | "gt_location_longitude" => decimal(value, 15, -180.0, 180.0),
+        "location_altitude" => decimal(value, 15, f64::MIN, f64::MAX),
+        "location_accuracy" | "location_vertical_accuracy" | "location_speed" => decimal(value, 15, 0.0, f64::MAX),
+        "location_semi_major_axis" | "location_semi_minor_axis" => decimal(value, 15, 0.0, f64::MAX),
+        "location_confidence" | "location_certainty" => decimal(value, 15, 0.0, 100.0),
+        "location_bearing" | "location_orientation" => decimal(value, 15, 0.0, 360.0),
+        "location_source" => matches!(value.to_lowercase().as_str(), "gps" | "wifi" | "cell" | "fused" | "unknown"),
+        "device_number" => value.is_empty() || digits(value.trim_start_matches('+'), 1, 15),
+        "device_imsi" => digits(value, 5, 15),
+        "device_imei" => digits(value, 14, 16),
+        "device_iccid" => digits(value, 18, 22),
+        "cell_home_mcc" | "cell_network_mcc" => digits(value, 3, 3),
+        "cell_home_mnc" | "cell_network_mnc" => digits(value, 2, 3),
+        "adr_rollover" | "test" => matches!(value.to_lowercase().as_str(), "true" | "false" | "1" | "0"),
+        "adr_impact_direction" => decimal(value, 15, 0.0, 360.0),
+        "adr_occupants" => integer(value, 0, 255),
+        "hmac" => value.len() == 40 && value.bytes().all(|b| b.is_ascii_hexdigit()),
+        _ => true,
+    }
+}
+
+fn digits(value: &str, min: usize, max: usize) -> bool {
+    (min..=max).contains(&value.len()) && value.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn integer(value: &str, min: i64, max: i64) -> bool {
+    value.parse::<i64>().is_ok_and(|v| (min..=max).contains(&v))
+}
+
+fn decimal(value: &str, max_decimals: usize, min: f64, max: f64) -> bool {
+    let decimals = value.split_once('.').map_or(0, |(_, fraction)| fraction.len());
+    decimals <= max_decimals && value.parse::<f64>().is_ok_and(|v| v.is_finite() && (min..=max).contains(&v))
+}
@@ -0,0 +1,58 @@
+use crate::{AmlData, Timestamp};
+use std::net::IpAddr;
+
+/// How and when a message has been received, as known to the receiver but not written
+/// in the message. See [`AmlData::with_context`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct ReceiveContext {
+    /// Time the message has been received, by the receiver clock.
+    #[cfg_attr(feature = "serde", serde(with = "crate::timestamp::rfc3339"))]
+    pub received_at: Option<Timestamp>,
+
+    /// Phone number the SMS has been sent from (the originating address).
+    pub source_msisdn: Option<String>,
+
+    /// Identifier of the SMS gateway or HTTPS endpoint which received the message.
+    pub gateway_id: Option<String>,
+
+    /// Address of the HTTPS client.
+    pub remote_ip: Option<IpAddr>,
+}
+
+impl AmlData {
+    /// The data with `context` attached, usually right after parsing. The context is
+    /// kept by [`AmlData::merge`] (the newer one wins), written by [`AmlData::to_json`],
+    /// redacted with the device number by [`AmlData::redacted`], and its `source_msisdn`
+    /// groups the SMS of a device in a [`Session`](crate::Session) when the message has
+    /// no other identifier.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{timestamp, AmlData, ReceiveContext};
+    ///
+    /// let context = ReceiveContext {
+    ///     received_at: timestamp::from_unix(1593187225),
+    ///     source_msisdn: Some("+33611223344".to_string()),
+    ///     gateway_id: Some("smsc-paris-1".to_string()),
+    ///     ..Default::default()
+    /// };
+    /// let aml = AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20"#)
+    ///     .unwrap()
+    ///     .with_context(context);
+    ///
+    /// assert_eq!(aml.received_at(), timestamp::from_unix(1593187225));
+    /// ```
+    pub fn with_context(self, context: ReceiveContext) -> Self {
+        AmlData {
+            context: Some(context),
+            ..self
+        }
+    }
+
+    /// Receive time of the message, if a context with it is attached.
+    pub fn received_at(&self) -> Option<Timestamp> {
+        self.context.as_ref().and_then(|context| context.received_at)
+    }
+}
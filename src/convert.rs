@@ -0,0 +1,142 @@
+use crate::{timestamp, AmlData, PositioningMethod, Timestamp};
+
+/// Output format of [`AmlData::convert`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetFormat {
+    /// v1 text SMS.
+    SmsV1,
+
+    /// v2 text SMS.
+    SmsV2,
+
+    /// urlencoded HTTPS body, without HMAC.
+    Https,
+}
+
+/// What a conversion has lost. See [`AmlData::convert`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LossReport {
+    /// Fields the target format cannot carry.
+    pub dropped: Vec<&'static str>,
+
+    /// Fields carried with less precision (truncated decimals or milliseconds, or a
+    /// coarser positioning method).
+    pub degraded: Vec<&'static str>,
+}
+
+impl LossReport {
+    /// True if nothing has been dropped nor degraded.
+    pub fn is_lossless(&self) -> bool {
+        self.dropped.is_empty() && self.degraded.is_empty()
+    }
+
+    pub(crate) fn drop_if(&mut self, field: &'static str, lost: bool) {
+        if lost {
+            self.dropped.push(field);
+        }
+    }
+
+    pub(crate) fn degrade_if(&mut self, field: &'static str, lost: bool) {
+        if lost {
+            self.degraded.push(field);
+        }
+    }
+}
+
+impl AmlData {
+    /// Encode the data in another transport format, with the fields which have been
+    /// dropped or degraded on the way (e.g. speed and bearing cannot go into a SMS,
+    /// milliseconds are truncated). The HTTPS version `v` is kept for HTTPS data and is
+    /// `1` otherwise; the civic address and the extras cannot go into HTTPS.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{AmlData, TargetFormat};
+    ///
+    /// let sms = AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;lc=68;ls=F;lg=fr"#).unwrap();
+    ///
+    /// let (v1, loss) = sms.convert(TargetFormat::SmsV1);
+    /// assert_eq!(v1, r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=20;top=20200626160019;lc=68;pm=U;ml=72"#);
+    /// assert_eq!(loss.dropped, ["emergency_number", "beginning_of_call", "languages"]);
+    /// assert_eq!(loss.degraded, ["positioning_method"]);
+    ///
+    /// let (https, loss) = sms.convert(TargetFormat::Https);
+    /// assert!(https.starts_with("v=1&emergency_number=112&time=1593187189000&location_latitude=48.82639"));
+    /// assert!(loss.is_lossless());
+    /// ```
+    pub fn convert(&self, target: TargetFormat) -> (String, LossReport) {
+        match target {
+            TargetFormat::SmsV1 => {
+                let (sms, loss) = self.to_sms("1");
+                (sms.to_text(), loss)
+            }
+            TargetFormat::SmsV2 => {
+                let (sms, loss) = self.to_sms("2");
+                (sms.to_text(), loss)
+            }
+            TargetFormat::Https => self.to_urlencoded(),
+        }
+    }
+
+    fn to_urlencoded(&self) -> (String, LossReport) {
+        let mut loss = LossReport::default();
+        let method = self.positioning_method.as_deref().map(|method| (method, PositioningMethod::parse(method).map(|method| method.as_str())));
+        loss.drop_if("positioning_method", method.is_some_and(|(_, source)| source.is_none()));
+        loss.drop_if("address", self.address.is_some());
+        loss.drop_if("extras", !self.extras.is_empty());
+
+        let number = |value: Option<f64>| value.map(|value| value.to_string());
+        let integer = |value: Option<i32>| value.map(|value| value.to_string());
+        let millis = |value: Option<Timestamp>| value.map(|value| timestamp::unix_millis(value).to_string());
+        let version = match self.transport.as_str() {
+            "https" => self.version.clone(),
+            _ => Some("1".to_string()),
+        };
+        let adr = self.adr.clone().unwrap_or_default();
+
+        let fields = [
+            ("v", version),
+            ("emergency_number", self.emergency_number.clone()),
+            ("source", self.source_of_activation.clone()),
+            ("time", millis(self.beginning_of_call)),
+            ("gt_location_latitude", number(self.ground_truth.map(|(latitude, _)| latitude))),
+            ("gt_location_longitude", number(self.ground_truth.map(|(_, longitude)| longitude))),
+            ("location_latitude", number(self.latitude)),
+            ("location_longitude", number(self.longitude)),
+            ("location_time", millis(self.time_of_positioning)),
+            ("location_altitude", number(self.altitude)),
+            ("location_floor", self.floor.clone()),
+            ("location_source", method.and_then(|(_, source)| source).map(str::to_string)),
+            ("location_accuracy", number(self.accuracy)),
+            ("location_vertical_accuracy", number(self.vertical_accuracy)),
+            ("location_semi_major_axis", number(self.ellipse.map(|ellipse| ellipse.semi_major))),
+            ("location_semi_minor_axis", number(self.ellipse.map(|ellipse| ellipse.semi_minor))),
+            ("location_orientation", number(self.ellipse.map(|ellipse| ellipse.orientation))),
+            ("location_confidence", number(self.confidence)),
+            ("location_bearing", number(self.bearing)),
+            ("location_speed", number(self.speed)),
+            ("device_number", self.device_number.clone()),
+            ("device_model", self.model.clone()),
+            ("device_imsi", self.imsi.clone()),
+            ("device_imei", self.imei.clone()),
+            ("device_iccid", self.iccid.clone()),
+            ("cell_home_mcc", integer(self.home_mcc)),
+            ("cell_home_mnc", integer(self.home_mnc)),
+            ("cell_network_mcc", integer(self.network_mcc)),
+            ("cell_network_mnc", integer(self.network_mnc)),
+            ("device_languages", self.languages.clone()),
+            ("adr_carcrash_time", millis(adr.carcrash_time)),
+            ("adr_severity", adr.severity),
+            ("adr_rollover", adr.rollover.map(|rollover| rollover.to_string())),
+            ("adr_impact_direction", number(adr.impact_direction)),
+            ("adr_occupants", adr.occupants.map(|occupants| occupants.to_string())),
+            ("test", Some("true".to_string()).filter(|_| self.test)),
+        ];
+
+        let urlencoded = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(fields.iter().filter_map(|(key, value)| value.as_ref().map(|value| (key, value))))
+            .finish();
+        (urlencoded, loss)
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{timestamp, AmlData, Duration, Timestamp};
+use std::collections::VecDeque;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+impl AmlData {
+    /// Stable 64-bit fingerprint (FNV-1a) of the canonicalized content of the message.
+    /// Two deliveries of the same AML message have the same fingerprint, whatever the
+    /// order of their attributes. The transport and the resolved address are not part of it.
+    pub fn fingerprint(&self) -> u64 {
+        let timestamp = |dt: Option<Timestamp>| dt.map(|dt| timestamp::unix_millis(dt).to_string());
+        let number = |n: Option<f64>| n.map(|n| n.to_string());
+        let integer = |n: Option<i32>| n.map(|n| n.to_string());
+
+        let fields = [
+            ("accuracy", number(self.accuracy)),
+            ("altitude", number(self.altitude)),
+            ("bearing", number(self.bearing)),
+            ("beginning_of_call", timestamp(self.beginning_of_call)),
+            ("confidence", number(self.confidence)),
+            ("device_number", self.device_number.clone()),
+            ("emergency_number", self.emergency_number.clone()),
+            ("floor", self.floor.clone()),
+            ("home_mcc", integer(self.home_mcc)),
+            ("home_mnc", integer(self.home_mnc)),
+            ("iccid", self.iccid.clone()),
+            ("imei", self.imei.clone()),
+            ("imsi", self.imsi.clone()),
+            ("languages", self.languages.clone()),
+            ("latitude", number(self.latitude)),
+            ("longitude", number(self.longitude)),
+            ("model", self.model.clone()),
+            ("network_mcc", integer(self.network_mcc)),
+            ("network_mnc", integer(self.network_mnc)),
+            ("positioning_method", self.positioning_method.clone()),
+            ("source_of_activation", self.source_of_activation.clone()),
+            ("speed", number(self.speed)),
+            ("time_of_positioning", timestamp(self.time_of_positioning)),
+            ("version", self.version.clone()),
+            ("vertical_accuracy", number(self.vertical_accuracy)),
+        ];
+
+        let mut hash = FNV_OFFSET_BASIS;
+        for (key, value) in fields.iter().filter_map(|(k, v)| v.as_ref().map(|v| (k, v))) {
+            for byte in key.bytes().chain(Some(b'=')).chain(value.bytes()).chain(Some(b';')) {
+                hash ^= u64::from(byte);
+                hash = hash.wrapping_mul(FNV_PRIME);
+            }
+        }
+        hash
+    }
+}
+
+/// Detects the messages delivered more than once within a time window.
+///
+/// # Example
+///
+/// ```
+/// use aml_lib::{timestamp, AmlData, Deduplicator, Duration};
+///
+/// let text = r#"A"ML=2;et=1593187189;lo=48.80,2.30,20;lt=5;ei=358239059042542"#;
+/// let mut dedup = Deduplicator::new(Duration::minutes(5), 1000);
+/// let now = timestamp::now();
+///
+/// assert!(!dedup.is_duplicate(&AmlData::from_text_sms(text).unwrap(), now));
+/// assert!(dedup.is_duplicate(&AmlData::from_text_sms(text).unwrap(), now + Duration::seconds(30)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Deduplicator {
+    /// How long a fingerprint is remembered.
+    pub window: Duration,
+
+    /// Maximum number of fingerprints remembered; the oldest are forgotten first.
+    pub capacity: usize,
+
+    seen: VecDeque<(u64, Timestamp)>,
+}
+
+impl Deduplicator {
+    pub fn new(window: Duration, capacity: usize) -> Self {
+        Deduplicator {
+            window,
+            capacity,
+            seen: VecDeque::new(),
+        }
+    }
+
+    /// True if the same message has already been seen in the window before `received_at`.
+    /// The message is remembered otherwise.
+    pub fn is_duplicate(&mut self, aml: &AmlData, received_at: Timestamp) -> bool {
+        let limit = received_at - self.window;
+        while self.seen.front().is_some_and(|(_, at)| *at < limit) {
+            self.seen.pop_front();
+        }
+
+        let fingerprint = aml.fingerprint();
+        if self.seen.iter().any(|(fp, _)| *fp == fingerprint) {
+            return true;
+        }
+
+        if self.seen.len() >= self.capacity {
+            self.seen.pop_front();
+        }
+        self.seen.push_back((fingerprint, received_at));
+        false
+    }
+}
@@ -0,0 +1,114 @@
+use crate::{timestamp, AmlData, Timestamp};
+
+/// A field which differs between two [`AmlData`]. See [`AmlData::diff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    /// Name of the field of [`AmlData`].
+    pub field: &'static str,
+
+    /// Value before, `None` if the field was not set.
+    pub old: Option<String>,
+
+    /// Value after, `None` if the field is no longer set.
+    pub new: Option<String>,
+}
+
+/// Render a field as text: numbers and strings as is, timestamps in RFC 3339 and
+/// structured values in their debug form.
+trait Rendered {
+    fn rendered(&self) -> Option<String>;
+}
+
+impl Rendered for Option<String> {
+    fn rendered(&self) -> Option<String> {
+        self.clone()
+    }
+}
+
+impl Rendered for Option<f64> {
+    fn rendered(&self) -> Option<String> {
+        self.map(|value| value.to_string())
+    }
+}
+
+impl Rendered for Option<i32> {
+    fn rendered(&self) -> Option<String> {
+        self.map(|value| value.to_string())
+    }
+}
+
+impl Rendered for Option<Timestamp> {
+    fn rendered(&self) -> Option<String> {
+        self.map(timestamp::to_rfc3339)
+    }
+}
+
+impl Rendered for String {
+    fn rendered(&self) -> Option<String> {
+        Some(self.clone()).filter(|value| !value.is_empty())
+    }
+}
+
+impl Rendered for bool {
+    fn rendered(&self) -> Option<String> {
+        Some(self.to_string())
+    }
+}
+
+macro_rules! changes {
+    ($old: expr, $new: expr, [$($field: ident),*], debug [$($structured: ident),*]) => {{
+        let mut changes = Vec::new();
+        $(
+            if $old.$field != $new.$field {
+                changes.push(FieldChange {
+                    field: stringify!($field),
+                    old: $old.$field.rendered(),
+                    new: $new.$field.rendered(),
+                });
+            }
+        )*
+        $(
+            if $old.$structured != $new.$structured {
+                changes.push(FieldChange {
+                    field: stringify!($structured),
+                    old: Some(format!("{:?}", $old.$structured)),
+                    new: Some(format!("{:?}", $new.$structured)),
+                });
+            }
+        )*
+        changes
+    }};
+}
+
+impl AmlData {
+    /// The fields which differ in `other`, with their old (`self`) and new (`other`)
+    /// values. Empty if both are equal. Structured fields (ellipse, ground truth, ADR, address,
+    /// profile, receive context and extras) come last, in their debug form.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::AmlData;
+    ///
+    /// let first = AmlData::from_https("v=1&location_latitude=48.85&location_longitude=2.35&location_accuracy=50").unwrap();
+    /// let update = AmlData::from_https("v=1&location_latitude=48.86&location_longitude=2.35&location_accuracy=12").unwrap();
+    ///
+    /// let changes = first.diff(&update);
+    /// let fields: Vec<_> = changes.iter().map(|change| change.field).collect();
+    /// assert_eq!(fields, ["latitude", "accuracy"]);
+    /// assert_eq!(changes[1].new.as_deref(), Some("12"));
+    /// ```
+    pub fn diff(&self, other: &AmlData) -> Vec<FieldChange> {
+        changes!(
+            self,
+            other,
+            [
+                version, emergency_number, source_of_activation, beginning_of_call, latitude, longitude,
+                time_of_positioning, altitude, floor, positioning_method, accuracy, vertical_accuracy,
+                confidence, bearing, speed, device_number, model, imsi, imei, iccid, home_mcc, home_mnc,
+                network_mcc, network_mnc, languages, transport, via, test
+            ],
+            debug [ellipse, ground_truth, adr, address, profile, context, extras]
+        )
+    }
+}
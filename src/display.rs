@@ -0,0 +1,159 @@
+use crate::json::JsonValue;
+use crate::{timestamp, AmlData, Duration, Timestamp};
+use std::fmt::{self, Write};
+
+/// Width of the field name column of [`AmlData::to_table`].
+const TABLE_KEY_WIDTH: usize = 22;
+
+impl AmlData {
+    /// Multi-line summary for dispatchers, with the age of the location at `now`.
+    /// Lines without any known value are omitted. `Display` gives the same summary
+    /// at the current time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{timestamp, AmlData};
+    ///
+    /// let aml = AmlData::from_text_sms(r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;lc=68;pm=G;si=208201771948415;ei=353472104343540;mcc=208;mnc=20;ml=126"#).unwrap();
+    /// let summary = aml.summary_at(timestamp::from_utc((2019, 11, 12), (11, 39, 28)).unwrap());
+    ///
+    /// assert_eq!(summary.lines().next(), Some("Location  48.82639, -2.36619 ±52 m (68%) GNSS"));
+    /// assert!(summary.contains("(10 min ago)"));
+    /// ```
+    pub fn summary_at(&self, now: Timestamp) -> String {
+        let mut summary = String::new();
+        // Writing to a String cannot fail
+        let _ = self.write_summary(&mut summary, now);
+        summary
+    }
+
+    fn write_summary<W: Write>(&self, out: &mut W, now: Timestamp) -> fmt::Result {
+        let mut lines: Vec<(&str, String)> = Vec::new();
+
+        let mut location = match (self.latitude, self.longitude) {
+            (Some(lat), Some(lon)) => format!("{}, {}", lat, lon),
+            _ => "unknown".to_string(),
+        };
+        if let Some(accuracy) = self.accuracy {
+            let _ = write!(location, " ±{} m", accuracy);
+        }
+        if let Some(confidence) = self.confidence {
+            let _ = write!(location, " ({}%)", confidence);
+        }
+        if let Some(method) = &self.positioning_method {
+            let _ = write!(location, " {}", method_name(method));
+        }
+        lines.push(("Location", location));
+
+        let mut vertical = Vec::new();
+        if let Some(altitude) = self.altitude {
+            let accuracy = self.vertical_accuracy.map(|v| format!(" ±{} m", v)).unwrap_or_default();
+            vertical.push(format!("{} m{}", altitude, accuracy));
+        }
+        if let Some(floor) = &self.floor {
+            vertical.push(format!("floor {}", floor));
+        }
+        if !vertical.is_empty() {
+            lines.push(("Altitude", vertical.join(", ")));
+        }
+
+        if let Some(top) = self.time_of_positioning {
+            lines.push(("Fixed", format!("{} UTC ({})", timestamp::format(top, " "), age(now - top))));
+        }
+
+        let mut call = Vec::new();
+        if let Some(number) = &self.emergency_number {
+            call.push(number.clone());
+        }
+        if let Some(boc) = self.beginning_of_call {
+            call.push(format!("at {} UTC", timestamp::format(boc, " ")));
+        }
+        call.push(format!("via {}", self.transport));
+        lines.push(("Call", call.join(" ")));
+
+        let identifiers: Vec<String> = [
+            ("", &self.device_number),
+            ("IMEI ", &self.imei),
+            ("IMSI ", &self.imsi),
+            ("ICCID ", &self.iccid),
+            ("model ", &self.model),
+        ]
+        .iter()
+        .filter_map(|(label, value)| value.as_ref().map(|v| format!("{}{}", label, v)))
+        .collect();
+        if !identifiers.is_empty() {
+            lines.push(("Device", identifiers.join(", ")));
+        }
+
+        let code = |mcc: Option<i32>, mnc: Option<i32>| match (mcc, mnc) {
+            (Some(mcc), Some(mnc)) => Some(format!("{:03}-{:02}", mcc, mnc)),
+            (Some(mcc), None) => Some(format!("{:03}", mcc)),
+            _ => None,
+        };
+        let carrier: Vec<String> = [
+            ("network ", code(self.network_mcc, self.network_mnc)),
+            ("home ", code(self.home_mcc, self.home_mnc)),
+        ]
+        .iter()
+        .filter_map(|(label, value)| value.as_ref().map(|v| format!("{}{}", label, v)))
+        .collect();
+        if !carrier.is_empty() {
+            lines.push(("Carrier", carrier.join(", ")));
+        }
+
+        for (i, (label, value)) in lines.iter().enumerate() {
+            if i > 0 {
+                out.write_char('\n')?;
+            }
+            write!(out, "{:<10}{}", label, value)?;
+        }
+        Ok(())
+    }
+
+    /// All the known values, one per line, as a fixed-width table of field names
+    /// and values, for console output.
+    pub fn to_table(&self) -> String {
+        let mut table = String::new();
+        if let JsonValue::Object(members) = self.to_json_value() {
+            for (key, value) in members {
+                let value = match value {
+                    JsonValue::String(s) => s,
+                    other => other.to_string(),
+                };
+                let _ = writeln!(table, "{:<width$}{}", key, value, width = TABLE_KEY_WIDTH);
+            }
+        }
+        table
+    }
+}
+
+impl fmt::Display for AmlData {
+    /// See [`AmlData::summary_at`].
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_summary(f, timestamp::now())
+    }
+}
+
+fn method_name(method: &str) -> &str {
+    match method.to_lowercase().as_str() {
+        "g" | "gps" => "GNSS",
+        "w" | "wifi" => "Wi-Fi",
+        "c" | "cell" => "Cell",
+        "f" | "fused" => "Fused",
+        "u" | "unknown" => "Unknown",
+        _ => method,
+    }
+}
+
+fn age(age: Duration) -> String {
+    if age < Duration::seconds(0) {
+        return "in the future".to_string();
+    }
+    match timestamp::whole_seconds(age) {
+        s if s < 60 => format!("{} s ago", s),
+        s if s < 3600 => format!("{} min ago", s / 60),
+        s if s < 86400 => format!("{} h {} min ago", s / 3600, s % 3600 / 60),
+        s => format!("{} d ago", s / 86400),
+    }
+}
@@ -0,0 +1,436 @@
+//! eCall Minimum Set of Data (CEN EN 15722, version 2), so that the in-band locations
+//! of eCall and AML go through the same [`AmlData`].
+//!
+//! The MSD is the `ECallMessage` ASN.1 type encoded with the unaligned packed encoding
+//! rules (UPER). The optional additional data is skipped. MSDs can also be built from
+//! AML data, to forward AML locations to eCall-only systems.
+//!
+//! # Example
+//!
+//! ```
+//! use aml_lib::AmlData;
+//!
+//! let msd = hex::decode("02141681d71d8208014a00dd0c818714220afd481fcbb9684409c832380b60a7b00c").unwrap();
+//! let aml = AmlData::from_ecall_msd(&msd).unwrap();
+//! assert_eq!(aml.transport, "ecall");
+//! assert_eq!(aml.latitude, Some(48.85));
+//! ```
+
+use crate::{timestamp, AdrData, AmlData, AmlError, Timestamp};
+
+/// Alphabet of the VIN characters, in the order of their UPER index.
+const VIN_ALPHABET: &[u8] = b"0123456789ABCDEFGHJKLMNPRSTUVWXYZ";
+
+/// Names of the vehicle types in the XML encoding, from 1.
+const VEHICLE_TYPES: &[&str] = &[
+    "passengerVehicleClassM1",
+    "busesAndCoachesClassM2",
+    "busesAndCoachesClassM3",
+    "lightCommercialVehiclesClassN1",
+    "heavyDutyVehiclesClassN2",
+    "heavyDutyVehiclesClassN3",
+    "motorcyclesClassL1e",
+    "motorcyclesClassL2e",
+    "motorcyclesClassL3e",
+    "motorcyclesClassL4e",
+    "motorcyclesClassL5e",
+    "motorcyclesClassL6e",
+    "motorcyclesClassL7e",
+];
+
+/// Largest accuracy in meters of a position that can be trusted (95 % within 150 m).
+const TRUSTED_ACCURACY: f64 = 150.0;
+
+/// Milliarcseconds per degree.
+const MAS_PER_DEGREE: f64 = 3_600_000.0;
+
+/// Kinds of energy storage of the vehicle.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Propulsion {
+    pub gasoline: bool,
+    pub diesel: bool,
+    pub compressed_natural_gas: bool,
+    pub liquid_propane_gas: bool,
+    pub electric: bool,
+    pub hydrogen: bool,
+    pub other: bool,
+}
+
+/// A decoded Minimum Set of Data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Msd {
+    /// Message identifier, incremented by the vehicle at each retransmission.
+    pub message_identifier: u8,
+
+    /// Triggered by the vehicle (true) or by an occupant.
+    pub automatic_activation: bool,
+
+    pub test_call: bool,
+
+    /// False when the vehicle has low confidence in its position.
+    pub position_can_be_trusted: bool,
+
+    /// Vehicle category: 1 for passenger vehicle (M1) to 13 for motorcycle (L7e).
+    pub vehicle_type: u8,
+
+    /// Vehicle identification number (17 characters).
+    pub vin: String,
+
+    pub propulsion: Propulsion,
+
+    /// Time of the incident.
+    pub timestamp: Timestamp,
+
+    /// Position of the vehicle in milliarcseconds.
+    pub latitude: i32,
+    pub longitude: i32,
+
+    /// Direction of travel in steps of 2 degrees from north (0 to 179), 255 if unknown.
+    pub direction: u8,
+
+    /// Previous positions, as deltas in steps of 100 milliarcseconds from the position
+    /// before them.
+    pub recent_location_n1: Option<(i16, i16)>,
+    pub recent_location_n2: Option<(i16, i16)>,
+
+    pub number_of_passengers: Option<u8>,
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn bit(&mut self) -> Result<bool, AmlError> {
+        let byte = self.bytes.get(self.pos / 8).ok_or(AmlError::InvalidMsd)?;
+        let bit = byte >> (7 - self.pos % 8) & 1 == 1;
+        self.pos += 1;
+        Ok(bit)
+    }
+
+    fn bits(&mut self, count: usize) -> Result<u64, AmlError> {
+        (0..count).try_fold(0_u64, |value, _| Ok(value << 1 | u64::from(self.bit()?)))
+    }
+
+    /// A constrained whole number in `min..=max`.
+    fn integer(&mut self, min: i64, max: i64) -> Result<i64, AmlError> {
+        let range = (max - min) as u64;
+        let width = 64 - range.leading_zeros() as usize;
+        Ok(min + self.bits(width)? as i64)
+    }
+
+    /// A sequence extension bit: extensions are not supported.
+    fn root_only(&mut self) -> Result<(), AmlError> {
+        match self.bit()? {
+            false => Ok(()),
+            true => Err(AmlError::InvalidMsd),
+        }
+    }
+
+    fn vin_part(&mut self, len: usize) -> Result<String, AmlError> {
+        (0..len)
+            .map(|_| VIN_ALPHABET.get(self.bits(6)? as usize).map(|c| char::from(*c)).ok_or(AmlError::InvalidMsd))
+            .collect()
+    }
+
+    fn delta(&mut self) -> Result<(i16, i16), AmlError> {
+        Ok((self.integer(-512, 511)? as i16, self.integer(-512, 511)? as i16))
+    }
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl BitWriter {
+    fn bit(&mut self, bit: bool) {
+        if self.len.is_multiple_of(8) {
+            self.bytes.push(0);
+        }
+        if bit {
+            *self.bytes.last_mut().unwrap() |= 1 << (7 - self.len % 8);
+        }
+        self.len += 1;
+    }
+
+    fn bits(&mut self, value: u64, count: usize) {
+        for i in (0..count).rev() {
+            self.bit(value >> i & 1 == 1);
+        }
+    }
+
+    fn integer(&mut self, value: i64, min: i64, max: i64) {
+        let range = (max - min) as u64;
+        let width = 64 - range.leading_zeros() as usize;
+        self.bits((value.clamp(min, max) - min) as u64, width);
+    }
+}
+
+impl Propulsion {
+    fn values(&self) -> [(&'static str, bool); 7] {
+        [
+            ("gasolineTankPresent", self.gasoline),
+            ("dieselTankPresent", self.diesel),
+            ("compressedNaturalGas", self.compressed_natural_gas),
+            ("liquidPropaneGas", self.liquid_propane_gas),
+            ("electricEnergyStorage", self.electric),
+            ("hydrogenStorage", self.hydrogen),
+            ("otherStorage", self.other),
+        ]
+    }
+}
+
+impl Msd {
+    /// A MSD reporting the location of `aml`, or `None` without position or time. The
+    /// vehicle is unknown: set [`Msd::vin`], [`Msd::vehicle_type`] and [`Msd::propulsion`]
+    /// if they are known otherwise. The position can be trusted if its accuracy is
+    /// within 150 m.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::ecall::Msd;
+    /// use aml_lib::AmlData;
+    ///
+    /// let aml = AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;ls=G"#).unwrap();
+    /// let msd = Msd::from_aml(&aml).unwrap();
+    /// assert!(msd.position_can_be_trusted);
+    ///
+    /// let decoded = AmlData::from_ecall_msd(&msd.to_uper()).unwrap();
+    /// assert!((decoded.latitude.unwrap() - 48.82639).abs() < 1e-6);
+    /// assert!(msd.to_xml().contains("<positionLatitude>175775004</positionLatitude>"));
+    /// ```
+    pub fn from_aml(aml: &AmlData) -> Option<Msd> {
+        let (latitude, longitude) = aml.position()?;
+        let timestamp = aml.time_of_positioning.or(aml.beginning_of_call)?;
+
+        Some(Msd {
+            message_identifier: 1,
+            automatic_activation: false,
+            test_call: aml.test,
+            position_can_be_trusted: aml.accuracy.is_some_and(|accuracy| accuracy > 0.0 && accuracy <= TRUSTED_ACCURACY),
+            vehicle_type: 1,
+            vin: "0".repeat(17),
+            propulsion: Propulsion::default(),
+            timestamp,
+            latitude: (latitude * MAS_PER_DEGREE).round() as i32,
+            longitude: (longitude * MAS_PER_DEGREE).round() as i32,
+            direction: aml.bearing.map_or(255, |bearing| (bearing.rem_euclid(360.0) / 2.0).round() as u8 % 180),
+            recent_location_n1: None,
+            recent_location_n2: None,
+            number_of_passengers: aml.adr.as_ref().and_then(|adr| adr.occupants).map(|n| n.min(255) as u8),
+        })
+    }
+
+    /// Encode as a version 2 `ECallMessage` in UPER, the inverse of [`Msd::from_uper`].
+    /// VIN characters outside of the VIN alphabet are encoded as `0`.
+    pub fn to_uper(&self) -> Vec<u8> {
+        let mut w = BitWriter::default();
+
+        w.bits(2, 8);
+        w.bit(false);
+        w.bit(false); // no optionalAdditionalData
+
+        w.bit(false);
+        w.bit(self.recent_location_n1.is_some());
+        w.bit(self.recent_location_n2.is_some());
+        w.bit(self.number_of_passengers.is_some());
+        w.bits(u64::from(self.message_identifier), 8);
+
+        w.bit(self.automatic_activation);
+        w.bit(self.test_call);
+        w.bit(self.position_can_be_trusted);
+        w.bit(false);
+        w.integer(i64::from(self.vehicle_type), 1, VEHICLE_TYPES.len() as i64);
+
+        let vin = self.vin.bytes().chain(std::iter::repeat(b'0')).take(17);
+        for c in vin {
+            w.bits(VIN_ALPHABET.iter().position(|a| *a == c).unwrap_or(0) as u64, 6);
+        }
+
+        // Values equal to the DEFAULT FALSE are not encoded
+        w.bit(false);
+        let storage = self.propulsion.values();
+        for (_, value) in storage {
+            w.bit(value);
+        }
+        for _ in storage.iter().filter(|(_, value)| *value) {
+            w.bit(true);
+        }
+
+        w.bits(timestamp::unix(self.timestamp).clamp(0, u32::MAX as i64) as u64, 32);
+        w.integer(i64::from(self.latitude), -324_000_000, 324_000_000);
+        w.integer(i64::from(self.longitude), -648_000_000, 648_000_000);
+        w.bits(u64::from(self.direction), 8);
+
+        for (latitude, longitude) in [self.recent_location_n1, self.recent_location_n2].iter().flatten() {
+            w.integer(i64::from(*latitude), -512, 511);
+            w.integer(i64::from(*longitude), -512, 511);
+        }
+        if let Some(passengers) = self.number_of_passengers {
+            w.bits(u64::from(passengers), 8);
+        }
+
+        w.bytes
+    }
+
+    /// The `ECallMessage` in the XML encoding rules of ASN.1 (XER), on a single line.
+    pub fn to_xml(&self) -> String {
+        let boolean = |value: bool| if value { "<true/>" } else { "<false/>" };
+        let vin = format!("{:0<17}", self.vin);
+        let delta = |name: &str, delta: Option<(i16, i16)>| {
+            delta.map_or(String::new(), |(latitude, longitude)| {
+                format!(
+                    "<{0}><latitudeDelta>{1}</latitudeDelta><longitudeDelta>{2}</longitudeDelta></{0}>",
+                    name, latitude, longitude
+                )
+            })
+        };
+
+        let mut xml = String::from("<ECallMessage><id>2</id><msd><msdStructure>");
+        xml += &format!("<messageIdentifier>{}</messageIdentifier>", self.message_identifier);
+        xml += &format!(
+            "<control><automaticActivation>{}</automaticActivation><testCall>{}</testCall><positionCanBeTrusted>{}</positionCanBeTrusted><vehicleType><{}/></vehicleType></control>",
+            boolean(self.automatic_activation),
+            boolean(self.test_call),
+            boolean(self.position_can_be_trusted),
+            VEHICLE_TYPES.get(usize::from(self.vehicle_type).saturating_sub(1)).unwrap_or(&VEHICLE_TYPES[0]),
+        );
+        xml += &format!(
+            "<vehicleIdentificationNumber><isowmi>{}</isowmi><isovds>{}</isovds><isovisModelyear>{}</isovisModelyear><isovisSeqPlant>{}</isovisSeqPlant></vehicleIdentificationNumber>",
+            vin.get(0..3).unwrap_or_default(),
+            vin.get(3..9).unwrap_or_default(),
+            vin.get(9..10).unwrap_or_default(),
+            vin.get(10..17).unwrap_or_default(),
+        );
+        xml += "<vehiclePropulsionStorageType>";
+        for (name, _) in self.propulsion.values().iter().filter(|(_, value)| *value) {
+            xml += &format!("<{0}><true/></{0}>", name);
+        }
+        xml += "</vehiclePropulsionStorageType>";
+        xml += &format!("<timestamp>{}</timestamp>", timestamp::unix(self.timestamp));
+        xml += &format!(
+            "<vehicleLocation><positionLatitude>{}</positionLatitude><positionLongitude>{}</positionLongitude></vehicleLocation>",
+            self.latitude, self.longitude
+        );
+        xml += &format!("<vehicleDirection>{}</vehicleDirection>", self.direction);
+        xml += &delta("recentVehicleLocationN1", self.recent_location_n1);
+        xml += &delta("recentVehicleLocationN2", self.recent_location_n2);
+        if let Some(passengers) = self.number_of_passengers {
+            xml += &format!("<numberOfPassengers>{}</numberOfPassengers>", passengers);
+        }
+        xml += "</msdStructure></msd></ECallMessage>";
+        xml
+    }
+
+    /// Decode an `ECallMessage` in UPER. Returns [`AmlError::UnimplementedVersion`] if the
+    /// MSD format is not version 2 and [`AmlError::InvalidMsd`] if it is truncated or
+    /// has extensions.
+    pub fn from_uper(bytes: &[u8]) -> Result<Msd, AmlError> {
+        let mut r = BitReader { bytes, pos: 0 };
+
+        // ECallMessage
+        if r.bits(8)? != 2 {
+            return Err(AmlError::UnimplementedVersion);
+        }
+
+        // MSDMessage
+        r.root_only()?;
+        r.bit()?; // optionalAdditionalData, after the MSDStructure
+
+        // MSDStructure
+        r.root_only()?;
+        let (has_n1, has_n2, has_passengers) = (r.bit()?, r.bit()?, r.bit()?);
+        let message_identifier = r.bits(8)? as u8;
+
+        let automatic_activation = r.bit()?;
+        let test_call = r.bit()?;
+        let position_can_be_trusted = r.bit()?;
+        r.root_only()?;
+        let vehicle_type = r.bits(4)? as u8 + 1;
+        if vehicle_type as usize > VEHICLE_TYPES.len() {
+            return Err(AmlError::InvalidMsd);
+        }
+
+        let vin = [3, 6, 1, 7].iter().map(|len| r.vin_part(*len)).collect::<Result<String, _>>()?;
+
+        r.root_only()?;
+        let present: Vec<bool> = (0..7).map(|_| r.bit()).collect::<Result<_, _>>()?;
+        let mut storage = [false; 7];
+        for (value, present) in storage.iter_mut().zip(present) {
+            if present {
+                *value = r.bit()?;
+            }
+        }
+        let propulsion = Propulsion {
+            gasoline: storage[0],
+            diesel: storage[1],
+            compressed_natural_gas: storage[2],
+            liquid_propane_gas: storage[3],
+            electric: storage[4],
+            hydrogen: storage[5],
+            other: storage[6],
+        };
+
+        let timestamp = timestamp::from_unix(r.bits(32)? as i64).ok_or(AmlError::InvalidMsd)?;
+        let latitude = r.integer(-324_000_000, 324_000_000)? as i32;
+        let longitude = r.integer(-648_000_000, 648_000_000)? as i32;
+        let direction = r.bits(8)? as u8;
+
+        let recent_location_n1 = if has_n1 { Some(r.delta()?) } else { None };
+        let recent_location_n2 = if has_n2 { Some(r.delta()?) } else { None };
+        let number_of_passengers = if has_passengers { Some(r.bits(8)? as u8) } else { None };
+
+        Ok(Msd {
+            message_identifier,
+            automatic_activation,
+            test_call,
+            position_can_be_trusted,
+            vehicle_type,
+            vin,
+            propulsion,
+            timestamp,
+            latitude,
+            longitude,
+            direction,
+            recent_location_n1,
+            recent_location_n2,
+            number_of_passengers,
+        })
+    }
+}
+
+impl From<&Msd> for AmlData {
+    fn from(msd: &Msd) -> Self {
+        AmlData {
+            version: Some("2".to_string()),
+            latitude: Some(f64::from(msd.latitude) / MAS_PER_DEGREE),
+            longitude: Some(f64::from(msd.longitude) / MAS_PER_DEGREE),
+            time_of_positioning: Some(msd.timestamp),
+            bearing: (msd.direction < 180).then(|| f64::from(msd.direction) * 2.0),
+            test: msd.test_call,
+            adr: Some(AdrData {
+                carcrash_time: Some(msd.timestamp),
+                occupants: msd.number_of_passengers.map(u32::from),
+                ..Default::default()
+            }),
+            transport: "ecall".to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+impl AmlData {
+    /// Decode an eCall MSD. See [`Msd::from_uper`].
+    pub fn from_ecall_msd(msd: &[u8]) -> Result<Self, AmlError> {
+        Msd::from_uper(msd).map(|msd| AmlData::from(&msd))
+    }
+
+    /// Encode as an eCall MSD in UPER. See [`Msd::from_aml`].
+    pub fn to_ecall_msd(&self) -> Option<Vec<u8>> {
+        Msd::from_aml(self).map(|msd| msd.to_uper())
+    }
+}
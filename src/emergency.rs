@@ -0,0 +1,221 @@
+//! Registry of the emergency numbers per country, to check the number dialed.
+
+use crate::AmlData;
+
+/// Numbers every GSM/UMTS/LTE handset treats as emergency calls on any network
+/// (3GPP TS 22.101), which the network then routes to the local emergency service.
+const UNIVERSAL: &[&str] = &["112", "911"];
+
+/// ISO 3166-1 alpha-2 country, its Mobile Country Codes and its emergency numbers.
+const REGISTRY: &[(&str, &[i32], &[&str])] = &[
+    ("AT", &[232], &["112", "122", "133", "144"]),
+    ("AU", &[505], &["000", "112", "106"]),
+    ("BE", &[206], &["112", "100", "101"]),
+    ("BR", &[724], &["190", "192", "193"]),
+    ("CA", &[302], &["911"]),
+    ("CH", &[228], &["112", "117", "118", "144"]),
+    ("CN", &[460, 461], &["110", "119", "120", "122"]),
+    ("CZ", &[230], &["112", "150", "155", "158"]),
+    ("DE", &[262], &["112", "110"]),
+    ("DK", &[238], &["112", "114"]),
+    ("ES", &[214], &["112", "091", "092", "061", "080", "085", "062"]),
+    ("FI", &[244], &["112"]),
+    ("FR", &[208], &["112", "15", "17", "18", "114", "115", "119", "191", "196", "197"]),
+    ("GB", &[234, 235], &["112", "999"]),
+    ("IE", &[272], &["112", "999"]),
+    ("IN", &[404, 405, 406], &["112", "100", "101", "102", "108"]),
+    ("IT", &[222], &["112", "113", "115", "118"]),
+    ("JP", &[440, 441], &["110", "118", "119"]),
+    ("LU", &[270], &["112", "113"]),
+    ("MX", &[334], &["911"]),
+    ("NL", &[204], &["112"]),
+    ("NO", &[242], &["112", "110", "113"]),
+    ("NZ", &[530], &["111"]),
+    ("PL", &[260], &["112", "997", "998", "999"]),
+    ("PT", &[268], &["112"]),
+    ("RU", &[250], &["112", "101", "102", "103", "104"]),
+    ("SE", &[240], &["112"]),
+    ("TR", &[286], &["112", "110", "155", "156"]),
+    ("US", &[310, 311, 312, 313, 314, 315, 316], &["911"]),
+];
+
+/// Service reached by the numbers of the registry which are not general emergency
+/// numbers, per country.
+const SERVICES: &[(&str, &str, ServiceType)] = &[
+    ("AT", "122", ServiceType::Fire),
+    ("AT", "133", ServiceType::Police),
+    ("AT", "144", ServiceType::Ambulance),
+    ("BE", "100", ServiceType::Rescue),
+    ("BE", "101", ServiceType::Police),
+    ("BR", "190", ServiceType::Police),
+    ("BR", "192", ServiceType::Ambulance),
+    ("BR", "193", ServiceType::Fire),
+    ("CH", "117", ServiceType::Police),
+    ("CH", "118", ServiceType::Fire),
+    ("CH", "144", ServiceType::Ambulance),
+    ("CN", "110", ServiceType::Police),
+    ("CN", "119", ServiceType::Fire),
+    ("CN", "120", ServiceType::Ambulance),
+    ("CN", "122", ServiceType::Police),
+    ("CZ", "150", ServiceType::Fire),
+    ("CZ", "155", ServiceType::Ambulance),
+    ("CZ", "158", ServiceType::Police),
+    ("DE", "110", ServiceType::Police),
+    ("DK", "114", ServiceType::Police),
+    ("ES", "061", ServiceType::Ambulance),
+    ("ES", "062", ServiceType::Police),
+    ("ES", "080", ServiceType::Fire),
+    ("ES", "085", ServiceType::Fire),
+    ("ES", "091", ServiceType::Police),
+    ("ES", "092", ServiceType::Police),
+    ("FR", "15", ServiceType::Ambulance),
+    ("FR", "17", ServiceType::Police),
+    ("FR", "18", ServiceType::Rescue),
+    ("FR", "115", ServiceType::Other),
+    ("FR", "119", ServiceType::Other),
+    ("FR", "191", ServiceType::Other),
+    ("FR", "196", ServiceType::Maritime),
+    ("FR", "197", ServiceType::Police),
+    ("IN", "100", ServiceType::Police),
+    ("IN", "101", ServiceType::Fire),
+    ("IN", "102", ServiceType::Ambulance),
+    ("IN", "108", ServiceType::Ambulance),
+    ("IT", "113", ServiceType::Police),
+    ("IT", "115", ServiceType::Fire),
+    ("IT", "118", ServiceType::Ambulance),
+    ("JP", "110", ServiceType::Police),
+    ("JP", "118", ServiceType::Maritime),
+    ("JP", "119", ServiceType::Rescue),
+    ("LU", "113", ServiceType::Police),
+    ("NO", "110", ServiceType::Fire),
+    ("NO", "112", ServiceType::Police),
+    ("NO", "113", ServiceType::Ambulance),
+    ("PL", "997", ServiceType::Police),
+    ("PL", "998", ServiceType::Fire),
+    ("PL", "999", ServiceType::Ambulance),
+    ("RU", "101", ServiceType::Fire),
+    ("RU", "102", ServiceType::Police),
+    ("RU", "103", ServiceType::Ambulance),
+    ("RU", "104", ServiceType::Other),
+    ("TR", "110", ServiceType::Fire),
+    ("TR", "155", ServiceType::Police),
+    ("TR", "156", ServiceType::Police),
+];
+
+/// Emergency service reached by the number dialed. See [`AmlData::service_type`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ServiceType {
+    /// All the services (112, 911, 999, ...).
+    General,
+
+    Police,
+
+    Fire,
+
+    /// Emergency medical service.
+    Ambulance,
+
+    /// Fire brigade and emergency medical service together (e.g. 18 in France, 119 in Japan).
+    Rescue,
+
+    /// Sea rescue or coast guard.
+    Maritime,
+
+    /// Other services of the registry: social emergency, child abuse, gas, aviation.
+    Other,
+}
+
+/// Country (ISO 3166-1 alpha-2) of a Mobile Country Code, if it is in the registry.
+pub fn country_of_mcc(mcc: i32) -> Option<&'static str> {
+    REGISTRY.iter().find(|(_, mccs, _)| mccs.contains(&mcc)).map(|(country, _, _)| *country)
+}
+
+/// Emergency numbers of a country (ISO 3166-1 alpha-2), if it is in the registry.
+pub fn emergency_numbers(country: &str) -> Option<&'static [&'static str]> {
+    REGISTRY
+        .iter()
+        .find(|(code, _, _)| code.eq_ignore_ascii_case(country))
+        .map(|(_, _, numbers)| *numbers)
+}
+
+/// Result of [`AmlData::check_emergency_number`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmergencyNumberCheck {
+    /// The number is an emergency number of the country, or 112/911.
+    Recognized,
+
+    /// The number is not an emergency number of `country`: a misconfigured handset
+    /// or network, or a forged message.
+    Unrecognized { country: &'static str },
+
+    /// The country of the network is not in the registry.
+    UnknownCountry,
+
+    /// The message has no emergency number or no network MCC.
+    Missing,
+}
+
+impl AmlData {
+    /// Check the number dialed against the emergency numbers of the country of the
+    /// network (from its MCC). 112 and 911 are recognized everywhere as handsets
+    /// treat them as emergency calls on any network.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{AmlData, EmergencyNumberCheck};
+    ///
+    /// let samu = AmlData::from_text_sms(r#"A"ML=2;en=15;lo=48.8,2.3,20;nc=20801"#).unwrap();
+    /// assert_eq!(samu.check_emergency_number(), EmergencyNumberCheck::Recognized);
+    ///
+    /// let odd = AmlData::from_text_sms(r#"A"ML=2;en=3615;lo=48.8,2.3,20;nc=20801"#).unwrap();
+    /// assert_eq!(odd.check_emergency_number(), EmergencyNumberCheck::Unrecognized { country: "FR" });
+    /// ```
+    pub fn check_emergency_number(&self) -> EmergencyNumberCheck {
+        let (number, mcc) = match (self.emergency_number.as_deref(), self.network_mcc) {
+            (Some(number), Some(mcc)) => (number.trim_start_matches('+'), mcc),
+            _ => return EmergencyNumberCheck::Missing,
+        };
+        if UNIVERSAL.contains(&number) {
+            return EmergencyNumberCheck::Recognized;
+        }
+        match country_of_mcc(mcc) {
+            Some(country) if emergency_numbers(country).unwrap_or_default().contains(&number) => {
+                EmergencyNumberCheck::Recognized
+            }
+            Some(country) => EmergencyNumberCheck::Unrecognized { country },
+            None => EmergencyNumberCheck::UnknownCountry,
+        }
+    }
+
+    /// Service reached by the number dialed, in the country of the network (or the home
+    /// country when the network is unknown). `None` if the number is not in the registry
+    /// of the country, or if the number or the country is unknown (112 and 911 are
+    /// [`ServiceType::General`] everywhere but where they reach a single service).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{AmlData, ServiceType};
+    ///
+    /// let samu = AmlData::from_text_sms(r#"A"ML=2;en=15;lo=48.8,2.3,20;nc=20801"#).unwrap();
+    /// assert_eq!(samu.service_type(), Some(ServiceType::Ambulance));
+    ///
+    /// let general = AmlData::from_text_sms(r#"A"ML=2;en=112;lo=48.8,2.3,20;nc=20801"#).unwrap();
+    /// assert_eq!(general.service_type(), Some(ServiceType::General));
+    /// ```
+    pub fn service_type(&self) -> Option<ServiceType> {
+        let number = self.emergency_number.as_deref()?.trim_start_matches('+');
+        let country = self.network_mcc.or(self.home_mcc).and_then(country_of_mcc);
+
+        if let Some(country) = country {
+            if let Some((_, _, service)) = SERVICES.iter().find(|(code, dialed, _)| *code == country && *dialed == number) {
+                return Some(*service);
+            }
+            if emergency_numbers(country).unwrap_or_default().contains(&number) {
+                return Some(ServiceType::General);
+            }
+        }
+        Some(ServiceType::General).filter(|_| UNIVERSAL.contains(&number))
+    }
+}
@@ -0,0 +1,203 @@
+use crate::precision::{truncate_decimals, ALTITUDE_DECIMALS, COORDINATE_DECIMALS};
+use crate::{seconds_to_utc, timestamp, AmlData, LossReport, SmsData, Timestamp};
+
+/// `mcc` and `mnc` as the 5 or 6 digits of the `nc`/`hc` attributes.
+fn network_code(mcc: Option<i32>, mnc: Option<i32>) -> Option<String> {
+    Some(format!("{:03}{:02}", mcc?, mnc?))
+}
+
+/// True if `value` can be written as an attribute value, which the SMS syntax cannot
+/// escape: it must not contain `;` nor `=`.
+pub(crate) fn is_attribute_value(value: &str) -> bool {
+    !value.contains(';') && !value.contains('=')
+}
+
+impl SmsData {
+    /// The SMS text of the data, the attributes of `header` version in the order of the
+    /// specification. Coordinates are truncated to 5 decimals, altitudes to 1; `ml` is
+    /// computed for v1. Free text values containing `;` or `=`, which cannot be escaped,
+    /// are left out. Empty if the header is neither `1` nor `2`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::SmsData;
+    ///
+    /// let text = r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;lc=68;ls=G"#;
+    /// assert_eq!(SmsData::from_text(text).unwrap().to_text(), text);
+    /// ```
+    pub fn to_text(&self) -> String {
+        let coordinate = |value: Option<f64>| value.map(|value| truncate_decimals(value, COORDINATE_DECIMALS).to_string());
+        let altitude = |value: Option<f64>| value.map(|value| truncate_decimals(value, ALTITUDE_DECIMALS).to_string());
+        let number = |value: Option<f64>| value.map(|value| value.to_string());
+        let text = |value: &Option<String>| value.clone().filter(|value| is_attribute_value(value));
+
+        let mut attributes: Vec<(&str, Option<String>)> = Vec::new();
+        match self.header.as_deref() {
+            Some("1") => {
+                attributes.push(("lt", coordinate(self.latitude)));
+                attributes.push(("lg", coordinate(self.longitude)));
+                attributes.push(("rd", number(self.accuracy)));
+                attributes.push(("top", self.time_of_positioning.map(timestamp::format_compact)));
+                attributes.push(("lc", number(self.level_of_confidence)));
+                attributes.push(("pm", text(&self.positioning_method)));
+                attributes.push(("si", text(&self.imsi)));
+                attributes.push(("ei", text(&self.imei)));
+                attributes.push(("mcc", self.network_mcc.map(|mcc| format!("{:03}", mcc))));
+                attributes.push(("mnc", self.network_mnc.map(|mnc| format!("{:02}", mnc))));
+            }
+            Some("2") => {
+                let location = match (coordinate(self.latitude), coordinate(self.longitude)) {
+                    (Some(latitude), Some(longitude)) => Some(match number(self.accuracy) {
+                        Some(accuracy) => format!("{},{},{}", latitude, longitude, accuracy),
+                        None => format!("{},{}", latitude, longitude),
+                    }),
+                    _ => None,
+                };
+                let elevation = match (altitude(self.altitude), altitude(self.vertical_accuracy)) {
+                    (Some(altitude), Some(vertical_accuracy)) => Some(format!("{},{}", altitude, vertical_accuracy)),
+                    (altitude, _) => altitude,
+                };
+                let delay = match (self.beginning_of_call, self.time_of_positioning) {
+                    (Some(et), Some(top)) => Some((timestamp::unix(top) - et).to_string()),
+                    _ => self.positioning_delay.filter(|lt| *lt < 0).map(|lt| lt.to_string()),
+                };
+
+                attributes.push(("en", text(&self.emergency_number)));
+                attributes.push(("et", self.beginning_of_call.map(|et| et.to_string())));
+                attributes.push(("lo", location));
+                attributes.push(("lt", delay));
+                attributes.push(("lc", number(self.level_of_confidence)));
+                attributes.push(("lz", elevation));
+                attributes.push(("ls", text(&self.positioning_method)));
+                attributes.push(("ei", text(&self.imei)));
+                attributes.push(("nc", network_code(self.network_mcc, self.network_mnc)));
+                attributes.push(("hc", network_code(self.home_mcc, self.home_mnc)));
+                attributes.push(("lg", text(&self.languages)));
+            }
+            _ => return String::new(),
+        }
+
+        let mut text = format!(r#"A"ML={}"#, self.header.as_deref().unwrap_or_default());
+        for (key, value) in attributes.iter().filter_map(|(key, value)| value.as_ref().map(|value| (key, value))) {
+            text.push_str(&format!(";{}={}", key, value));
+        }
+
+        if self.header.as_deref() == Some("1") {
+            // The length includes its own digits
+            text.push_str(";ml=");
+            let mut length = text.len() + 1;
+            while text.len() + length.to_string().len() != length {
+                length += 1;
+            }
+            text.push_str(&length.to_string());
+        }
+        text
+    }
+}
+
+impl AmlData {
+    /// The data as a v2 SMS, for downstream systems which only accept SMS. The second
+    /// value lists the fields of `self` which cannot be represented in a v2 SMS and
+    /// have been dropped: `source_of_activation`, `floor`, `bearing`, `speed`,
+    /// `ellipse`, `device_number`, `model`, `imsi`, `iccid`, `ground_truth`, `test`, `adr`,
+    /// `address` and `extras`, `time_of_positioning` without a beginning of call, a
+    /// `positioning_method` which has no SMS letter and the `emergency_number`, `imei`
+    /// or `languages` containing `;` or `=`.
+    /// Timestamps are truncated to the second. The text is given by [`SmsData::to_text`].
+    /// See [`AmlData::convert`] for the degraded values too.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::AmlData;
+    ///
+    /// let https = "v=1&emergency_number=112&time=1604912121000&location_latitude=48.85&location_longitude=2.35&location_time=1604912130000&location_accuracy=12&location_source=gps&location_speed=3";
+    /// let (sms, dropped) = AmlData::from_https(https).unwrap().to_sms_v2();
+    ///
+    /// assert_eq!(sms.to_text(), r#"A"ML=2;en=112;et=1604912121;lo=48.85,2.35,12;lt=9;ls=G"#);
+    /// assert_eq!(dropped, ["speed"]);
+    /// ```
+    pub fn to_sms_v2(&self) -> (SmsData, Vec<&'static str>) {
+        let (sms, loss) = self.to_sms("2");
+        (sms, loss.dropped)
+    }
+
+    /// The data as a SMS of version `header` (`1` or `2`), with what has been lost.
+    pub(crate) fn to_sms(&self, header: &str) -> (SmsData, LossReport) {
+        let v1 = header == "1";
+        let mut loss = LossReport::default();
+        let unwritable = |value: &Option<String>| value.as_deref().is_some_and(|value| !is_attribute_value(value));
+        let text = |value: &Option<String>| value.clone().filter(|value| is_attribute_value(value));
+
+        loss.drop_if("emergency_number", (v1 && self.emergency_number.is_some()) || unwritable(&self.emergency_number));
+        loss.drop_if("source_of_activation", self.source_of_activation.is_some());
+        loss.drop_if("beginning_of_call", v1 && self.beginning_of_call.is_some());
+        loss.drop_if("time_of_positioning", !v1 && self.time_of_positioning.is_some() && self.beginning_of_call.is_none());
+        loss.drop_if("altitude", v1 && self.altitude.is_some());
+        loss.drop_if("floor", self.floor.is_some());
+        let method = self.method().map(|method| method.letter());
+        loss.drop_if("positioning_method", self.positioning_method.is_some() && method.is_none());
+        loss.drop_if("vertical_accuracy", v1 && self.vertical_accuracy.is_some());
+        loss.drop_if("ellipse", self.ellipse.is_some());
+        loss.drop_if("bearing", self.bearing.is_some());
+        loss.drop_if("speed", self.speed.is_some());
+        loss.drop_if("device_number", self.device_number.is_some());
+        loss.drop_if("model", self.model.is_some());
+        loss.drop_if("imsi", (!v1 && self.imsi.is_some()) || unwritable(&self.imsi));
+        loss.drop_if("imei", unwritable(&self.imei));
+        loss.drop_if("iccid", self.iccid.is_some());
+        loss.drop_if("home_mcc", v1 && self.home_mcc.is_some());
+        loss.drop_if("home_mnc", v1 && self.home_mnc.is_some());
+        loss.drop_if("languages", (v1 && self.languages.is_some()) || unwritable(&self.languages));
+        loss.drop_if("ground_truth", self.ground_truth.is_some());
+        loss.drop_if("test", self.test);
+        loss.drop_if("adr", self.adr.is_some());
+        loss.drop_if("address", self.address.is_some());
+        loss.drop_if("extras", !self.extras.is_empty());
+
+        let truncated = |value: Option<f64>, decimals| value.is_some_and(|value| truncate_decimals(value, decimals) != value);
+        let subsecond = |value: Option<Timestamp>| value.is_some_and(|value| timestamp::subsec_nanos(value) / 1_000_000 != 0);
+        loss.degrade_if("beginning_of_call", !v1 && subsecond(self.beginning_of_call));
+        loss.degrade_if("latitude", truncated(self.latitude, COORDINATE_DECIMALS));
+        loss.degrade_if("longitude", truncated(self.longitude, COORDINATE_DECIMALS));
+        loss.degrade_if("time_of_positioning", (v1 || self.beginning_of_call.is_some()) && subsecond(self.time_of_positioning));
+        loss.degrade_if("altitude", !v1 && truncated(self.altitude, ALTITUDE_DECIMALS));
+        loss.degrade_if("vertical_accuracy", !v1 && truncated(self.vertical_accuracy, ALTITUDE_DECIMALS));
+        // v1 has no fused method
+        loss.degrade_if("positioning_method", v1 && method == Some("F"));
+
+        let seconds = |value: Option<Timestamp>| value.and_then(|value| seconds_to_utc!(timestamp::unix(value)));
+        let mut sms = SmsData {
+            header: Some(header.to_string()),
+            latitude: self.latitude,
+            longitude: self.longitude,
+            accuracy: self.accuracy,
+            level_of_confidence: self.confidence,
+            imei: text(&self.imei),
+            network_mcc: self.network_mcc,
+            network_mnc: self.network_mnc,
+            ..Default::default()
+        };
+        if v1 {
+            sms.time_of_positioning = seconds(self.time_of_positioning);
+            sms.positioning_method = method.map(|method| if method == "F" { "U" } else { method }.to_string());
+            sms.imsi = text(&self.imsi);
+        } else {
+            sms.emergency_number = text(&self.emergency_number);
+            sms.beginning_of_call = self.beginning_of_call.map(timestamp::unix);
+            sms.time_of_positioning = seconds(self.time_of_positioning.filter(|_| self.beginning_of_call.is_some()));
+            sms.altitude = self.altitude;
+            sms.vertical_accuracy = self.vertical_accuracy;
+            sms.positioning_method = method.map(str::to_string);
+            sms.home_mcc = self.home_mcc;
+            sms.home_mnc = self.home_mnc;
+            sms.languages = text(&self.languages);
+        }
+        let text = sms.to_text();
+        sms.message_length = text.rsplit_once(";ml=").and_then(|(_, length)| length.parse().ok());
+        sms.validation = sms.validate(&text);
+
+        (sms, loss)
+    }
+}
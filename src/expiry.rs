@@ -0,0 +1,66 @@
+use crate::{timestamp, AmlData, Duration, Timestamp};
+
+/// Rules used to decide whether an AML location is too old to be trusted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpiryPolicy {
+    /// A location older than this is considered stale.
+    pub max_age: Duration,
+
+    /// Use the beginning of call when the time of positioning is unknown.
+    pub fallback_to_beginning_of_call: bool,
+}
+
+impl Default for ExpiryPolicy {
+    /// One hour, falling back to the beginning of call.
+    fn default() -> Self {
+        ExpiryPolicy {
+            max_age: Duration::hours(1),
+            fallback_to_beginning_of_call: true,
+        }
+    }
+}
+
+impl AmlData {
+    /// Age of the location at `now`, computed from `time_of_positioning` or,
+    /// if it is missing, from `beginning_of_call`.
+    /// A location timestamped in the future has a negative age.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{timestamp, AmlData, Duration};
+    ///
+    /// let aml = AmlData::from_text_sms(r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;ml=62"#).unwrap();
+    /// let now = timestamp::from_utc((2019, 11, 12), (11, 39, 28)).unwrap();
+    /// assert_eq!(aml.location_age(now), Some(Duration::minutes(10)));
+    /// ```
+    pub fn location_age(&self, now: Timestamp) -> Option<Duration> {
+        self.location_timestamp(true).map(|ts| now - ts)
+    }
+
+    /// True if the location is stale according to `policy`. See [`AmlData::is_expired_at`].
+    pub fn is_expired(&self, policy: &ExpiryPolicy) -> bool {
+        self.is_expired_at(timestamp::now(), policy)
+    }
+
+    /// True if the location is stale at `now` according to `policy`.
+    /// A message without location or without any usable timestamp is always expired.
+    pub fn is_expired_at(&self, now: Timestamp, policy: &ExpiryPolicy) -> bool {
+        if self.latitude.is_none() || self.longitude.is_none() {
+            return true;
+        }
+
+        match self.location_timestamp(policy.fallback_to_beginning_of_call) {
+            Some(ts) => now - ts > policy.max_age,
+            None => true,
+        }
+    }
+
+    fn location_timestamp(&self, fallback: bool) -> Option<Timestamp> {
+        match (self.time_of_positioning, fallback) {
+            (Some(ts), _) => Some(ts),
+            (None, true) => self.beginning_of_call,
+            (None, false) => None,
+        }
+    }
+}
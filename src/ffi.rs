@@ -0,0 +1,204 @@
+//! C interface, declared in `include/aml_lib.h`.
+//!
+//! Strings returned by the library must be released with [`aml_string_free`] and
+//! records with [`aml_record_free`].
+//!
+//! The crate is only built as a Rust library, the C library is built on demand:
+//!
+//! ```text
+//! cargo rustc --release --features ffi --crate-type cdylib
+//! cargo rustc --release --features ffi --crate-type staticlib
+//! ```
+
+use crate::{batch, json::JsonValue, timestamp, AmlData, AmlError, HttpsData, Timestamp};
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+
+/// Detect the format of the payload. See [`batch::parse_line`].
+pub const AML_FORMAT_AUTO: c_int = 0;
+/// Text SMS.
+pub const AML_FORMAT_SMS_TEXT: c_int = 1;
+/// Binary SMS in hexadecimal.
+pub const AML_FORMAT_SMS_HEX: c_int = 2;
+/// Binary SMS in base64.
+pub const AML_FORMAT_SMS_BASE64: c_int = 3;
+/// Urlencoded HTTPS message.
+pub const AML_FORMAT_HTTPS: c_int = 4;
+
+/// Flat view of [`AmlData`]. Unknown values are `NaN` for numbers, `INT64_MIN` for
+/// timestamps (milliseconds since epoch), `-1` for network codes and `NULL` for strings.
+#[repr(C)]
+pub struct AmlRecord {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub accuracy: f64,
+    pub altitude: f64,
+    pub vertical_accuracy: f64,
+    pub confidence: f64,
+    pub bearing: f64,
+    pub speed: f64,
+    pub beginning_of_call_ms: i64,
+    pub time_of_positioning_ms: i64,
+    pub home_mcc: i32,
+    pub home_mnc: i32,
+    pub network_mcc: i32,
+    pub network_mnc: i32,
+    pub version: *mut c_char,
+    pub emergency_number: *mut c_char,
+    pub source_of_activation: *mut c_char,
+    pub floor: *mut c_char,
+    pub positioning_method: *mut c_char,
+    pub device_number: *mut c_char,
+    pub model: *mut c_char,
+    pub imsi: *mut c_char,
+    pub imei: *mut c_char,
+    pub iccid: *mut c_char,
+    pub languages: *mut c_char,
+    pub transport: *mut c_char,
+}
+
+impl From<&AmlData> for AmlRecord {
+    fn from(aml: &AmlData) -> Self {
+        let number = |v: Option<f64>| v.unwrap_or(f64::NAN);
+        let code = |v: Option<i32>| v.unwrap_or(-1);
+        let millis = |v: Option<Timestamp>| v.map_or(i64::MIN, timestamp::unix_millis);
+
+        AmlRecord {
+            latitude: number(aml.latitude),
+            longitude: number(aml.longitude),
+            accuracy: number(aml.accuracy),
+            altitude: number(aml.altitude),
+            vertical_accuracy: number(aml.vertical_accuracy),
+            confidence: number(aml.confidence),
+            bearing: number(aml.bearing),
+            speed: number(aml.speed),
+            beginning_of_call_ms: millis(aml.beginning_of_call),
+            time_of_positioning_ms: millis(aml.time_of_positioning),
+            home_mcc: code(aml.home_mcc),
+            home_mnc: code(aml.home_mnc),
+            network_mcc: code(aml.network_mcc),
+            network_mnc: code(aml.network_mnc),
+            version: c_string(aml.version.as_deref()),
+            emergency_number: c_string(aml.emergency_number.as_deref()),
+            source_of_activation: c_string(aml.source_of_activation.as_deref()),
+            floor: c_string(aml.floor.as_deref()),
+            positioning_method: c_string(aml.positioning_method.as_deref()),
+            device_number: c_string(aml.device_number.as_deref()),
+            model: c_string(aml.model.as_deref()),
+            imsi: c_string(aml.imsi.as_deref()),
+            imei: c_string(aml.imei.as_deref()),
+            iccid: c_string(aml.iccid.as_deref()),
+            languages: c_string(aml.languages.as_deref()),
+            transport: c_string(Some(&aml.transport)),
+        }
+    }
+}
+
+fn c_string(value: Option<&str>) -> *mut c_char {
+    value
+        .and_then(|v| CString::new(v).ok())
+        .map_or(ptr::null_mut(), CString::into_raw)
+}
+
+unsafe fn parse(payload: *const c_char, format: c_int) -> Result<AmlData, AmlError> {
+    if payload.is_null() {
+        return Err(AmlError::InvalidUtf8(None));
+    }
+    let payload = CStr::from_ptr(payload).to_str()?.trim();
+
+    match format {
+        AML_FORMAT_AUTO => batch::parse_line(payload),
+        AML_FORMAT_SMS_TEXT => AmlData::from_text_sms(payload),
+        AML_FORMAT_SMS_HEX => AmlData::from_hex_sms(payload),
+        AML_FORMAT_SMS_BASE64 => AmlData::from_base64_sms(payload),
+        AML_FORMAT_HTTPS => AmlData::from_https(payload),
+        _ => Err(AmlError::InvalidRecord),
+    }
+}
+
+/// Parse `payload` (a NUL terminated string) with the given `AML_FORMAT_*` and return
+/// a JSON document (see [`AmlData::to_json`]), or `{"error": "..."}` on failure.
+///
+/// # Safety
+///
+/// `payload` must be a valid NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn aml_parse_json(payload: *const c_char, format: c_int) -> *mut c_char {
+    let json = match parse(payload, format) {
+        Ok(aml) => aml.to_json(),
+        Err(err) => JsonValue::Object(vec![("error".to_string(), JsonValue::String(err.to_string()))]).to_string(),
+    };
+    c_string(Some(&json))
+}
+
+/// Parse `payload` with the given `AML_FORMAT_*` into a record, `NULL` on failure.
+///
+/// # Safety
+///
+/// `payload` must be a valid NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn aml_parse_record(payload: *const c_char, format: c_int) -> *mut AmlRecord {
+    match parse(payload, format) {
+        Ok(aml) => Box::into_raw(Box::new(AmlRecord::from(&aml))),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Verify the HMAC of a HTTPS message. Returns 1 if authenticated, 0 otherwise.
+/// See [`HttpsData::is_authenticated`].
+///
+/// # Safety
+///
+/// `payload` must be a valid NUL terminated string and `key` must point to `key_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn aml_https_is_authenticated(payload: *const c_char, key: *const u8, key_len: usize) -> c_int {
+    if payload.is_null() || key.is_null() {
+        return 0;
+    }
+    match CStr::from_ptr(payload).to_str() {
+        Ok(payload) => HttpsData::is_authenticated(payload, std::slice::from_raw_parts(key, key_len)) as c_int,
+        Err(_) => 0,
+    }
+}
+
+/// Release a string returned by the library.
+///
+/// # Safety
+///
+/// `s` must come from this library and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn aml_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Release a record returned by [`aml_parse_record`] with all its strings.
+///
+/// # Safety
+///
+/// `record` must come from [`aml_parse_record`] and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn aml_record_free(record: *mut AmlRecord) {
+    if record.is_null() {
+        return;
+    }
+    let record = Box::from_raw(record);
+    for s in [
+        record.version,
+        record.emergency_number,
+        record.source_of_activation,
+        record.floor,
+        record.positioning_method,
+        record.device_number,
+        record.model,
+        record.imsi,
+        record.imei,
+        record.iccid,
+        record.languages,
+        record.transport,
+    ] {
+        aml_string_free(s);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::AmlData;
+
+/// Confidence assumed for a report which does not give one.
+const DEFAULT_CONFIDENCE: f64 = 0.68;
+
+/// Confidence, in percent, of the accuracy of a fused location.
+const FUSED_CONFIDENCE: f64 = 68.0;
+
+/// Contribution of one report to a fused location.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FusionInput {
+    /// Index of the report in the slice given to [`AmlData::fuse`].
+    pub index: usize,
+
+    /// Transport of the report (`sms` or `https`).
+    pub transport: String,
+
+    /// Standard deviation of the position error in meters, derived from accuracy and confidence.
+    pub sigma: f64,
+
+    /// Normalized weight of the report (the weights sum to 1).
+    pub weight: f64,
+}
+
+/// Best estimate of the location from several reports of the same call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FusedLocation {
+    /// All the reports merged (see [`AmlData::merge`]) with the fused position,
+    /// accuracy and confidence.
+    pub data: AmlData,
+
+    /// The reports used for the position; reports without location or accuracy are ignored.
+    pub inputs: Vec<FusionInput>,
+}
+
+impl AmlData {
+    /// Fuse the locations of `reports` with an inverse-variance weighting.
+    /// Each accuracy is converted to a standard deviation using its confidence (68% when unknown).
+    /// The fused accuracy is given with a confidence of 68 percent.
+    /// Returns `None` if no report has a location with a known accuracy.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::AmlData;
+    ///
+    /// let sms = AmlData::from_text_sms(r#"A"ML=2;lo=48.80000,2.30000,30;lc=68"#).unwrap();
+    /// let https = AmlData::from_https("location_latitude=48.80010&location_longitude=2.30000&location_accuracy=30&location_confidence=0.68").unwrap();
+    ///
+    /// let fused = AmlData::fuse(&[sms, https]).unwrap();
+    /// assert!((fused.data.latitude.unwrap() - 48.80005).abs() < 1e-6);
+    /// assert!(fused.data.accuracy.unwrap() < 30.0);
+    /// ```
+    pub fn fuse(reports: &[AmlData]) -> Option<FusedLocation> {
+        let mut inputs = Vec::new();
+        let mut positions = Vec::new();
+
+        for (index, report) in reports.iter().enumerate() {
+            let (position, accuracy) = match (report.position(), report.accuracy) {
+                (Some(position), Some(accuracy)) if accuracy > 0.0 => (position, accuracy),
+                _ => continue,
+            };
+            let confidence = report.confidence_ratio().unwrap_or(DEFAULT_CONFIDENCE);
+            let sigma = accuracy / (-2.0 * (1.0 - confidence).ln()).sqrt();
+
+            positions.push(position);
+            inputs.push(FusionInput {
+                index,
+                transport: report.transport.clone(),
+                sigma,
+                weight: sigma.powi(-2),
+            });
+        }
+
+        if inputs.is_empty() {
+            return None;
+        }
+
+        let total: f64 = inputs.iter().map(|i| i.weight).sum();
+        let (mut lat, mut lon) = (0.0, 0.0);
+        // Averaging longitudes relative to the first one avoids the antimeridian wrap
+        let origin = positions[0].1;
+        for (input, position) in inputs.iter_mut().zip(&positions) {
+            input.weight /= total;
+            lat += input.weight * position.0;
+            lon += input.weight * ((position.1 - origin + 540.0) % 360.0 - 180.0);
+        }
+        let sigma = total.sqrt().recip();
+
+        let mut data = AmlData::default();
+        for report in reports {
+            data.merge(report.clone());
+        }
+        data.latitude = Some(lat);
+        data.longitude = Some((origin + lon + 540.0) % 360.0 - 180.0);
+        data.accuracy = Some(sigma * (-2.0 * (1.0 - FUSED_CONFIDENCE / 100.0_f64).ln()).sqrt());
+        data.confidence = Some(FUSED_CONFIDENCE);
+
+        Some(FusedLocation { data, inputs })
+    }
+}
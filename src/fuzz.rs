@@ -0,0 +1,246 @@
+//! Hostile inputs for fuzzing and property testing of the parsers.
+//!
+//! [`Corruptor`] derives malformed payloads from valid ones, deterministically from a seed,
+//! so that a failing case can be replayed. [`SmsData`], [`HttpsData`] and [`AmlData`]
+//! implement [`arbitrary::Arbitrary`] to build data of any shape from the bytes of a
+//! fuzzer, timestamps being whole milliseconds. The validation report of the SMS data
+//! is the default one and [`AmlData::extras`] are empty.
+//!
+//! # Example
+//!
+//! ```
+//! use aml_lib::{batch, fuzz::Corruptor};
+//!
+//! let mut corruptor = Corruptor::new(42);
+//! for _ in 0..100 {
+//!     let payload = corruptor.corrupt(br#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;ml=62"#);
+//!     // Parsers must return an error, never panic
+//!     let _ = batch::parse_line(&String::from_utf8_lossy(&payload));
+//! }
+//! ```
+//!
+//! ```
+//! use aml_lib::AmlData;
+//! use arbitrary::{Arbitrary, Unstructured};
+//!
+//! let bytes: Vec<u8> = (0..=255).cycle().take(4096).collect();
+//! let aml = AmlData::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+//! let _ = aml.to_json();
+//! ```
+
+use crate::geo::Ellipse;
+use crate::rng::Rng;
+use crate::{timestamp, AdrData, AmlData, CivicAddress, HttpsData, Profile, ReceiveContext, SmsData, Timestamp};
+use arbitrary::{Arbitrary, Unstructured};
+
+/// Bytes meaningful to the AML syntaxes, inserted to confuse the parsers.
+const SEPARATORS: &[u8] = b";=&,%+.-\"\n\0";
+
+/// Generator of corrupted payloads.
+#[derive(Debug, Clone)]
+pub struct Corruptor {
+    rng: Rng,
+
+    /// Most mutations applied to one payload (at least one is applied).
+    pub max_mutations: usize,
+}
+
+impl Corruptor {
+    pub fn new(seed: u64) -> Self {
+        Corruptor {
+            rng: Rng::new(seed),
+            max_mutations: 4,
+        }
+    }
+
+    /// A copy of `payload` with random mutations: flipped bits, AML separators or random
+    /// bytes inserted or substituted, ranges deleted or duplicated, truncation.
+    pub fn corrupt(&mut self, payload: &[u8]) -> Vec<u8> {
+        let mut out = payload.to_vec();
+        let mutations = 1 + self.rng.below(self.max_mutations.max(1));
+
+        for _ in 0..mutations {
+            let len = out.len();
+            let at = self.rng.below(len + 1);
+            match self.rng.below(7) {
+                0 if at < len => out[at] ^= 1 << self.rng.below(8),
+                1 if at < len => out[at] = SEPARATORS[self.rng.below(SEPARATORS.len())],
+                2 => out.insert(at, SEPARATORS[self.rng.below(SEPARATORS.len())]),
+                3 => out.insert(at, self.rng.next_u64() as u8),
+                4 if at < len => {
+                    let end = at + 1 + self.rng.below(len - at);
+                    out.drain(at..end);
+                }
+                5 if at < len => {
+                    let end = at + 1 + self.rng.below((len - at).min(16));
+                    let copy = out[at..end].to_vec();
+                    out.splice(at..at, copy);
+                }
+                6 => out.truncate(at),
+                _ => out.push(self.rng.next_u64() as u8),
+            }
+        }
+
+        out
+    }
+}
+
+/// A timestamp of whole milliseconds, `None` when out of the range of the date and time backend.
+fn timestamp(u: &mut Unstructured) -> arbitrary::Result<Option<Timestamp>> {
+    Ok(u.arbitrary::<Option<i64>>()?.and_then(timestamp::from_unix_millis))
+}
+
+impl<'a> Arbitrary<'a> for Profile {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(*u.choose(&[Profile::Generic, Profile::Els, Profile::Apple])?)
+    }
+}
+
+impl<'a> Arbitrary<'a> for SmsData {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(SmsData {
+            header: u.arbitrary()?,
+            emergency_number: u.arbitrary()?,
+            beginning_of_call: u.arbitrary()?,
+            latitude: u.arbitrary()?,
+            longitude: u.arbitrary()?,
+            accuracy: u.arbitrary()?,
+            time_of_positioning: timestamp(u)?,
+            positioning_delay: u.arbitrary()?,
+            level_of_confidence: u.arbitrary()?,
+            altitude: u.arbitrary()?,
+            vertical_accuracy: u.arbitrary()?,
+            positioning_method: u.arbitrary()?,
+            imsi: u.arbitrary()?,
+            imei: u.arbitrary()?,
+            network_mcc: u.arbitrary()?,
+            network_mnc: u.arbitrary()?,
+            home_mcc: u.arbitrary()?,
+            home_mnc: u.arbitrary()?,
+            languages: u.arbitrary()?,
+            message_length: u.arbitrary()?,
+            originating_address: u.arbitrary()?,
+            validation: Default::default(),
+            profile: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for HttpsData {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(HttpsData {
+            v: u.arbitrary()?,
+            emergency_number: u.arbitrary()?,
+            source: u.arbitrary()?,
+            thunderbird_version: u.arbitrary()?,
+            time: u.arbitrary()?,
+            gt_location_latitude: u.arbitrary()?,
+            gt_location_longitude: u.arbitrary()?,
+            test: u.arbitrary()?,
+            location_latitude: u.arbitrary()?,
+            location_longitude: u.arbitrary()?,
+            location_time: timestamp(u)?,
+            location_altitude: u.arbitrary()?,
+            location_floor: u.arbitrary()?,
+            location_source: u.arbitrary()?,
+            location_accuracy: u.arbitrary()?,
+            location_vertical_accuracy: u.arbitrary()?,
+            location_semi_major_axis: u.arbitrary()?,
+            location_semi_minor_axis: u.arbitrary()?,
+            location_orientation: u.arbitrary()?,
+            location_confidence: u.arbitrary()?,
+            location_certainty: u.arbitrary()?,
+            location_bearing: u.arbitrary()?,
+            location_speed: u.arbitrary()?,
+            device_number: u.arbitrary()?,
+            device_model: u.arbitrary()?,
+            device_imsi: u.arbitrary()?,
+            device_imei: u.arbitrary()?,
+            device_iccid: u.arbitrary()?,
+            cell_home_mcc: u.arbitrary()?,
+            cell_home_mnc: u.arbitrary()?,
+            cell_network_mcc: u.arbitrary()?,
+            cell_network_mnc: u.arbitrary()?,
+            device_languages: u.arbitrary()?,
+            adr_carcrash_time: timestamp(u)?,
+            adr_severity: u.arbitrary()?,
+            adr_rollover: u.arbitrary()?,
+            adr_impact_direction: u.arbitrary()?,
+            adr_occupants: u.arbitrary()?,
+            hmac: u.arbitrary()?,
+            profile: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for AmlData {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(AmlData {
+            version: u.arbitrary()?,
+            emergency_number: u.arbitrary()?,
+            source_of_activation: u.arbitrary()?,
+            beginning_of_call: timestamp(u)?,
+            latitude: u.arbitrary()?,
+            longitude: u.arbitrary()?,
+            time_of_positioning: timestamp(u)?,
+            altitude: u.arbitrary()?,
+            floor: u.arbitrary()?,
+            positioning_method: u.arbitrary()?,
+            accuracy: u.arbitrary()?,
+            vertical_accuracy: u.arbitrary()?,
+            ellipse: u
+                .arbitrary::<Option<(f64, f64, f64)>>()?
+                .map(|(semi_major, semi_minor, orientation)| Ellipse { semi_major, semi_minor, orientation }),
+            confidence: u.arbitrary()?,
+            bearing: u.arbitrary()?,
+            speed: u.arbitrary()?,
+            device_number: u.arbitrary()?,
+            model: u.arbitrary()?,
+            imsi: u.arbitrary()?,
+            imei: u.arbitrary()?,
+            iccid: u.arbitrary()?,
+            home_mcc: u.arbitrary()?,
+            home_mnc: u.arbitrary()?,
+            network_mcc: u.arbitrary()?,
+            network_mnc: u.arbitrary()?,
+            languages: u.arbitrary()?,
+            ground_truth: u.arbitrary()?,
+            test: u.arbitrary()?,
+            adr: match u.arbitrary()? {
+                true => Some(AdrData {
+                    carcrash_time: timestamp(u)?,
+                    severity: u.arbitrary()?,
+                    rollover: u.arbitrary()?,
+                    impact_direction: u.arbitrary()?,
+                    occupants: u.arbitrary()?,
+                }),
+                false => None,
+            },
+            transport: u.choose(&["sms", "https"])?.to_string(),
+            via: u.arbitrary()?,
+            address: match u.arbitrary()? {
+                true => Some(CivicAddress {
+                    country: u.arbitrary()?,
+                    region: u.arbitrary()?,
+                    city: u.arbitrary()?,
+                    postal_code: u.arbitrary()?,
+                    street: u.arbitrary()?,
+                    house_number: u.arbitrary()?,
+                    formatted: u.arbitrary()?,
+                }),
+                false => None,
+            },
+            profile: u.arbitrary()?,
+            context: match u.arbitrary()? {
+                true => Some(ReceiveContext {
+                    received_at: timestamp(u)?,
+                    source_msisdn: u.arbitrary()?,
+                    gateway_id: u.arbitrary()?,
+                    remote_ip: u.arbitrary()?,
+                }),
+                false => None,
+            },
+            extras: Vec::new(),
+        })
+    }
+}
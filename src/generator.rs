@@ -0,0 +1,229 @@
+//! Realistic synthetic AML messages, to load-test an ingestion chain without real
+//! subscriber data.
+//!
+//! The messages are syntactically valid (they pass [`conformance`](crate::conformance))
+//! and are generated deterministically from a seed.
+//!
+//! # Example
+//!
+//! ```
+//! use aml_lib::generator::{Generator, GeneratorConfig};
+//! use aml_lib::AmlData;
+//!
+//! let mut generator = Generator::new(GeneratorConfig::default(), 1);
+//! let sms = generator.sms_v1();
+//! assert!(AmlData::from_text_sms(&sms).unwrap().latitude.is_some());
+//!
+//! let https = generator.https(Some(b"AML"));
+//! assert!(aml_lib::HttpsData::is_authenticated(&https, b"AML"));
+//! ```
+
+use crate::hmac::hmac_sha1;
+use crate::precision::{truncate_decimals, COORDINATE_DECIMALS};
+use crate::rng::Rng;
+use crate::{timestamp, Duration, Timestamp};
+
+/// Parameter ranges of the generated messages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratorConfig {
+    /// Region of the locations: south, west, north, east in degrees.
+    pub bounding_box: (f64, f64, f64, f64),
+
+    /// Smallest and largest accuracy in meters. Accuracies are log-uniform, so that
+    /// GNSS-like and cell-like values are both frequent.
+    pub accuracy: (f64, f64),
+
+    /// Network codes (MCC, MNC) the devices are attached to.
+    pub operators: Vec<(i32, i32)>,
+
+    /// Emergency numbers dialed.
+    pub emergency_numbers: Vec<String>,
+
+    /// Earliest beginning of call.
+    pub start: Timestamp,
+
+    /// Calls begin between `start` and `start + period`.
+    pub period: Duration,
+
+    /// Largest delay between the beginning of call and the time of positioning.
+    pub max_positioning_delay: Duration,
+}
+
+impl Default for GeneratorConfig {
+    /// Europe, 3 m to 2 km, a few European operators, 112, during 2021.
+    fn default() -> Self {
+        GeneratorConfig {
+            bounding_box: (36.0, -9.0, 60.0, 25.0),
+            accuracy: (3.0, 2000.0),
+            operators: vec![(208, 1), (208, 20), (234, 15), (262, 1), (222, 10)],
+            emergency_numbers: vec!["112".to_string()],
+            start: timestamp::from_unix(1609459200).unwrap(), // 2021-01-01T00:00:00Z
+            period: Duration::days(365),
+            max_positioning_delay: Duration::seconds(30),
+        }
+    }
+}
+
+/// Generator of AML messages.
+#[derive(Debug, Clone)]
+pub struct Generator {
+    pub config: GeneratorConfig,
+    rng: Rng,
+}
+
+/// Values shared by all the formats.
+struct Sample {
+    emergency_number: String,
+    beginning_of_call: Timestamp,
+    time_of_positioning: Timestamp,
+    latitude: f64,
+    longitude: f64,
+    accuracy: f64,
+    method: usize,
+    imsi: String,
+    imei: String,
+    mcc: i32,
+    mnc: i32,
+}
+
+/// Positioning methods: SMS v1, SMS v2 and HTTPS names.
+const METHODS: &[(&str, &str, &str)] = &[("G", "G", "gps"), ("W", "W", "wifi"), ("C", "C", "cell"), ("U", "F", "fused")];
+
+impl Generator {
+    pub fn new(config: GeneratorConfig, seed: u64) -> Self {
+        Generator {
+            config,
+            rng: Rng::new(seed),
+        }
+    }
+
+    fn sample(&mut self) -> Sample {
+        let config = &self.config;
+        let rng = &mut self.rng;
+        let (south, west, north, east) = config.bounding_box;
+        let (min_accuracy, max_accuracy) = config.accuracy;
+
+        let (mcc, mnc) = match config.operators.len() {
+            0 => (1, 1),
+            n => config.operators[rng.below(n)],
+        };
+        let emergency_number = match config.emergency_numbers.len() {
+            0 => "112".to_string(),
+            n => config.emergency_numbers[rng.below(n)].clone(),
+        };
+
+        let beginning_of_call =
+            config.start + Duration::seconds((rng.unit() * timestamp::whole_seconds(config.period) as f64) as i64);
+        let time_of_positioning = beginning_of_call
+            + Duration::seconds((rng.unit() * timestamp::whole_seconds(config.max_positioning_delay) as f64) as i64);
+
+        let accuracy = (min_accuracy.max(1.0).ln() + rng.unit() * (max_accuracy.ln() - min_accuracy.max(1.0).ln())).exp();
+        let method = match accuracy {
+            a if a < 20.0 => 0,
+            a if a < 100.0 => 1 + rng.below(2) * 2,
+            _ => 2,
+        };
+
+        let mut imsi = format!("{:03}{:02}", mcc, mnc);
+        while imsi.len() < 15 {
+            imsi.push(char::from(b'0' + rng.below(10) as u8));
+        }
+
+        Sample {
+            emergency_number,
+            beginning_of_call,
+            time_of_positioning,
+            latitude: truncate_decimals(rng.range(south, north), COORDINATE_DECIMALS),
+            longitude: truncate_decimals(rng.range(west, east), COORDINATE_DECIMALS),
+            accuracy,
+            method,
+            imsi,
+            imei: imei(rng),
+            mcc,
+            mnc,
+        }
+    }
+
+    /// A v1 text SMS, with the right `ml`.
+    pub fn sms_v1(&mut self) -> String {
+        let s = self.sample();
+        let body = format!(
+            r#"A"ML=1;lt={:.5};lg={:.5};rd={:.0};top={};lc=68;pm={};si={};ei={};mcc={:03};mnc={:02};ml="#,
+            s.latitude,
+            s.longitude,
+            s.accuracy,
+            timestamp::format_compact(s.time_of_positioning),
+            METHODS[s.method].0,
+            s.imsi,
+            s.imei,
+            s.mcc,
+            s.mnc,
+        );
+
+        // The length includes its own digits
+        let mut length = body.len() + 1;
+        while body.len() + length.to_string().len() != length {
+            length += 1;
+        }
+        format!("{}{}", body, length)
+    }
+
+    /// A v2 text SMS.
+    pub fn sms_v2(&mut self) -> String {
+        let s = self.sample();
+        format!(
+            r#"A"ML=2;en={};et={};lo={:.5},{:.5},{:.1};lt={};lc=68;ls={};ei={};nc={:03}{:02};hc={:03}{:02};lg=en"#,
+            s.emergency_number,
+            timestamp::unix(s.beginning_of_call),
+            s.latitude,
+            s.longitude,
+            s.accuracy,
+            timestamp::whole_seconds(s.time_of_positioning - s.beginning_of_call),
+            METHODS[s.method].1,
+            s.imei,
+            s.mcc,
+            s.mnc,
+            s.mcc,
+            s.mnc,
+        )
+    }
+
+    /// A urlencoded HTTPS body, ending with its HMAC when `key` is given.
+    pub fn https(&mut self, key: Option<&[u8]>) -> String {
+        let s = self.sample();
+        let body = format!(
+            "v=1&emergency_number={}&source=call&time={}&location_latitude={:.5}&location_longitude={:.5}&location_time={}&location_accuracy={:.1}&location_source={}&location_confidence=68&device_imsi={}&device_imei={}&cell_home_mcc={:03}&cell_home_mnc={:02}&cell_network_mcc={:03}&cell_network_mnc={:02}",
+            s.emergency_number,
+            timestamp::unix_millis(s.beginning_of_call),
+            s.latitude,
+            s.longitude,
+            timestamp::unix_millis(s.time_of_positioning),
+            s.accuracy,
+            METHODS[s.method].2,
+            s.imsi,
+            s.imei,
+            s.mcc,
+            s.mnc,
+            s.mcc,
+            s.mnc,
+        );
+
+        match key {
+            Some(key) => format!("{}&hmac={}", body, hex::encode(hmac_sha1(key, body.as_bytes()))),
+            None => body,
+        }
+    }
+}
+
+/// A 15 digit IMEI with a valid Luhn check digit.
+fn imei(rng: &mut Rng) -> String {
+    let digits: Vec<u32> = (0..14).map(|_| rng.below(10) as u32).collect();
+    let sum: u32 = digits
+        .iter()
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 1 { (d * 2) / 10 + (d * 2) % 10 } else { *d })
+        .sum();
+    let check = (10 - sum % 10) % 10;
+
+    digits.iter().chain(std::iter::once(&check)).map(|d| char::from(b'0' + *d as u8)).collect()
+}
@@ -0,0 +1,589 @@
+//! Geodesic helpers working on WGS84 `(latitude, longitude)` pairs in degrees.
+
+use crate::{AmlData, HttpsData};
+
+/// Mean Earth radius in meters (IUGG).
+pub const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// WGS84 semi-major axis in meters.
+pub const WGS84_A: f64 = 6_378_137.0;
+
+/// WGS84 flattening.
+pub const WGS84_F: f64 = 1.0 / 298.257_223_563;
+
+/// Great-circle distance in meters using the haversine formula (spherical Earth).
+///
+/// # Example
+///
+/// ```
+/// use aml_lib::geo;
+///
+/// let d = geo::haversine_distance((48.85341, 2.3488), (51.50853, -0.12574));
+/// assert!((d - 343_923.0).abs() < 500.0);
+/// ```
+pub fn haversine_distance(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat1, lat2) = (from.0.to_radians(), to.0.to_radians());
+    let d_lat = lat2 - lat1;
+    let d_lon = (to.1 - from.1).to_radians();
+
+    let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * a.sqrt().atan2((1.0 - a).sqrt())
+}
+
+/// Geodesic distance in meters on the WGS84 ellipsoid using Vincenty's inverse formula.
+/// Returns `None` when the iteration does not converge (nearly antipodal points).
+pub fn vincenty_distance(from: (f64, f64), to: (f64, f64)) -> Option<f64> {
+    let b = WGS84_A * (1.0 - WGS84_F);
+    let l = (to.1 - from.1).to_radians();
+    let u1 = ((1.0 - WGS84_F) * from.0.to_radians().tan()).atan();
+    let u2 = ((1.0 - WGS84_F) * to.0.to_radians().tan()).atan();
+    let (sin_u1, cos_u1) = u1.sin_cos();
+    let (sin_u2, cos_u2) = u2.sin_cos();
+
+    let mut lambda = l;
+    for _ in 0..200 {
+        let (sin_lambda, cos_lambda) = lambda.sin_cos();
+        let sin_sigma = ((cos_u2 * sin_lambda).powi(2)
+            + (cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda).powi(2))
+        .sqrt();
+        if sin_sigma == 0.0 {
+            // Coincident points
+            return Some(0.0);
+        }
+        let cos_sigma = sin_u1 * sin_u2 + cos_u1 * cos_u2 * cos_lambda;
+        let sigma = sin_sigma.atan2(cos_sigma);
+        let sin_alpha = cos_u1 * cos_u2 * sin_lambda / sin_sigma;
+        let cos_sq_alpha = 1.0 - sin_alpha * sin_alpha;
+        let cos_2sigma_m = if cos_sq_alpha == 0.0 {
+            // Equatorial line
+            0.0
+        } else {
+            cos_sigma - 2.0 * sin_u1 * sin_u2 / cos_sq_alpha
+        };
+        let c = WGS84_F / 16.0 * cos_sq_alpha * (4.0 + WGS84_F * (4.0 - 3.0 * cos_sq_alpha));
+        let previous = lambda;
+        lambda = l
+            + (1.0 - c)
+                * WGS84_F
+                * sin_alpha
+                * (sigma
+                    + c * sin_sigma
+                        * (cos_2sigma_m + c * cos_sigma * (-1.0 + 2.0 * cos_2sigma_m.powi(2))));
+
+        if (lambda - previous).abs() < 1e-12 {
+            let u_sq = cos_sq_alpha * (WGS84_A.powi(2) - b.powi(2)) / b.powi(2);
+            let big_a =
+                1.0 + u_sq / 16384.0 * (4096.0 + u_sq * (-768.0 + u_sq * (320.0 - 175.0 * u_sq)));
+            let big_b = u_sq / 1024.0 * (256.0 + u_sq * (-128.0 + u_sq * (74.0 - 47.0 * u_sq)));
+            let delta_sigma = big_b
+                * sin_sigma
+                * (cos_2sigma_m
+                    + big_b / 4.0
+                        * (cos_sigma * (-1.0 + 2.0 * cos_2sigma_m.powi(2))
+                            - big_b / 6.0
+                                * cos_2sigma_m
+                                * (-3.0 + 4.0 * sin_sigma.powi(2))
+                                * (-3.0 + 4.0 * cos_2sigma_m.powi(2))));
+            return Some(b * big_a * (sigma - delta_sigma));
+        }
+    }
+
+    None
+}
+
+/// A ring of `(latitude, longitude)` vertices. Rings built by this module are closed:
+/// the last vertex repeats the first one.
+pub type Polygon = Vec<(f64, f64)>;
+
+/// The point reached by travelling `distance` meters from `from` with the initial
+/// `bearing` in degrees (spherical Earth).
+pub fn destination(from: (f64, f64), bearing: f64, distance: f64) -> (f64, f64) {
+    let (lat1, lon1) = (from.0.to_radians(), from.1.to_radians());
+    let (theta, delta) = (bearing.to_radians(), distance / EARTH_RADIUS);
+
+    let lat2 = (lat1.sin() * delta.cos() + lat1.cos() * delta.sin() * theta.cos()).asin();
+    let lon2 = lon1
+        + (theta.sin() * delta.sin() * lat1.cos()).atan2(delta.cos() - lat1.sin() * lat2.sin());
+    (lat2.to_degrees(), (lon2.to_degrees() + 540.0) % 360.0 - 180.0)
+}
+
+/// Rescale a radius given at `from_confidence` to `to_confidence`, assuming a circular
+/// normal distribution of the error. Confidences are ratios in ]0, 1[; `None` if one
+/// of them is not, or if the radius is not a finite positive number.
+///
+/// # Example
+///
+/// ```
+/// use aml_lib::geo;
+///
+/// let r95 = geo::scale_radius(10.0, 0.68, 0.95).unwrap();
+/// assert!((r95 - 16.2).abs() < 0.1);
+/// assert_eq!(geo::scale_radius(10.0, 0.68, 1.0), None);
+/// ```
+pub fn scale_radius(radius: f64, from_confidence: f64, to_confidence: f64) -> Option<f64> {
+    let ratio = |p: f64| p > 0.0 && p < 1.0;
+    if !(radius.is_finite() && radius >= 0.0 && ratio(from_confidence) && ratio(to_confidence)) {
+        return None;
+    }
+    let k = |p: f64| (-2.0 * (1.0 - p).ln()).sqrt();
+    Some(radius * k(to_confidence) / k(from_confidence))
+}
+
+/// Closed polygon of `points` vertices approximating the circle of `radius` meters around `center`.
+pub fn circle_polygon(center: (f64, f64), radius: f64, points: usize) -> Polygon {
+    let points = points.max(3);
+    let mut ring: Polygon = (0..points)
+        .map(|i| destination(center, 360.0 * i as f64 / points as f64, radius))
+        .collect();
+    ring.push(ring[0]);
+    ring
+}
+
+/// Uncertainty ellipse around a location, as sent by some ELS handsets instead of a
+/// single radius.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct Ellipse {
+    /// Semi-major axis in meters.
+    pub semi_major: f64,
+
+    /// Semi-minor axis in meters.
+    pub semi_minor: f64,
+
+    /// Orientation of the semi-major axis in degrees clockwise from true north.
+    pub orientation: f64,
+}
+
+impl Ellipse {
+    /// The ellipse with both axes multiplied by `factor`.
+    pub fn scaled(self, factor: f64) -> Self {
+        Ellipse {
+            semi_major: self.semi_major * factor,
+            semi_minor: self.semi_minor * factor,
+            ..self
+        }
+    }
+}
+
+/// Closed polygon of `points` vertices approximating `ellipse` around `center`.
+///
+/// # Example
+///
+/// ```
+/// use aml_lib::geo::{ellipse_polygon, haversine_distance, Ellipse};
+///
+/// let ellipse = Ellipse { semi_major: 100.0, semi_minor: 20.0, orientation: 90.0 };
+/// let polygon = ellipse_polygon((48.85, 2.35), &ellipse, 4);
+/// // The first vertex is on the semi-major axis, due east
+/// assert!((haversine_distance((48.85, 2.35), polygon[0]) - 100.0).abs() < 0.01);
+/// assert!(polygon[0].1 > 2.35);
+/// ```
+pub fn ellipse_polygon(center: (f64, f64), ellipse: &Ellipse, points: usize) -> Polygon {
+    let points = points.max(3);
+    let mut ring: Polygon = (0..points)
+        .map(|i| {
+            let t = 2.0 * std::f64::consts::PI * i as f64 / points as f64;
+            let (along, across) = (ellipse.semi_major * t.cos(), ellipse.semi_minor * t.sin());
+            let bearing = ellipse.orientation + across.atan2(along).to_degrees();
+            destination(center, bearing, along.hypot(across))
+        })
+        .collect();
+    ring.push(ring[0]);
+    ring
+}
+
+/// Position of a location, with its uncertainty, relative to a polygon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Containment {
+    /// The whole uncertainty circle is inside the polygon.
+    Within,
+
+    /// The uncertainty circle crosses the boundary of the polygon.
+    /// The reported position itself may be inside or outside.
+    Partial { center_inside: bool },
+
+    /// The whole uncertainty circle is outside the polygon.
+    Outside,
+}
+
+/// True if `point` is inside `polygon` (ray casting, the ring may be open or closed).
+pub fn contains(polygon: &[(f64, f64)], point: (f64, f64)) -> bool {
+    let mut inside = false;
+    let mut j = polygon.len().wrapping_sub(1);
+
+    for (i, &(lat_i, lon_i)) in polygon.iter().enumerate() {
+        let (lat_j, lon_j) = polygon[j];
+        if (lat_i > point.0) != (lat_j > point.0)
+            && point.1 < (lon_j - lon_i) * (point.0 - lat_i) / (lat_j - lat_i) + lon_i
+        {
+            inside = !inside;
+        }
+        j = i;
+    }
+
+    inside
+}
+
+/// Shortest distance in meters from `point` to the boundary of `polygon`.
+/// Uses a local equirectangular projection, accurate for PSAP-sized areas.
+pub fn distance_to_boundary(polygon: &[(f64, f64)], point: (f64, f64)) -> f64 {
+    let scale_lat = EARTH_RADIUS.to_radians();
+    let scale_lon = scale_lat * point.0.to_radians().cos();
+    let project = |(lat, lon): (f64, f64)| ((lon - point.1) * scale_lon, (lat - point.0) * scale_lat);
+
+    let mut min = f64::INFINITY;
+    for i in 0..polygon.len() {
+        let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+        let ((x1, y1), (x2, y2)) = (project(a), project(b));
+        let (dx, dy) = (x2 - x1, y2 - y1);
+        let len_sq = dx * dx + dy * dy;
+        let t = if len_sq == 0.0 {
+            0.0
+        } else {
+            (-(x1 * dx + y1 * dy) / len_sq).clamp(0.0, 1.0)
+        };
+        min = min.min((x1 + t * dx).hypot(y1 + t * dy));
+    }
+
+    min
+}
+
+/// Universal Transverse Mercator coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Utm {
+    /// Longitudinal zone (1 to 60).
+    pub zone: u8,
+
+    /// Latitude band letter (`C` to `X`, without `I` and `O`).
+    pub band: char,
+
+    /// Easting in meters.
+    pub easting: f64,
+
+    /// Northing in meters (with a false northing of 10,000 km in the southern hemisphere).
+    pub northing: f64,
+}
+
+impl std::fmt::Display for Utm {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}{} {:.0} {:.0}", self.zone, self.band, self.easting.floor(), self.northing.floor())
+    }
+}
+
+const UTM_BANDS: &[u8] = b"CDEFGHJKLMNPQRSTUVWXX";
+const MGRS_COLUMNS: [&[u8]; 3] = [b"STUVWXYZ", b"ABCDEFGH", b"JKLMNPQR"];
+const MGRS_ROWS: &[u8] = b"ABCDEFGHJKLMNPQRSTUV";
+
+/// Convert a WGS84 position to UTM. Returns `None` outside of the UTM latitude
+/// range [-80, 84] (polar regions use UPS).
+pub fn to_utm(position: (f64, f64)) -> Option<Utm> {
+    let (lat, lon) = position;
+    if !(-80.0..=84.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return None;
+    }
+
+    let mut zone = (((lon + 180.0) / 6.0).floor() as u8).min(59) + 1;
+    // Norway and Svalbard exceptions
+    if (56.0..64.0).contains(&lat) && (3.0..12.0).contains(&lon) {
+        zone = 32;
+    } else if (72.0..=84.0).contains(&lat) && (0.0..42.0).contains(&lon) {
+        zone = match lon {
+            l if l < 9.0 => 31,
+            l if l < 21.0 => 33,
+            l if l < 33.0 => 35,
+            _ => 37,
+        };
+    }
+    let band = UTM_BANDS[(((lat + 80.0) / 8.0).floor() as usize).min(UTM_BANDS.len() - 1)] as char;
+
+    let k0 = 0.9996;
+    let e2 = WGS84_F * (2.0 - WGS84_F);
+    let ep2 = e2 / (1.0 - e2);
+    let phi = lat.to_radians();
+    let lon0 = (f64::from(zone) - 1.0) * 6.0 - 180.0 + 3.0;
+
+    let n = WGS84_A / (1.0 - e2 * phi.sin().powi(2)).sqrt();
+    let t = phi.tan().powi(2);
+    let c = ep2 * phi.cos().powi(2);
+    let a = phi.cos() * (lon - lon0).to_radians();
+    let m = WGS84_A
+        * ((1.0 - e2 / 4.0 - 3.0 * e2.powi(2) / 64.0 - 5.0 * e2.powi(3) / 256.0) * phi
+            - (3.0 * e2 / 8.0 + 3.0 * e2.powi(2) / 32.0 + 45.0 * e2.powi(3) / 1024.0) * (2.0 * phi).sin()
+            + (15.0 * e2.powi(2) / 256.0 + 45.0 * e2.powi(3) / 1024.0) * (4.0 * phi).sin()
+            - (35.0 * e2.powi(3) / 3072.0) * (6.0 * phi).sin());
+
+    let easting = k0
+        * n
+        * (a + (1.0 - t + c) * a.powi(3) / 6.0
+            + (5.0 - 18.0 * t + t * t + 72.0 * c - 58.0 * ep2) * a.powi(5) / 120.0)
+        + 500_000.0;
+    let mut northing = k0
+        * (m + n
+            * phi.tan()
+            * (a * a / 2.0
+                + (5.0 - t + 9.0 * c + 4.0 * c * c) * a.powi(4) / 24.0
+                + (61.0 - 58.0 * t + t * t + 600.0 * c - 330.0 * ep2) * a.powi(6) / 720.0));
+    if lat < 0.0 {
+        northing += 10_000_000.0;
+    }
+
+    Some(Utm { zone, band, easting, northing })
+}
+
+/// Convert a WGS84 position to a MGRS string such as `31U DQ 48251 11943`.
+/// `precision` is the number of digits of easting and northing, from 1 (10 km) to 5 (1 m).
+///
+/// # Example
+///
+/// ```
+/// use aml_lib::geo;
+///
+/// assert_eq!(geo::to_mgrs((48.8583, 2.2945), 3).unwrap(), "31U DQ 482 119");
+/// ```
+pub fn to_mgrs(position: (f64, f64), precision: usize) -> Option<String> {
+    let utm = to_utm(position)?;
+    let precision = precision.clamp(1, 5);
+
+    let column = MGRS_COLUMNS[usize::from(utm.zone % 3)]
+        [((utm.easting / 100_000.0).floor() as usize).clamp(1, 8) - 1] as char;
+    let mut row_index = (utm.northing / 100_000.0).floor() as usize % 20;
+    if utm.zone % 2 == 0 {
+        row_index = (row_index + 5) % 20;
+    }
+    let row = MGRS_ROWS[row_index] as char;
+
+    let divisor = 10_f64.powi(5 - precision as i32);
+    let easting = ((utm.easting % 100_000.0) / divisor).floor() as u32;
+    let northing = ((utm.northing % 100_000.0) / divisor).floor() as u32;
+
+    Some(format!(
+        "{}{} {}{} {:0width$} {:0width$}",
+        utm.zone,
+        utm.band,
+        column,
+        row,
+        easting,
+        northing,
+        width = precision
+    ))
+}
+
+const GEOHASH_BASE32: &[u8] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// Approximate width in meters of a geohash cell at the equator, by hash length.
+const GEOHASH_CELL_WIDTH: [f64; 12] = [
+    5_009_400.0, 1_252_300.0, 156_500.0, 39_100.0, 4_890.0, 1_220.0, 152.9, 38.2, 4.77, 1.19, 0.149, 0.037,
+];
+
+/// Geohash of `position` with `precision` characters (1 to 12).
+///
+/// # Example
+///
+/// ```
+/// use aml_lib::geo;
+///
+/// assert_eq!(geo::geohash((57.64911, 10.40744), 11), "u4pruydqqvj");
+/// ```
+pub fn geohash(position: (f64, f64), precision: usize) -> String {
+    let precision = precision.clamp(1, GEOHASH_CELL_WIDTH.len());
+    let (mut lat_range, mut lon_range) = ((-90.0, 90.0), (-180.0, 180.0));
+    let mut hash = String::with_capacity(precision);
+    let (mut bits, mut value, mut even) = (0, 0_usize, true);
+
+    while hash.len() < precision {
+        let (range, coordinate) = if even {
+            (&mut lon_range, position.1)
+        } else {
+            (&mut lat_range, position.0)
+        };
+        let middle = (range.0 + range.1) / 2.0;
+        value <<= 1;
+        if coordinate >= middle {
+            value |= 1;
+            range.0 = middle;
+        } else {
+            range.1 = middle;
+        }
+        even = !even;
+        bits += 1;
+
+        if bits == 5 {
+            hash.push(GEOHASH_BASE32[value] as char);
+            bits = 0;
+            value = 0;
+        }
+    }
+
+    hash
+}
+
+/// Longest geohash length whose cells are not smaller than `accuracy` meters,
+/// so that the hash does not claim more precision than the location has.
+/// An unknown accuracy (`None` or 0) gives 9 characters (about 5 m).
+pub fn geohash_precision(accuracy: Option<f64>) -> usize {
+    match accuracy {
+        Some(acc) if acc > 0.0 => GEOHASH_CELL_WIDTH
+            .iter()
+            .rposition(|width| *width >= acc)
+            .map_or(1, |i| i + 1),
+        _ => 9,
+    }
+}
+
+/// Initial bearing (forward azimuth) in degrees within [0, 360) to go from `from` to `to`.
+///
+/// # Example
+///
+/// ```
+/// use aml_lib::geo;
+///
+/// assert!((geo::initial_bearing((0.0, 0.0), (1.0, 0.0)) - 0.0).abs() < 1e-9);
+/// assert!((geo::initial_bearing((0.0, 0.0), (0.0, 1.0)) - 90.0).abs() < 1e-9);
+/// ```
+pub fn initial_bearing(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat1, lat2) = (from.0.to_radians(), to.0.to_radians());
+    let d_lon = (to.1 - from.1).to_radians();
+
+    let y = d_lon.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * d_lon.cos();
+    (y.atan2(x).to_degrees() + 360.0) % 360.0
+}
+
+impl AmlData {
+    /// The `(latitude, longitude)` pair if both are known.
+    pub fn position(&self) -> Option<(f64, f64)> {
+        self.latitude.zip(self.longitude)
+    }
+
+    /// Distance in meters to the location of `other` (Vincenty, falling back to haversine).
+    pub fn distance_to(&self, other: &AmlData) -> Option<f64> {
+        let (from, to) = (self.position()?, other.position()?);
+        vincenty_distance(from, to).or_else(|| Some(haversine_distance(from, to)))
+    }
+
+    /// Initial bearing in degrees to the location of `other`.
+    pub fn bearing_to(&self, other: &AmlData) -> Option<f64> {
+        Some(initial_bearing(self.position()?, other.position()?))
+    }
+
+    /// The location in UTM coordinates. See [`geo::to_utm`](crate::geo::to_utm).
+    pub fn utm(&self) -> Option<Utm> {
+        to_utm(self.position()?)
+    }
+
+    /// The location as a MGRS string with `precision` digits. See [`geo::to_mgrs`](crate::geo::to_mgrs).
+    pub fn mgrs(&self, precision: usize) -> Option<String> {
+        to_mgrs(self.position()?, precision)
+    }
+
+    /// Geohash of the location with a length derived from the accuracy.
+    /// See [`geo::geohash_precision`](crate::geo::geohash_precision).
+    pub fn geohash(&self) -> Option<String> {
+        self.geohash_with_precision(geohash_precision(self.accuracy))
+    }
+
+    /// Geohash of the location with `precision` characters.
+    pub fn geohash_with_precision(&self, precision: usize) -> Option<String> {
+        Some(geohash(self.position()?, precision))
+    }
+
+    /// Where the location, with its accuracy radius, stands relative to `polygon`
+    /// (e.g. a PSAP boundary). An unknown accuracy is handled as a radius of 0.
+    /// Returns `None` without location.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{geo::Containment, AmlData};
+    ///
+    /// let area = vec![(48.0, 2.0), (49.0, 2.0), (49.0, 3.0), (48.0, 3.0), (48.0, 2.0)];
+    /// let aml = AmlData::from_https("location_latitude=48.5&location_longitude=2.5&location_accuracy=50").unwrap();
+    /// assert_eq!(aml.is_within(&area), Some(Containment::Within));
+    /// ```
+    pub fn is_within(&self, polygon: &[(f64, f64)]) -> Option<Containment> {
+        let position = self.position()?;
+        let radius = self.accuracy.unwrap_or_default().max(0.0);
+        let center_inside = contains(polygon, position);
+
+        Some(match (center_inside, distance_to_boundary(polygon, position) > radius) {
+            (true, true) => Containment::Within,
+            (false, true) => Containment::Outside,
+            (center_inside, false) => Containment::Partial { center_inside },
+        })
+    }
+
+    /// Closed polygon of `points` vertices for the uncertainty circle at the reported confidence,
+    /// or for the uncertainty ellipse when there is one. Requires a location and a non-zero
+    /// accuracy or ellipse.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::AmlData;
+    ///
+    /// let aml = AmlData::from_https("location_latitude=48.85341&location_longitude=2.3488&location_accuracy=50").unwrap();
+    /// let polygon = aml.uncertainty_polygon(32).unwrap();
+    /// assert_eq!(polygon.len(), 33);
+    /// ```
+    pub fn uncertainty_polygon(&self, points: usize) -> Option<Polygon> {
+        if let Some(ellipse) = self.ellipse.filter(|ellipse| ellipse.semi_major > 0.0) {
+            return Some(ellipse_polygon(self.position()?, &ellipse, points));
+        }
+        let radius = self.accuracy.filter(|acc| *acc > 0.0)?;
+        Some(circle_polygon(self.position()?, radius, points))
+    }
+
+    /// Same as [`AmlData::uncertainty_polygon`] with the radius rescaled to `target_confidence`
+    /// (ratio in ]0, 1[). Requires the reported confidence, see [`AmlData::confidence_ratio`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::AmlData;
+    ///
+    /// let aml = AmlData::from_text_sms(r#"A"ML=2;lo=48.85341,2.3488,50;lc=68"#).unwrap();
+    /// assert_eq!(aml.uncertainty_polygon_at(32, 0.95).unwrap().len(), 33);
+    /// assert_eq!(aml.uncertainty_polygon_at(32, 1.0), None);
+    /// ```
+    pub fn uncertainty_polygon_at(&self, points: usize, target_confidence: f64) -> Option<Polygon> {
+        let confidence = self.confidence_ratio()?;
+        if let Some(ellipse) = self.ellipse.filter(|ellipse| ellipse.semi_major > 0.0) {
+            let ellipse = ellipse.scaled(scale_radius(1.0, confidence, target_confidence)?);
+            return Some(ellipse_polygon(self.position()?, &ellipse, points));
+        }
+        let radius = self.accuracy.filter(|acc| *acc > 0.0)?;
+        let radius = scale_radius(radius, confidence, target_confidence)?;
+        Some(circle_polygon(self.position()?, radius, points))
+    }
+
+    /// The confidence as a ratio in ]0, 1[, `None` if it is unknown or out of range.
+    /// SMS carry a percentage (`lc=1` is 1%); HTTPS carry a ratio, read as a percentage
+    /// for values above 1.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::AmlData;
+    ///
+    /// let sms = AmlData::from_text_sms(r#"A"ML=2;lo=48.85341,2.3488,50;lc=1"#).unwrap();
+    /// let https = AmlData::from_https("v=1&location_latitude=48.85341&location_longitude=2.3488&location_confidence=0.68").unwrap();
+    /// assert_eq!((sms.confidence_ratio(), https.confidence_ratio()), (Some(0.01), Some(0.68)));
+    /// ```
+    pub fn confidence_ratio(&self) -> Option<f64> {
+        let confidence = self.confidence?;
+        let ratio = match self.transport.as_str() {
+            "https" if confidence <= 1.0 => confidence,
+            _ => confidence / 100.0,
+        };
+        Some(ratio).filter(|ratio| *ratio > 0.0 && *ratio < 1.0)
+    }
+}
+
+impl HttpsData {
+    /// Distance in meters between the reported location and the ground truth location (for testing).
+    pub fn ground_truth_error(&self) -> Option<f64> {
+        let location = self.location_latitude.zip(self.location_longitude)?;
+        let ground_truth = self.gt_location_latitude.zip(self.gt_location_longitude)?;
+        vincenty_distance(location, ground_truth).or_else(|| Some(haversine_distance(location, ground_truth)))
+    }
+}
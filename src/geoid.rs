@@ -0,0 +1,156 @@
+//! Conversion between heights above the WGS84 ellipsoid (reported by handsets)
+//! and heights above the mean sea level, using the EGM96 geoid model or an undulation grid.
+//!
+//! The EGM96 coefficients are bundled: [`AmlData::altitude_msl_egm96`] evaluates the model
+//! at the position of the message. As each evaluation costs a spherical harmonic synthesis,
+//! a receiver converting many messages over the same area should precompute the subset it
+//! covers with [`GeoidGrid::egm96`], or load it from the `WW15MGH.GRD` file published by NGA
+//! with [`GeoidGrid::parse_grd`].
+
+use crate::AmlData;
+
+/// Regular grid of geoid undulations (height of the geoid above the ellipsoid) in meters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoidGrid {
+    pub south: f64,
+    pub north: f64,
+    pub west: f64,
+    pub east: f64,
+
+    /// Latitude spacing in degrees.
+    pub lat_step: f64,
+
+    /// Longitude spacing in degrees.
+    pub lon_step: f64,
+
+    /// Undulations row by row, from north to south, each row from west to east.
+    pub values: Vec<f64>,
+}
+
+impl GeoidGrid {
+    /// Parse the text format of the NGA `.GRD` files: a header
+    /// `south north west east lat_step lon_step` followed by the values.
+    /// Returns `None` if the header is invalid or if values are missing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::geoid::GeoidGrid;
+    ///
+    /// let grid = GeoidGrid::parse_grd("48 49 2 3 1 1\n 44.0 45.0\n 46.0 47.0").unwrap();
+    /// assert_eq!(grid.undulation(48.5, 2.5), Some(45.5));
+    /// ```
+    pub fn parse_grd(text: &str) -> Option<Self> {
+        let mut numbers = text.split_whitespace().map(|n| n.parse::<f64>().ok());
+        let mut header = [0.0; 6];
+        for value in header.iter_mut() {
+            *value = numbers.next()??;
+        }
+        let [south, north, west, east, lat_step, lon_step] = header;
+        let values = numbers.collect::<Option<Vec<f64>>>()?;
+
+        let grid = GeoidGrid { south, north, west, east, lat_step, lon_step, values };
+        if header.iter().any(|value| !value.is_finite()) || lat_step <= 0.0 || lon_step <= 0.0 || south >= north || west >= east {
+            return None;
+        }
+        if grid.values.len() != grid.rows() * grid.columns() {
+            return None;
+        }
+        Some(grid)
+    }
+
+    /// Grid of the EGM96 undulations over `south..=north`, `west..=east` every `step`
+    /// degrees. Returns `None` if the bounds or the step are invalid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::geoid::GeoidGrid;
+    ///
+    /// let grid = GeoidGrid::egm96(29.0, 30.0, -96.0, -95.0, 0.25).unwrap();
+    /// let undulation = grid.undulation(29.7604, -95.3698).unwrap();
+    /// assert!((undulation + 28.4).abs() < 0.2);
+    /// ```
+    pub fn egm96(south: f64, north: f64, west: f64, east: f64, step: f64) -> Option<Self> {
+        let bounds = [south, north, west, east, step];
+        if bounds.iter().any(|value| !value.is_finite()) || step <= 0.0 || south >= north || west >= east {
+            return None;
+        }
+        if south < -90.0 || north > 90.0 || east - west > 360.0 {
+            return None;
+        }
+
+        let mut grid = GeoidGrid { south, north, west, east, lat_step: step, lon_step: step, values: Vec::new() };
+        let (rows, columns) = (grid.rows(), grid.columns());
+        grid.values.reserve(rows * columns);
+        for row in 0..rows {
+            let latitude = (north - row as f64 * step).max(south);
+            for column in 0..columns {
+                let longitude = (west + column as f64 * step).min(east);
+                grid.values.push(egm96::egm96_compute_altitude_offset(latitude, longitude));
+            }
+        }
+        Some(grid)
+    }
+
+    fn rows(&self) -> usize {
+        ((self.north - self.south) / self.lat_step).round() as usize + 1
+    }
+
+    fn columns(&self) -> usize {
+        ((self.east - self.west) / self.lon_step).round() as usize + 1
+    }
+
+    /// Undulation at (`latitude`, `longitude`) by bilinear interpolation,
+    /// `None` outside of the grid or for non-finite coordinates.
+    pub fn undulation(&self, latitude: f64, longitude: f64) -> Option<f64> {
+        if !latitude.is_finite() || !longitude.is_finite() {
+            return None;
+        }
+        let longitude = self.west + (longitude - self.west).rem_euclid(360.0);
+        if !(self.south..=self.north).contains(&latitude) || longitude > self.east {
+            return None;
+        }
+
+        let (rows, columns) = (self.rows(), self.columns());
+        let y = (self.north - latitude) / self.lat_step;
+        let x = (longitude - self.west) / self.lon_step;
+        let (row, column) = ((y.floor() as usize).min(rows - 1), (x.floor() as usize).min(columns - 1));
+        let (next_row, next_column) = ((row + 1).min(rows - 1), (column + 1).min(columns - 1));
+        let (dy, dx) = (y - row as f64, x - column as f64);
+
+        let at = |r: usize, c: usize| self.values[r * columns + c];
+        let top = at(row, column) * (1.0 - dx) + at(row, next_column) * dx;
+        let bottom = at(next_row, column) * (1.0 - dx) + at(next_row, next_column) * dx;
+        Some(top * (1.0 - dy) + bottom * dy)
+    }
+}
+
+impl AmlData {
+    /// Altitude above the mean sea level, from the altitude above the WGS84 ellipsoid
+    /// and the undulation given by `grid`.
+    pub fn altitude_msl(&self, grid: &GeoidGrid) -> Option<f64> {
+        let (latitude, longitude) = self.position()?;
+        Some(self.altitude? - grid.undulation(latitude, longitude)?)
+    }
+
+    /// Altitude above the mean sea level, from the altitude above the WGS84 ellipsoid
+    /// and the EGM96 undulation at the position.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::AmlData;
+    ///
+    /// let aml = AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=29.7604,-95.3698,10;lz=-15.0,5;lt=30"#).unwrap();
+    /// let msl = aml.altitude_msl_egm96().unwrap();
+    /// assert!((msl - 13.4).abs() < 0.2);
+    /// ```
+    pub fn altitude_msl_egm96(&self) -> Option<f64> {
+        let (latitude, longitude) = self.position()?;
+        if !(latitude.is_finite() && longitude.is_finite() && (-90.0..=90.0).contains(&latitude)) {
+            return None;
+        }
+        Some(self.altitude? - egm96::egm96_compute_altitude_offset(latitude, longitude))
+    }
+}
@@ -0,0 +1,135 @@
+use crate::validation::clean_text;
+use crate::{AmlData, AmlError, SmsData, ValidationFailure};
+use std::borrow::Cow;
+
+/// Quotes seen instead of `"` in the headers of buggy firmwares.
+const QUOTES: &[&str] = &["'", "`", "\u{2018}", "\u{2019}", "\u{201c}", "\u{201d}"];
+
+/// A variant of the `A"ML=` header accepted by [`SmsData::from_text_with_header`],
+/// recorded in [`ValidationReport::header_deviations`](crate::ValidationReport::header_deviations).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HeaderDeviation {
+    /// Another quote than `"` (`A'ML`, `A“ML`) or none (`AML`).
+    Quoting,
+
+    /// Spaces around the key or the `=` (`A"ML = 1`).
+    Spaces,
+
+    /// Lowercase letters (`a"ml`).
+    Case,
+}
+
+/// The deviations from the `A"ML=` header that are normalized before parsing. A header
+/// with a deviation which is not tolerated is left as is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderTolerance {
+    pub quoting: bool,
+    pub spaces: bool,
+    pub case: bool,
+}
+
+impl HeaderTolerance {
+    /// The header must be exactly `A"ML=`, as with [`SmsData::from_text`].
+    pub const NONE: HeaderTolerance = HeaderTolerance { quoting: false, spaces: false, case: false };
+
+    /// All the [`HeaderDeviation`]s are normalized.
+    pub const ALL: HeaderTolerance = HeaderTolerance { quoting: true, spaces: true, case: true };
+
+    fn allows(&self, deviation: HeaderDeviation) -> bool {
+        match deviation {
+            HeaderDeviation::Quoting => self.quoting,
+            HeaderDeviation::Spaces => self.spaces,
+            HeaderDeviation::Case => self.case,
+        }
+    }
+
+    /// `text` with its first header rewritten as `A"ML=`, and the deviations found.
+    fn normalize<'a>(&self, text: &'a str) -> (Cow<'a, str>, Vec<HeaderDeviation>) {
+        let mut start = 0;
+        for attribute in text.split(';') {
+            let end = start + attribute.len();
+            if let Some((key, value)) = attribute.split_once('=') {
+                if let Some(mut deviations) = deviations(key) {
+                    if key.trim() != key || value.trim_start() != value {
+                        deviations.push(HeaderDeviation::Spaces);
+                    }
+                    if deviations.is_empty() || !deviations.iter().all(|deviation| self.allows(*deviation)) {
+                        break;
+                    }
+                    deviations.sort();
+                    let normalized = format!(r#"{}A"ML={}{}"#, &text[..start], value.trim(), &text[end..]);
+                    return (Cow::Owned(normalized), deviations);
+                }
+            }
+            start = end + 1;
+        }
+        (Cow::Borrowed(text), Vec::new())
+    }
+}
+
+/// The deviations of `key` if it is a header, but for the spaces.
+fn deviations(key: &str) -> Option<Vec<HeaderDeviation>> {
+    let key = key.trim();
+    let upper = key.to_uppercase();
+    let quote = upper.strip_prefix('A')?.strip_suffix("ML")?;
+    if !(quote.is_empty() || quote == "\"" || QUOTES.contains(&quote)) {
+        return None;
+    }
+
+    let mut deviations = Vec::new();
+    if quote != "\"" {
+        deviations.push(HeaderDeviation::Quoting);
+    }
+    if key != upper {
+        deviations.push(HeaderDeviation::Case);
+    }
+    Some(deviations)
+}
+
+impl SmsData {
+    /// Parse a SMS text like [`SmsData::from_text`], the header variants of buggy
+    /// firmwares allowed by `tolerance` being normalized first. The deviations are
+    /// recorded in the validation report, which is still valid. The message length
+    /// (v1) is the one of the text as received.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{HeaderDeviation, HeaderTolerance, SmsData};
+    ///
+    /// let text = "a'ml = 2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30";
+    /// assert!(SmsData::from_text(text).is_err());
+    ///
+    /// let sms = SmsData::from_text_with_header(text, HeaderTolerance::ALL).unwrap();
+    /// assert_eq!(sms.latitude, Some(48.82639));
+    /// assert!(sms.is_valid());
+    /// assert_eq!(
+    ///     sms.validation.header_deviations,
+    ///     vec![HeaderDeviation::Quoting, HeaderDeviation::Spaces, HeaderDeviation::Case]
+    /// );
+    /// ```
+    pub fn from_text_with_header<S: AsRef<str>>(text_sms: S, tolerance: HeaderTolerance) -> Result<Self, AmlError> {
+        let text_sms = text_sms.as_ref();
+        let (normalized, deviations) = tolerance.normalize(text_sms);
+        let mut sms_data = Self::from_text(&normalized)?;
+
+        if !deviations.is_empty() {
+            let mut report = sms_data.validate(text_sms);
+            report.header_first = clean_text(&normalized).0.starts_with(r#"A"ML="#);
+            if report.header_first {
+                report.failures.retain(|failure| *failure != ValidationFailure::HeaderNotFirst);
+            }
+            report.header_deviations = deviations;
+            sms_data.validation = report;
+        }
+
+        Ok(sms_data)
+    }
+}
+
+impl AmlData {
+    /// Parse a SMS text with a tolerant header. See [`SmsData::from_text_with_header`].
+    pub fn from_text_sms_with_header<S: AsRef<str>>(text_sms: S, tolerance: HeaderTolerance) -> Result<Self, AmlError> {
+        Ok(SmsData::from_text_with_header(text_sms, tolerance)?.into())
+    }
+}
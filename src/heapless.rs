@@ -0,0 +1,271 @@
+//! Parsing of SMS texts without heap allocation, for constrained gateways.
+//!
+//! [`FixedSms::from_text`] goes over the attributes of the text without building a map
+//! and copies the string values into [`FixedStr`] buffers on the stack. The capacities are
+//! the `MAX_*` constants: a value longer than its capacity is dropped and
+//! [`FixedSms::overflow`] is set, the rest of the message is still decoded.
+//!
+//! Whatever the input, the parse uses at most [`MEMORY_CEILING`] bytes, all on the
+//! stack: nothing grows with the length or the number of attributes of the text.
+//! [`FixedSms::from_text_audited`] reports the bytes of the string buffers actually
+//! used, for the safety case of the emergency call path.
+//!
+//! # Example
+//!
+//! ```
+//! use aml_lib::heapless::FixedSms;
+//!
+//! let sms = FixedSms::from_text(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;lc=68;ls=G"#).unwrap();
+//! assert_eq!(sms.emergency_number.as_deref(), Some("112"));
+//! assert_eq!((sms.latitude, sms.accuracy), (Some(48.82639), Some(20.0)));
+//! assert!(!sms.overflow);
+//! ```
+
+use crate::{seconds_to_utc, timestamp, AmlError, Timestamp};
+use std::ops::Deref;
+
+/// Capacity of the emergency number (`en`): longest national numbers plus margin.
+pub const MAX_EMERGENCY_NUMBER: usize = 16;
+
+/// Capacity of the IMSI (`si`), 15 digits.
+pub const MAX_IMSI: usize = 15;
+
+/// Capacity of the IMEI (`ei`), 15 digits or 16 for an IMEISV.
+pub const MAX_IMEI: usize = 16;
+
+/// Capacity of the language tags (`lg`), e.g. `fr-FR,en-GB,de-DE,es-ES`.
+pub const MAX_LANGUAGES: usize = 32;
+
+/// Bytes of the string buffers of a [`FixedSms`], the scratch memory of the parse.
+pub const SCRATCH_CAPACITY: usize = MAX_EMERGENCY_NUMBER + MAX_IMSI + MAX_IMEI + MAX_LANGUAGES;
+
+/// Memory used by a parse with [`FixedSms::from_text`], whatever the input: the
+/// decoded message, its string buffers included. Nothing is allocated on the heap.
+pub const MEMORY_CEILING: usize = std::mem::size_of::<FixedSms>();
+
+/// Memory usage observed by [`FixedSms::from_text_audited`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Audit {
+    /// Most bytes of the string buffers in use at once, at most [`SCRATCH_CAPACITY`].
+    pub peak_scratch: usize,
+
+    /// Attributes of the text decoded.
+    pub properties: usize,
+}
+
+/// A string of at most `N` bytes stored inline.
+#[derive(Clone, Copy)]
+pub struct FixedStr<const N: usize> {
+    bytes: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> FixedStr<N> {
+    /// Copy `value`, or `None` if it is longer than `N` bytes.
+    pub fn new(value: &str) -> Option<Self> {
+        if value.len() > N {
+            return None;
+        }
+        let mut bytes = [0; N];
+        bytes[..value.len()].copy_from_slice(value.as_bytes());
+        Some(FixedStr { bytes, len: value.len() })
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Only whole `&str` are copied in
+        std::str::from_utf8(&self.bytes[..self.len]).unwrap_or_default()
+    }
+}
+
+impl<const N: usize> Default for FixedStr<N> {
+    fn default() -> Self {
+        FixedStr { bytes: [0; N], len: 0 }
+    }
+}
+
+impl<const N: usize> Deref for FixedStr<N> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> PartialEq for FixedStr<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl<const N: usize> std::fmt::Debug for FixedStr<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl<const N: usize> std::fmt::Display for FixedStr<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A SMS AML message decoded without allocation. The fields are those of
+/// [`SmsData`](crate::SmsData), `header` being the version number and
+/// `beginning_of_call` a timestamp.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct FixedSms {
+    pub header: u8,
+    pub emergency_number: Option<FixedStr<MAX_EMERGENCY_NUMBER>>,
+    pub beginning_of_call: Option<Timestamp>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub accuracy: Option<f64>,
+    pub time_of_positioning: Option<Timestamp>,
+
+    /// (v2) `lt` as received, negative for an unknown time of positioning.
+    pub positioning_delay: Option<i64>,
+    pub level_of_confidence: Option<f64>,
+    pub altitude: Option<f64>,
+    pub vertical_accuracy: Option<f64>,
+
+    /// `G`, `W`, `C`, `F` or `U`, in upper case.
+    pub positioning_method: Option<char>,
+    pub imsi: Option<FixedStr<MAX_IMSI>>,
+    pub imei: Option<FixedStr<MAX_IMEI>>,
+    pub network_mcc: Option<i32>,
+    pub network_mnc: Option<i32>,
+    pub home_mcc: Option<i32>,
+    pub home_mnc: Option<i32>,
+    pub languages: Option<FixedStr<MAX_LANGUAGES>>,
+    pub message_length: Option<i32>,
+
+    /// A string value was longer than its capacity and has been dropped.
+    pub overflow: bool,
+}
+
+/// The `n`th comma separated number of `value`.
+fn nth_f64(value: &str, n: usize) -> Option<f64> {
+    value.split(',').nth(n).and_then(|v| v.parse::<f64>().ok())
+}
+
+impl FixedSms {
+    /// Parse a SMS text like [`SmsData::from_text`](crate::SmsData::from_text), without
+    /// the validation report. Returns [`AmlError::UnimplementedVersion`] if there is no
+    /// `A"ML=1` or `A"ML=2` attribute.
+    pub fn from_text(text_sms: &str) -> Result<Self, AmlError> {
+        Self::parse(text_sms, &mut Audit::default())
+    }
+
+    /// Parse a SMS text like [`FixedSms::from_text`] and report its memory usage.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::heapless::{FixedSms, MEMORY_CEILING, SCRATCH_CAPACITY};
+    ///
+    /// let (sms, audit) = FixedSms::from_text_audited(r#"A"ML=2;en=112;et=1593187189;ei=358239059042542"#).unwrap();
+    /// assert_eq!(audit.peak_scratch, sms.scratch_used());
+    /// assert_eq!(audit.peak_scratch, 3 + 15);
+    /// assert!(SCRATCH_CAPACITY < MEMORY_CEILING);
+    /// ```
+    pub fn from_text_audited(text_sms: &str) -> Result<(Self, Audit), AmlError> {
+        let mut audit = Audit::default();
+        let sms = Self::parse(text_sms, &mut audit)?;
+        Ok((sms, audit))
+    }
+
+    /// Bytes of the string buffers in use.
+    pub fn scratch_used(&self) -> usize {
+        self.emergency_number.map_or(0, |s| s.len())
+            + self.imsi.map_or(0, |s| s.len())
+            + self.imei.map_or(0, |s| s.len())
+            + self.languages.map_or(0, |s| s.len())
+    }
+
+    fn parse(text_sms: &str, audit: &mut Audit) -> Result<Self, AmlError> {
+        let header = text_sms
+            .split(';')
+            .filter_map(|property| property.split_once('='))
+            .rfind(|(key, _)| key.trim() == r#"A"ML"#)
+            .map(|(_, value)| value.trim());
+        let mut sms = FixedSms {
+            header: match header {
+                Some("1") => 1,
+                Some("2") => 2,
+                _ => return Err(AmlError::UnimplementedVersion),
+            },
+            ..Default::default()
+        };
+
+        let mut emergency_time = None;
+        for (key, value) in text_sms
+            .split(';')
+            .filter_map(|property| property.split_once('='))
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .filter(|(key, value)| !key.is_empty() && !value.is_empty())
+        {
+            match (sms.header, key) {
+                (_, "ei") => sms.imei = sms.fixed(value),
+                (1, "lt") => sms.latitude = value.parse().ok(),
+                (1, "lg") => sms.longitude = value.parse().ok(),
+                (1, "rd") => sms.accuracy = value.parse().ok(),
+                (1, "top") => sms.time_of_positioning = timestamp::parse_compact(value),
+                (1, "lc") | (2, "lc") => sms.level_of_confidence = value.parse().ok(),
+                (1, "pm") => sms.positioning_method = method(value, "GWCU"),
+                (1, "si") => sms.imsi = sms.fixed(value),
+                (1, "mcc") => sms.network_mcc = value.parse().ok(),
+                (1, "mnc") => sms.network_mnc = value.parse().ok(),
+                (1, "ml") => sms.message_length = value.parse().ok(),
+                (2, "en") => sms.emergency_number = sms.fixed(value),
+                (2, "et") => emergency_time = value.parse::<i64>().ok(),
+                (2, "lo") => {
+                    sms.latitude = nth_f64(value, 0);
+                    sms.longitude = nth_f64(value, 1);
+                    sms.accuracy = nth_f64(value, 2);
+                }
+                (2, "lt") => sms.positioning_delay = value.parse::<i64>().ok(),
+                (2, "lz") => {
+                    sms.altitude = nth_f64(value, 0);
+                    sms.vertical_accuracy = nth_f64(value, 1);
+                }
+                (2, "ls") => sms.positioning_method = method(value, "GWCUF"),
+                (2, "nc") => {
+                    sms.network_mcc = value.get(..3).and_then(|s| s.parse().ok());
+                    sms.network_mnc = value.get(3..).and_then(|s| s.parse().ok());
+                }
+                (2, "hc") => {
+                    sms.home_mcc = value.get(..3).and_then(|s| s.parse().ok());
+                    sms.home_mnc = value.get(3..).and_then(|s| s.parse().ok());
+                }
+                (2, "lg") => sms.languages = sms.fixed(value),
+                _ => (),
+            }
+            audit.properties += 1;
+            audit.peak_scratch = audit.peak_scratch.max(sms.scratch_used());
+        }
+
+        if let Some(et) = emergency_time {
+            sms.beginning_of_call = seconds_to_utc!(et);
+            if let Some(lt) = sms.positioning_delay.filter(|lt| *lt >= 0) {
+                sms.time_of_positioning = et.checked_add(lt).and_then(|top| seconds_to_utc!(top));
+            }
+        }
+
+        Ok(sms)
+    }
+
+    fn fixed<const N: usize>(&mut self, value: &str) -> Option<FixedStr<N>> {
+        let fixed = FixedStr::new(value);
+        self.overflow |= fixed.is_none();
+        fixed
+    }
+}
+
+/// The one letter method of `value` if it is in `allowed`, in upper case.
+fn method(value: &str, allowed: &str) -> Option<char> {
+    let mut chars = value.chars();
+    match (chars.next().map(|c| c.to_ascii_uppercase()), chars.next()) {
+        (Some(c), None) if allowed.contains(c) => Some(c),
+        _ => None,
+    }
+}
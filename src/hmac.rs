@@ -1,5 +1,3 @@
-use sha1;
-
 pub const SHA1_DIGEST_BYTES: usize = 20;
 const SHA1_KEY_BYTES: usize = 64;
 
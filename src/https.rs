@@ -1,10 +1,48 @@
 use std::borrow::Cow;
-use chrono::{ DateTime, LocalResult, TimeZone, Utc };
-use crate::{char_millis_to_utc, valid_list, hmac::hmac_sha1 };
+use subtle::ConstantTimeEq;
+use crate::{char_millis_to_utc, valid_list, hmac::hmac_sha1, Profile, Timestamp };
 
 const HMAC_FIELD: &str = "hmac";
 
-#[derive(Debug, Default)]
+/// Reason why a HTTPS AML message is not authenticated. See [`HttpsData::authenticate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthError {
+    /// The message has no `hmac` field.
+    MissingHmac,
+
+    /// The `hmac` field is not hexadecimal.
+    MalformedHmac,
+
+    /// The `hmac` field does not match the message. `expected_len` is the number of hex
+    /// digits of the expected HMAC, to tell a truncated field from a wrong key.
+    Mismatch { expected_len: usize },
+
+    /// The message has several `hmac` fields.
+    MultipleHmacFields,
+
+    /// The body is not a JSON object. See [`HttpsData::authenticate_json`].
+    MalformedBody,
+}
+
+impl std::error::Error for AuthError {}
+
+impl std::fmt::Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let text = match self {
+            AuthError::MissingHmac => String::from("The message has no HMAC"),
+            AuthError::MalformedHmac => String::from("The HMAC of the message is not hexadecimal"),
+            AuthError::Mismatch { expected_len } => {
+                format!("The HMAC does not match the message ({} hex digits expected)", expected_len)
+            }
+            AuthError::MultipleHmacFields => String::from("The message has several HMAC fields"),
+            AuthError::MalformedBody => String::from("The body is not a JSON object"),
+        };
+        write!(f, "Error: {}", text)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct HttpsData {
     /// This is the version of AML.
     pub v: Option<String>,
@@ -27,6 +65,9 @@ pub struct HttpsData {
     /// Ground truth longitude* (for testing).
     pub gt_location_longitude: Option<f64>,
 
+    /// Test flag set by the operator.
+    pub test: Option<bool>,
+
     /// The WGS84 latitude in degrees. Latitude is truncated to 5 decimal points.
     pub location_latitude: Option<f64>,
 
@@ -34,7 +75,8 @@ pub struct HttpsData {
     pub location_longitude: Option<f64>,
 
     /// The date and time that the handset determined the location area specified in UTC.
-    pub location_time: Option<DateTime<Utc>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::timestamp::rfc3339"))]
+    pub location_time: Option<Timestamp>,
 
     /// Vertical location in meters.
     pub location_altitude: Option<f64>,
@@ -51,9 +93,21 @@ pub struct HttpsData {
     /// Vertical accuracy in meters.
     pub location_vertical_accuracy: Option<f64>,
 
-    /// Confidence in location accuracy (percent between 0 and 1).
+    /// Semi-major axis of the uncertainty ellipse in meters.
+    pub location_semi_major_axis: Option<f64>,
+
+    /// Semi-minor axis of the uncertainty ellipse in meters.
+    pub location_semi_minor_axis: Option<f64>,
+
+    /// Orientation of the semi-major axis in degrees clockwise from true north.
+    pub location_orientation: Option<f64>,
+
+    /// Confidence in location accuracy (ratio between 0 and 1).
     pub location_confidence: Option<f64>,
 
+    /// (ELS) Confidence in location accuracy in percent. See [`Profile::Els`].
+    pub location_certainty: Option<f64>,
+
     /// Bearing in degrees.
     pub location_bearing: Option<f64>,
 
@@ -91,10 +145,26 @@ pub struct HttpsData {
     pub device_languages: Option<String>,
 
     /// Car crash date time
-    pub adr_carcrash_time: Option<DateTime<Utc>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::timestamp::rfc3339"))]
+    pub adr_carcrash_time: Option<Timestamp>,
+
+    /// Car crash severity, on the scale of the vehicle maker
+    pub adr_severity: Option<String>,
+
+    /// The vehicle has rolled over
+    pub adr_rollover: Option<bool>,
+
+    /// Direction of the impact in degrees clockwise from the front of the vehicle
+    pub adr_impact_direction: Option<f64>,
+
+    /// Number of occupants of the vehicle
+    pub adr_occupants: Option<u32>,
 
     /// Message Authentification Code
     pub hmac: Option<String>,
+
+    /// Conventions the message has been parsed with. See [`HttpsData::from_urlencoded_with`].
+    pub profile: Profile,
 }
 
 impl HttpsData {
@@ -113,16 +183,57 @@ impl HttpsData {
     /// }
     /// ```
     pub fn is_authenticated<S: AsRef<str>>(payload: S, key: &[u8]) -> bool {
+        Self::authenticate(payload, key).is_ok()
+    }
+
+    /// Verify the `hmac` field like [`HttpsData::is_authenticated`], telling why the
+    /// message is not authenticated.
+    ///
+    /// ```
+    /// use aml_lib::{AuthError, HttpsData};
+    ///
+    /// let https = "v=1&location_latitude=0.85732&hmac=f64c70eb238bb239e00e8ac8c023bf2b";
+    /// assert_eq!(HttpsData::authenticate(https, b"AML"), Err(AuthError::Mismatch { expected_len: 40 }));
+    /// assert_eq!(HttpsData::authenticate("v=1&location_latitude=0.85732", b"AML"), Err(AuthError::MissingHmac));
+    /// ```
+    pub fn authenticate<S: AsRef<str>>(payload: S, key: &[u8]) -> Result<(), AuthError> {
         let splitted: Vec<&str> = payload.as_ref().split(&format!("&{}=", HMAC_FIELD)).collect();
 
-        if splitted.len() != 2 { 
-            return false; 
+        let (message, hmac) = match splitted.as_slice() {
+            [_] => return Err(AuthError::MissingHmac),
+            [message, hmac] => (*message, *hmac),
+            _ => return Err(AuthError::MultipleHmacFields),
+        };
+
+        let hmac = hex::decode(hmac).map_err(|_| AuthError::MalformedHmac)?;
+        let expected = hmac_sha1(key, message.as_bytes());
+
+        if !bool::from(hmac.as_slice().ct_eq(&expected)) {
+            return Err(AuthError::Mismatch { expected_len: expected.len() * 2 });
         }
+        Ok(())
+    }
 
-        let message = splitted[0];
-        let hmac = hex::encode(hmac_sha1(key, message.as_bytes()));
+    /// Parse a HTTPS AML message from a request URL, absolute (`https://host/path?query`)
+    /// or relative (`/path?query`). The fragment is ignored and a URL without query
+    /// gives empty data. See [`HttpsData::from_urlencoded`].
+    ///
+    /// ```
+    /// use aml_lib::HttpsData;
+    ///
+    /// let url = "https://psap.example/aml/v1?v=1&location_latitude=0.85732&location_longitude=-4.26325#map";
+    /// let https_data = HttpsData::from_url(url);
+    /// assert_eq!(https_data.location_longitude, Some(-4.26325));
+    /// ```
+    pub fn from_url<S: AsRef<str>>(url: S) -> Self {
+        Self::from_urlencoded(Self::url_query(url.as_ref()))
+    }
 
-        hmac.eq(splitted[1])
+    /// The query component of `url`, empty without query.
+    pub(crate) fn url_query(url: &str) -> &str {
+        let url = url.trim();
+        let url = url.split_once('#').map_or(url, |(url, _)| url);
+        url.split_once('?').map_or("", |(_, query)| query)
     }
 
     /// Parse a HTTPS AML message. That assumes it is an URL encoded string. 
@@ -136,6 +247,8 @@ impl HttpsData {
     /// assert_eq!(https_data.location_latitude, Some(0.85732));
     /// ```    
     pub fn from_urlencoded<S: AsRef<str>>(payload: S) -> Self {
+        #[cfg(feature = "trace")]
+        let span = crate::trace::parse_span("https").entered();
         let mut https_data: HttpsData = Default::default();
 
         let attributes: Vec<(Cow<str>, Cow<str>)> =
@@ -161,6 +274,13 @@ impl HttpsData {
                 ("gt_location_longitude", val) => {
                     https_data.gt_location_longitude = val.parse::<f64>().ok()
                 }
+                ("test", val) => {
+                    https_data.test = match val.to_lowercase().as_str() {
+                        "true" | "1" => Some(true),
+                        "false" | "0" => Some(false),
+                        _ => None,
+                    }
+                }
 
                 ("location_latitude", val) => {
                     https_data.location_latitude = val.parse::<f64>().ok()
@@ -185,9 +305,15 @@ impl HttpsData {
                 ("location_vertical_accuracy", val) => {
                     https_data.location_vertical_accuracy = val.parse::<f64>().ok()
                 }
+                ("location_semi_major_axis", val) => https_data.location_semi_major_axis = val.parse::<f64>().ok(),
+                ("location_semi_minor_axis", val) => https_data.location_semi_minor_axis = val.parse::<f64>().ok(),
+                ("location_orientation", val) => https_data.location_orientation = val.parse::<f64>().ok(),
                 ("location_confidence", val) => {
                     https_data.location_confidence = val.parse::<f64>().ok()
                 }
+                ("location_certainty", val) => {
+                    https_data.location_certainty = val.parse::<f64>().ok()
+                }
                 ("location_bearing", val) => https_data.location_bearing = val.parse::<f64>().ok(),
                 ("location_speed", val) => https_data.location_speed = val.parse::<f64>().ok(),
 
@@ -204,12 +330,28 @@ impl HttpsData {
                 
                 ("device_languages", val) => https_data.device_languages = Some(val.to_string()),
                 ("adr_carcrash_time", val) => https_data.adr_carcrash_time = char_millis_to_utc!(val),
+                ("adr_severity", val) => https_data.adr_severity = Some(val.to_string()),
+                ("adr_rollover", val) => {
+                    https_data.adr_rollover = match val.to_lowercase().as_str() {
+                        "true" | "1" => Some(true),
+                        "false" | "0" => Some(false),
+                        _ => None,
+                    }
+                }
+                ("adr_impact_direction", val) => https_data.adr_impact_direction = val.parse::<f64>().ok(),
+                ("adr_occupants", val) => https_data.adr_occupants = val.parse::<u32>().ok(),
                 ("hmac", val) => https_data.hmac = Some(val.to_string()),
 
-                (_, _) => (),
+                (_key, _) => {
+                    #[cfg(feature = "trace")]
+                    crate::trace::unknown_key(_key);
+                }
             }
         }
 
+        #[cfg(feature = "trace")]
+        crate::trace::https_parsed(&span, https_data.v.as_deref());
+
         https_data
     }
 }
@@ -0,0 +1,88 @@
+use crate::AmlData;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Shares the values of low-cardinality fields (version, positioning method, source of
+/// activation, languages, transport) between the messages kept by long-running daemons.
+/// Only short values are cached, up to `capacity` of them, so that high-cardinality
+/// garbage cannot grow the cache.
+///
+/// # Example
+///
+/// ```
+/// use aml_lib::{AmlData, Interner};
+/// use std::sync::Arc;
+///
+/// let mut interner = Interner::new(64);
+/// let first = AmlData::from_text_sms(r#"A"ML=2;en=112;lo=48.8,2.3,20;ls=G;lg=fr-FR"#).unwrap().tags(&mut interner);
+/// let second = AmlData::from_text_sms(r#"A"ML=2;en=112;lo=43.3,5.4,12;ls=G;lg=fr-FR"#).unwrap().tags(&mut interner);
+///
+/// assert!(Arc::ptr_eq(first.positioning_method.as_ref().unwrap(), second.positioning_method.as_ref().unwrap()));
+/// assert_eq!(interner.len(), 4);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Interner {
+    /// Maximum number of cached values; other values are still returned, not cached.
+    pub capacity: usize,
+
+    /// Longest cached value in bytes.
+    pub max_len: usize,
+
+    values: HashSet<Arc<str>>,
+}
+
+impl Interner {
+    pub fn new(capacity: usize) -> Self {
+        Interner {
+            capacity,
+            max_len: 32,
+            values: HashSet::new(),
+        }
+    }
+
+    /// The shared copy of `value`.
+    pub fn intern(&mut self, value: &str) -> Arc<str> {
+        if let Some(shared) = self.values.get(value) {
+            return Arc::clone(shared);
+        }
+        let shared: Arc<str> = Arc::from(value);
+        if value.len() <= self.max_len && self.values.len() < self.capacity {
+            self.values.insert(Arc::clone(&shared));
+        }
+        shared
+    }
+
+    /// Number of cached values.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+/// The low-cardinality fields of an [`AmlData`], shared through an [`Interner`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct InternedTags {
+    pub version: Option<Arc<str>>,
+    pub positioning_method: Option<Arc<str>>,
+    pub source_of_activation: Option<Arc<str>>,
+    pub languages: Option<Arc<str>>,
+    pub transport: Option<Arc<str>>,
+}
+
+impl AmlData {
+    /// The low-cardinality fields interned with `interner`, to be kept instead of the
+    /// strings of each message.
+    pub fn tags(&self, interner: &mut Interner) -> InternedTags {
+        let mut intern = |value: Option<&str>| value.map(|value| interner.intern(value));
+        InternedTags {
+            version: intern(self.version.as_deref()),
+            positioning_method: intern(self.positioning_method.as_deref()),
+            source_of_activation: intern(self.source_of_activation.as_deref()),
+            languages: intern(self.languages.as_deref()),
+            transport: intern(Some(self.transport.as_str()).filter(|transport| !transport.is_empty())),
+        }
+    }
+}
@@ -0,0 +1,587 @@
+//! JSON form of the data, read and written with serde_json.
+//!
+//! With the `serde` feature, [`AmlData`] implements `Serialize` and `Deserialize` with
+//! the members of [`AmlData::to_json_value`], unknown values being written as `null`.
+
+use crate::geo::Ellipse;
+use crate::{timestamp, AdrData, AmlData, AmlError, AuthError, CivicAddress, HttpsData, Profile, ReceiveContext, Timestamp};
+use serde::de::{DeserializeSeed, Error as _, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::value::RawValue;
+
+/// A JSON value. Object members keep their order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Parse a JSON document. Returns `None` if it is not valid JSON as of RFC 8259
+    /// (e.g. `+1`, `.5`, raw control characters or unpaired surrogates in strings), or if
+    /// a number does not fit into a `f64`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::json::JsonValue;
+    ///
+    /// let value = JsonValue::parse(r#"{"transport": "sms", "payload": "A\"ML=1"}"#).unwrap();
+    /// assert_eq!(value.get("payload").and_then(JsonValue::as_str), Some(r#"A"ML=1"#));
+    /// ```
+    pub fn parse(text: &str) -> Option<JsonValue> {
+        serde_json::from_str(text).ok()
+    }
+
+    /// Member `key` of an object.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonValue::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for JsonValue {
+    /// Compact serialization, on a single line.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&serde_json::to_string(self).map_err(|_| std::fmt::Error)?)
+    }
+}
+
+/// Integral numbers are written without fraction and non-finite ones as `null`.
+impl Serialize for JsonValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            JsonValue::Null => serializer.serialize_unit(),
+            JsonValue::Bool(b) => serializer.serialize_bool(*b),
+            JsonValue::Number(n) if n.fract() == 0.0 && n.abs() < 9.0e15 => serializer.serialize_i64(*n as i64),
+            JsonValue::Number(n) if n.is_finite() => serializer.serialize_f64(*n),
+            JsonValue::Number(_) => serializer.serialize_unit(),
+            JsonValue::String(s) => serializer.serialize_str(s),
+            JsonValue::Array(values) => serializer.collect_seq(values),
+            JsonValue::Object(members) => serializer.collect_map(members.iter().map(|(k, v)| (k, v))),
+        }
+    }
+}
+
+/// Object members are kept in order, and arrays and objects nested deeper than
+/// [`MAX_DEPTH`] are rejected.
+impl<'de> Deserialize<'de> for JsonValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Nested(0).deserialize(deserializer)
+    }
+}
+
+/// Maximum nesting of arrays and objects accepted by the parser.
+pub(crate) const MAX_DEPTH: usize = 64;
+
+/// A value at the given nesting depth.
+#[derive(Clone, Copy)]
+struct Nested(usize);
+
+impl<'de> DeserializeSeed<'de> for Nested {
+    type Value = JsonValue;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<JsonValue, D::Error> {
+        if self.0 > MAX_DEPTH {
+            return Err(D::Error::custom("too deeply nested"));
+        }
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Nested {
+    type Value = JsonValue;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_unit<E>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<JsonValue, D::Error> {
+        self.deserialize(deserializer)
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<JsonValue, E> {
+        Ok(JsonValue::Bool(b))
+    }
+
+    fn visit_i64<E>(self, n: i64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(n as f64))
+    }
+
+    fn visit_u64<E>(self, n: u64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(n as f64))
+    }
+
+    fn visit_f64<E>(self, n: f64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(n))
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(s.to_string()))
+    }
+
+    fn visit_string<E>(self, s: String) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(s))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<JsonValue, A::Error> {
+        let mut values = Vec::new();
+        while let Some(value) = seq.next_element_seed(Nested(self.0 + 1))? {
+            values.push(value);
+        }
+        Ok(JsonValue::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<JsonValue, A::Error> {
+        let mut members = Vec::new();
+        while let Some(key) = map.next_key::<String>()? {
+            members.push((key, map.next_value_seed(Nested(self.0 + 1))?));
+        }
+        Ok(JsonValue::Object(members))
+    }
+}
+
+/// serde form of [`AmlData::ground_truth`]: the `ground_truth_latitude` and
+/// `ground_truth_longitude` members of [`AmlData::to_json_value`].
+#[cfg(feature = "serde")]
+pub(crate) mod ground_truth {
+    use serde::ser::SerializeMap;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(ground_truth: &Option<(f64, f64)>, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        if let Some((latitude, longitude)) = ground_truth {
+            map.serialize_entry("ground_truth_latitude", latitude)?;
+            map.serialize_entry("ground_truth_longitude", longitude)?;
+        }
+        map.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<(f64, f64)>, D::Error> {
+        #[derive(Deserialize)]
+        struct GroundTruth {
+            ground_truth_latitude: Option<f64>,
+            ground_truth_longitude: Option<f64>,
+        }
+
+        let ground_truth = GroundTruth::deserialize(deserializer)?;
+        Ok(ground_truth.ground_truth_latitude.zip(ground_truth.ground_truth_longitude))
+    }
+}
+
+/// serde form of [`AmlData::extras`]: members following the known ones, as in
+/// [`AmlData::to_json_value`].
+#[cfg(feature = "serde")]
+pub(crate) mod extras {
+    use super::{JsonValue, MEMBERS};
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(extras: &[(String, JsonValue)], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(extras.iter().filter(|(key, _)| !MEMBERS.contains(&key.as_str())).map(|(k, v)| (k, v)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<(String, JsonValue)>, D::Error> {
+        match JsonValue::deserialize(deserializer)? {
+            JsonValue::Object(members) => Ok(members.into_iter().filter(|(key, _)| !MEMBERS.contains(&key.as_str())).collect()),
+            _ => Err(D::Error::custom("expected an object")),
+        }
+    }
+}
+
+/// Members written by [`AmlData::to_json_value`].
+const MEMBERS: &[&str] = &[
+    "version",
+    "emergency_number",
+    "source_of_activation",
+    "beginning_of_call",
+    "latitude",
+    "longitude",
+    "time_of_positioning",
+    "altitude",
+    "floor",
+    "positioning_method",
+    "accuracy",
+    "vertical_accuracy",
+    "confidence",
+    "bearing",
+    "speed",
+    "device_number",
+    "model",
+    "imsi",
+    "imei",
+    "iccid",
+    "home_mcc",
+    "home_mnc",
+    "network_mcc",
+    "network_mnc",
+    "languages",
+    "ground_truth_latitude",
+    "ground_truth_longitude",
+    "test",
+    "adr",
+    "ellipse",
+    "address",
+    "profile",
+    "context",
+    "transport",
+    "via",
+];
+
+impl AmlData {
+    /// The data as a JSON object. Unknown values and the [`Profile::Generic`] profile are
+    /// omitted, timestamps are RFC 3339 strings in UTC to the millisecond and
+    /// [`AmlData::extras`] follow the known members.
+    pub fn to_json_value(&self) -> JsonValue {
+        let text = |v: &Option<String>| v.clone().map(JsonValue::String);
+        let number = |v: Option<f64>| v.map(JsonValue::Number);
+        let integer = |v: Option<i32>| v.map(|v| JsonValue::Number(f64::from(v)));
+        let timestamp = |v: Option<Timestamp>| v.map(|dt| JsonValue::String(timestamp::to_rfc3339_millis(dt)));
+
+        let mut members = Vec::new();
+        let mut push = |key: &str, value: Option<JsonValue>| {
+            if let Some(v) = value {
+                members.push((key.to_string(), v));
+            }
+        };
+
+        push("version", text(&self.version));
+        push("emergency_number", text(&self.emergency_number));
+        push("source_of_activation", text(&self.source_of_activation));
+        push("beginning_of_call", timestamp(self.beginning_of_call));
+        push("latitude", number(self.latitude));
+        push("longitude", number(self.longitude));
+        push("time_of_positioning", timestamp(self.time_of_positioning));
+        push("altitude", number(self.altitude));
+        push("floor", text(&self.floor));
+        push("positioning_method", text(&self.positioning_method));
+        push("accuracy", number(self.accuracy));
+        push("vertical_accuracy", number(self.vertical_accuracy));
+        push("confidence", number(self.confidence));
+        push("bearing", number(self.bearing));
+        push("speed", number(self.speed));
+        push("device_number", text(&self.device_number));
+        push("model", text(&self.model));
+        push("imsi", text(&self.imsi));
+        push("imei", text(&self.imei));
+        push("iccid", text(&self.iccid));
+        push("home_mcc", integer(self.home_mcc));
+        push("home_mnc", integer(self.home_mnc));
+        push("network_mcc", integer(self.network_mcc));
+        push("network_mnc", integer(self.network_mnc));
+        push("languages", text(&self.languages));
+        push("ground_truth_latitude", self.ground_truth.map(|(latitude, _)| JsonValue::Number(latitude)));
+        push("ground_truth_longitude", self.ground_truth.map(|(_, longitude)| JsonValue::Number(longitude)));
+        push("test", self.test.then_some(JsonValue::Bool(true)));
+        push("adr", self.adr.as_ref().map(|adr| {
+            let mut members = Vec::new();
+            let mut push = |key: &str, value: Option<JsonValue>| {
+                if let Some(v) = value {
+                    members.push((key.to_string(), v));
+                }
+            };
+            push("carcrash_time", timestamp(adr.carcrash_time));
+            push("severity", text(&adr.severity));
+            push("rollover", adr.rollover.map(JsonValue::Bool));
+            push("impact_direction", number(adr.impact_direction));
+            push("occupants", adr.occupants.map(|v| JsonValue::Number(f64::from(v))));
+            JsonValue::Object(members)
+        }));
+        push("ellipse", self.ellipse.map(|ellipse| {
+            JsonValue::Object(vec![
+                ("semi_major".to_string(), JsonValue::Number(ellipse.semi_major)),
+                ("semi_minor".to_string(), JsonValue::Number(ellipse.semi_minor)),
+                ("orientation".to_string(), JsonValue::Number(ellipse.orientation)),
+            ])
+        }));
+        push("address", self.address.as_ref().map(|address| {
+            let mut members = Vec::new();
+            let mut push = |key: &str, value: Option<JsonValue>| {
+                if let Some(v) = value {
+                    members.push((key.to_string(), v));
+                }
+            };
+            push("country", text(&address.country));
+            push("region", text(&address.region));
+            push("city", text(&address.city));
+            push("postal_code", text(&address.postal_code));
+            push("street", text(&address.street));
+            push("house_number", text(&address.house_number));
+            push("formatted", text(&address.formatted));
+            JsonValue::Object(members)
+        }));
+        push("profile", (self.profile != Profile::Generic).then(|| JsonValue::String(self.profile.as_str().to_string())));
+        push("context", self.context.as_ref().map(|context| {
+            let mut members = Vec::new();
+            let mut push = |key: &str, value: Option<JsonValue>| {
+                if let Some(v) = value {
+                    members.push((key.to_string(), v));
+                }
+            };
+            push("received_at", timestamp(context.received_at));
+            push("source_msisdn", text(&context.source_msisdn));
+            push("gateway_id", text(&context.gateway_id));
+            push("remote_ip", context.remote_ip.map(|ip| JsonValue::String(ip.to_string())));
+            JsonValue::Object(members)
+        }));
+        push("transport", Some(JsonValue::String(self.transport.clone())));
+        push("via", text(&self.via));
+
+        for (key, value) in &self.extras {
+            if !MEMBERS.contains(&key.as_str()) {
+                members.push((key.clone(), value.clone()));
+            }
+        }
+
+        JsonValue::Object(members)
+    }
+
+    /// The data as a single line JSON object. See [`AmlData::to_json_value`].
+    pub fn to_json(&self) -> String {
+        self.to_json_value().to_string()
+    }
+
+    /// Read back an object written by [`AmlData::to_json_value`]. Unknown members are
+    /// kept in [`AmlData::extras`] and written back by [`AmlData::to_json_value`], so
+    /// that no data is lost. Returns `None` if `value` is not an object.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::json::JsonValue;
+    /// use aml_lib::AmlData;
+    ///
+    /// let stored = r#"{"latitude":48.82639,"transport":"sms","vendor_cell_id":[208,1,4242]}"#;
+    /// let aml = AmlData::from_json_value(&JsonValue::parse(stored).unwrap()).unwrap();
+    /// assert_eq!(aml.extras[0].0, "vendor_cell_id");
+    /// assert_eq!(aml.to_json(), stored);
+    /// ```
+    pub fn from_json_value(value: &JsonValue) -> Option<AmlData> {
+        let extras = match value {
+            JsonValue::Object(members) => {
+                members.iter().filter(|(key, _)| !MEMBERS.contains(&key.as_str())).cloned().collect()
+            }
+            _ => return None,
+        };
+        let text = |key: &str| value.get(key).and_then(JsonValue::as_str).map(str::to_string);
+        let number = |key: &str| value.get(key).and_then(JsonValue::as_f64);
+        let integer = |key: &str| number(key).map(|v| v as i32);
+        let timestamp = |key: &str| value.get(key).and_then(JsonValue::as_str).and_then(timestamp::parse_rfc3339);
+        let object = |key: &str| value.get(key).filter(|member| matches!(member, JsonValue::Object(_)));
+
+        Some(AmlData {
+            version: text("version"),
+            emergency_number: text("emergency_number"),
+            source_of_activation: text("source_of_activation"),
+            beginning_of_call: timestamp("beginning_of_call"),
+            latitude: number("latitude"),
+            longitude: number("longitude"),
+            time_of_positioning: timestamp("time_of_positioning"),
+            altitude: number("altitude"),
+            floor: text("floor"),
+            positioning_method: text("positioning_method"),
+            accuracy: number("accuracy"),
+            vertical_accuracy: number("vertical_accuracy"),
+            confidence: number("confidence"),
+            bearing: number("bearing"),
+            speed: number("speed"),
+            device_number: text("device_number"),
+            model: text("model"),
+            imsi: text("imsi"),
+            imei: text("imei"),
+            iccid: text("iccid"),
+            home_mcc: integer("home_mcc"),
+            home_mnc: integer("home_mnc"),
+            network_mcc: integer("network_mcc"),
+            network_mnc: integer("network_mnc"),
+            languages: text("languages"),
+            ground_truth: number("ground_truth_latitude").zip(number("ground_truth_longitude")),
+            test: value.get("test").and_then(JsonValue::as_bool).unwrap_or(false),
+            adr: object("adr").map(|adr| AdrData {
+                carcrash_time: adr.get("carcrash_time").and_then(JsonValue::as_str).and_then(timestamp::parse_rfc3339),
+                severity: adr.get("severity").and_then(JsonValue::as_str).map(str::to_string),
+                rollover: adr.get("rollover").and_then(JsonValue::as_bool),
+                impact_direction: adr.get("impact_direction").and_then(JsonValue::as_f64),
+                occupants: adr.get("occupants").and_then(JsonValue::as_f64).map(|v| v as u32),
+            }),
+            ellipse: value.get("ellipse").and_then(|ellipse| {
+                Some(Ellipse {
+                    semi_major: ellipse.get("semi_major").and_then(JsonValue::as_f64)?,
+                    semi_minor: ellipse.get("semi_minor").and_then(JsonValue::as_f64)?,
+                    orientation: ellipse.get("orientation").and_then(JsonValue::as_f64).unwrap_or_default(),
+                })
+            }),
+            transport: text("transport").unwrap_or_default(),
+            address: object("address").map(|address| {
+                let text = |key: &str| address.get(key).and_then(JsonValue::as_str).map(str::to_string);
+                CivicAddress {
+                    country: text("country"),
+                    region: text("region"),
+                    city: text("city"),
+                    postal_code: text("postal_code"),
+                    street: text("street"),
+                    house_number: text("house_number"),
+                    formatted: text("formatted"),
+                }
+            }),
+            profile: text("profile").as_deref().and_then(Profile::parse).unwrap_or_default(),
+            context: object("context").map(|context| ReceiveContext {
+                received_at: context.get("received_at").and_then(JsonValue::as_str).and_then(timestamp::parse_rfc3339),
+                source_msisdn: context.get("source_msisdn").and_then(JsonValue::as_str).map(str::to_string),
+                gateway_id: context.get("gateway_id").and_then(JsonValue::as_str).map(str::to_string),
+                remote_ip: context.get("remote_ip").and_then(JsonValue::as_str).and_then(|v| v.parse().ok()),
+            }),
+            via: text("via"),
+            extras,
+        })
+    }
+}
+
+impl HttpsData {
+    /// Parse a HTTPS AML message posted as a JSON object. The members have the names of
+    /// the urlencoded fields, nested objects being flattened with `_`
+    /// (`{"location": {"latitude": 0.85}}` is `location_latitude`), and arrays being
+    /// joined with `,`. See [`HttpsData::from_urlencoded`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::HttpsData;
+    ///
+    /// let body = r#"{"v": "1", "location": {"latitude": 0.85732, "longitude": -4.26325, "time": 1604912121000}}"#;
+    /// let https_data = HttpsData::from_json(body).unwrap();
+    /// assert_eq!(https_data.location_latitude, Some(0.85732));
+    /// assert!(https_data.location_time.is_some());
+    /// ```
+    pub fn from_json(body: &str) -> Result<Self, AmlError> {
+        Ok(Self::from_urlencoded(json_to_urlencoded(body)?))
+    }
+
+    /// Verify the `hmac` member of a JSON body. The HMAC is the one of the canonical form
+    /// of the body: the members other than `hmac`, in the order of the body and
+    /// flattened as with [`HttpsData::from_json`], sent urlencoded
+    /// (`application/x-www-form-urlencoded`, spaces as `+`) and joined with `&`.
+    /// Numbers and booleans are written as in the body, e.g. `10.40` stays `10.40`, and
+    /// strings are unescaped. See [`HttpsData::authenticate`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{AuthError, HttpsData};
+    ///
+    /// // Signed as `v=1&location_accuracy=10.40&device_model=ABC+ABC`
+    /// let body = r#"{"v": "1", "location": {"accuracy": 10.40}, "device_model": "ABC ABC", "hmac": "2d32e6e5b98c0d82623426e1f8ff27616d83f6e2"}"#;
+    /// assert_eq!(HttpsData::authenticate_json(body, b"AML"), Ok(()));
+    /// assert_eq!(HttpsData::authenticate_json("[1, 2]", b"AML"), Err(AuthError::MalformedBody));
+    /// ```
+    pub fn authenticate_json(body: &str, key: &[u8]) -> Result<(), AuthError> {
+        match json_to_urlencoded(body) {
+            Ok(urlencoded) => Self::authenticate(urlencoded, key),
+            Err(_) => Err(AuthError::MalformedBody),
+        }
+    }
+}
+
+/// The members of a JSON object, in order, with their text as in the document.
+struct RawMembers<'a>(Vec<(String, &'a RawValue)>);
+
+impl<'de: 'a, 'a> Deserialize<'de> for RawMembers<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Members;
+
+        impl<'de> Visitor<'de> for Members {
+            type Value = RawMembers<'de>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a JSON object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<RawMembers<'de>, A::Error> {
+                let mut members = Vec::new();
+                while let Some(member) = map.next_entry()? {
+                    members.push(member);
+                }
+                Ok(RawMembers(members))
+            }
+        }
+
+        deserializer.deserialize_map(Members)
+    }
+}
+
+/// The members of a JSON object as a urlencoded body, `hmac` last: the canonical form of
+/// [`HttpsData::authenticate_json`].
+fn json_to_urlencoded(body: &str) -> Result<String, AmlError> {
+    /// A string unescaped, a number or a boolean as written, `None` for `null` and
+    /// arrays or objects.
+    fn scalar(value: &RawValue) -> Result<Option<String>, serde_json::Error> {
+        match value.get().as_bytes()[0] {
+            b'"' => serde_json::from_str(value.get()).map(Some),
+            b'n' | b'[' | b'{' => Ok(None),
+            _ => Ok(Some(value.get().to_string())),
+        }
+    }
+
+    fn flatten(prefix: &str, members: RawMembers, fields: &mut Vec<(String, String)>) -> Result<(), serde_json::Error> {
+        for (key, value) in members.0 {
+            let name = if prefix.is_empty() { key } else { format!("{}_{}", prefix, key) };
+            match value.get().as_bytes()[0] {
+                b'{' => flatten(&name, serde_json::from_str(value.get())?, fields)?,
+                b'[' => {
+                    let values: Vec<&RawValue> = serde_json::from_str(value.get())?;
+                    let values: Vec<String> = values.into_iter().filter_map(|value| scalar(value).transpose()).collect::<Result<_, _>>()?;
+                    fields.push((name, values.join(",")));
+                }
+                _ => fields.extend(scalar(value)?.map(|value| (name, value))),
+            }
+        }
+        Ok(())
+    }
+
+    // Checked as a whole first, for the nesting limit
+    JsonValue::parse(body).filter(|value| matches!(value, JsonValue::Object(_))).ok_or(AmlError::InvalidJson)?;
+    let mut fields = Vec::new();
+    serde_json::from_str(body).and_then(|members| flatten("", members, &mut fields)).map_err(|_| AmlError::InvalidJson)?;
+
+    let (hmac, fields): (Vec<_>, Vec<_>) = fields.into_iter().partition(|(key, _)| key == "hmac");
+    let mut urlencoded = url::form_urlencoded::Serializer::new(String::new()).extend_pairs(fields).finish();
+    for (_, hmac) in hmac {
+        urlencoded.push_str(&format!("&hmac={}", hmac));
+    }
+    Ok(urlencoded)
+}
@@ -0,0 +1,161 @@
+//! Publication of the parsed messages to Kafka.
+//!
+//! [`KafkaSink`] builds the records and hands them to a [`KafkaProducer`], implemented
+//! by the [`rdkafka`] `BaseProducer` and `ThreadedProducer` (whose context does not
+//! take delivery opaques), and by any adapter over another client.
+//!
+//! ```
+//! use aml_lib::kafka::KafkaSink;
+//! use aml_lib::AmlData;
+//! use rdkafka::producer::ThreadedProducer;
+//! use rdkafka::ClientConfig;
+//!
+//! let producer: ThreadedProducer<_> = ClientConfig::new().set("bootstrap.servers", "localhost:9092").create()?;
+//! let mut sink = KafkaSink::new(producer, "aml.locations");
+//!
+//! let aml = AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;ei=358239059042542"#).unwrap();
+//! sink.publish(&aml)?;
+//! # Ok::<(), rdkafka::error::KafkaError>(())
+//! ```
+
+use crate::{timestamp, AmlData};
+use ::rdkafka::error::KafkaError;
+use ::rdkafka::message::{Header, OwnedHeaders};
+use ::rdkafka::producer::{BaseProducer, BaseRecord, Partitioner, ProducerContext, ThreadedProducer};
+
+/// A record ready to be produced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KafkaRecord {
+    pub topic: String,
+
+    /// See [`message_key`].
+    pub key: String,
+
+    /// The data as JSON (see [`AmlData::to_json`]), including the receive context.
+    pub payload: String,
+
+    /// `content-type`, `aml-transport` and `aml-version` (when known).
+    pub headers: Vec<(String, String)>,
+}
+
+impl KafkaRecord {
+    /// The record of `aml` for `topic`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::kafka::KafkaRecord;
+    /// use aml_lib::AmlData;
+    ///
+    /// let aml = AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;ei=358239059042542"#).unwrap();
+    /// let record = KafkaRecord::of("aml.locations", &aml);
+    /// assert_eq!(record.key, "358239059042542");
+    /// assert!(record.payload.starts_with(r#"{"version":"2""#));
+    /// ```
+    pub fn of(topic: &str, aml: &AmlData) -> Self {
+        let mut headers = vec![
+            ("content-type".to_string(), "application/json".to_string()),
+            ("aml-transport".to_string(), aml.transport.clone()),
+        ];
+        if let Some(version) = &aml.version {
+            headers.push(("aml-version".to_string(), version.clone()));
+        }
+
+        KafkaRecord {
+            topic: topic.to_string(),
+            key: message_key(aml),
+            payload: aml.to_json(),
+            headers,
+        }
+    }
+}
+
+/// Key of the records of `aml`, so that all the messages of a handset go to the same
+/// partition, in order: the IMEI, else a call identifier made of the IMSI, phone number
+/// or originating number and the beginning of call, else the fingerprint of the message.
+pub fn message_key(aml: &AmlData) -> String {
+    if let Some(imei) = &aml.imei {
+        return imei.clone();
+    }
+
+    let device = aml
+        .imsi
+        .as_ref()
+        .or(aml.device_number.as_ref())
+        .or_else(|| aml.context.as_ref().and_then(|context| context.source_msisdn.as_ref()));
+    match (device, aml.beginning_of_call) {
+        (Some(device), Some(et)) => format!("{}@{}", device, timestamp::unix(et)),
+        _ => format!("{:016x}", aml.fingerprint()),
+    }
+}
+
+/// The client sending the records to the brokers.
+pub trait KafkaProducer {
+    type Error;
+
+    fn send(&mut self, record: KafkaRecord) -> Result<(), Self::Error>;
+}
+
+/// Enqueue the record, the delivery being reported by the polls of the producer.
+impl<C, Part> KafkaProducer for BaseProducer<C, Part>
+where
+    C: ProducerContext<Part, DeliveryOpaque = ()>,
+    Part: Partitioner,
+{
+    type Error = KafkaError;
+
+    fn send(&mut self, record: KafkaRecord) -> Result<(), KafkaError> {
+        let headers = headers(&record);
+        let base = BaseRecord::to(&record.topic).key(&record.key).payload(&record.payload).headers(headers);
+        BaseProducer::send(self, base).map_err(|(error, _)| error)
+    }
+}
+
+/// Enqueue the record, the delivery being reported by the thread of the producer.
+impl<C, Part> KafkaProducer for ThreadedProducer<C, Part>
+where
+    C: ProducerContext<Part, DeliveryOpaque = ()> + 'static,
+    Part: Partitioner + Send + Sync + 'static,
+{
+    type Error = KafkaError;
+
+    fn send(&mut self, record: KafkaRecord) -> Result<(), KafkaError> {
+        let headers = headers(&record);
+        let base = BaseRecord::to(&record.topic).key(&record.key).payload(&record.payload).headers(headers);
+        ThreadedProducer::send(self, base).map_err(|(error, _)| error)
+    }
+}
+
+fn headers(record: &KafkaRecord) -> OwnedHeaders {
+    record.headers.iter().fold(OwnedHeaders::new_with_capacity(record.headers.len()), |headers, (name, value)| {
+        headers.insert(Header {
+            key: name,
+            value: Some(value.as_str()),
+        })
+    })
+}
+
+/// Publishes the parsed messages to a topic.
+pub struct KafkaSink<P> {
+    producer: P,
+    topic: String,
+}
+
+impl<P: KafkaProducer> KafkaSink<P> {
+    pub fn new(producer: P, topic: &str) -> Self {
+        KafkaSink {
+            producer,
+            topic: topic.to_string(),
+        }
+    }
+
+    /// Send the record of `aml`. See [`KafkaRecord::of`].
+    pub fn publish(&mut self, aml: &AmlData) -> Result<(), P::Error> {
+        self.producer.send(KafkaRecord::of(&self.topic, aml))
+    }
+
+    /// The producer, e.g. to flush it.
+    pub fn producer(&mut self) -> &mut P {
+        &mut self.producer
+    }
+}
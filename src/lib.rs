@@ -1,12 +1,139 @@
+//! A library for dealing with AML (Advanced Mobile Location) messages, as of ETSI TS 103 625.
+//!
+//! # Date and time backend
+//!
+//! The timestamps are the types of chrono (`chrono` feature, the default) or of the `time`
+//! crate (`time` feature). These two features are not additive: with both, chrono is used,
+//! so code that must build with either backend should only use the [`timestamp`] module.
+
+mod address;
+mod adr;
 mod aml;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod batch;
+mod compare;
+mod context;
+mod convert;
+pub mod conformance;
+mod dedup;
+mod diff;
+mod display;
+mod encode;
+#[cfg(feature = "ecall")]
+pub mod ecall;
+pub mod emergency;
+mod expiry;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+mod fusion;
+pub mod generator;
+pub mod geo;
+#[cfg(feature = "geoid")]
+pub mod geoid;
+mod header;
+#[cfg(feature = "heapless")]
+pub mod heapless;
+mod intern;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+mod map;
+mod mapping;
+mod merge;
+mod method;
+mod model;
+mod movement;
+#[cfg(feature = "msgpack")]
+pub mod msgpack;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+#[cfg(feature = "multipart")]
+mod multipart;
+mod observer;
+mod openapi;
+mod pdu;
+pub mod pemea;
+pub mod precision;
+mod profile;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+mod quality;
+mod redaction;
+mod retention;
+mod rng;
+mod roaming;
+mod sentinel;
+#[cfg(feature = "server")]
+pub mod server;
+mod session;
+mod signature;
+mod skew;
+#[cfg(feature = "sql")]
+pub mod sql;
+mod stats;
+#[cfg(feature = "store")]
+pub mod store;
+mod test_message;
+#[cfg(feature = "testkit")]
+pub mod testkit;
 mod https;
 mod sms;
+pub mod timestamp;
 mod tools;
+#[cfg(feature = "trace")]
+pub mod trace;
 mod hmac;
+mod limits;
+pub mod json;
+mod unescape;
+mod units;
+mod validation;
+mod version;
+pub mod v2;
+pub mod web;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
+pub use adr::AdrData;
+pub use address::{AsyncReverseGeocoder, CivicAddress, ReverseGeocoder};
 pub use aml::AmlData;
-pub use https::HttpsData;
+pub use compare::Tolerance;
+pub use context::ReceiveContext;
+pub use convert::{LossReport, TargetFormat};
+pub use dedup::Deduplicator;
+pub use emergency::{EmergencyNumberCheck, ServiceType};
+pub use diff::FieldChange;
+pub use expiry::ExpiryPolicy;
+pub use fusion::{FusedLocation, FusionInput};
+pub use intern::{InternedTags, Interner};
+pub use map::MapProvider;
+pub use mapping::{KeyMapping, KeyRule};
+pub use method::PositioningMethod;
+pub use movement::{Movement, MovementIssue};
+pub use model::{SmsDataV1, SmsDataV2, SmsMessage};
+pub use observer::{DropObserver, DropReason, DroppedField};
+pub use pdu::{ApplicationPorts, PduEnvelope};
+pub use profile::Profile;
+pub use quality::AccuracyClass;
+pub use redaction::{Redaction, RedactionPolicy};
+pub use retention::{RetentionPolicy, RetentionStage};
+pub use roaming::Roaming;
+pub use sentinel::{Sentinel, SentinelAction, SentinelPolicy, SentinelValue};
+pub use session::{EstimatedMotion, Session, SessionAggregator, Transition};
+pub use test_message::TEST_EMERGENCY_NUMBERS;
+pub use stats::{Stats, ACCURACY_BUCKETS, MAX_DISTINCT_KEYS, OTHER_KEY};
+pub use skew::{ClockSkew, SkewIssue, SkewPolicy};
+pub use signature::{Canonicalization, HmacSha1, SignatureVerifier, SmsSignature};
+pub use https::{AuthError, HttpsData};
 pub use sms::SmsData;
+pub use timestamp::{Duration, Timestamp};
+pub use units::{Degrees, Meters, MetersPerSecond, Percent};
+pub use header::{HeaderDeviation, HeaderTolerance};
+pub use limits::{Limit, Limits};
+pub use validation::{LengthMode, MessageLengths, TextCleanup, ValidationFailure, ValidationReport};
+pub use version::{AmlVersion, VersionPolicy};
 
 #[derive(Debug)]
 pub enum AmlError {
@@ -14,20 +141,182 @@ pub enum AmlError {
     UnimplementedVersion,
 
     /// You have tried to parse an corrumpted base64 SMS data.
+    #[deprecated(note = "base64 decoding failures are reported as `AmlError::Base64`, with the same code")]
     InvalidBase64,
+
+    /// You have tried to parse a SMS data which is not valid hexadecimal.
+    #[deprecated(note = "hexadecimal decoding failures are reported as `AmlError::Hex`, with the same code")]
+    InvalidHex,
+
+    /// The AML messages could not be read.
+    Io(std::io::Error),
+
+    /// The payload is not valid UTF-8 text (or is missing, or is not valid UTF-16 in a
+    /// UCS2 SMS PDU, in which case there is no underlying UTF-8 error).
+    InvalidUtf8(Option<std::str::Utf8Error>),
+
+    /// A NDJSON record is not valid JSON, has no payload or has an unknown transport.
+    InvalidRecord,
+
+    /// A PEMEA envelope is malformed or lacks a mandatory element.
+    InvalidEnvelope,
+
+    /// A SMS PDU is truncated or is not a SMS-DELIVER.
+    InvalidPdu,
+
+    /// An eCall MSD is truncated or has unsupported extensions.
+    InvalidMsd,
+
+    /// A multipart body has no boundary, a part without name or no close delimiter.
+    InvalidMultipart,
+
+    /// A JSON body is not valid JSON or is not an object.
+    InvalidJson,
+
+    /// You have tried to parse an corrumpted base64 SMS data. The decoding error is
+    /// the [`source`](std::error::Error::source).
+    Base64(base64::DecodeError),
+
+    /// You have tried to parse a SMS data which is not valid hexadecimal. The decoding
+    /// error is the [`source`](std::error::Error::source).
+    Hex(hex::FromHexError),
+
+    /// A MessagePack payload is truncated, uses unsupported types or is not a map.
+    InvalidMsgpack,
+
+    /// A Protocol Buffers message is truncated or has a field of an unexpected type.
+    InvalidProtobuf,
+
+    /// The payload exceeds a hard limit. See [`Limits`].
+    LimitExceeded(Limit),
+}
+
+impl AmlError {
+    /// Stable numeric code of the error, to key on instead of the display text. Codes
+    /// are never reused nor renumbered; the deprecated variants share the code of the
+    /// variant replacing them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{AmlError, SmsData};
+    ///
+    /// let err = SmsData::from_hex("4151Z3").unwrap_err();
+    /// assert_eq!((err.code(), err.name()), (3, "invalid_hex"));
+    /// assert!(std::error::Error::source(&err).is_some());
+    /// ```
+    #[allow(deprecated)]
+    pub fn code(&self) -> u16 {
+        match self {
+            AmlError::UnimplementedVersion => 1,
+            AmlError::InvalidBase64 | AmlError::Base64(_) => 2,
+            AmlError::InvalidHex | AmlError::Hex(_) => 3,
+            AmlError::Io(_) => 4,
+            AmlError::InvalidUtf8(_) => 5,
+            AmlError::InvalidRecord => 6,
+            AmlError::InvalidEnvelope => 7,
+            AmlError::InvalidPdu => 8,
+            AmlError::InvalidMsd => 9,
+            AmlError::InvalidMultipart => 10,
+            AmlError::InvalidJson => 11,
+            AmlError::InvalidMsgpack => 12,
+            AmlError::InvalidProtobuf => 13,
+            AmlError::LimitExceeded(_) => 14,
+        }
+    }
+
+    /// Stable string code of the error, in snake case. See [`AmlError::code`].
+    #[allow(deprecated)]
+    pub fn name(&self) -> &'static str {
+        match self {
+            AmlError::UnimplementedVersion => "unimplemented_version",
+            AmlError::InvalidBase64 | AmlError::Base64(_) => "invalid_base64",
+            AmlError::InvalidHex | AmlError::Hex(_) => "invalid_hex",
+            AmlError::Io(_) => "io",
+            AmlError::InvalidUtf8(_) => "invalid_utf8",
+            AmlError::InvalidRecord => "invalid_record",
+            AmlError::InvalidEnvelope => "invalid_envelope",
+            AmlError::InvalidPdu => "invalid_pdu",
+            AmlError::InvalidMsd => "invalid_msd",
+            AmlError::InvalidMultipart => "invalid_multipart",
+            AmlError::InvalidJson => "invalid_json",
+            AmlError::InvalidMsgpack => "invalid_msgpack",
+            AmlError::InvalidProtobuf => "invalid_protobuf",
+            AmlError::LimitExceeded(_) => "limit_exceeded",
+        }
+    }
 }
 
-impl std::error::Error for AmlError {}
+impl std::error::Error for AmlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AmlError::Base64(err) => Some(err),
+            AmlError::Hex(err) => Some(err),
+            AmlError::Io(err) => Some(err),
+            AmlError::InvalidUtf8(Some(err)) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<base64::DecodeError> for AmlError {
+    fn from(err: base64::DecodeError) -> Self {
+        AmlError::Base64(err)
+    }
+}
+
+impl From<hex::FromHexError> for AmlError {
+    fn from(err: hex::FromHexError) -> Self {
+        AmlError::Hex(err)
+    }
+}
+
+impl From<std::str::Utf8Error> for AmlError {
+    fn from(err: std::str::Utf8Error) -> Self {
+        AmlError::InvalidUtf8(Some(err))
+    }
+}
+
+impl From<std::string::FromUtf8Error> for AmlError {
+    fn from(err: std::string::FromUtf8Error) -> Self {
+        AmlError::InvalidUtf8(Some(err.utf8_error()))
+    }
+}
 
 impl std::fmt::Display for AmlError {
+    #[allow(deprecated)]
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let text = match *self {
+        let text = match self {
             AmlError::UnimplementedVersion => {
                 String::from("You have tried to parse an unimplemented version of SMS AML")
             }
-            AmlError::InvalidBase64 => {
+            AmlError::InvalidBase64 | AmlError::Base64(_) => {
                 String::from("You have tried to parse an corrumpted base64 SMS data")
             }
+            AmlError::InvalidHex | AmlError::Hex(_) => {
+                String::from("You have tried to parse a SMS data which is not valid hexadecimal")
+            }
+            AmlError::Io(err) => format!("The AML messages could not be read ({})", err),
+            AmlError::InvalidUtf8(_) => String::from("The payload is not valid UTF-8 text"),
+            AmlError::InvalidRecord => {
+                String::from("The record is not valid JSON, has no payload or has an unknown transport")
+            }
+            AmlError::InvalidEnvelope => {
+                String::from("The PEMEA envelope is malformed or lacks a mandatory element")
+            }
+            AmlError::InvalidPdu => String::from("The SMS PDU is truncated or is not a SMS-DELIVER"),
+            AmlError::InvalidMsd => String::from("The eCall MSD is truncated or has unsupported extensions"),
+            AmlError::InvalidMultipart => {
+                String::from("The multipart body has no boundary, a part without name or no close delimiter")
+            }
+            AmlError::InvalidJson => String::from("The JSON body is not valid JSON or is not an object"),
+            AmlError::InvalidMsgpack => {
+                String::from("The MessagePack payload is truncated, uses unsupported types or is not a map")
+            }
+            AmlError::InvalidProtobuf => {
+                String::from("The Protocol Buffers message is truncated or has a field of an unexpected type")
+            }
+            AmlError::LimitExceeded(limit) => format!("The payload exceeds a hard limit ({:?})", limit),
         };
         write!(f, "Error: {}", text)
     }
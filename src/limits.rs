@@ -0,0 +1,94 @@
+use crate::{AmlData, AmlError, HttpsData, SmsData};
+
+/// A limit of [`Limits`], see [`AmlError::LimitExceeded`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    PayloadLength,
+    Properties,
+    ValueLength,
+}
+
+/// Hard limits on untrusted payloads, checked by scanning the raw payload before the
+/// parser allocates anything. The defaults are far above what handsets send.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Bytes of the payload, 4096 by default.
+    pub max_payload_length: usize,
+
+    /// Properties (`key=value` pairs) of the payload, 64 by default.
+    pub max_properties: usize,
+
+    /// Bytes of the value of a property, still urlencoded for HTTPS, 256 by default.
+    pub max_value_length: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_payload_length: 4096,
+            max_properties: 64,
+            max_value_length: 256,
+        }
+    }
+}
+
+impl Limits {
+    /// Check `payload`, made of properties separated by `separator` (`;` for SMS, `&`
+    /// for HTTPS).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{AmlError, Limit, Limits};
+    ///
+    /// let limits = Limits { max_properties: 2, ..Default::default() };
+    /// assert!(limits.check("v=1&location_latitude=0.85", b'&').is_ok());
+    /// assert!(matches!(limits.check("v=1&location_latitude=0.85&x=1", b'&'), Err(AmlError::LimitExceeded(Limit::Properties))));
+    /// ```
+    pub fn check(&self, payload: &str, separator: u8) -> Result<(), AmlError> {
+        if payload.len() > self.max_payload_length {
+            return Err(AmlError::LimitExceeded(Limit::PayloadLength));
+        }
+
+        let properties = payload.as_bytes().split(|b| *b == separator).filter(|property| !property.is_empty());
+        for (i, property) in properties.enumerate() {
+            if i >= self.max_properties {
+                return Err(AmlError::LimitExceeded(Limit::Properties));
+            }
+            let value = property.iter().position(|b| *b == b'=').map_or(property, |equal| &property[equal + 1..]);
+            if value.len() > self.max_value_length {
+                return Err(AmlError::LimitExceeded(Limit::ValueLength));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SmsData {
+    /// Parse a SMS text like [`SmsData::from_text`] once checked against `limits`.
+    pub fn from_text_limited<S: AsRef<str>>(text_sms: S, limits: &Limits) -> Result<Self, AmlError> {
+        limits.check(text_sms.as_ref(), b';')?;
+        Self::from_text(text_sms)
+    }
+}
+
+impl HttpsData {
+    /// Parse a HTTPS AML message like [`HttpsData::from_urlencoded`] once checked
+    /// against `limits`.
+    pub fn from_urlencoded_limited<S: AsRef<str>>(payload: S, limits: &Limits) -> Result<Self, AmlError> {
+        limits.check(payload.as_ref(), b'&')?;
+        Ok(Self::from_urlencoded(payload))
+    }
+}
+
+impl AmlData {
+    /// Parse a SMS text checked against `limits`. See [`SmsData::from_text_limited`].
+    pub fn from_text_sms_limited<S: AsRef<str>>(text_sms: S, limits: &Limits) -> Result<Self, AmlError> {
+        Ok(SmsData::from_text_limited(text_sms, limits)?.into())
+    }
+
+    /// Parse a HTTPS AML message checked against `limits`. See [`HttpsData::from_urlencoded_limited`].
+    pub fn from_https_limited(payload: &str, limits: &Limits) -> Result<Self, AmlError> {
+        Ok(HttpsData::from_urlencoded_limited(payload, limits)?.into())
+    }
+}
@@ -0,0 +1,74 @@
+use crate::AmlData;
+
+/// Web map services supported by [`AmlData::map_url`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapProvider {
+    OpenStreetMap,
+    Google,
+    Bing,
+}
+
+/// Zoom level used when the accuracy is unknown.
+const DEFAULT_ZOOM: u8 = 16;
+
+/// Width in pixels the uncertainty circle should fit in.
+const VIEW_PIXELS: f64 = 400.0;
+
+impl AmlData {
+    /// Link to `provider` centered on the location, with a marker when the provider supports it.
+    /// The accuracy is encoded as the zoom level (or as the viewport altitude for Google)
+    /// so that the uncertainty circle fits in the view.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{AmlData, MapProvider};
+    ///
+    /// let aml = AmlData::from_https("location_latitude=48.8583&location_longitude=2.2945&location_accuracy=50").unwrap();
+    /// assert_eq!(
+    ///     aml.map_url(MapProvider::OpenStreetMap).unwrap(),
+    ///     "https://www.openstreetmap.org/?mlat=48.8583&mlon=2.2945#map=18/48.8583/2.2945"
+    /// );
+    /// ```
+    pub fn map_url(&self, provider: MapProvider) -> Option<String> {
+        let (lat, lon) = self.position()?;
+        let zoom = self.map_zoom();
+
+        Some(match provider {
+            MapProvider::OpenStreetMap => format!(
+                "https://www.openstreetmap.org/?mlat={lat}&mlon={lon}#map={zoom}/{lat}/{lon}",
+                lat = lat,
+                lon = lon,
+                zoom = zoom
+            ),
+            MapProvider::Google => match self.accuracy.filter(|acc| *acc > 0.0) {
+                Some(acc) => format!(
+                    "https://www.google.com/maps/search/{lat},{lon}/@{lat},{lon},{span:.0}m",
+                    lat = lat,
+                    lon = lon,
+                    span = (acc * 4.0).max(50.0)
+                ),
+                None => format!("https://www.google.com/maps/search/?api=1&query={},{}", lat, lon),
+            },
+            MapProvider::Bing => format!(
+                "https://www.bing.com/maps?cp={lat}~{lon}&lvl={zoom}&sp=point.{lat}_{lon}_AML",
+                lat = lat,
+                lon = lon,
+                zoom = zoom
+            ),
+        })
+    }
+
+    /// Web Mercator zoom level (3 to 19) fitting the uncertainty circle.
+    fn map_zoom(&self) -> u8 {
+        match (self.latitude, self.accuracy) {
+            (Some(lat), Some(acc)) if acc > 0.0 => {
+                // Meters per pixel at zoom 0 for 256 px tiles
+                let resolution = 156_543.034 * lat.to_radians().cos();
+                let zoom = (resolution * VIEW_PIXELS / (2.0 * acc)).log2().floor();
+                zoom.clamp(3.0, 19.0) as u8
+            }
+            _ => DEFAULT_ZOOM,
+        }
+    }
+}
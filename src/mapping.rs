@@ -0,0 +1,219 @@
+use crate::json::JsonValue;
+use crate::{millis_to_utc, seconds_to_utc, AmlData, AmlError, HttpsData, SmsData};
+use std::borrow::Cow;
+
+/// How the value of a mapped key is converted. See [`KeyMapping`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyRule {
+    /// Key as sent by the handset.
+    pub key: String,
+
+    /// Field of [`AmlData`] the value goes to. Any other name adds the value to
+    /// [`AmlData::extras`] under that name.
+    pub field: String,
+
+    /// Factor applied to numbers, e.g. 100 for a confidence sent as a ratio.
+    pub scale: f64,
+
+    /// Added to numbers after `scale`.
+    pub offset: f64,
+
+    /// Timestamps are in milliseconds rather than seconds.
+    pub millis: bool,
+}
+
+/// Extra keys and aliases of vendor messages, loaded at run time: the mapped keys are
+/// applied once the message has been parsed, overriding the standard attributes.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct KeyMapping {
+    pub rules: Vec<KeyRule>,
+}
+
+impl KeyMapping {
+    /// Read a mapping from a JSON object whose members are the keys, each mapped to the
+    /// name of a field or to an object with `field` and optionally `scale`, `offset`
+    /// (numbers) and `unit` (`s` or `ms`, for timestamps).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{AmlData, KeyMapping};
+    ///
+    /// let mapping = KeyMapping::from_json(r#"{"loc_conf": {"field": "confidence", "scale": 100}, "cid": "cell_id"}"#).unwrap();
+    ///
+    /// let https = "v=1&location_latitude=48.85&location_longitude=2.35&loc_conf=0.68&cid=4242";
+    /// let aml = AmlData::from_https_mapped(https, &mapping).unwrap();
+    /// assert_eq!(aml.confidence, Some(68.0));
+    /// assert_eq!(aml.extras[0].0, "cell_id");
+    /// ```
+    pub fn from_json(text: &str) -> Result<Self, AmlError> {
+        match JsonValue::parse(text) {
+            Some(JsonValue::Object(members)) => Self::from_members(members).ok_or(AmlError::InvalidJson),
+            _ => Err(AmlError::InvalidJson),
+        }
+    }
+
+    /// Read a mapping from a TOML document, with the same structure as
+    /// [`KeyMapping::from_json`]: the keys are mapped to the name of a field or to a table.
+    /// The rules are sorted by key. A valid TOML document of another structure gives an
+    /// "invalid key mapping" error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{AmlData, KeyMapping};
+    ///
+    /// let mapping = KeyMapping::from_toml(r#"
+    ///     cid = "cell_id"
+    ///
+    ///     [loc_conf]
+    ///     field = "confidence"
+    ///     scale = 100
+    /// "#).unwrap();
+    ///
+    /// let https = "v=1&location_latitude=48.85&location_longitude=2.35&loc_conf=0.68&cid=4242";
+    /// let aml = AmlData::from_https_mapped(https, &mapping).unwrap();
+    /// assert_eq!(aml.confidence, Some(68.0));
+    /// ```
+    #[cfg(feature = "toml")]
+    pub fn from_toml(text: &str) -> Result<Self, ::toml::de::Error> {
+        fn json(value: ::toml::Value) -> JsonValue {
+            match value {
+                ::toml::Value::String(text) => JsonValue::String(text),
+                ::toml::Value::Integer(number) => JsonValue::Number(number as f64),
+                ::toml::Value::Float(number) => JsonValue::Number(number),
+                ::toml::Value::Boolean(value) => JsonValue::Bool(value),
+                ::toml::Value::Datetime(datetime) => JsonValue::String(datetime.to_string()),
+                ::toml::Value::Array(values) => JsonValue::Array(values.into_iter().map(json).collect()),
+                ::toml::Value::Table(table) => JsonValue::Object(table.into_iter().map(|(key, value)| (key, json(value))).collect()),
+            }
+        }
+
+        let table = text.parse::<::toml::Table>()?;
+        Self::from_members(table.into_iter().map(|(key, value)| (key, json(value))).collect())
+            .ok_or_else(|| serde::de::Error::custom("invalid key mapping"))
+    }
+
+    /// The rules of the members of a JSON object, `None` if one is invalid.
+    fn from_members(members: Vec<(String, JsonValue)>) -> Option<Self> {
+        let mut rules = Vec::new();
+        for (key, target) in members {
+            let rule = match &target {
+                JsonValue::String(field) => KeyRule {
+                    key,
+                    field: field.clone(),
+                    scale: 1.0,
+                    offset: 0.0,
+                    millis: false,
+                },
+                JsonValue::Object(_) => KeyRule {
+                    key,
+                    field: target.get("field").and_then(JsonValue::as_str)?.to_string(),
+                    scale: number_or(&target, "scale", 1.0)?,
+                    offset: number_or(&target, "offset", 0.0)?,
+                    millis: match target.get("unit").map(JsonValue::as_str) {
+                        None | Some(Some("s")) => false,
+                        Some(Some("ms")) => true,
+                        Some(_) => return None,
+                    },
+                },
+                _ => return None,
+            };
+            rules.push(rule);
+        }
+        Some(KeyMapping { rules })
+    }
+
+    /// Apply the rules to the `attributes` of a message parsed into `aml`. Values which
+    /// cannot be converted are ignored.
+    pub fn apply<'a, I: IntoIterator<Item = (&'a str, &'a str)>>(&self, aml: &mut AmlData, attributes: I) {
+        for (key, value) in attributes {
+            if let Some(rule) = self.rules.iter().find(|rule| rule.key == key) {
+                rule.apply(aml, value.trim());
+            }
+        }
+    }
+}
+
+fn number_or(target: &JsonValue, name: &str, default: f64) -> Option<f64> {
+    match target.get(name) {
+        Some(value) => value.as_f64(),
+        None => Some(default),
+    }
+}
+
+impl KeyRule {
+    fn apply(&self, aml: &mut AmlData, value: &str) {
+        let number = value.parse::<f64>().ok().map(|number| number * self.scale + self.offset);
+        let integer = number.map(|number| number as i32);
+        let text = Some(value.to_string()).filter(|value| !value.is_empty());
+        let timestamp = number.and_then(|number| {
+            if self.millis {
+                millis_to_utc!(number as i64)
+            } else {
+                seconds_to_utc!(number as i64)
+            }
+        });
+
+        macro_rules! set {
+            ($field: ident, $value: expr) => {
+                if let Some(value) = $value {
+                    aml.$field = Some(value);
+                }
+            };
+        }
+
+        match self.field.as_str() {
+            "latitude" => set!(latitude, number),
+            "longitude" => set!(longitude, number),
+            "altitude" => set!(altitude, number),
+            "accuracy" => set!(accuracy, number),
+            "vertical_accuracy" => set!(vertical_accuracy, number),
+            "confidence" => set!(confidence, number),
+            "bearing" => set!(bearing, number),
+            "speed" => set!(speed, number),
+            "home_mcc" => set!(home_mcc, integer),
+            "home_mnc" => set!(home_mnc, integer),
+            "network_mcc" => set!(network_mcc, integer),
+            "network_mnc" => set!(network_mnc, integer),
+            "beginning_of_call" => set!(beginning_of_call, timestamp),
+            "time_of_positioning" => set!(time_of_positioning, timestamp),
+            "emergency_number" => set!(emergency_number, text),
+            "source_of_activation" => set!(source_of_activation, text),
+            "floor" => set!(floor, text),
+            "positioning_method" => set!(positioning_method, text),
+            "device_number" => set!(device_number, text),
+            "model" => set!(model, text),
+            "imsi" => set!(imsi, text),
+            "imei" => set!(imei, text),
+            "iccid" => set!(iccid, text),
+            "languages" => set!(languages, text),
+            extra => {
+                let value = number.map_or_else(|| JsonValue::String(value.to_string()), JsonValue::Number);
+                aml.extras.retain(|(name, _)| name != extra);
+                aml.extras.push((extra.to_string(), value));
+            }
+        }
+    }
+}
+
+impl AmlData {
+    /// Parse a SMS text, then apply `mapping` to its attributes.
+    /// See [`AmlData::from_text_sms`] and [`KeyMapping`].
+    pub fn from_text_sms_mapped<S: AsRef<str>>(text_sms: S, mapping: &KeyMapping) -> Result<Self, AmlError> {
+        let text_sms = text_sms.as_ref();
+        let mut aml: AmlData = SmsData::from_text(text_sms)?.into();
+        let attributes = text_sms.split(';').filter_map(|property| property.split_once('='));
+        mapping.apply(&mut aml, attributes.map(|(key, value)| (key.trim(), value)));
+        Ok(aml)
+    }
+
+    /// Parse a HTTPS AML message, then apply `mapping` to its attributes.
+    /// See [`AmlData::from_https`] and [`KeyMapping`].
+    pub fn from_https_mapped(payload: &str, mapping: &KeyMapping) -> Result<Self, AmlError> {
+        let mut aml: AmlData = HttpsData::from_urlencoded(payload).into();
+        let pairs: Vec<(Cow<str>, Cow<str>)> = url::form_urlencoded::parse(payload.trim().as_bytes()).collect();
+        mapping.apply(&mut aml, pairs.iter().map(|(key, value)| (key.as_ref(), value.as_ref())));
+        Ok(aml)
+    }
+}
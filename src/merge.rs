@@ -0,0 +1,96 @@
+use crate::AmlData;
+
+impl AmlData {
+    /// Update the data with a `newer` message of the same call.
+    ///
+    /// - The location fields (position, accuracies, ellipse, confidence, altitude, floor, method,
+    ///   bearing, speed, time of positioning and address) are taken as a whole from the message
+    ///   with the most recent time of positioning, together with its version, transport and
+    ///   tunnel, which tell where the location comes from. When the times are equal or unknown, the
+    ///   location with the best (smallest non-zero) accuracy is kept. A message without
+    ///   location never replaces a location.
+    /// - The beginning of call is the earliest one known.
+    /// - Any other field takes the newer value when present, so identifiers are never lost.
+    ///   Data without transport takes the version, transport and tunnel of the newer message.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::AmlData;
+    ///
+    /// let mut aml = AmlData::from_text_sms(r#"A"ML=2;et=1593187189;lo=48.80,2.30,1500;lt=5;ls=C;ei=358239059042542"#).unwrap();
+    /// let newer = AmlData::from_text_sms(r#"A"ML=2;et=1593187189;lo=48.82639,2.36619,12;lt=20;ls=G"#).unwrap();
+    ///
+    /// aml.merge(newer);
+    /// assert_eq!(aml.accuracy, Some(12.0));
+    /// assert_eq!(aml.imei.as_deref(), Some("358239059042542"));
+    /// ```
+    pub fn merge(&mut self, newer: AmlData) {
+        if self.prefers_location_of(&newer) || self.transport.is_empty() {
+            self.version = newer.version.clone();
+            self.transport = newer.transport.clone();
+            self.via = newer.via.clone();
+        }
+        if self.prefers_location_of(&newer) {
+            self.latitude = newer.latitude;
+            self.longitude = newer.longitude;
+            self.accuracy = newer.accuracy;
+            self.confidence = newer.confidence;
+            self.time_of_positioning = newer.time_of_positioning;
+            self.altitude = newer.altitude;
+            self.vertical_accuracy = newer.vertical_accuracy;
+            self.ellipse = newer.ellipse;
+            self.floor = newer.floor;
+            self.positioning_method = newer.positioning_method;
+            self.bearing = newer.bearing;
+            self.speed = newer.speed;
+            self.address = newer.address;
+        }
+
+        self.beginning_of_call = match (self.beginning_of_call, newer.beginning_of_call) {
+            (Some(current), Some(other)) => Some(current.min(other)),
+            (current, other) => current.or(other),
+        };
+
+        macro_rules! newer_or_current {
+            ($( $field: ident ),+) => {
+                $( if newer.$field.is_some() { self.$field = newer.$field; } )+
+            };
+        }
+        newer_or_current!(
+            emergency_number,
+            source_of_activation,
+            device_number,
+            model,
+            imsi,
+            imei,
+            iccid,
+            home_mcc,
+            home_mnc,
+            network_mcc,
+            network_mnc,
+            languages,
+            adr,
+            context
+        );
+    }
+
+    fn prefers_location_of(&self, newer: &AmlData) -> bool {
+        if newer.position().is_none() {
+            return false;
+        }
+        if self.position().is_none() {
+            return true;
+        }
+
+        match (self.time_of_positioning, newer.time_of_positioning) {
+            (Some(current), Some(other)) if current != other => other > current,
+            (None, Some(_)) => true,
+            (Some(_), None) => false,
+            _ => {
+                let known = |acc: Option<f64>| acc.filter(|a| *a > 0.0).unwrap_or(f64::INFINITY);
+                known(newer.accuracy) <= known(self.accuracy)
+            }
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use crate::AmlData;
+
+/// Positioning method of a location, whatever the transport: the SMS letters
+/// (`G`, `W`, `C`, `F`, `U`) and the HTTPS `location_source` values (`gps`, `wifi`,
+/// `cell`, `fused`, `unknown`) map to the same variants.
+///
+/// # Example
+///
+/// ```
+/// use aml_lib::PositioningMethod;
+///
+/// assert_eq!(PositioningMethod::parse("G"), Some(PositioningMethod::Gnss));
+/// assert_eq!(PositioningMethod::parse("GPS"), Some(PositioningMethod::Gnss));
+/// assert_eq!(PositioningMethod::Fused.as_str(), "fused");
+/// assert_eq!(PositioningMethod::Fused.letter(), "F");
+/// assert_eq!(PositioningMethod::parse("bluetooth"), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PositioningMethod {
+    Gnss,
+    Wifi,
+    Cell,
+    Fused,
+    Unknown,
+}
+
+impl PositioningMethod {
+    /// The method of a SMS letter or of a HTTPS value, case insensitive.
+    pub fn parse(method: &str) -> Option<Self> {
+        match method.to_lowercase().as_str() {
+            "g" | "gps" => Some(PositioningMethod::Gnss),
+            "w" | "wifi" => Some(PositioningMethod::Wifi),
+            "c" | "cell" => Some(PositioningMethod::Cell),
+            "f" | "fused" => Some(PositioningMethod::Fused),
+            "u" | "unknown" => Some(PositioningMethod::Unknown),
+            _ => None,
+        }
+    }
+
+    /// The canonical name, the HTTPS `location_source` value.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PositioningMethod::Gnss => "gps",
+            PositioningMethod::Wifi => "wifi",
+            PositioningMethod::Cell => "cell",
+            PositioningMethod::Fused => "fused",
+            PositioningMethod::Unknown => "unknown",
+        }
+    }
+
+    /// The one letter SMS method.
+    pub fn letter(&self) -> &'static str {
+        match self {
+            PositioningMethod::Gnss => "G",
+            PositioningMethod::Wifi => "W",
+            PositioningMethod::Cell => "C",
+            PositioningMethod::Fused => "F",
+            PositioningMethod::Unknown => "U",
+        }
+    }
+}
+
+impl std::fmt::Display for PositioningMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl AmlData {
+    /// The canonical positioning method, [`AmlData::positioning_method`] keeping the
+    /// value as received. `None` if there is none or it is not recognized.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{AmlData, PositioningMethod};
+    ///
+    /// let sms = AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;ls=F"#).unwrap();
+    /// let https = AmlData::from_https("v=1&location_latitude=0.85732&location_longitude=-4.26325&location_source=Fused").unwrap();
+    ///
+    /// assert_eq!((sms.positioning_method.as_deref(), https.positioning_method.as_deref()), (Some("F"), Some("fused")));
+    /// assert_eq!(sms.method(), Some(PositioningMethod::Fused));
+    /// assert_eq!(sms.method(), https.method());
+    /// ```
+    pub fn method(&self) -> Option<PositioningMethod> {
+        self.positioning_method.as_deref().and_then(PositioningMethod::parse)
+    }
+}
@@ -0,0 +1,345 @@
+use crate::validation::clean_text;
+use crate::{seconds_to_utc, timestamp, valid_list, AmlError, SmsData, Timestamp, VersionPolicy};
+
+/// Attributes of a v1 SMS AML message, as sent.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SmsDataV1 {
+    /// `lt`: WGS84 latitude in degrees.
+    pub latitude: Option<f64>,
+
+    /// `lg`: WGS84 longitude in degrees.
+    pub longitude: Option<f64>,
+
+    /// `rd`: radius of the location area in meters.
+    pub radius: Option<f64>,
+
+    /// `top`: time of positioning, in UTC.
+    pub time_of_positioning: Option<Timestamp>,
+
+    /// `lc`: level of confidence in percent.
+    pub level_of_confidence: Option<f64>,
+
+    /// `pm`: positioning method, `"G"`, `"W"`, `"C"` or `"U"`.
+    pub positioning_method: Option<String>,
+
+    /// `si`: IMSI.
+    pub imsi: Option<String>,
+
+    /// `ei`: IMEI.
+    pub imei: Option<String>,
+
+    /// `mcc`: network Mobile Country Code.
+    pub mcc: Option<i32>,
+
+    /// `mnc`: network Mobile Network Code.
+    pub mnc: Option<i32>,
+
+    /// `ml`: length of the entire message.
+    pub message_length: Option<i32>,
+}
+
+/// Attributes of a v2 SMS AML message, as sent.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SmsDataV2 {
+    /// `en`: emergency number dialed.
+    pub emergency_number: Option<String>,
+
+    /// `et`: beginning of the emergency call (epoch time in seconds).
+    pub emergency_time: Option<i64>,
+
+    /// `lo`: WGS84 latitude in degrees.
+    pub latitude: Option<f64>,
+
+    /// `lo`: WGS84 longitude in degrees.
+    pub longitude: Option<f64>,
+
+    /// `lo`: accuracy in meters.
+    pub accuracy: Option<f64>,
+
+    /// `lt`: seconds from the beginning of the call to the time of positioning. A
+    /// negative value (e.g. `-9999`) means that the time of positioning is unknown.
+    pub positioning_delay: Option<i64>,
+
+    /// `lc`: level of confidence in percent.
+    pub level_of_confidence: Option<f64>,
+
+    /// `lz`: altitude in meters.
+    pub altitude: Option<f64>,
+
+    /// `lz`: vertical accuracy in meters.
+    pub vertical_accuracy: Option<f64>,
+
+    /// `ls`: location source, `"G"`, `"W"`, `"C"`, `"F"` or `"U"`.
+    pub location_source: Option<String>,
+
+    /// `ei`: IMEI.
+    pub imei: Option<String>,
+
+    /// `nc`: network Mobile Country Code.
+    pub network_mcc: Option<i32>,
+
+    /// `nc`: network Mobile Network Code.
+    pub network_mnc: Option<i32>,
+
+    /// `hc`: home Mobile Country Code.
+    pub home_mcc: Option<i32>,
+
+    /// `hc`: home Mobile Network Code.
+    pub home_mnc: Option<i32>,
+
+    /// `lg`: language tags (IETF BCP 47).
+    pub languages: Option<String>,
+}
+
+/// A SMS AML message with the attributes of its version.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SmsMessage {
+    V1(SmsDataV1),
+    V2(SmsDataV2),
+}
+
+/// Raw values of the attributes of a SMS text, keys being matched directly in a single
+/// pass over the text. A repeated attribute keeps its last value.
+#[derive(Debug, Default)]
+pub(crate) struct Properties<'a> {
+    pub header: Option<&'a str>,
+    lt: Option<&'a str>,
+    lg: Option<&'a str>,
+    rd: Option<&'a str>,
+    top: Option<&'a str>,
+    lc: Option<&'a str>,
+    pm: Option<&'a str>,
+    si: Option<&'a str>,
+    ei: Option<&'a str>,
+    mcc: Option<&'a str>,
+    mnc: Option<&'a str>,
+    ml: Option<&'a str>,
+    en: Option<&'a str>,
+    et: Option<&'a str>,
+    lo: Option<&'a str>,
+    lz: Option<&'a str>,
+    ls: Option<&'a str>,
+    nc: Option<&'a str>,
+    hc: Option<&'a str>,
+}
+
+impl<'a> Properties<'a> {
+    pub(crate) fn of(text_sms: &'a str) -> Self {
+        let mut properties = Properties::default();
+
+        for (key, value) in text_sms
+            .split(';')
+            .filter_map(|property| property.split_once('='))
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .filter(|(key, value)| !key.is_empty() && !value.is_empty())
+        {
+            let slot = match key {
+                r#"A"ML"# => &mut properties.header,
+                "lt" => &mut properties.lt,
+                "lg" => &mut properties.lg,
+                "rd" => &mut properties.rd,
+                "top" => &mut properties.top,
+                "lc" => &mut properties.lc,
+                "pm" => &mut properties.pm,
+                "si" => &mut properties.si,
+                "ei" => &mut properties.ei,
+                "mcc" => &mut properties.mcc,
+                "mnc" => &mut properties.mnc,
+                "ml" => &mut properties.ml,
+                "en" => &mut properties.en,
+                "et" => &mut properties.et,
+                "lo" => &mut properties.lo,
+                "lz" => &mut properties.lz,
+                "ls" => &mut properties.ls,
+                "nc" => &mut properties.nc,
+                "hc" => &mut properties.hc,
+                _ => {
+                    #[cfg(feature = "trace")]
+                    crate::trace::unknown_key(key);
+                    continue;
+                }
+            };
+            *slot = Some(value);
+        }
+
+        properties
+    }
+
+    /// The keys present in the text which are not read by [`SmsDataV1`] (`v1`) or
+    /// [`SmsDataV2`], those of the other version.
+    #[cfg(feature = "trace")]
+    pub(crate) fn unread(&self, v1: bool) -> Vec<&'static str> {
+        let other = match v1 {
+            true => [("en", self.en), ("et", self.et), ("lo", self.lo), ("lz", self.lz), ("ls", self.ls), ("nc", self.nc), ("hc", self.hc)],
+            false => [("rd", self.rd), ("top", self.top), ("pm", self.pm), ("si", self.si), ("mcc", self.mcc), ("mnc", self.mnc), ("ml", self.ml)],
+        };
+        other.iter().filter(|(_, value)| value.is_some()).map(|(key, _)| *key).collect()
+    }
+}
+
+/// The comma separated numbers of `value`, missing or invalid ones being `None`.
+fn numbers<const N: usize>(value: &str) -> [Option<f64>; N] {
+    let mut numbers = [None; N];
+    for (number, item) in numbers.iter_mut().zip(value.split(',')) {
+        *number = item.parse::<f64>().ok();
+    }
+    numbers
+}
+
+impl SmsDataV1 {
+    pub(crate) fn from_properties(properties: &Properties) -> Self {
+        SmsDataV1 {
+            latitude: properties.lt.and_then(|value| value.parse::<f64>().ok()),
+            longitude: properties.lg.and_then(|value| value.parse::<f64>().ok()),
+            radius: properties.rd.and_then(|value| value.parse::<f64>().ok()),
+            time_of_positioning: properties.top.and_then(timestamp::parse_compact),
+            level_of_confidence: properties.lc.and_then(|value| value.parse::<f64>().ok()),
+            positioning_method: properties
+                .pm
+                .and_then(|value| valid_list!(value.to_uppercase(), "G", "W", "C", "U")),
+            imsi: properties.si.map(str::to_string),
+            imei: properties.ei.map(str::to_string),
+            mcc: properties.mcc.and_then(|value| value.parse::<i32>().ok()),
+            mnc: properties.mnc.and_then(|value| value.parse::<i32>().ok()),
+            message_length: properties.ml.and_then(|value| value.parse::<i32>().ok()),
+        }
+    }
+}
+
+impl SmsDataV2 {
+    pub(crate) fn from_properties(properties: &Properties) -> Self {
+        let [latitude, longitude, accuracy] = properties.lo.map_or([None; 3], numbers);
+        let [altitude, vertical_accuracy] = properties.lz.map_or([None; 2], numbers);
+        let mcc = |value: &str| value.get(..3).and_then(|s| s.parse::<i32>().ok());
+        let mnc = |value: &str| value.get(3..).and_then(|s| s.parse::<i32>().ok());
+
+        SmsDataV2 {
+            emergency_number: properties.en.map(str::to_string),
+            emergency_time: properties.et.and_then(|value| value.parse::<i64>().ok()),
+            latitude,
+            longitude,
+            accuracy,
+            positioning_delay: properties.lt.and_then(|value| value.parse::<i64>().ok()),
+            level_of_confidence: properties.lc.and_then(|value| value.parse::<f64>().ok()),
+            altitude,
+            vertical_accuracy,
+            location_source: properties
+                .ls
+                .and_then(|value| valid_list!(value.to_uppercase(), "G", "W", "C", "U", "F")),
+            imei: properties.ei.map(str::to_string),
+            network_mcc: properties.nc.and_then(mcc),
+            network_mnc: properties.nc.and_then(mnc),
+            home_mcc: properties.hc.and_then(mcc),
+            home_mnc: properties.hc.and_then(mnc),
+            languages: properties.lg.map(str::to_string),
+        }
+    }
+}
+
+impl SmsMessage {
+    /// Parse a SMS text, keeping the attributes of its version.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{SmsData, SmsMessage};
+    ///
+    /// let text = r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;ls=G"#;
+    /// match SmsMessage::from_text(text).unwrap() {
+    ///     SmsMessage::V2(v2) => assert_eq!(v2.positioning_delay, Some(30)),
+    ///     SmsMessage::V1(_) => unreachable!(),
+    /// }
+    ///
+    /// let sms = SmsData::from(SmsMessage::from_text(text).unwrap());
+    /// assert_eq!(sms.accuracy, Some(20.0));
+    /// ```
+    pub fn from_text<S: AsRef<str>>(text_sms: S) -> Result<Self, AmlError> {
+        Self::from_text_with_version(text_sms, VersionPolicy::Reject)
+    }
+
+    /// Parse a SMS text like [`SmsMessage::from_text`], the header selecting the version
+    /// according to `policy` (see [`SmsData::from_text_with_version`]). A byte order mark
+    /// and NUL padding are removed as with [`SmsData::from_text`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{SmsMessage, VersionPolicy};
+    ///
+    /// let text = "\u{feff}A\"ML=2.1;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30\0\0";
+    /// assert!(SmsMessage::from_text(text).is_err());
+    /// assert!(matches!(SmsMessage::from_text_with_version(text, VersionPolicy::MajorCompatible), Ok(SmsMessage::V2(_))));
+    /// ```
+    pub fn from_text_with_version<S: AsRef<str>>(text_sms: S, policy: VersionPolicy) -> Result<Self, AmlError> {
+        let properties = Properties::of(clean_text(text_sms.as_ref()).0);
+        let header = properties.header.ok_or(AmlError::UnimplementedVersion)?;
+
+        match policy.select(header) {
+            Some(1) => Ok(SmsMessage::V1(SmsDataV1::from_properties(&properties))),
+            Some(_) => Ok(SmsMessage::V2(SmsDataV2::from_properties(&properties))),
+            None => Err(AmlError::UnimplementedVersion),
+        }
+    }
+}
+
+/// The flattened data, without validation (that needs the text of the message).
+impl From<SmsDataV1> for SmsData {
+    fn from(v1: SmsDataV1) -> Self {
+        SmsData {
+            header: Some("1".to_string()),
+            latitude: v1.latitude,
+            longitude: v1.longitude,
+            accuracy: v1.radius,
+            time_of_positioning: v1.time_of_positioning,
+            level_of_confidence: v1.level_of_confidence,
+            positioning_method: v1.positioning_method,
+            imsi: v1.imsi,
+            imei: v1.imei,
+            network_mcc: v1.mcc,
+            network_mnc: v1.mnc,
+            message_length: v1.message_length,
+            ..Default::default()
+        }
+    }
+}
+
+/// The flattened data, without validation (that needs the text of the message).
+impl From<SmsDataV2> for SmsData {
+    fn from(v2: SmsDataV2) -> Self {
+        let time_of_positioning = match (v2.emergency_time, v2.positioning_delay) {
+            (Some(et), Some(lt)) if lt >= 0 => et.checked_add(lt).and_then(|top| seconds_to_utc!(top)),
+            _ => None,
+        };
+
+        SmsData {
+            header: Some("2".to_string()),
+            emergency_number: v2.emergency_number,
+            beginning_of_call: v2.emergency_time,
+            positioning_delay: v2.positioning_delay,
+            latitude: v2.latitude,
+            longitude: v2.longitude,
+            accuracy: v2.accuracy,
+            time_of_positioning,
+            level_of_confidence: v2.level_of_confidence,
+            altitude: v2.altitude,
+            vertical_accuracy: v2.vertical_accuracy,
+            positioning_method: v2.location_source,
+            imei: v2.imei,
+            network_mcc: v2.network_mcc,
+            network_mnc: v2.network_mnc,
+            home_mcc: v2.home_mcc,
+            home_mnc: v2.home_mnc,
+            languages: v2.languages,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<SmsMessage> for SmsData {
+    fn from(message: SmsMessage) -> Self {
+        match message {
+            SmsMessage::V1(v1) => v1.into(),
+            SmsMessage::V2(v2) => v2.into(),
+        }
+    }
+}
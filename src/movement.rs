@@ -0,0 +1,101 @@
+use crate::geo::destination;
+use crate::{timestamp, AmlData, Duration, PositioningMethod, Timestamp};
+
+/// Why the movement of a handset cannot be used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovementIssue {
+    /// The bearing is not in [0, 360).
+    BearingOutOfRange,
+
+    /// The speed is negative or not a number.
+    NegativeSpeed,
+
+    /// The location comes from the cell only: bearing and speed are meaningless.
+    CellOnly,
+}
+
+/// Bearing and speed of a handset, with their validity. See [`AmlData::movement`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Movement {
+    /// Direction of travel in degrees clockwise from true north, if valid.
+    pub bearing: Option<f64>,
+
+    /// Speed in meters per second, if valid.
+    pub speed: Option<f64>,
+
+    /// Position and time the movement applies to.
+    pub position: Option<(f64, f64)>,
+    pub time_of_positioning: Option<Timestamp>,
+
+    /// Reasons why bearing or speed have been discarded.
+    pub issues: Vec<MovementIssue>,
+}
+
+impl Movement {
+    /// True if both bearing and speed can be used.
+    pub fn is_usable(&self) -> bool {
+        self.bearing.is_some() && self.speed.is_some()
+    }
+
+    /// Dead-reckoned position at `at`, assuming a constant bearing and speed since the
+    /// time of positioning. `None` if the movement is not usable or if `at` is more than
+    /// `horizon` after the time of positioning (or before it).
+    pub fn project(&self, at: Timestamp, horizon: Duration) -> Option<(f64, f64)> {
+        let elapsed = at - self.time_of_positioning?;
+        if elapsed < Duration::seconds(0) || elapsed > horizon {
+            return None;
+        }
+        let distance = self.speed? * timestamp::whole_millis(elapsed) as f64 / 1000.0;
+        Some(destination(self.position?, self.bearing?, distance))
+    }
+}
+
+impl AmlData {
+    /// Bearing and speed of the handset, validated.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{AmlData, Duration};
+    ///
+    /// let https = "v=1&location_latitude=48.85&location_longitude=2.35&location_time=1604912121000&location_source=gps&location_bearing=90&location_speed=20";
+    /// let movement = AmlData::from_https(https).unwrap().movement();
+    /// assert!(movement.is_usable());
+    ///
+    /// // 10 s later, 200 m east
+    /// let at = movement.time_of_positioning.unwrap() + Duration::seconds(10);
+    /// let (latitude, longitude) = movement.project(at, Duration::minutes(1)).unwrap();
+    /// assert!((latitude - 48.85).abs() < 1e-4 && longitude > 2.35);
+    /// ```
+    pub fn movement(&self) -> Movement {
+        let mut issues = Vec::new();
+
+        let cell_only = self.method() == Some(PositioningMethod::Cell);
+        if cell_only && (self.bearing.is_some() || self.speed.is_some()) {
+            issues.push(MovementIssue::CellOnly);
+        }
+
+        let bearing = self.bearing.filter(|bearing| {
+            let valid = (0.0..360.0).contains(bearing);
+            if !valid {
+                issues.push(MovementIssue::BearingOutOfRange);
+            }
+            valid
+        });
+        let speed = self.speed.filter(|speed| {
+            let valid = *speed >= 0.0;
+            if !valid {
+                issues.push(MovementIssue::NegativeSpeed);
+            }
+            valid
+        });
+
+        Movement {
+            bearing: bearing.filter(|_| !cell_only),
+            speed: speed.filter(|_| !cell_only),
+            position: self.position(),
+            time_of_positioning: self.time_of_positioning,
+            issues,
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! Publication of the parsed messages over MQTT, e.g. to field units.
+//!
+//! [`MqttSink`] renders the topic of each message from a [`TopicTemplate`] and hands
+//! the JSON payload to a [`MqttClient`], implemented by the [`rumqttc`] `Client`, and by
+//! any adapter over another client.
+//!
+//! ```no_run
+//! use aml_lib::mqtt::{MqttSink, Qos, TopicTemplate};
+//! use aml_lib::AmlData;
+//! use rumqttc::{Client, MqttOptions};
+//! # fn psap_of(_: &AmlData) -> String { "paris".to_string() }
+//!
+//! let (client, mut connection) = Client::new(MqttOptions::new("aml-receiver", "localhost", 1883), 16);
+//! std::thread::spawn(move || connection.iter().for_each(drop));
+//!
+//! let mut sink = MqttSink::new(client, TopicTemplate::new("aml/{country}/{psap}"))
+//!     .with_qos(Qos::AtLeastOnce)
+//!     .with_resolver(|name, aml| (name == "psap").then(|| psap_of(aml)));
+//!
+//! let aml = AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;nc=20801"#).unwrap();
+//! sink.publish(&aml)?;
+//! # Ok::<(), rumqttc::ClientError>(())
+//! ```
+
+use crate::emergency::country_of_mcc;
+use crate::AmlData;
+
+/// Values of the template variables unknown to the crate. See [`MqttSink::with_resolver`].
+type Resolver = Box<dyn Fn(&str, &AmlData) -> Option<String>>;
+
+/// Replaces a variable which has no value.
+const UNKNOWN: &str = "unknown";
+
+/// MQTT quality of service.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Qos {
+    AtMostOnce,
+
+    #[default]
+    AtLeastOnce,
+
+    ExactlyOnce,
+}
+
+/// A topic with `{variable}` placeholders, rendered for each message. The variables
+/// known to the crate are `country` (ISO 3166-1 alpha-2 of the network, else of the
+/// home network), `mcc`, `mnc`, `transport`, `version`, `service` (see
+/// [`AmlData::service_type`]) and `imei`; others are asked to the resolver of the
+/// [`MqttSink`]. A variable without value is rendered as `unknown`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopicTemplate {
+    template: String,
+}
+
+impl TopicTemplate {
+    pub fn new(template: &str) -> Self {
+        TopicTemplate {
+            template: template.to_string(),
+        }
+    }
+
+    /// The topic of `aml`, the values of the variables unknown to the crate coming from
+    /// `resolve`. The MQTT separators and wildcards (`/`, `+` and `#`) of the values are
+    /// replaced by `_`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::mqtt::TopicTemplate;
+    /// use aml_lib::AmlData;
+    ///
+    /// let aml = AmlData::from_text_sms(r#"A"ML=2;en=18;lo=48.8,2.3,20;nc=20801"#).unwrap();
+    /// let template = TopicTemplate::new("aml/{country}/{service}/{psap}");
+    ///
+    /// assert_eq!(template.render(&aml, &|_, _| Some("paris/75".to_string())), "aml/FR/rescue/paris_75");
+    /// assert_eq!(template.render(&aml, &|_, _| None), "aml/FR/rescue/unknown");
+    /// ```
+    pub fn render(&self, aml: &AmlData, resolve: &dyn Fn(&str, &AmlData) -> Option<String>) -> String {
+        let mut topic = String::new();
+        let mut rest = self.template.as_str();
+
+        while let Some(start) = rest.find('{') {
+            let end = match rest[start..].find('}') {
+                Some(end) => start + end,
+                None => break,
+            };
+            topic.push_str(&rest[..start]);
+            let name = &rest[start + 1..end];
+            let value = variable(name, aml).or_else(|| resolve(name, aml));
+            topic.extend(value.as_deref().unwrap_or(UNKNOWN).chars().map(|c| if matches!(c, '/' | '+' | '#') { '_' } else { c }));
+            rest = &rest[end + 1..];
+        }
+        topic.push_str(rest);
+        topic
+    }
+}
+
+/// Value of a variable known to the crate.
+fn variable(name: &str, aml: &AmlData) -> Option<String> {
+    match name {
+        "country" => aml.network_mcc.or(aml.home_mcc).and_then(country_of_mcc).map(str::to_string),
+        "mcc" => aml.network_mcc.map(|mcc| format!("{:03}", mcc)),
+        "mnc" => aml.network_mnc.map(|mnc| format!("{:02}", mnc)),
+        "transport" => Some(aml.transport.clone()).filter(|transport| !transport.is_empty()),
+        "version" => aml.version.clone(),
+        "service" => aml.service_type().map(|service| format!("{:?}", service).to_lowercase()),
+        "imei" => aml.imei.clone(),
+        _ => None,
+    }
+}
+
+/// The client sending the messages to the broker.
+pub trait MqttClient {
+    type Error;
+
+    fn publish(&mut self, topic: &str, payload: &[u8], qos: Qos, retain: bool) -> Result<(), Self::Error>;
+}
+
+impl From<Qos> for ::rumqttc::QoS {
+    fn from(qos: Qos) -> Self {
+        match qos {
+            Qos::AtMostOnce => ::rumqttc::QoS::AtMostOnce,
+            Qos::AtLeastOnce => ::rumqttc::QoS::AtLeastOnce,
+            Qos::ExactlyOnce => ::rumqttc::QoS::ExactlyOnce,
+        }
+    }
+}
+
+/// Queue the message to the event loop of the connection, blocking while the queue is
+/// full.
+impl MqttClient for ::rumqttc::Client {
+    type Error = ::rumqttc::ClientError;
+
+    fn publish(&mut self, topic: &str, payload: &[u8], qos: Qos, retain: bool) -> Result<(), Self::Error> {
+        ::rumqttc::Client::publish(self, topic, qos.into(), retain, payload)
+    }
+}
+
+/// Publishes the parsed messages as JSON (see [`AmlData::to_json`]) to the topic of
+/// each message.
+pub struct MqttSink<C> {
+    client: C,
+    template: TopicTemplate,
+    qos: Qos,
+    retain: bool,
+    resolver: Resolver,
+}
+
+impl<C: MqttClient> MqttSink<C> {
+    /// A sink publishing with [`Qos::AtLeastOnce`], not retained.
+    pub fn new(client: C, template: TopicTemplate) -> Self {
+        MqttSink {
+            client,
+            template,
+            qos: Qos::default(),
+            retain: false,
+            resolver: Box::new(|_, _| None),
+        }
+    }
+
+    pub fn with_qos(self, qos: Qos) -> Self {
+        MqttSink { qos, ..self }
+    }
+
+    /// Retain the last message of each topic, so that a unit subscribing later gets
+    /// the last known location.
+    pub fn with_retain(self, retain: bool) -> Self {
+        MqttSink { retain, ..self }
+    }
+
+    /// Values of the template variables unknown to the crate, e.g. the PSAP in charge.
+    pub fn with_resolver<F: Fn(&str, &AmlData) -> Option<String> + 'static>(self, resolver: F) -> Self {
+        MqttSink {
+            resolver: Box::new(resolver),
+            ..self
+        }
+    }
+
+    /// Publish `aml`, returning the topic.
+    pub fn publish(&mut self, aml: &AmlData) -> Result<String, C::Error> {
+        let topic = self.template.render(aml, &*self.resolver);
+        self.client.publish(&topic, aml.to_json().as_bytes(), self.qos, self.retain)?;
+        Ok(topic)
+    }
+
+    /// The client, e.g. to disconnect it.
+    pub fn client(&mut self) -> &mut C {
+        &mut self.client
+    }
+}
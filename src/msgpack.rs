@@ -0,0 +1,320 @@
+//! Compact binary encoding of the data with MessagePack, e.g. for satellite links.
+//!
+//! The data is the object of [`AmlData::to_json_value`], encoded with the smallest
+//! MessagePack types: integral numbers as integers, others as 32-bit floats when this
+//! is lossless, and the timestamps of the data with the timestamp extension type.
+//! Decoding gives the same JSON value back, so [`AmlData::from_msgpack`] is lossless.
+
+use crate::json::{JsonValue, MAX_DEPTH};
+use crate::{timestamp, AmlData, AmlError, Timestamp};
+use std::convert::TryInto;
+
+/// Members holding a timestamp, at any level of the object.
+const TIMESTAMPS: &[&str] = &["beginning_of_call", "time_of_positioning", "carcrash_time", "received_at"];
+
+/// Type of the MessagePack timestamp extension.
+const TIMESTAMP_EXT: i8 = -1;
+
+/// Encode `value`, numbers with the smallest type.
+///
+/// # Example
+///
+/// ```
+/// use aml_lib::json::JsonValue;
+/// use aml_lib::msgpack;
+///
+/// let value = JsonValue::parse(r#"{"a":[1,-1,0.5,null,true]}"#).unwrap();
+/// let bytes = msgpack::encode(&value);
+/// assert_eq!(bytes, [0x81, 0xa1, b'a', 0x95, 0x01, 0xff, 0xca, 0x3f, 0x00, 0x00, 0x00, 0xc0, 0xc3]);
+/// assert_eq!(msgpack::decode(&bytes), Some(value));
+/// ```
+pub fn encode(value: &JsonValue) -> Vec<u8> {
+    let mut out = Vec::new();
+    write(&mut out, value, false);
+    out
+}
+
+/// Decode a MessagePack value. Timestamps are decoded as RFC 3339 strings; binary data,
+/// other extension types and non-string keys are not supported. `None` if `bytes` is
+/// not exactly one such value.
+pub fn decode(bytes: &[u8]) -> Option<JsonValue> {
+    let mut reader = Reader { bytes, pos: 0 };
+    let value = reader.value(0)?;
+    if reader.pos == bytes.len() {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+/// Write `value`, with the timestamp members of its objects as timestamps if `timestamps`.
+fn write(out: &mut Vec<u8>, value: &JsonValue, timestamps: bool) {
+    match value {
+        JsonValue::Null => out.push(0xc0),
+        JsonValue::Bool(value) => out.push(if *value { 0xc3 } else { 0xc2 }),
+        JsonValue::Number(value) => write_number(out, *value),
+        JsonValue::String(text) => write_str(out, text),
+        JsonValue::Array(values) => {
+            write_length(out, values.len(), 0x90, 0xdc);
+            for value in values {
+                write(out, value, false);
+            }
+        }
+        JsonValue::Object(members) => {
+            write_length(out, members.len(), 0x80, 0xde);
+            for (key, value) in members {
+                write_str(out, key);
+                let timestamp = match value {
+                    JsonValue::String(text) if timestamps && TIMESTAMPS.contains(&key.as_str()) => {
+                        timestamp::parse_rfc3339(text)
+                    }
+                    _ => None,
+                };
+                match timestamp {
+                    Some(dt) => write_timestamp(out, dt),
+                    None => write(out, value, timestamps),
+                }
+            }
+        }
+    }
+}
+
+fn write_number(out: &mut Vec<u8>, value: f64) {
+    if value.fract() == 0.0 && value.abs() < 9_007_199_254_740_992.0 {
+        let value = value as i64;
+        match value {
+            0..=0x7f => out.push(value as u8),
+            -32..=-1 => out.push(value as i8 as u8),
+            0x80..=0xff => out.extend_from_slice(&[0xcc, value as u8]),
+            0x100..=0xffff => {
+                out.push(0xcd);
+                out.extend_from_slice(&(value as u16).to_be_bytes());
+            }
+            0x1_0000..=0xffff_ffff => {
+                out.push(0xce);
+                out.extend_from_slice(&(value as u32).to_be_bytes());
+            }
+            -0x80..=-33 => out.extend_from_slice(&[0xd0, value as i8 as u8]),
+            -0x8000..=-0x81 => {
+                out.push(0xd1);
+                out.extend_from_slice(&(value as i16).to_be_bytes());
+            }
+            -0x8000_0000..=-0x8001 => {
+                out.push(0xd2);
+                out.extend_from_slice(&(value as i32).to_be_bytes());
+            }
+            _ => {
+                out.push(0xd3);
+                out.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+    } else if f64::from(value as f32) == value {
+        out.push(0xca);
+        out.extend_from_slice(&(value as f32).to_be_bytes());
+    } else {
+        out.push(0xcb);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn write_str(out: &mut Vec<u8>, text: &str) {
+    match text.len() {
+        length @ 0..=31 => out.push(0xa0 | length as u8),
+        length @ 32..=0xff => out.extend_from_slice(&[0xd9, length as u8]),
+        length @ 0x100..=0xffff => {
+            out.push(0xda);
+            out.extend_from_slice(&(length as u16).to_be_bytes());
+        }
+        length => {
+            out.push(0xdb);
+            out.extend_from_slice(&(length as u32).to_be_bytes());
+        }
+    }
+    out.extend_from_slice(text.as_bytes());
+}
+
+/// Length of an array or map: `fix | length` up to 15, else the 16-bit (`marker`) or
+/// 32-bit (`marker + 1`) form.
+fn write_length(out: &mut Vec<u8>, length: usize, fix: u8, marker: u8) {
+    if length < 16 {
+        out.push(fix | length as u8);
+    } else if length <= 0xffff {
+        out.push(marker);
+        out.extend_from_slice(&(length as u16).to_be_bytes());
+    } else {
+        out.push(marker + 1);
+        out.extend_from_slice(&(length as u32).to_be_bytes());
+    }
+}
+
+/// The 32-bit form of the timestamp extension for whole seconds, else the 64-bit form.
+fn write_timestamp(out: &mut Vec<u8>, dt: Timestamp) {
+    let (seconds, nanos) = (timestamp::unix(dt), timestamp::subsec_nanos(dt));
+    if nanos == 0 && (0..=0xffff_ffff).contains(&seconds) {
+        out.extend_from_slice(&[0xd6, TIMESTAMP_EXT as u8]);
+        out.extend_from_slice(&(seconds as u32).to_be_bytes());
+    } else if (0..1 << 34).contains(&seconds) {
+        out.extend_from_slice(&[0xd7, TIMESTAMP_EXT as u8]);
+        out.extend_from_slice(&((u64::from(nanos) << 34) | seconds as u64).to_be_bytes());
+    } else {
+        out.extend_from_slice(&[0xc7, 12, TIMESTAMP_EXT as u8]);
+        out.extend_from_slice(&nanos.to_be_bytes());
+        out.extend_from_slice(&seconds.to_be_bytes());
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Option<&'a [u8]> {
+        let bytes = self.bytes.get(self.pos..self.pos.checked_add(length)?)?;
+        self.pos += length;
+        Some(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.take(N)?.try_into().ok()
+    }
+
+    fn value(&mut self, depth: usize) -> Option<JsonValue> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+
+        let marker = *self.take(1)?.first()?;
+        let number = |value: f64| Some(JsonValue::Number(value));
+        match marker {
+            0x00..=0x7f => number(f64::from(marker)),
+            0x80..=0x8f => self.map(usize::from(marker & 0x0f), depth),
+            0x90..=0x9f => self.values(usize::from(marker & 0x0f), depth),
+            0xa0..=0xbf => self.string(usize::from(marker & 0x1f)),
+            0xc0 => Some(JsonValue::Null),
+            0xc2 => Some(JsonValue::Bool(false)),
+            0xc3 => Some(JsonValue::Bool(true)),
+            0xc7 => {
+                let [length, kind] = self.array()?;
+                self.timestamp(kind as i8, usize::from(length))
+            }
+            0xca => number(f64::from(f32::from_be_bytes(self.array()?))),
+            0xcb => number(f64::from_be_bytes(self.array()?)),
+            0xcc => number(f64::from(self.array::<1>()?[0])),
+            0xcd => number(f64::from(u16::from_be_bytes(self.array()?))),
+            0xce => number(f64::from(u32::from_be_bytes(self.array()?))),
+            0xcf => number(u64::from_be_bytes(self.array()?) as f64),
+            0xd0 => number(f64::from(self.array::<1>()?[0] as i8)),
+            0xd1 => number(f64::from(i16::from_be_bytes(self.array()?))),
+            0xd2 => number(f64::from(i32::from_be_bytes(self.array()?))),
+            0xd3 => number(i64::from_be_bytes(self.array()?) as f64),
+            0xd6 => {
+                let [kind] = self.array()?;
+                self.timestamp(kind as i8, 4)
+            }
+            0xd7 => {
+                let [kind] = self.array()?;
+                self.timestamp(kind as i8, 8)
+            }
+            0xd9 => {
+                let [length] = self.array()?;
+                self.string(usize::from(length))
+            }
+            0xda => {
+                let length = u16::from_be_bytes(self.array()?);
+                self.string(usize::from(length))
+            }
+            0xdb => {
+                let length = u32::from_be_bytes(self.array()?);
+                self.string(length as usize)
+            }
+            0xdc => {
+                let length = u16::from_be_bytes(self.array()?);
+                self.values(usize::from(length), depth)
+            }
+            0xdd => {
+                let length = u32::from_be_bytes(self.array()?);
+                self.values(length as usize, depth)
+            }
+            0xde => {
+                let length = u16::from_be_bytes(self.array()?);
+                self.map(usize::from(length), depth)
+            }
+            0xdf => {
+                let length = u32::from_be_bytes(self.array()?);
+                self.map(length as usize, depth)
+            }
+            0xe0..=0xff => number(f64::from(marker as i8)),
+            _ => None,
+        }
+    }
+
+    fn string(&mut self, length: usize) -> Option<JsonValue> {
+        let bytes = self.take(length)?;
+        std::str::from_utf8(bytes).ok().map(|text| JsonValue::String(text.to_string()))
+    }
+
+    fn values(&mut self, length: usize, depth: usize) -> Option<JsonValue> {
+        // Each value takes at least one byte: bounds the allocation by the input.
+        let mut values = Vec::with_capacity(length.min(self.bytes.len() - self.pos));
+        for _ in 0..length {
+            values.push(self.value(depth + 1)?);
+        }
+        Some(JsonValue::Array(values))
+    }
+
+    fn map(&mut self, length: usize, depth: usize) -> Option<JsonValue> {
+        let mut members = Vec::with_capacity(length.min(self.bytes.len() - self.pos));
+        for _ in 0..length {
+            let key = match self.value(depth + 1)? {
+                JsonValue::String(key) => key,
+                _ => return None,
+            };
+            members.push((key, self.value(depth + 1)?));
+        }
+        Some(JsonValue::Object(members))
+    }
+
+    fn timestamp(&mut self, kind: i8, length: usize) -> Option<JsonValue> {
+        if kind != TIMESTAMP_EXT {
+            return None;
+        }
+        let data = self.take(length)?;
+        let (seconds, nanos) = match length {
+            4 => (i64::from(u32::from_be_bytes(data.try_into().ok()?)), 0),
+            8 => {
+                let value = u64::from_be_bytes(data.try_into().ok()?);
+                ((value & ((1 << 34) - 1)) as i64, (value >> 34) as u32)
+            }
+            12 => (i64::from_be_bytes(data[4..].try_into().ok()?), u32::from_be_bytes(data[..4].try_into().ok()?)),
+            _ => return None,
+        };
+        timestamp::from_unix_nanos(seconds, nanos).map(|dt| JsonValue::String(timestamp::to_rfc3339_millis(dt)))
+    }
+}
+
+impl AmlData {
+    /// The data as MessagePack, about 30% smaller than [`AmlData::to_json`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::AmlData;
+    ///
+    /// let aml = AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;ls=G;ei=358239059042542"#).unwrap();
+    /// let bytes = aml.to_msgpack();
+    ///
+    /// assert_eq!((bytes.len(), aml.to_json().len()), (185, 259));
+    /// assert_eq!(AmlData::from_msgpack(&bytes).unwrap(), aml);
+    /// ```
+    pub fn to_msgpack(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        write(&mut out, &self.to_json_value(), true);
+        out
+    }
+
+    /// Read back the data of [`AmlData::to_msgpack`].
+    pub fn from_msgpack(bytes: &[u8]) -> Result<AmlData, AmlError> {
+        decode(bytes).as_ref().and_then(AmlData::from_json_value).ok_or(AmlError::InvalidMsgpack)
+    }
+}
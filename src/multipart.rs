@@ -0,0 +1,83 @@
+use crate::{AmlError, HttpsData};
+
+/// The `boundary` parameter of a `multipart/form-data` content type.
+fn boundary(content_type: &str) -> Option<&str> {
+    let (mime, parameters) = content_type.split_once(';')?;
+    if !mime.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    parameters
+        .split(';')
+        .filter_map(|parameter| parameter.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("boundary"))
+        .map(|(_, value)| value.trim().trim_matches('"'))
+        .filter(|value| !value.is_empty())
+}
+
+/// The `name` of a part from its headers.
+fn part_name(headers: &str) -> Option<String> {
+    let disposition = headers
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(header, _)| header.trim().eq_ignore_ascii_case("content-disposition"))?
+        .1;
+    disposition
+        .split(';')
+        .filter_map(|parameter| parameter.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("name"))
+        .map(|(_, value)| value.trim().trim_matches('"').to_string())
+}
+
+/// The `(name, value)` fields of a `multipart/form-data` body.
+fn fields(body: &str, boundary: &str) -> Result<Vec<(String, String)>, AmlError> {
+    let delimiter = format!("--{}", boundary);
+    let mut parts = body.split(delimiter.as_str());
+
+    // Preamble
+    parts.next();
+    let mut fields = Vec::new();
+
+    for part in parts {
+        if part.starts_with("--") {
+            return Ok(fields);
+        }
+        let part = part.strip_prefix("\r\n").or_else(|| part.strip_prefix('\n')).ok_or(AmlError::InvalidMultipart)?;
+        let (headers, content) = part
+            .split_once("\r\n\r\n")
+            .or_else(|| part.split_once("\n\n"))
+            .ok_or(AmlError::InvalidMultipart)?;
+        let content = content.strip_suffix("\r\n").or_else(|| content.strip_suffix('\n')).unwrap_or(content);
+
+        fields.push((part_name(headers).ok_or(AmlError::InvalidMultipart)?, content.to_string()));
+    }
+
+    // No close delimiter
+    Err(AmlError::InvalidMultipart)
+}
+
+impl HttpsData {
+    /// Parse a HTTPS AML message posted as `multipart/form-data`, `content_type` being
+    /// the value of the `Content-Type` header (which gives the boundary). The fields
+    /// are the same as in urlencoded bodies. See [`HttpsData::from_urlencoded`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::HttpsData;
+    ///
+    /// let body = "--XyZ\r\nContent-Disposition: form-data; name=\"v\"\r\n\r\n1\r\n\
+    ///             --XyZ\r\nContent-Disposition: form-data; name=\"location_latitude\"\r\n\r\n0.85732\r\n\
+    ///             --XyZ--\r\n";
+    /// let https_data = HttpsData::from_multipart(body.as_bytes(), "multipart/form-data; boundary=XyZ").unwrap();
+    /// assert_eq!(https_data.location_latitude, Some(0.85732));
+    /// ```
+    pub fn from_multipart(body: &[u8], content_type: &str) -> Result<Self, AmlError> {
+        let boundary = boundary(content_type).ok_or(AmlError::InvalidMultipart)?;
+        let body = std::str::from_utf8(body)?;
+
+        let urlencoded = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(fields(body, boundary)?)
+            .finish();
+        Ok(Self::from_urlencoded(urlencoded))
+    }
+}
@@ -0,0 +1,177 @@
+use crate::conformance::{HTTPS_KNOWN, SMS_V1_KNOWN, SMS_V2_KNOWN};
+use crate::validation::clean_text;
+use crate::{AmlData, AmlError, HttpsData, SmsData};
+use std::borrow::Cow;
+
+/// Why an attribute has not made it into the data. See [`DroppedField`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropReason {
+    /// The key is not defined for the version of the message.
+    Unknown,
+
+    /// The value cannot be converted, or cannot be used (e.g. a v2 `lt` without `et`).
+    Invalid,
+
+    /// The key is repeated later in the message, whose value wins.
+    Repeated,
+}
+
+/// An attribute of a message which the parser has ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DroppedField<'a> {
+    /// `sms` or `https`.
+    pub transport: &'static str,
+
+    pub key: &'a str,
+
+    /// The value as sent, trimmed.
+    pub value: &'a str,
+
+    pub reason: DropReason,
+}
+
+/// Receives the attributes dropped while parsing, e.g. to discover new handset fields
+/// in production. Implemented by closures taking a [`DroppedField`].
+pub trait DropObserver {
+    fn dropped(&mut self, field: &DroppedField);
+}
+
+impl<F: FnMut(&DroppedField)> DropObserver for F {
+    fn dropped(&mut self, field: &DroppedField) {
+        self(field)
+    }
+}
+
+/// Report to `observer` the `attributes` which are unknown, repeated or not `converted`.
+fn observe(
+    observer: &mut dyn DropObserver,
+    transport: &'static str,
+    attributes: &[(&str, &str)],
+    header: &str,
+    known: &[&str],
+    converted: &dyn Fn(&str) -> bool,
+) {
+    for (i, (key, value)) in attributes.iter().enumerate() {
+        let reason = if *key == header {
+            continue;
+        } else if !known.contains(key) {
+            DropReason::Unknown
+        } else if attributes[i + 1..].iter().any(|(k, _)| k == key) {
+            DropReason::Repeated
+        } else if !converted(key) {
+            DropReason::Invalid
+        } else {
+            continue;
+        };
+        observer.dropped(&DroppedField { transport, key, value, reason });
+    }
+}
+
+impl SmsData {
+    /// Parse a SMS text like [`SmsData::from_text`], reporting each attribute which is
+    /// ignored to `observer`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{DropReason, SmsData};
+    ///
+    /// let mut dropped = Vec::new();
+    /// let text = r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lc=high;ls=G;bt=87"#;
+    /// SmsData::from_text_observed(text, &mut |field: &aml_lib::DroppedField| dropped.push((field.key.to_string(), field.reason))).unwrap();
+    ///
+    /// assert_eq!(dropped, [("lc".to_string(), DropReason::Invalid), ("bt".to_string(), DropReason::Unknown)]);
+    /// ```
+    pub fn from_text_observed<S: AsRef<str>>(text_sms: S, observer: &mut dyn DropObserver) -> Result<Self, AmlError> {
+        let text_sms = text_sms.as_ref();
+        let sms = Self::from_text(text_sms)?;
+
+        let attributes: Vec<(&str, &str)> = clean_text(text_sms)
+            .0
+            .split(';')
+            .filter_map(|property| property.split_once('='))
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .filter(|(key, value)| !key.is_empty() && !value.is_empty())
+            .collect();
+        let v1 = sms.header.as_deref() == Some("1");
+        let converted = |key: &str| match (v1, key) {
+            (true, "lt") => sms.latitude.is_some(),
+            (true, "lg") => sms.longitude.is_some(),
+            (true, "rd") => sms.accuracy.is_some(),
+            (true, "top") => sms.time_of_positioning.is_some(),
+            (true, "pm") | (false, "ls") => sms.positioning_method.is_some(),
+            (true, "mcc") => sms.network_mcc.is_some(),
+            (true, "mnc") => sms.network_mnc.is_some(),
+            (true, "ml") => sms.message_length.is_some(),
+            (_, "lc") => sms.level_of_confidence.is_some(),
+            (false, "et") => sms.beginning_of_call.is_some(),
+            (false, "lo") => sms.latitude.is_some() && sms.longitude.is_some(),
+            // A negative delay is the sentinel of an unknown time of positioning
+            (false, "lt") => sms.positioning_delay.is_some_and(|lt| lt < 0 || sms.time_of_positioning.is_some()),
+            (false, "lz") => sms.altitude.is_some(),
+            (false, "nc") => sms.network_mcc.is_some() && sms.network_mnc.is_some(),
+            (false, "hc") => sms.home_mcc.is_some() && sms.home_mnc.is_some(),
+            _ => true,
+        };
+        let known = if v1 { SMS_V1_KNOWN } else { SMS_V2_KNOWN };
+        observe(observer, "sms", &attributes, r#"A"ML"#, known, &converted);
+
+        Ok(sms)
+    }
+}
+
+impl HttpsData {
+    /// Parse a HTTPS AML message like [`HttpsData::from_urlencoded`], reporting each
+    /// attribute which is ignored to `observer`.
+    pub fn from_urlencoded_observed<S: AsRef<str>>(payload: S, observer: &mut dyn DropObserver) -> Self {
+        let https = Self::from_urlencoded(payload.as_ref());
+
+        let pairs: Vec<(Cow<str>, Cow<str>)> = url::form_urlencoded::parse(payload.as_ref().as_bytes()).collect();
+        let attributes: Vec<(&str, &str)> = pairs.iter().map(|(key, value)| (key.as_ref(), value.trim())).collect();
+        let converted = |key: &str| match key {
+            "source" => https.source.is_some(),
+            "time" => https.time.is_some(),
+            "gt_location_latitude" => https.gt_location_latitude.is_some(),
+            "gt_location_longitude" => https.gt_location_longitude.is_some(),
+            "test" => https.test.is_some(),
+            "location_latitude" => https.location_latitude.is_some(),
+            "location_longitude" => https.location_longitude.is_some(),
+            "location_time" => https.location_time.is_some(),
+            "location_altitude" => https.location_altitude.is_some(),
+            "location_source" => https.location_source.is_some(),
+            "location_accuracy" => https.location_accuracy.is_some(),
+            "location_vertical_accuracy" => https.location_vertical_accuracy.is_some(),
+            "location_semi_major_axis" => https.location_semi_major_axis.is_some(),
+            "location_semi_minor_axis" => https.location_semi_minor_axis.is_some(),
+            "location_orientation" => https.location_orientation.is_some(),
+            "location_confidence" => https.location_confidence.is_some(),
+            "location_certainty" => https.location_certainty.is_some(),
+            "location_bearing" => https.location_bearing.is_some(),
+            "location_speed" => https.location_speed.is_some(),
+            "cell_home_mcc" => https.cell_home_mcc.is_some(),
+            "cell_home_mnc" => https.cell_home_mnc.is_some(),
+            "cell_network_mcc" => https.cell_network_mcc.is_some(),
+            "cell_network_mnc" => https.cell_network_mnc.is_some(),
+            "adr_carcrash_time" => https.adr_carcrash_time.is_some(),
+            "adr_rollover" => https.adr_rollover.is_some(),
+            "adr_impact_direction" => https.adr_impact_direction.is_some(),
+            "adr_occupants" => https.adr_occupants.is_some(),
+            _ => true,
+        };
+        observe(observer, "https", &attributes, "", HTTPS_KNOWN, &converted);
+
+        https
+    }
+}
+
+impl AmlData {
+    /// Parse a SMS text, reporting the ignored attributes. See [`SmsData::from_text_observed`].
+    pub fn from_text_sms_observed<S: AsRef<str>>(text_sms: S, observer: &mut dyn DropObserver) -> Result<Self, AmlError> {
+        Ok(SmsData::from_text_observed(text_sms, observer)?.into())
+    }
+
+    /// Parse a HTTPS AML message, reporting the ignored attributes. See [`HttpsData::from_urlencoded_observed`].
+    pub fn from_https_observed(payload: &str, observer: &mut dyn DropObserver) -> Result<Self, AmlError> {
+        Ok(HttpsData::from_urlencoded_observed(payload, observer).into())
+    }
+}
@@ -0,0 +1,166 @@
+use crate::conformance::{HTTPS_KNOWN, HTTPS_MANDATORY};
+use crate::json::JsonValue;
+
+/// Schema and description of a HTTPS AML field, following the formats checked by
+/// [`conformance::check_https`](crate::conformance::check_https).
+fn property(key: &str) -> JsonValue {
+    let (schema, description): (Vec<(&str, JsonValue)>, &str) = match key {
+        "v" => (string(Some(r"^.+$")), "Version of AML."),
+        "emergency_number" => (string(Some(r"^\+?[0-9]{1,15}$")), "Emergency number dialed."),
+        "source" => (enumeration(&["call", "sms"]), "Source of activation (case insensitive)."),
+        "thunderbird_version" => (string(None), "Version number of the thunderbird module."),
+        "time" => (timestamp(), "Time of the beginning of call, in milliseconds since the Unix epoch."),
+        "gt_location_latitude" => (number(-90.0, 90.0), "Ground truth latitude, sent by test handsets."),
+        "gt_location_longitude" => (number(-180.0, 180.0), "Ground truth longitude, sent by test handsets."),
+        "test" => (boolean(), "Test flag set by the operator."),
+        "location_latitude" => (number(-90.0, 90.0), "WGS84 latitude in degrees."),
+        "location_longitude" => (number(-180.0, 180.0), "WGS84 longitude in degrees."),
+        "location_time" => (timestamp(), "Time of positioning, in milliseconds since the Unix epoch."),
+        "location_altitude" => (vec![("type", text("number"))], "Altitude in meters."),
+        "location_floor" => (string(None), "Floor label, as on the elevator buttons (may be non-numeric)."),
+        "location_source" => (
+            enumeration(&["gps", "wifi", "cell", "fused", "unknown"]),
+            "Positioning method (case insensitive).",
+        ),
+        "location_accuracy" => (positive(), "Accuracy radius in meters."),
+        "location_vertical_accuracy" => (positive(), "Vertical accuracy in meters."),
+        "location_semi_major_axis" => (positive(), "Semi-major axis of the uncertainty ellipse in meters."),
+        "location_semi_minor_axis" => (positive(), "Semi-minor axis of the uncertainty ellipse in meters."),
+        "location_orientation" => (number(0.0, 360.0), "Orientation of the semi-major axis in degrees clockwise from true north."),
+        "location_confidence" => (number(0.0, 100.0), "Confidence in the location accuracy, in percent."),
+        "location_certainty" => (number(0.0, 100.0), "Confidence in the location accuracy, in percent (ELS)."),
+        "location_bearing" => (number(0.0, 360.0), "Bearing in degrees clockwise from true north."),
+        "location_speed" => (positive(), "Speed in meters per second."),
+        "device_number" => (string(Some(r"^(\+?[0-9]{1,15})?$")), "Phone number of the handset (may be empty)."),
+        "device_model" => (string(None), "Model of the handset."),
+        "device_imsi" => (string(Some("^[0-9]{5,15}$")), "IMSI of the SIM card of the handset."),
+        "device_imei" => (string(Some("^[0-9]{14,16}$")), "IMEI of the handset."),
+        "device_iccid" => (string(Some("^[0-9]{18,22}$")), "ICCID of the SIM card of the handset."),
+        "device_languages" => (string(None), "BCP 47 language tags, comma separated, from highest to lowest priority."),
+        "cell_home_mcc" => (string(Some("^[0-9]{3}$")), "Mobile country code of the home network."),
+        "cell_home_mnc" => (string(Some("^[0-9]{2,3}$")), "Mobile network code of the home network."),
+        "cell_network_mcc" => (string(Some("^[0-9]{3}$")), "Mobile country code of the network the call is made on."),
+        "cell_network_mnc" => (string(Some("^[0-9]{2,3}$")), "Mobile network code of the network the call is made on."),
+        "adr_carcrash_time" => (timestamp(), "Time of the car crash, in milliseconds since the Unix epoch."),
+        "adr_severity" => (string(None), "Severity of the crash, on the scale of the vehicle maker."),
+        "adr_rollover" => (boolean(), "The vehicle has rolled over."),
+        "adr_impact_direction" => (number(0.0, 360.0), "Direction of the impact in degrees clockwise from the front of the vehicle."),
+        "adr_occupants" => (vec![("type", text("integer")), ("minimum", JsonValue::Number(0.0)), ("maximum", JsonValue::Number(255.0))], "Number of occupants of the vehicle."),
+        "hmac" => (
+            string(Some("^[0-9A-Fa-f]{40}$")),
+            "HMAC-SHA1, in hexadecimal, of the urlencoded body preceding `&hmac=`, keyed with the secret \
+             shared with the receiver. Must be the last field of the body.",
+        ),
+        _ => (string(None), ""),
+    };
+
+    let mut members: Vec<(String, JsonValue)> = schema.into_iter().map(|(name, value)| (name.to_string(), value)).collect();
+    members.push(("description".to_string(), text(description)));
+    JsonValue::Object(members)
+}
+
+fn text(value: &str) -> JsonValue {
+    JsonValue::String(value.to_string())
+}
+
+fn string(pattern: Option<&str>) -> Vec<(&'static str, JsonValue)> {
+    let mut schema = vec![("type", text("string"))];
+    if let Some(pattern) = pattern {
+        schema.push(("pattern", text(pattern)));
+    }
+    schema
+}
+
+fn enumeration(values: &[&str]) -> Vec<(&'static str, JsonValue)> {
+    vec![("type", text("string")), ("enum", JsonValue::Array(values.iter().map(|value| text(value)).collect()))]
+}
+
+fn number(minimum: f64, maximum: f64) -> Vec<(&'static str, JsonValue)> {
+    vec![("type", text("number")), ("minimum", JsonValue::Number(minimum)), ("maximum", JsonValue::Number(maximum))]
+}
+
+fn positive() -> Vec<(&'static str, JsonValue)> {
+    vec![("type", text("number")), ("minimum", JsonValue::Number(0.0))]
+}
+
+fn timestamp() -> Vec<(&'static str, JsonValue)> {
+    vec![("type", text("integer")), ("format", text("int64")), ("minimum", JsonValue::Number(0.0))]
+}
+
+fn boolean() -> Vec<(&'static str, JsonValue)> {
+    enumeration(&["true", "false", "1", "0"])
+}
+
+/// OpenAPI 3 document of a HTTPS AML receiver accepting `POST` requests on `path`, as
+/// served by [`AmlPayload::from_body`](crate::web::AmlPayload::from_body): the fields of
+/// [`HttpsData`](crate::HttpsData) with their formats, the HMAC field and the statuses
+/// of [`Rejection`](crate::web::Rejection). Senders can validate their requests against it.
+///
+/// # Example
+///
+/// ```
+/// use aml_lib::web;
+///
+/// let document = web::openapi("/aml");
+/// let schema = document.get("components").and_then(|c| c.get("schemas")).and_then(|s| s.get("AmlMessage")).unwrap();
+///
+/// assert_eq!(document.get("openapi").and_then(|v| v.as_str()), Some("3.0.3"));
+/// assert!(document.get("paths").and_then(|p| p.get("/aml")).and_then(|p| p.get("post")).is_some());
+/// assert!(schema.get("properties").and_then(|p| p.get("hmac")).is_some());
+/// ```
+pub fn openapi(path: &str) -> JsonValue {
+    let object = |members: Vec<(&str, JsonValue)>| {
+        JsonValue::Object(members.into_iter().map(|(name, value)| (name.to_string(), value)).collect())
+    };
+    let response = |description: &str| object(vec![("description", text(description))]);
+
+    let properties = HTTPS_KNOWN.iter().map(|key| (key.to_string(), property(key))).collect();
+    let required = HTTPS_MANDATORY.iter().map(|key| text(key)).collect();
+    let message = object(vec![
+        ("type", text("object")),
+        ("required", JsonValue::Array(required)),
+        ("properties", JsonValue::Object(properties)),
+        ("additionalProperties", JsonValue::Bool(true)),
+    ]);
+
+    let post = object(vec![
+        ("summary", text("Receive an AML message")),
+        ("description", text("The body is authenticated by its `hmac` field, then parsed. Unknown fields are ignored.")),
+        ("operationId", text("receiveAml")),
+        (
+            "requestBody",
+            object(vec![
+                ("required", JsonValue::Bool(true)),
+                (
+                    "content",
+                    object(vec![(
+                        "application/x-www-form-urlencoded",
+                        object(vec![("schema", object(vec![("$ref", text("#/components/schemas/AmlMessage"))]))]),
+                    )]),
+                ),
+            ]),
+        ),
+        (
+            "responses",
+            object(vec![
+                ("200", response("The message has been received.")),
+                ("400", response("The body is empty or is not UTF-8 text.")),
+                ("401", response("The body has no `hmac` field or no key of the receiver authenticates it.")),
+                ("413", response("The body exceeds the limits of the receiver.")),
+            ]),
+        ),
+    ]);
+
+    object(vec![
+        ("openapi", text("3.0.3")),
+        (
+            "info",
+            object(vec![
+                ("title", text("Advanced Mobile Location over HTTPS")),
+                ("version", text(env!("CARGO_PKG_VERSION"))),
+            ]),
+        ),
+        ("paths", JsonValue::Object(vec![(path.to_string(), object(vec![("post", post)]))])),
+        ("components", object(vec![("schemas", object(vec![("AmlMessage", message)]))])),
+    ])
+}
@@ -0,0 +1,272 @@
+use crate::{timestamp, AmlError, Duration, SmsData, Timestamp};
+
+/// Envelope of a SMS-DELIVER PDU (3GPP TS 23.040), as found in SMSC logs.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PduEnvelope {
+    /// Address of the service center, if present in the PDU.
+    pub smsc: Option<String>,
+
+    /// Originating address: the phone number of the handset (`+` prefixed when
+    /// international) or an alphanumeric sender.
+    pub sender: String,
+
+    /// TP-Protocol-Identifier.
+    pub protocol_id: u8,
+
+    /// TP-Data-Coding-Scheme.
+    pub data_coding_scheme: u8,
+
+    /// Service center time stamp, in UTC.
+    pub service_center_time: Option<Timestamp>,
+
+    /// User data header, including its length octet, if any.
+    pub user_data_header: Vec<u8>,
+}
+
+/// Application ports the SMS is addressed to (3GPP TS 23.040 clauses 9.2.3.24.3 and
+/// 9.2.3.24.4). See [`PduEnvelope::ports`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApplicationPorts {
+    pub destination: u16,
+    pub source: u16,
+}
+
+impl PduEnvelope {
+    /// Information elements (identifier, data) of the user data header, in order.
+    /// A truncated element ends the list.
+    pub fn information_elements(&self) -> Vec<(u8, &[u8])> {
+        let mut elements = Vec::new();
+        let mut rest = self.user_data_header.get(1..).unwrap_or_default();
+        while let [id, len, tail @ ..] = rest {
+            match tail.get(..*len as usize) {
+                Some(data) => elements.push((*id, data)),
+                None => break,
+            }
+            rest = &tail[*len as usize..];
+        }
+        elements
+    }
+
+    /// Application ports of the 8 bit or 16 bit port addressing element of the user
+    /// data header, if any.
+    pub fn ports(&self) -> Option<ApplicationPorts> {
+        self.information_elements().into_iter().find_map(|(id, data)| match (id, data) {
+            (0x04, [destination, source]) => Some(ApplicationPorts {
+                destination: u16::from(*destination),
+                source: u16::from(*source),
+            }),
+            (0x05, [d0, d1, s0, s1]) => Some(ApplicationPorts {
+                destination: u16::from_be_bytes([*d0, *d1]),
+                source: u16::from_be_bytes([*s0, *s1]),
+            }),
+            _ => None,
+        })
+    }
+}
+
+/// Alphabet of the user data, from the data coding scheme (3GPP TS 23.038 clause 4).
+enum Alphabet {
+    Gsm7,
+    Eight,
+    Ucs2,
+}
+
+fn alphabet(dcs: u8) -> Alphabet {
+    let bits = match dcs >> 4 {
+        0x0..=0x7 => (dcs >> 2) & 0x03,
+        0xC | 0xD => 0,
+        0xE => 2,
+        0xF => (dcs >> 2) & 0x01,
+        _ => 1,
+    };
+    match bits {
+        0 => Alphabet::Gsm7,
+        2 => Alphabet::Ucs2,
+        _ => Alphabet::Eight,
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, AmlError> {
+        let byte = *self.bytes.get(self.pos).ok_or(AmlError::InvalidPdu)?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], AmlError> {
+        let slice = self.bytes.get(self.pos..self.pos + len).ok_or(AmlError::InvalidPdu)?;
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.bytes[self.pos..]
+    }
+}
+
+/// Bytes of a PDU given as hexadecimal digits, whitespace ignored.
+fn pdu_bytes(pdu: &str) -> Result<Vec<u8>, AmlError> {
+    let digits: String = pdu.chars().filter(|c| !c.is_whitespace()).collect();
+    Ok(hex::decode(digits)?)
+}
+
+/// Digits of a semi-octet (nibble swapped BCD) field, `F` filler excluded.
+fn semi_octets(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .flat_map(|byte| [byte & 0x0F, byte >> 4])
+        .filter(|nibble| *nibble < 10)
+        .map(|nibble| char::from(b'0' + nibble))
+        .collect()
+}
+
+/// An address: type of address then value, `+` prefixed when international.
+fn address(type_of_address: u8, value: &[u8], digits: usize) -> String {
+    match (type_of_address >> 4) & 0x07 {
+        // Alphanumeric, packed in septets
+        5 => SmsData::decode_7to8(value, Some(digits * 4 / 7), 0)
+            .into_iter()
+            .map(char::from)
+            .collect(),
+        1 => format!("+{}", semi_octets(value)),
+        _ => semi_octets(value),
+    }
+}
+
+fn service_center_time(scts: &[u8]) -> Option<Timestamp> {
+    let value = |byte: u8| i64::from((byte & 0x0F) * 10 + (byte >> 4));
+    let year = value(scts[0]);
+    let year = if year < 70 { 2000 + year } else { 1900 + year };
+    let quarters = i64::from((scts[6] & 0x07) * 10 + (scts[6] >> 4));
+    let offset = if scts[6] & 0x08 != 0 { -quarters } else { quarters };
+
+    let local = timestamp::from_utc(
+        (year as i32, value(scts[1]) as u32, value(scts[2]) as u32),
+        (value(scts[3]) as u32, value(scts[4]) as u32, value(scts[5]) as u32),
+    )?;
+    Some(local - Duration::minutes(offset * 15))
+}
+
+/// True if `address` is a phone number rather than an alphanumeric sender.
+fn is_msisdn(address: &str) -> bool {
+    let digits = address.strip_prefix('+').unwrap_or(address);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+impl SmsData {
+    /// Parse a full SMS-DELIVER PDU given as hexadecimal digits, starting with the
+    /// service center address. The text is decoded according to the data coding scheme
+    /// (GSM 7 bit, 8 bit or UCS-2) and the user data header, if any, is skipped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{timestamp, SmsData};
+    ///
+    /// let pdu = "07917283010010F5040BC87238880900F10000993092516195800AE8329BFD4697D9EC37";
+    /// let err = SmsData::from_pdu_hex(pdu).unwrap_err(); // "hellohello" is not an AML message
+    /// assert!(matches!(err, aml_lib::AmlError::UnimplementedVersion));
+    ///
+    /// let (envelope, text) = SmsData::pdu_text(pdu).unwrap();
+    /// assert_eq!(text, "hellohello");
+    /// assert_eq!(envelope.smsc.as_deref(), Some("+27381000015"));
+    /// assert_eq!(envelope.sender, "27838890001");
+    /// assert_eq!(timestamp::to_rfc3339(envelope.service_center_time.unwrap()), "1999-03-29T13:16:59+00:00");
+    /// ```
+    ///
+    /// The sender, when it is a phone number, is kept as [`SmsData::originating_address`].
+    pub fn from_pdu_hex<S: AsRef<str>>(pdu: S) -> Result<(SmsData, PduEnvelope), AmlError> {
+        let (envelope, text) = Self::pdu_text(pdu)?;
+        let sms = SmsData {
+            originating_address: Some(envelope.sender.clone()).filter(|sender| is_msisdn(sender)),
+            ..Self::from_text(text)?
+        };
+        Ok((sms, envelope))
+    }
+
+    /// Envelope and decoded text of a SMS-DELIVER PDU. See [`SmsData::from_pdu_hex`].
+    pub fn pdu_text<S: AsRef<str>>(pdu: S) -> Result<(PduEnvelope, String), AmlError> {
+        let bytes = pdu_bytes(pdu.as_ref())?;
+        let mut reader = Reader { bytes: &bytes, pos: 0 };
+        let (envelope, user_data_length) = Self::read_envelope(&mut reader)?;
+        let user_data = reader.rest();
+        let header_len = envelope.user_data_header.len();
+
+        let text = match alphabet(envelope.data_coding_scheme) {
+            Alphabet::Gsm7 => {
+                let header_septets = (header_len * 8).div_ceil(7);
+                let septets = user_data_length.checked_sub(header_septets).ok_or(AmlError::InvalidPdu)?;
+                let raw = Self::decode_7to8(&user_data[header_len..], Some(septets), header_len);
+                String::from_utf8(raw)?
+            }
+            Alphabet::Eight => {
+                let data = user_data.get(header_len..user_data_length).ok_or(AmlError::InvalidPdu)?;
+                String::from_utf8(data.to_vec())?
+            }
+            Alphabet::Ucs2 => {
+                let data = user_data.get(header_len..user_data_length).ok_or(AmlError::InvalidPdu)?;
+                let units: Vec<u16> = data.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+                String::from_utf16(&units).map_err(|_| AmlError::InvalidUtf8(None))?
+            }
+        };
+
+        Ok((envelope, text))
+    }
+
+    /// Envelope of a SMS-DELIVER PDU, without decoding the text: gateways can check the
+    /// sender or the [application ports](PduEnvelope::ports) before decoding.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{ApplicationPorts, SmsData};
+    ///
+    /// // 16 bit port addressing to port 9200 from port 0
+    /// let pdu = "0044 0B91 3316213243F4 00 04 02112151120040 0A 06050423F00000 414243";
+    /// let envelope = SmsData::pdu_envelope(pdu).unwrap();
+    /// assert_eq!(envelope.ports(), Some(ApplicationPorts { destination: 9200, source: 0 }));
+    /// ```
+    pub fn pdu_envelope<S: AsRef<str>>(pdu: S) -> Result<PduEnvelope, AmlError> {
+        let bytes = pdu_bytes(pdu.as_ref())?;
+        Self::read_envelope(&mut Reader { bytes: &bytes, pos: 0 }).map(|(envelope, _)| envelope)
+    }
+
+    /// The envelope up to the user data header included, and the user data length.
+    fn read_envelope(reader: &mut Reader) -> Result<(PduEnvelope, usize), AmlError> {
+        let mut envelope = PduEnvelope::default();
+
+        let smsc_len = reader.byte()? as usize;
+        if smsc_len > 0 {
+            let smsc = reader.take(smsc_len)?;
+            envelope.smsc = Some(address(smsc[0], &smsc[1..], (smsc_len - 1) * 2));
+        }
+
+        let first_octet = reader.byte()?;
+        if first_octet & 0x03 != 0 {
+            // Not a SMS-DELIVER
+            return Err(AmlError::InvalidPdu);
+        }
+        let has_header = first_octet & 0x40 != 0;
+
+        let sender_digits = reader.byte()? as usize;
+        let type_of_address = reader.byte()?;
+        envelope.sender = address(type_of_address, reader.take(sender_digits.div_ceil(2))?, sender_digits);
+
+        envelope.protocol_id = reader.byte()?;
+        envelope.data_coding_scheme = reader.byte()?;
+        envelope.service_center_time = service_center_time(reader.take(7)?);
+        let user_data_length = reader.byte()? as usize;
+
+        if has_header {
+            let user_data = reader.rest();
+            let header_len = *user_data.first().ok_or(AmlError::InvalidPdu)? as usize + 1;
+            envelope.user_data_header = user_data.get(..header_len).ok_or(AmlError::InvalidPdu)?.to_vec();
+        }
+        Ok((envelope, user_data_length))
+    }
+}
@@ -0,0 +1,278 @@
+//! Wrapping of AML data into envelopes, to forward it between the providers of a PEMEA
+//! (Pan-European Mobile Emergency Application) network.
+//!
+//! An envelope identifies the message, its originating and destination providers and
+//! carries either the decoded data or the raw payload as received. It is written either
+//! as XML, in the [`NAMESPACE`] of this crate, or as JSON. The XML is read with a
+//! namespace aware parser: any prefix, CDATA sections, character references and
+//! unknown elements are accepted.
+
+use crate::json::JsonValue;
+use crate::{batch, timestamp, AmlData, AmlError, Timestamp};
+use quick_xml::events::{BytesDecl, BytesText, Event};
+use quick_xml::name::ResolveResult;
+use quick_xml::{NsReader, Writer};
+
+/// XML namespace of the envelope.
+pub const NAMESPACE: &str = "https://github.com/cpilatre/aml-lib/ns/envelope";
+
+/// Media type of decoded data, serialized by [`AmlData::to_json`].
+const JSON: &str = "application/json";
+
+/// Media type of a raw payload, with its transport as `transport` parameter.
+const TEXT: &str = "text/plain";
+
+/// What an envelope carries.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Content {
+    /// Decoded data.
+    Aml(Box<AmlData>),
+
+    /// A payload as received, with its transport (see [`batch::parse_record`] for the names).
+    Raw { transport: String, payload: String },
+}
+
+/// A PEMEA envelope.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Envelope {
+    /// Unique identifier of the message.
+    pub id: String,
+
+    /// Identifier of the provider sending the envelope.
+    pub origin: String,
+
+    /// Identifier of the provider the envelope is sent to.
+    pub destination: String,
+
+    /// When the envelope has been created.
+    pub created: Timestamp,
+
+    /// The data or the raw payload.
+    pub content: Content,
+}
+
+impl Envelope {
+    /// Data carried by the envelope, parsing the raw payload if needed.
+    pub fn aml_data(&self) -> Result<AmlData, AmlError> {
+        match &self.content {
+            Content::Aml(aml) => Ok(aml.as_ref().clone()),
+            Content::Raw { transport, payload } => batch::parse_payload(Some(transport), payload),
+        }
+    }
+
+    fn content_type(&self) -> String {
+        match &self.content {
+            Content::Aml(_) => JSON.to_string(),
+            Content::Raw { transport, .. } => format!("{}; transport={}", TEXT, transport),
+        }
+    }
+
+    fn content_body(&self) -> String {
+        match &self.content {
+            Content::Aml(aml) => aml.to_json(),
+            Content::Raw { payload, .. } => payload.clone(),
+        }
+    }
+
+    fn from_parts(id: String, origin: String, destination: String, created: &str, content_type: &str, body: String) -> Result<Self, AmlError> {
+        let created = timestamp::parse_rfc3339(created).ok_or(AmlError::InvalidEnvelope)?;
+
+        let mut params = content_type.split(';').map(str::trim);
+        let media_type = params.next().unwrap_or_default();
+        let content = if media_type.eq_ignore_ascii_case(JSON) {
+            let value = JsonValue::parse(&body).ok_or(AmlError::InvalidEnvelope)?;
+            Content::Aml(Box::new(AmlData::from_json_value(&value).ok_or(AmlError::InvalidEnvelope)?))
+        } else if media_type.eq_ignore_ascii_case(TEXT) {
+            let transport = params
+                .filter_map(|param| param.split_once('='))
+                .find(|(name, _)| name.trim().eq_ignore_ascii_case("transport"))
+                .map(|(_, value)| value.trim().trim_matches('"'))
+                .ok_or(AmlError::InvalidEnvelope)?;
+            Content::Raw {
+                transport: transport.to_string(),
+                payload: body,
+            }
+        } else {
+            return Err(AmlError::InvalidEnvelope);
+        };
+
+        Ok(Envelope {
+            id,
+            origin,
+            destination,
+            created,
+            content,
+        })
+    }
+
+    /// The envelope as a XML document.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::pemea::{Content, Envelope};
+    /// use aml_lib::timestamp;
+    ///
+    /// let envelope = Envelope {
+    ///     id: "42".to_string(),
+    ///     origin: "ap.example.fr".to_string(),
+    ///     destination: "ap.example.be".to_string(),
+    ///     created: timestamp::from_unix(1604912121).unwrap(),
+    ///     content: Content::Raw { transport: "sms".to_string(), payload: r#"A"ML=1;lt=48.82639;lg=-2.36619"#.to_string() },
+    /// };
+    ///
+    /// let xml = envelope.to_xml();
+    /// let back = Envelope::from_xml(&xml).unwrap();
+    /// assert_eq!(back.aml_data().unwrap().latitude, Some(48.82639));
+    /// ```
+    pub fn to_xml(&self) -> String {
+        let created = timestamp::to_rfc3339_millis(self.created);
+        let content_type = self.content_type();
+        let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+
+        // Writing to a `Vec` does not fail
+        writer
+            .write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))
+            .expect("write to a Vec");
+        writer
+            .create_element("pemea:envelope")
+            .with_attribute(("xmlns:pemea", NAMESPACE))
+            .write_inner_content(|writer| {
+                for (name, text) in [("id", &self.id), ("origin", &self.origin), ("destination", &self.destination), ("created", &created)] {
+                    writer.create_element(format!("pemea:{}", name)).write_text_content(BytesText::new(text))?;
+                }
+                writer
+                    .create_element("pemea:content")
+                    .with_attribute(("type", content_type.as_str()))
+                    .write_text_content(BytesText::new(&self.content_body()))?;
+                Ok(())
+            })
+            .expect("write to a Vec");
+
+        let mut xml = String::from_utf8(writer.into_inner()).expect("UTF-8 XML");
+        xml.push('\n');
+        xml
+    }
+
+    /// Parse a XML envelope, as written by [`Envelope::to_xml`].
+    pub fn from_xml(xml: &str) -> Result<Self, AmlError> {
+        let mut reader = NsReader::from_str(xml);
+        let (mut id, mut origin, mut destination, mut created, mut content) = (None, None, None, None, None);
+
+        let mut in_envelope = false;
+        loop {
+            let event = reader.read_resolved_event().map_err(|_| AmlError::InvalidEnvelope)?;
+            match event {
+                (ResolveResult::Bound(ns), Event::Start(start)) if ns.as_ref() == NAMESPACE.as_bytes() => {
+                    let name = start.local_name();
+                    if !in_envelope {
+                        if name.as_ref() != b"envelope" {
+                            return Err(AmlError::InvalidEnvelope);
+                        }
+                        in_envelope = true;
+                        continue;
+                    }
+                    let field = match name.as_ref() {
+                        b"id" => &mut id,
+                        b"origin" => &mut origin,
+                        b"destination" => &mut destination,
+                        b"created" => &mut created,
+                        b"content" => {
+                            let content_type = start
+                                .try_get_attribute("type")
+                                .ok()
+                                .flatten()
+                                .and_then(|attribute| attribute.unescape_value().ok())
+                                .ok_or(AmlError::InvalidEnvelope)?;
+                            content = Some((content_type.into_owned(), xml_text(&mut reader)?));
+                            continue;
+                        }
+                        _ => {
+                            reader.read_to_end(start.name()).map_err(|_| AmlError::InvalidEnvelope)?;
+                            continue;
+                        }
+                    };
+                    *field = Some(xml_text(&mut reader)?);
+                }
+                (_, Event::Start(start)) if in_envelope => {
+                    reader.read_to_end(start.name()).map_err(|_| AmlError::InvalidEnvelope)?;
+                }
+                (_, Event::Start(_)) | (_, Event::Empty(_)) if !in_envelope => return Err(AmlError::InvalidEnvelope),
+                (_, Event::End(_)) | (_, Event::Eof) => break,
+                _ => {}
+            }
+        }
+
+        let (content_type, body) = content.ok_or(AmlError::InvalidEnvelope)?;
+        Self::from_parts(
+            id.ok_or(AmlError::InvalidEnvelope)?,
+            origin.ok_or(AmlError::InvalidEnvelope)?,
+            destination.ok_or(AmlError::InvalidEnvelope)?,
+            created.ok_or(AmlError::InvalidEnvelope)?.trim(),
+            &content_type,
+            body,
+        )
+    }
+
+    /// The envelope as a JSON object. Decoded data is embedded as an object.
+    pub fn to_json_value(&self) -> JsonValue {
+        let text = |s: &str| JsonValue::String(s.to_string());
+        let content = match &self.content {
+            Content::Aml(aml) => aml.to_json_value(),
+            Content::Raw { payload, .. } => text(payload),
+        };
+
+        JsonValue::Object(vec![
+            ("id".to_string(), text(&self.id)),
+            ("origin".to_string(), text(&self.origin)),
+            ("destination".to_string(), text(&self.destination)),
+            ("created".to_string(), text(&timestamp::to_rfc3339_millis(self.created))),
+            ("content_type".to_string(), text(&self.content_type())),
+            ("content".to_string(), content),
+        ])
+    }
+
+    /// The envelope as a single line JSON document. See [`Envelope::to_json_value`].
+    pub fn to_json(&self) -> String {
+        self.to_json_value().to_string()
+    }
+
+    /// Parse a JSON envelope written by [`Envelope::to_json`].
+    pub fn from_json(json: &str) -> Result<Self, AmlError> {
+        let value = JsonValue::parse(json).ok_or(AmlError::InvalidEnvelope)?;
+        let text = |key: &str| {
+            value
+                .get(key)
+                .and_then(JsonValue::as_str)
+                .map(str::to_string)
+                .ok_or(AmlError::InvalidEnvelope)
+        };
+        let body = match value.get("content").ok_or(AmlError::InvalidEnvelope)? {
+            JsonValue::String(payload) => payload.clone(),
+            object => object.to_string(),
+        };
+
+        Self::from_parts(
+            text("id")?,
+            text("origin")?,
+            text("destination")?,
+            &text("created")?,
+            &text("content_type")?,
+            body,
+        )
+    }
+}
+
+/// Text of the element just opened, up to its end.
+fn xml_text(reader: &mut NsReader<&[u8]>) -> Result<String, AmlError> {
+    let mut text = String::new();
+    loop {
+        match reader.read_event().map_err(|_| AmlError::InvalidEnvelope)? {
+            Event::Text(chunk) => text.push_str(&chunk.unescape().map_err(|_| AmlError::InvalidEnvelope)?),
+            Event::CData(chunk) => text.push_str(std::str::from_utf8(&chunk).map_err(|_| AmlError::InvalidEnvelope)?),
+            Event::Comment(_) | Event::PI(_) => {}
+            Event::End(_) => return Ok(text),
+            _ => return Err(AmlError::InvalidEnvelope),
+        }
+    }
+}
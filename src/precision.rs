@@ -0,0 +1,61 @@
+//! Precision of the values in AML messages: latitude and longitude are truncated to
+//! 5 decimals, altitude and vertical accuracy to 1 decimal.
+
+use crate::AmlData;
+
+/// Decimals of latitude and longitude in degrees (about 1 m).
+pub const COORDINATE_DECIMALS: i32 = 5;
+
+/// Decimals of altitude and vertical accuracy in meters.
+pub const ALTITUDE_DECIMALS: i32 = 1;
+
+/// `value` truncated (toward zero) to `decimals` decimals. Values which are already at
+/// that precision but not exactly representable (e.g. `48.82639`) are kept as is.
+///
+/// # Example
+///
+/// ```
+/// use aml_lib::precision::truncate_decimals;
+///
+/// assert_eq!(truncate_decimals(48.826398, 5), 48.82639);
+/// assert_eq!(truncate_decimals(-2.366199, 5), -2.36619);
+/// assert_eq!(truncate_decimals(48.82639, 5), 48.82639);
+/// ```
+pub fn truncate_decimals(value: f64, decimals: i32) -> f64 {
+    let factor = 10_f64.powi(decimals);
+    let scaled = value * factor;
+    let nearest = scaled.round();
+
+    if (scaled - nearest).abs() < 1e-6 {
+        nearest / factor
+    } else {
+        scaled.trunc() / factor
+    }
+}
+
+impl AmlData {
+    /// Truncate the location to the precision of the specification, so that encoding
+    /// and parsing it again gives the same values.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::AmlData;
+    ///
+    /// let mut aml = AmlData::from_https("location_latitude=48.8263987&location_altitude=35.27").unwrap();
+    /// aml.normalize_precision();
+    /// assert_eq!((aml.latitude, aml.altitude), (Some(48.82639), Some(35.2)));
+    /// ```
+    pub fn normalize_precision(&mut self) {
+        let truncate = |value: &mut Option<f64>, decimals: i32| {
+            if let Some(v) = value {
+                *v = truncate_decimals(*v, decimals);
+            }
+        };
+
+        truncate(&mut self.latitude, COORDINATE_DECIMALS);
+        truncate(&mut self.longitude, COORDINATE_DECIMALS);
+        truncate(&mut self.altitude, ALTITUDE_DECIMALS);
+        truncate(&mut self.vertical_accuracy, ALTITUDE_DECIMALS);
+    }
+}
@@ -0,0 +1,198 @@
+use crate::validation::{check_location, clean_text};
+use crate::{seconds_to_utc, timestamp, AmlData, AmlError, HttpsData, SmsData, ValidationFailure, ValidationReport};
+
+/// Versions of the Android Emergency Location Service messages.
+const ELS_VERSIONS: &[&str] = &["1", "2", "3"];
+
+/// Smallest `et` read as milliseconds (3 March 1973 in milliseconds, year 5138 in seconds).
+const MILLIS_THRESHOLD: i64 = 100_000_000_000;
+
+/// The sender conventions a message has been parsed with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "lowercase"))]
+pub enum Profile {
+    /// ETSI TS 103 625 as is.
+    #[default]
+    Generic,
+
+    /// Android Emergency Location Service (Google ELS) HTTPS messages.
+    /// `location_certainty` (percent) divided by 100 stands for `location_confidence`, and `v`,
+    /// `emergency_number` and `time` are required.
+    Els,
+
+    /// iPhone text SMS. `top` may carry a fractional part or a `Z` suffix, `et`
+    /// may be in milliseconds and `lc` may be a ratio between 0 and 1.
+    Apple,
+}
+
+impl Profile {
+    /// The profile of a name given by [`Profile::as_str`], case insensitive.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "generic" => Some(Profile::Generic),
+            "els" => Some(Profile::Els),
+            "apple" => Some(Profile::Apple),
+            _ => None,
+        }
+    }
+
+    /// The lowercase name of the profile, as in [`AmlData::to_json_value`].
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Profile::Generic => "generic",
+            Profile::Els => "els",
+            Profile::Apple => "apple",
+        }
+    }
+
+    /// Guess the profile of a raw payload: text SMS showing an iPhone convention (a `top`
+    /// with a fractional part or a suffix, an `et` in milliseconds or a decimal `lc` below
+    /// 1) come from iPhones, and HTTPS messages with Android specific fields come from ELS.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::Profile;
+    ///
+    /// assert_eq!(Profile::detect(r#"A"ML=1;lt=48.82639;lg=-2.36619;top=20191112112928.250Z"#), Profile::Apple);
+    /// assert_eq!(Profile::detect(r#"A"ML=1;lt=48.82639;lg=-2.36619;lc=1"#), Profile::Generic);
+    /// assert_eq!(Profile::detect("v=1&thunderbird_version=1.0&location_latitude=0.85"), Profile::Els);
+    /// assert_eq!(Profile::detect("v=1&location_latitude=0.85"), Profile::Generic);
+    /// ```
+    pub fn detect(payload: &str) -> Profile {
+        let payload = clean_text(payload).0;
+
+        if payload.starts_with(r#"A"ML="#) {
+            let properties = SmsData::get_properties(payload);
+            let top = properties.get("top").is_some_and(|top| top.len() > 14);
+            let et = properties.get("et").and_then(|et| et.parse::<i64>().ok()).is_some_and(|et| et >= MILLIS_THRESHOLD);
+            let lc = properties
+                .get("lc")
+                .is_some_and(|lc| lc.contains('.') && lc.parse::<f64>().is_ok_and(|lc| lc > 0.0 && lc < 1.0));
+            if top || et || lc {
+                Profile::Apple
+            } else {
+                Profile::Generic
+            }
+        } else if payload
+            .split('&')
+            .any(|pair| pair.starts_with("thunderbird_version=") || pair.starts_with("location_certainty="))
+        {
+            Profile::Els
+        } else {
+            Profile::Generic
+        }
+    }
+}
+
+impl SmsData {
+    /// Parse a SMS text with the conventions of `profile`, which is recorded in
+    /// [`SmsData::profile`]. See [`SmsData::from_text`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{Profile, SmsData};
+    ///
+    /// let sms_text = r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928.250Z;lc=0.68;pm=G"#;
+    /// let sms = SmsData::from_text_with(sms_text, Profile::Apple).unwrap();
+    /// assert_eq!(sms.level_of_confidence, Some(68.0));
+    /// assert!(sms.time_of_positioning.is_some());
+    /// ```
+    pub fn from_text_with<S: AsRef<str>>(text_sms: S, profile: Profile) -> Result<Self, AmlError> {
+        let text_sms = text_sms.as_ref();
+        let mut sms_data = Self::from_text(text_sms)?;
+        sms_data.profile = profile;
+
+        if profile == Profile::Apple {
+            let properties = Self::get_properties(text_sms);
+
+            if let Some(top) = properties.get("top") {
+                if let Some(top) = top.get(..14).and_then(timestamp::parse_compact) {
+                    sms_data.time_of_positioning = Some(top);
+                }
+            }
+
+            if let Some(et) = sms_data.beginning_of_call.filter(|et| *et >= MILLIS_THRESHOLD) {
+                sms_data.beginning_of_call = Some(et / 1000);
+                let lt = properties.get("lt").and_then(|lt| lt.parse::<i64>().ok()).filter(|lt| *lt >= 0);
+                if let (Some("2"), Some(lt)) = (sms_data.header.as_deref(), lt) {
+                    sms_data.time_of_positioning = (et / 1000).checked_add(lt).and_then(|top| seconds_to_utc!(top));
+                }
+            }
+
+            if let Some(lc) = sms_data.level_of_confidence.filter(|lc| *lc > 0.0 && *lc <= 1.0) {
+                sms_data.level_of_confidence = Some(lc * 100.0);
+            }
+
+            sms_data.validation = sms_data.validate(text_sms);
+        }
+
+        Ok(sms_data)
+    }
+}
+
+impl HttpsData {
+    /// Parse a HTTPS AML message with the conventions of `profile`, which is recorded
+    /// in [`HttpsData::profile`]. See [`HttpsData::from_urlencoded`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{HttpsData, Profile};
+    ///
+    /// let https = "v=1&emergency_number=112&time=1604912100000&location_latitude=0.85732&location_longitude=-4.26325&location_certainty=83";
+    /// let data = HttpsData::from_urlencoded_with(https, Profile::Els);
+    /// assert_eq!(data.location_confidence, Some(0.83));
+    /// assert!(data.validate().is_valid());
+    /// ```
+    pub fn from_urlencoded_with<S: AsRef<str>>(payload: S, profile: Profile) -> Self {
+        let mut https_data = Self::from_urlencoded(payload);
+        https_data.profile = profile;
+
+        if profile == Profile::Els && https_data.location_confidence.is_none() {
+            https_data.location_confidence = https_data.location_certainty.map(|certainty| certainty / 100.0);
+        }
+
+        https_data
+    }
+
+    /// Check the message against the rules of its profile.
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport {
+            header_first: true,
+            ..Default::default()
+        };
+
+        if self.profile == Profile::Els {
+            match self.v.as_deref() {
+                Some(v) if ELS_VERSIONS.contains(&v) => (),
+                Some(v) => report.failures.push(ValidationFailure::UnsupportedVersion(v.to_string())),
+                None => report.failures.push(ValidationFailure::MissingField("v")),
+            }
+            if self.emergency_number.is_none() {
+                report.failures.push(ValidationFailure::MissingField("emergency_number"));
+            }
+            if self.time.is_none() {
+                report.failures.push(ValidationFailure::MissingField("time"));
+            }
+        }
+
+        check_location(&mut report, self.location_latitude, self.location_longitude, self.location_confidence);
+
+        report
+    }
+}
+
+impl AmlData {
+    /// Parse a HTTPS AML message with the conventions of `profile`.
+    /// See [`HttpsData::from_urlencoded_with`].
+    pub fn from_https_with(payload: &str, profile: Profile) -> Result<Self, AmlError> {
+        Ok(HttpsData::from_urlencoded_with(payload, profile).into())
+    }
+
+    /// Parse a SMS text with the conventions of `profile`. See [`SmsData::from_text_with`].
+    pub fn from_text_sms_with<S: AsRef<str>>(text_sms: S, profile: Profile) -> Result<Self, AmlError> {
+        Ok(SmsData::from_text_with(text_sms, profile)?.into())
+    }
+}
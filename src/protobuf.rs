@@ -0,0 +1,390 @@
+//! Protocol Buffers encoding of the data, following `proto/aml.proto`.
+//!
+//! [`AmlData::to_protobuf`] and [`AmlData::from_protobuf`] read and write the wire
+//! format of the `aml.v1.AmlData` message directly, so that the types generated from
+//! the definition by any Protocol Buffers compiler convert from and to [`AmlData`]
+//! through their bytes.
+//!
+//! With the `prost` feature, [`v1`] holds the types generated by prost, which convert
+//! from [`AmlData`] with `From` and back with `TryFrom`:
+//!
+//! ```
+//! # #[cfg(feature = "prost")] {
+//! use aml_lib::protobuf::v1;
+//! use aml_lib::AmlData;
+//! use prost::Message;
+//! use std::convert::TryFrom;
+//!
+//! let aml = AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20"#).unwrap();
+//! let message = v1::AmlData::from(&aml);
+//! assert_eq!(message.encode_to_vec(), aml.to_protobuf());
+//! assert_eq!(AmlData::try_from(message).unwrap(), aml);
+//! # }
+//! ```
+
+use crate::geo::Ellipse;
+use crate::json::JsonValue;
+use crate::{timestamp, AdrData, AmlData, AmlError, ReceiveContext, Timestamp};
+use std::convert::{TryFrom, TryInto};
+
+/// The types generated by prost from [`PROTO`], checked in so that building does not
+/// need `protoc`. Regenerate them with `prost_build::compile_protos(&["proto/aml.proto"], &["proto"])`.
+#[cfg(feature = "prost")]
+#[allow(clippy::all)]
+pub mod v1 {
+    include!("protobuf/aml.v1.rs");
+}
+#[cfg(feature = "prost")]
+mod prost;
+
+/// The definition of the messages.
+pub const PROTO: &str = include_str!("../proto/aml.proto");
+
+const VARINT: u8 = 0;
+const FIXED64: u8 = 1;
+const BYTES: u8 = 2;
+const FIXED32: u8 = 5;
+
+#[derive(Default)]
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+
+    fn key(&mut self, field: u32, wire_type: u8) {
+        self.varint(u64::from(field) << 3 | u64::from(wire_type));
+    }
+
+    fn bytes(&mut self, field: u32, bytes: &[u8]) {
+        self.key(field, BYTES);
+        self.varint(bytes.len() as u64);
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn string(&mut self, field: u32, value: Option<&str>) {
+        if let Some(value) = value {
+            self.bytes(field, value.as_bytes());
+        }
+    }
+
+    fn double(&mut self, field: u32, value: Option<f64>) {
+        if let Some(value) = value {
+            self.key(field, FIXED64);
+            self.0.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    /// Negative values are sign-extended to 64 bits, as for `int32`.
+    fn int(&mut self, field: u32, value: Option<i64>) {
+        if let Some(value) = value {
+            self.key(field, VARINT);
+            self.varint(value as u64);
+        }
+    }
+
+    fn message(&mut self, field: u32, message: Option<Writer>) {
+        if let Some(message) = message {
+            self.bytes(field, &message.0);
+        }
+    }
+
+    fn timestamp(&mut self, field: u32, value: Option<Timestamp>) {
+        self.message(field, value.map(|value| {
+            let mut timestamp = Writer::default();
+            timestamp.int(1, Some(timestamp::unix(value)).filter(|seconds| *seconds != 0));
+            timestamp.int(2, Some(i64::from(timestamp::subsec_nanos(value))).filter(|nanos| *nanos != 0));
+            timestamp
+        }));
+    }
+}
+
+/// A field as read, by wire type.
+enum Value<'a> {
+    Varint(u64),
+    Fixed64([u8; 8]),
+    Bytes(&'a [u8]),
+    Fixed32,
+}
+
+impl<'a> Value<'a> {
+    fn varint(&self) -> Option<u64> {
+        match self {
+            Value::Varint(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn double(&self) -> Option<f64> {
+        match self {
+            Value::Fixed64(bytes) => Some(f64::from_le_bytes(*bytes)),
+            _ => None,
+        }
+    }
+
+    fn int32(&self) -> Option<i32> {
+        self.varint().map(|value| value as i32)
+    }
+
+    fn bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            Value::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    fn string(&self) -> Option<String> {
+        std::str::from_utf8(self.bytes()?).ok().map(str::to_string)
+    }
+
+    fn timestamp(&self) -> Option<Timestamp> {
+        let (mut seconds, mut nanos) = (0, 0);
+        for (field, value) in fields(self.bytes()?)? {
+            match field {
+                1 => seconds = value.varint()? as i64,
+                2 => nanos = value.varint()? as u32,
+                _ => {}
+            }
+        }
+        timestamp::from_unix_nanos(seconds, nanos)
+    }
+}
+
+/// The fields of a message, `None` if it is truncated or malformed.
+fn fields(bytes: &[u8]) -> Option<Vec<(u32, Value<'_>)>> {
+    let mut pos = 0;
+    let varint = |pos: &mut usize| {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *bytes.get(*pos)?;
+            *pos += 1;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    };
+
+    let mut fields = Vec::new();
+    while pos < bytes.len() {
+        let key = varint(&mut pos)?;
+        let field = u32::try_from(key >> 3).ok().filter(|field| *field != 0)?;
+        let value = match key as u8 & 0x07 {
+            VARINT => Value::Varint(varint(&mut pos)?),
+            FIXED64 => {
+                let value = bytes.get(pos..pos + 8)?;
+                pos += 8;
+                Value::Fixed64(value.try_into().ok()?)
+            }
+            BYTES => {
+                let length = usize::try_from(varint(&mut pos)?).ok()?;
+                let value = bytes.get(pos..pos.checked_add(length)?)?;
+                pos += length;
+                Value::Bytes(value)
+            }
+            FIXED32 => {
+                bytes.get(pos..pos + 4)?;
+                pos += 4;
+                Value::Fixed32
+            }
+            _ => return None,
+        };
+        fields.push((field, value));
+    }
+    Some(fields)
+}
+
+impl AmlData {
+    /// The data as a `aml.v1.AmlData` message, see [`PROTO`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::AmlData;
+    ///
+    /// let aml = AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;ei=358239059042542"#).unwrap();
+    /// let bytes = aml.to_protobuf();
+    ///
+    /// assert_eq!(&bytes[..3], [0x0a, 0x01, b'2']);
+    /// assert_eq!(AmlData::from_protobuf(&bytes).unwrap(), aml);
+    /// ```
+    pub fn to_protobuf(&self) -> Vec<u8> {
+        let mut message = Writer::default();
+        message.string(1, self.version.as_deref());
+        message.string(2, self.emergency_number.as_deref());
+        message.string(3, self.source_of_activation.as_deref());
+        message.timestamp(4, self.beginning_of_call);
+        message.double(5, self.latitude);
+        message.double(6, self.longitude);
+        message.timestamp(7, self.time_of_positioning);
+        message.double(8, self.altitude);
+        message.string(9, self.floor.as_deref());
+        message.string(10, self.positioning_method.as_deref());
+        message.double(11, self.accuracy);
+        message.double(12, self.vertical_accuracy);
+        message.message(13, self.ellipse.map(|ellipse| {
+            let mut message = Writer::default();
+            message.double(1, Some(ellipse.semi_major).filter(|value| *value != 0.0));
+            message.double(2, Some(ellipse.semi_minor).filter(|value| *value != 0.0));
+            message.double(3, Some(ellipse.orientation).filter(|value| *value != 0.0));
+            message
+        }));
+        message.double(14, self.confidence);
+        message.double(15, self.bearing);
+        message.double(16, self.speed);
+        message.string(17, self.device_number.as_deref());
+        message.string(18, self.model.as_deref());
+        message.string(19, self.imsi.as_deref());
+        message.string(20, self.imei.as_deref());
+        message.string(21, self.iccid.as_deref());
+        message.int(22, self.home_mcc.map(i64::from));
+        message.int(23, self.home_mnc.map(i64::from));
+        message.int(24, self.network_mcc.map(i64::from));
+        message.int(25, self.network_mnc.map(i64::from));
+        message.string(26, self.languages.as_deref());
+        message.string(27, Some(self.transport.as_str()).filter(|transport| !transport.is_empty()));
+        message.string(28, self.via.as_deref());
+        message.message(29, self.ground_truth.map(|(latitude, longitude)| {
+            let mut message = Writer::default();
+            message.double(1, Some(latitude).filter(|value| *value != 0.0));
+            message.double(2, Some(longitude).filter(|value| *value != 0.0));
+            message
+        }));
+        message.int(30, Some(1).filter(|_| self.test));
+        message.message(31, self.adr.as_ref().map(|adr| {
+            let mut message = Writer::default();
+            message.timestamp(1, adr.carcrash_time);
+            message.string(2, adr.severity.as_deref());
+            message.int(3, adr.rollover.map(i64::from));
+            message.double(4, adr.impact_direction);
+            message.int(5, adr.occupants.map(i64::from));
+            message
+        }));
+        message.message(32, self.context.as_ref().map(|context| {
+            let mut message = Writer::default();
+            message.timestamp(1, context.received_at);
+            message.string(2, context.source_msisdn.as_deref());
+            message.string(3, context.gateway_id.as_deref());
+            message.string(4, context.remote_ip.map(|ip| ip.to_string()).as_deref());
+            message
+        }));
+        for (key, value) in &self.extras {
+            let mut extra = Writer::default();
+            extra.string(1, Some(key.as_str()));
+            extra.string(2, Some(value.to_string().as_str()));
+            message.message(33, Some(extra));
+        }
+        message.0
+    }
+
+    /// Read back a `aml.v1.AmlData` message. Unknown fields are skipped and, as usual
+    /// with Protocol Buffers, the last value of a repeated field wins.
+    pub fn from_protobuf(bytes: &[u8]) -> Result<AmlData, AmlError> {
+        let mut aml = AmlData::default();
+        for (field, value) in fields(bytes).ok_or(AmlError::InvalidProtobuf)? {
+            let mut read = || -> Option<()> {
+                match field {
+                    1 => aml.version = Some(value.string()?),
+                    2 => aml.emergency_number = Some(value.string()?),
+                    3 => aml.source_of_activation = Some(value.string()?),
+                    4 => aml.beginning_of_call = Some(value.timestamp()?),
+                    5 => aml.latitude = Some(value.double()?),
+                    6 => aml.longitude = Some(value.double()?),
+                    7 => aml.time_of_positioning = Some(value.timestamp()?),
+                    8 => aml.altitude = Some(value.double()?),
+                    9 => aml.floor = Some(value.string()?),
+                    10 => aml.positioning_method = Some(value.string()?),
+                    11 => aml.accuracy = Some(value.double()?),
+                    12 => aml.vertical_accuracy = Some(value.double()?),
+                    13 => {
+                        let mut ellipse = Ellipse::default();
+                        for (field, value) in fields(value.bytes()?)? {
+                            match field {
+                                1 => ellipse.semi_major = value.double()?,
+                                2 => ellipse.semi_minor = value.double()?,
+                                3 => ellipse.orientation = value.double()?,
+                                _ => {}
+                            }
+                        }
+                        aml.ellipse = Some(ellipse);
+                    }
+                    14 => aml.confidence = Some(value.double()?),
+                    15 => aml.bearing = Some(value.double()?),
+                    16 => aml.speed = Some(value.double()?),
+                    17 => aml.device_number = Some(value.string()?),
+                    18 => aml.model = Some(value.string()?),
+                    19 => aml.imsi = Some(value.string()?),
+                    20 => aml.imei = Some(value.string()?),
+                    21 => aml.iccid = Some(value.string()?),
+                    22 => aml.home_mcc = Some(value.int32()?),
+                    23 => aml.home_mnc = Some(value.int32()?),
+                    24 => aml.network_mcc = Some(value.int32()?),
+                    25 => aml.network_mnc = Some(value.int32()?),
+                    26 => aml.languages = Some(value.string()?),
+                    27 => aml.transport = value.string()?,
+                    28 => aml.via = Some(value.string()?),
+                    29 => {
+                        let (mut latitude, mut longitude) = (0.0, 0.0);
+                        for (field, value) in fields(value.bytes()?)? {
+                            match field {
+                                1 => latitude = value.double()?,
+                                2 => longitude = value.double()?,
+                                _ => {}
+                            }
+                        }
+                        aml.ground_truth = Some((latitude, longitude));
+                    }
+                    30 => aml.test = value.varint()? != 0,
+                    31 => {
+                        let mut adr = AdrData::default();
+                        for (field, value) in fields(value.bytes()?)? {
+                            match field {
+                                1 => adr.carcrash_time = Some(value.timestamp()?),
+                                2 => adr.severity = Some(value.string()?),
+                                3 => adr.rollover = Some(value.varint()? != 0),
+                                4 => adr.impact_direction = Some(value.double()?),
+                                5 => adr.occupants = Some(value.varint()? as u32),
+                                _ => {}
+                            }
+                        }
+                        aml.adr = Some(adr);
+                    }
+                    32 => {
+                        let mut context = ReceiveContext::default();
+                        for (field, value) in fields(value.bytes()?)? {
+                            match field {
+                                1 => context.received_at = Some(value.timestamp()?),
+                                2 => context.source_msisdn = Some(value.string()?),
+                                3 => context.gateway_id = Some(value.string()?),
+                                4 => context.remote_ip = Some(value.string()?.parse().ok()?),
+                                _ => {}
+                            }
+                        }
+                        aml.context = Some(context);
+                    }
+                    33 => {
+                        let (mut key, mut json) = (String::new(), String::new());
+                        for (field, value) in fields(value.bytes()?)? {
+                            match field {
+                                1 => key = value.string()?,
+                                2 => json = value.string()?,
+                                _ => {}
+                            }
+                        }
+                        aml.extras.push((key, JsonValue::parse(&json)?));
+                    }
+                    _ => {}
+                }
+                Some(())
+            };
+            read().ok_or(AmlError::InvalidProtobuf)?;
+        }
+        Ok(aml)
+    }
+}
@@ -0,0 +1,122 @@
+// This file is @generated by prost-build.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AmlData {
+    #[prost(string, optional, tag = "1")]
+    pub version: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "2")]
+    pub emergency_number: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "3")]
+    pub source_of_activation: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(message, optional, tag = "4")]
+    pub beginning_of_call: ::core::option::Option<::prost_types::Timestamp>,
+    #[prost(double, optional, tag = "5")]
+    pub latitude: ::core::option::Option<f64>,
+    #[prost(double, optional, tag = "6")]
+    pub longitude: ::core::option::Option<f64>,
+    #[prost(message, optional, tag = "7")]
+    pub time_of_positioning: ::core::option::Option<::prost_types::Timestamp>,
+    #[prost(double, optional, tag = "8")]
+    pub altitude: ::core::option::Option<f64>,
+    #[prost(string, optional, tag = "9")]
+    pub floor: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "10")]
+    pub positioning_method: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(double, optional, tag = "11")]
+    pub accuracy: ::core::option::Option<f64>,
+    #[prost(double, optional, tag = "12")]
+    pub vertical_accuracy: ::core::option::Option<f64>,
+    #[prost(message, optional, tag = "13")]
+    pub ellipse: ::core::option::Option<Ellipse>,
+    #[prost(double, optional, tag = "14")]
+    pub confidence: ::core::option::Option<f64>,
+    #[prost(double, optional, tag = "15")]
+    pub bearing: ::core::option::Option<f64>,
+    #[prost(double, optional, tag = "16")]
+    pub speed: ::core::option::Option<f64>,
+    #[prost(string, optional, tag = "17")]
+    pub device_number: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "18")]
+    pub model: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "19")]
+    pub imsi: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "20")]
+    pub imei: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "21")]
+    pub iccid: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(int32, optional, tag = "22")]
+    pub home_mcc: ::core::option::Option<i32>,
+    #[prost(int32, optional, tag = "23")]
+    pub home_mnc: ::core::option::Option<i32>,
+    #[prost(int32, optional, tag = "24")]
+    pub network_mcc: ::core::option::Option<i32>,
+    #[prost(int32, optional, tag = "25")]
+    pub network_mnc: ::core::option::Option<i32>,
+    #[prost(string, optional, tag = "26")]
+    pub languages: ::core::option::Option<::prost::alloc::string::String>,
+    /// `sms` or `https`.
+    #[prost(string, tag = "27")]
+    pub transport: ::prost::alloc::string::String,
+    #[prost(string, optional, tag = "28")]
+    pub via: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(message, optional, tag = "29")]
+    pub ground_truth: ::core::option::Option<GroundTruth>,
+    #[prost(bool, tag = "30")]
+    pub test: bool,
+    #[prost(message, optional, tag = "31")]
+    pub adr: ::core::option::Option<Adr>,
+    #[prost(message, optional, tag = "32")]
+    pub context: ::core::option::Option<ReceiveContext>,
+    /// Members unknown to the writer, in order.
+    #[prost(message, repeated, tag = "33")]
+    pub extras: ::prost::alloc::vec::Vec<Extra>,
+}
+/// Confidence ellipse, axes in meters, orientation in degrees clockwise from true north.
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct Ellipse {
+    #[prost(double, tag = "1")]
+    pub semi_major: f64,
+    #[prost(double, tag = "2")]
+    pub semi_minor: f64,
+    #[prost(double, tag = "3")]
+    pub orientation: f64,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct GroundTruth {
+    #[prost(double, tag = "1")]
+    pub latitude: f64,
+    #[prost(double, tag = "2")]
+    pub longitude: f64,
+}
+/// Advanced crash notification of a vehicle.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Adr {
+    #[prost(message, optional, tag = "1")]
+    pub carcrash_time: ::core::option::Option<::prost_types::Timestamp>,
+    #[prost(string, optional, tag = "2")]
+    pub severity: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(bool, optional, tag = "3")]
+    pub rollover: ::core::option::Option<bool>,
+    #[prost(double, optional, tag = "4")]
+    pub impact_direction: ::core::option::Option<f64>,
+    #[prost(uint32, optional, tag = "5")]
+    pub occupants: ::core::option::Option<u32>,
+}
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct ReceiveContext {
+    #[prost(message, optional, tag = "1")]
+    pub received_at: ::core::option::Option<::prost_types::Timestamp>,
+    #[prost(string, optional, tag = "2")]
+    pub source_msisdn: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "3")]
+    pub gateway_id: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "4")]
+    pub remote_ip: ::core::option::Option<::prost::alloc::string::String>,
+}
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct Extra {
+    #[prost(string, tag = "1")]
+    pub key: ::prost::alloc::string::String,
+    /// The value as JSON text.
+    #[prost(string, tag = "2")]
+    pub json: ::prost::alloc::string::String,
+}
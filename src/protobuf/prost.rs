@@ -0,0 +1,168 @@
+//! Conversions between [`AmlData`] and the prost types of the definition.
+
+use super::v1;
+use crate::geo::Ellipse;
+use crate::json::JsonValue;
+use crate::{timestamp, AdrData, AmlData, AmlError, ReceiveContext, Timestamp};
+use ::prost_types::Timestamp as ProtoTimestamp;
+use std::convert::TryFrom;
+
+fn to_timestamp(timestamp: Option<Timestamp>) -> Option<ProtoTimestamp> {
+    timestamp.map(|timestamp| ProtoTimestamp {
+        seconds: timestamp::unix(timestamp),
+        nanos: timestamp::subsec_nanos(timestamp) as i32,
+    })
+}
+
+fn from_timestamp(timestamp: Option<ProtoTimestamp>) -> Result<Option<Timestamp>, AmlError> {
+    match timestamp {
+        None => Ok(None),
+        Some(proto) => match u32::try_from(proto.nanos).ok().and_then(|nanos| timestamp::from_unix_nanos(proto.seconds, nanos)) {
+            Some(timestamp) => Ok(Some(timestamp)),
+            None => Err(AmlError::InvalidProtobuf),
+        },
+    }
+}
+
+impl From<&AmlData> for v1::AmlData {
+    /// The message written by [`AmlData::to_protobuf`].
+    fn from(aml: &AmlData) -> Self {
+        v1::AmlData {
+            version: aml.version.clone(),
+            emergency_number: aml.emergency_number.clone(),
+            source_of_activation: aml.source_of_activation.clone(),
+            beginning_of_call: to_timestamp(aml.beginning_of_call),
+            latitude: aml.latitude,
+            longitude: aml.longitude,
+            time_of_positioning: to_timestamp(aml.time_of_positioning),
+            altitude: aml.altitude,
+            floor: aml.floor.clone(),
+            positioning_method: aml.positioning_method.clone(),
+            accuracy: aml.accuracy,
+            vertical_accuracy: aml.vertical_accuracy,
+            ellipse: aml.ellipse.map(|ellipse| v1::Ellipse {
+                semi_major: ellipse.semi_major,
+                semi_minor: ellipse.semi_minor,
+                orientation: ellipse.orientation,
+            }),
+            confidence: aml.confidence,
+            bearing: aml.bearing,
+            speed: aml.speed,
+            device_number: aml.device_number.clone(),
+            model: aml.model.clone(),
+            imsi: aml.imsi.clone(),
+            imei: aml.imei.clone(),
+            iccid: aml.iccid.clone(),
+            home_mcc: aml.home_mcc,
+            home_mnc: aml.home_mnc,
+            network_mcc: aml.network_mcc,
+            network_mnc: aml.network_mnc,
+            languages: aml.languages.clone(),
+            transport: aml.transport.clone(),
+            via: aml.via.clone(),
+            ground_truth: aml.ground_truth.map(|(latitude, longitude)| v1::GroundTruth { latitude, longitude }),
+            test: aml.test,
+            adr: aml.adr.as_ref().map(|adr| v1::Adr {
+                carcrash_time: to_timestamp(adr.carcrash_time),
+                severity: adr.severity.clone(),
+                rollover: adr.rollover,
+                impact_direction: adr.impact_direction,
+                occupants: adr.occupants,
+            }),
+            context: aml.context.as_ref().map(|context| v1::ReceiveContext {
+                received_at: to_timestamp(context.received_at),
+                source_msisdn: context.source_msisdn.clone(),
+                gateway_id: context.gateway_id.clone(),
+                remote_ip: context.remote_ip.map(|ip| ip.to_string()),
+            }),
+            extras: aml
+                .extras
+                .iter()
+                .map(|(key, value)| v1::Extra {
+                    key: key.clone(),
+                    json: value.to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<AmlData> for v1::AmlData {
+    fn from(aml: AmlData) -> Self {
+        v1::AmlData::from(&aml)
+    }
+}
+
+impl TryFrom<v1::AmlData> for AmlData {
+    type Error = AmlError;
+
+    /// The data of a message, as [`AmlData::from_protobuf`]: a timestamp out of range,
+    /// a remote IP or the JSON of an extra which does not parse are invalid.
+    fn try_from(message: v1::AmlData) -> Result<Self, AmlError> {
+        let adr = match message.adr {
+            Some(adr) => Some(AdrData {
+                carcrash_time: from_timestamp(adr.carcrash_time)?,
+                severity: adr.severity,
+                rollover: adr.rollover,
+                impact_direction: adr.impact_direction,
+                occupants: adr.occupants,
+            }),
+            None => None,
+        };
+        let context = match message.context {
+            Some(context) => Some(ReceiveContext {
+                received_at: from_timestamp(context.received_at)?,
+                source_msisdn: context.source_msisdn,
+                gateway_id: context.gateway_id,
+                remote_ip: context.remote_ip.map(|ip| ip.parse().map_err(|_| AmlError::InvalidProtobuf)).transpose()?,
+            }),
+            None => None,
+        };
+        let extras = message
+            .extras
+            .into_iter()
+            .map(|extra| JsonValue::parse(&extra.json).map(|value| (extra.key, value)).ok_or(AmlError::InvalidProtobuf))
+            .collect::<Result<_, _>>()?;
+
+        Ok(AmlData {
+            version: message.version,
+            emergency_number: message.emergency_number,
+            source_of_activation: message.source_of_activation,
+            beginning_of_call: from_timestamp(message.beginning_of_call)?,
+            latitude: message.latitude,
+            longitude: message.longitude,
+            time_of_positioning: from_timestamp(message.time_of_positioning)?,
+            altitude: message.altitude,
+            floor: message.floor,
+            positioning_method: message.positioning_method,
+            accuracy: message.accuracy,
+            vertical_accuracy: message.vertical_accuracy,
+            ellipse: message.ellipse.map(|ellipse| Ellipse {
+                semi_major: ellipse.semi_major,
+                semi_minor: ellipse.semi_minor,
+                orientation: ellipse.orientation,
+            }),
+            confidence: message.confidence,
+            bearing: message.bearing,
+            speed: message.speed,
+            device_number: message.device_number,
+            model: message.model,
+            imsi: message.imsi,
+            imei: message.imei,
+            iccid: message.iccid,
+            home_mcc: message.home_mcc,
+            home_mnc: message.home_mnc,
+            network_mcc: message.network_mcc,
+            network_mnc: message.network_mnc,
+            languages: message.languages,
+            transport: message.transport,
+            via: message.via,
+            ground_truth: message.ground_truth.map(|truth| (truth.latitude, truth.longitude)),
+            test: message.test,
+            adr,
+            context,
+            extras,
+            ..Default::default()
+        })
+    }
+}
@@ -0,0 +1,75 @@
+use crate::geo::scale_radius;
+use crate::{AmlData, PositioningMethod};
+
+/// Confidence the accuracy is brought to before being classified.
+const REFERENCE_CONFIDENCE: f64 = 0.68;
+
+/// Quality class of a location as used in EENA AML reporting. See [`AmlData::accuracy_class`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AccuracyClass {
+    /// Handset-derived (GNSS, Wi-Fi or fused) location within 50 m.
+    HandsetUnder50m,
+
+    /// Handset-derived location within 100 m.
+    HandsetUnder100m,
+
+    /// Handset-derived location with an accuracy above 100 m.
+    HandsetOver100m,
+
+    /// Location of the serving cell, or of an unknown method.
+    Network,
+
+    /// No location or no accuracy.
+    #[default]
+    Unknown,
+}
+
+impl AccuracyClass {
+    /// Label of the class in reports.
+    pub fn label(self) -> &'static str {
+        match self {
+            AccuracyClass::HandsetUnder50m => "<50 m handset",
+            AccuracyClass::HandsetUnder100m => "50-100 m handset",
+            AccuracyClass::HandsetOver100m => ">100 m handset",
+            AccuracyClass::Network => "network",
+            AccuracyClass::Unknown => "unknown",
+        }
+    }
+}
+
+impl AmlData {
+    /// Quality class of the location. The accuracy is first brought to a 68 % confidence
+    /// when the confidence is known, so that a 95 % radius is not penalized; a cell or
+    /// unknown positioning method is a network location whatever its accuracy.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{AccuracyClass, AmlData};
+    ///
+    /// let gnss = AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;lc=68;ls=G"#).unwrap();
+    /// assert_eq!(gnss.accuracy_class(), AccuracyClass::HandsetUnder50m);
+    ///
+    /// let cell = AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;lc=68;ls=C"#).unwrap();
+    /// assert_eq!(cell.accuracy_class(), AccuracyClass::Network);
+    /// ```
+    pub fn accuracy_class(&self) -> AccuracyClass {
+        let accuracy = match (self.position(), self.accuracy.filter(|accuracy| *accuracy > 0.0)) {
+            (Some(_), Some(accuracy)) => accuracy,
+            _ => return AccuracyClass::Unknown,
+        };
+        if !matches!(self.method(), Some(PositioningMethod::Gnss) | Some(PositioningMethod::Wifi) | Some(PositioningMethod::Fused)) {
+            return AccuracyClass::Network;
+        }
+
+        let accuracy = self
+            .confidence_ratio()
+            .and_then(|confidence| scale_radius(accuracy, confidence, REFERENCE_CONFIDENCE))
+            .unwrap_or(accuracy);
+        match accuracy {
+            accuracy if accuracy < 50.0 => AccuracyClass::HandsetUnder50m,
+            accuracy if accuracy <= 100.0 => AccuracyClass::HandsetUnder100m,
+            _ => AccuracyClass::HandsetOver100m,
+        }
+    }
+}
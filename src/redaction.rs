@@ -0,0 +1,96 @@
+use crate::hmac::hmac_sha1;
+use crate::{AmlData, ReceiveContext};
+
+/// What to do with a subscriber identifier.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Redaction {
+    /// Leave the identifier as is.
+    Keep,
+
+    /// Remove the identifier.
+    #[default]
+    Remove,
+
+    /// Keep only the last digits of the identifier (e.g. 4).
+    LastDigits(usize),
+
+    /// Replace the identifier with the hexadecimal HMAC-SHA1 of it, keyed with the salt.
+    /// Equal identifiers keep equal hashes, so records can still be correlated.
+    Hash(Vec<u8>),
+}
+
+impl Redaction {
+    /// Apply to an identifier.
+    pub fn apply(&self, value: &str) -> Option<String> {
+        match self {
+            Redaction::Keep => Some(value.to_string()),
+            Redaction::Remove => None,
+            Redaction::LastDigits(n) => {
+                let skip = value.chars().count().saturating_sub(*n);
+                Some(value.chars().skip(skip).collect())
+            }
+            Redaction::Hash(salt) => Some(hex::encode(hmac_sha1(salt, value.as_bytes()))),
+        }
+    }
+}
+
+/// How each subscriber identifier is redacted. By default, all of them are removed.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RedactionPolicy {
+    pub imsi: Redaction,
+    pub imei: Redaction,
+    pub iccid: Redaction,
+
+    /// The phone number of the device.
+    pub device_number: Redaction,
+}
+
+impl RedactionPolicy {
+    /// The same redaction for all the identifiers.
+    pub fn all(redaction: Redaction) -> Self {
+        RedactionPolicy {
+            imsi: redaction.clone(),
+            imei: redaction.clone(),
+            iccid: redaction.clone(),
+            device_number: redaction,
+        }
+    }
+}
+
+impl AmlData {
+    /// A copy of the data with the subscriber identifiers (IMSI, IMEI, ICCID and phone
+    /// number) redacted according to `policy`, e.g. for analytics systems. The originating
+    /// number of the [`ReceiveContext`] is redacted as the phone number.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{AmlData, Redaction, RedactionPolicy};
+    ///
+    /// let aml = AmlData::from_https("v=1&device_number=%2B33611223344&device_imei=353472104343540&location_latitude=0.85732").unwrap();
+    /// let policy = RedactionPolicy {
+    ///     device_number: Redaction::LastDigits(4),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let redacted = aml.redacted(&policy);
+    /// assert_eq!(redacted.device_number.as_deref(), Some("3344"));
+    /// assert_eq!(redacted.imei, None);
+    /// assert_eq!(redacted.latitude, Some(0.85732));
+    /// ```
+    pub fn redacted(&self, policy: &RedactionPolicy) -> AmlData {
+        let redact = |value: &Option<String>, redaction: &Redaction| value.as_deref().and_then(|v| redaction.apply(v));
+
+        AmlData {
+            imsi: redact(&self.imsi, &policy.imsi),
+            imei: redact(&self.imei, &policy.imei),
+            iccid: redact(&self.iccid, &policy.iccid),
+            device_number: redact(&self.device_number, &policy.device_number),
+            context: self.context.clone().map(|context| ReceiveContext {
+                source_msisdn: redact(&context.source_msisdn, &policy.device_number),
+                ..context
+            }),
+            ..self.clone()
+        }
+    }
+}
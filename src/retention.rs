@@ -0,0 +1,110 @@
+use crate::{AmlData, Duration, RedactionPolicy, Timestamp};
+
+/// Meters per degree of latitude, used to widen the accuracy of coarsened locations.
+const METERS_PER_DEGREE: f64 = 111_320.0;
+
+/// How stored AML data is reduced as it ages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetentionPolicy {
+    /// Age after which the subscriber identifiers are redacted with `identifiers`.
+    pub strip_identifiers_after: Duration,
+
+    /// Redaction applied to the identifiers.
+    pub identifiers: RedactionPolicy,
+
+    /// Age after which the location is coarsened to `location_decimals`.
+    pub coarsen_location_after: Duration,
+
+    /// Decimals of degree kept for coarsened locations (2 decimals is about 1 km).
+    pub location_decimals: i32,
+}
+
+impl Default for RetentionPolicy {
+    /// Identifiers removed after 24 hours, location kept to 2 decimals after 30 days.
+    fn default() -> Self {
+        RetentionPolicy {
+            strip_identifiers_after: Duration::hours(24),
+            identifiers: RedactionPolicy::default(),
+            coarsen_location_after: Duration::days(30),
+            location_decimals: 2,
+        }
+    }
+}
+
+/// Reductions applied by a [`RetentionPolicy`], for audit purposes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RetentionStage {
+    /// The subscriber identifiers have been redacted.
+    pub identifiers_stripped: bool,
+
+    /// The location has been coarsened.
+    pub location_coarsened: bool,
+}
+
+impl RetentionPolicy {
+    /// Reductions due for `aml` at `now`. The age of the record is counted from the
+    /// beginning of call or, if unknown, from the time of positioning. A record of unknown
+    /// age gets all the reductions.
+    pub fn stage(&self, aml: &AmlData, now: Timestamp) -> RetentionStage {
+        match aml.beginning_of_call.or(aml.time_of_positioning).map(|ts| now - ts) {
+            Some(age) => RetentionStage {
+                identifiers_stripped: age >= self.strip_identifiers_after,
+                location_coarsened: age >= self.coarsen_location_after,
+            },
+            None => RetentionStage {
+                identifiers_stripped: true,
+                location_coarsened: true,
+            },
+        }
+    }
+}
+
+impl AmlData {
+    /// The record as it may be kept at `now` under `policy`, with the reductions applied.
+    ///
+    /// A coarsened location is rounded to `location_decimals`, its accuracy is widened to
+    /// the rounding step, and the floor, street and house number are removed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{timestamp, AmlData, Duration, RetentionPolicy};
+    ///
+    /// let aml = AmlData::from_text_sms(r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;si=208201771948415;ml=80"#).unwrap();
+    /// let now = timestamp::from_utc((2019, 11, 12), (11, 29, 28)).unwrap() + Duration::days(60);
+    ///
+    /// let (kept, stage) = aml.retained(&RetentionPolicy::default(), now);
+    /// assert!(stage.identifiers_stripped && stage.location_coarsened);
+    /// assert_eq!(kept.imsi, None);
+    /// assert_eq!(kept.latitude, Some(48.83));
+    /// assert_eq!(kept.accuracy, Some(1113.2));
+    /// ```
+    pub fn retained(&self, policy: &RetentionPolicy, now: Timestamp) -> (AmlData, RetentionStage) {
+        let stage = policy.stage(self, now);
+        let mut aml = if stage.identifiers_stripped {
+            self.redacted(&policy.identifiers)
+        } else {
+            self.clone()
+        };
+
+        if stage.location_coarsened {
+            let factor = 10_f64.powi(policy.location_decimals);
+            let round = |v: Option<f64>| v.map(|v| (v * factor).round() / factor);
+            let step = METERS_PER_DEGREE / factor;
+
+            aml.latitude = round(aml.latitude);
+            aml.longitude = round(aml.longitude);
+            if aml.latitude.is_some() {
+                aml.accuracy = Some(aml.accuracy.map_or(step, |accuracy| accuracy.max(step)));
+            }
+            aml.floor = None;
+            if let Some(address) = aml.address.as_mut() {
+                address.street = None;
+                address.house_number = None;
+                address.formatted = None;
+            }
+        }
+
+        (aml, stage)
+    }
+}
@@ -0,0 +1,58 @@
+use crate::emergency::country_of_mcc;
+use crate::AmlData;
+
+/// Where the handset is relative to its home network. See [`AmlData::roaming`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Roaming {
+    /// On its home network.
+    Home,
+
+    /// On another network of its home country: national roaming, or a MVNO whose home
+    /// MNC differs from the host network.
+    National,
+
+    /// In another country.
+    International,
+}
+
+/// True if both Mobile Country Codes are the same country.
+fn same_country(home: i32, network: i32) -> bool {
+    home == network || country_of_mcc(home).is_some_and(|country| country_of_mcc(network) == Some(country))
+}
+
+impl AmlData {
+    /// Where the handset is relative to its home network, from the home and network
+    /// MCC/MNC. `None` if either MCC is unknown; with an unknown MNC, a handset in its
+    /// home country is assumed at home. Countries with several MCCs (e.g. 310 to 316
+    /// for the United States) are handled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{AmlData, Roaming};
+    ///
+    /// let visitor = AmlData::from_text_sms(r#"A"ML=2;en=112;lo=48.8,2.3,20;nc=20801;hc=23415"#).unwrap();
+    /// assert_eq!(visitor.roaming(), Some(Roaming::International));
+    /// assert_eq!(visitor.is_roaming(), Some(true));
+    ///
+    /// let mvno = AmlData::from_text_sms(r#"A"ML=2;en=112;lo=48.8,2.3,20;nc=20801;hc=20826"#).unwrap();
+    /// assert_eq!(mvno.roaming(), Some(Roaming::National));
+    /// assert_eq!(mvno.is_roaming(), Some(false));
+    /// ```
+    pub fn roaming(&self) -> Option<Roaming> {
+        let (home, network) = (self.home_mcc?, self.network_mcc?);
+        if !same_country(home, network) {
+            return Some(Roaming::International);
+        }
+        match (self.home_mnc, self.network_mnc) {
+            (Some(home_mnc), Some(network_mnc)) if home != network || home_mnc != network_mnc => Some(Roaming::National),
+            _ => Some(Roaming::Home),
+        }
+    }
+
+    /// True if the caller is abroad, and so may need another language and an
+    /// international callback number. National roaming and MVNOs are not roaming.
+    pub fn is_roaming(&self) -> Option<bool> {
+        self.roaming().map(|roaming| roaming == Roaming::International)
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{AmlData, AmlError};
+
+/// A magic value sent by a handset to mean "unknown".
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sentinel {
+    /// One of the [`SentinelPolicy::magic_values`], e.g. `-9999`.
+    Magic(f64),
+
+    /// Latitude and longitude both 0.0, the "null island" of unset GNSS fixes.
+    NullIsland,
+
+    /// An accuracy of 0, which ETSI TS 103 625 defines as unknown.
+    ZeroAccuracy,
+}
+
+/// What a [`SentinelPolicy`] does with the sentinels found.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SentinelAction {
+    /// Set the fields to `None`.
+    #[default]
+    Remove,
+
+    /// Leave the fields as they are, only report them.
+    Flag,
+}
+
+/// A sentinel found in a field of an [`AmlData`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SentinelValue {
+    /// Name of the field, as in [`AmlData::to_json`], `position` for both coordinates.
+    pub field: &'static str,
+
+    pub sentinel: Sentinel,
+}
+
+/// The sentinels recognized in the data, whatever the transport. By default, `-9999`,
+/// null island and zero accuracies are removed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SentinelPolicy {
+    pub action: SentinelAction,
+
+    /// Values meaning unknown in any numeric field of the location.
+    pub magic_values: Vec<f64>,
+
+    /// Latitude and longitude both 0.0 mean no location.
+    pub null_island: bool,
+
+    /// An accuracy or vertical accuracy of 0 means unknown.
+    pub zero_accuracy: bool,
+}
+
+impl Default for SentinelPolicy {
+    fn default() -> Self {
+        SentinelPolicy {
+            action: SentinelAction::Remove,
+            magic_values: vec![-9999.0],
+            null_island: true,
+            zero_accuracy: true,
+        }
+    }
+}
+
+impl AmlData {
+    /// Look for the sentinels of `policy` in the location fields, removed unless the
+    /// action is [`SentinelAction::Flag`], and return them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{AmlData, Sentinel, SentinelPolicy};
+    ///
+    /// let mut aml = AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,0;lz=-9999,0;ls=G"#).unwrap();
+    /// let found = aml.apply_sentinels(&SentinelPolicy::default());
+    ///
+    /// assert_eq!(found.iter().map(|s| s.field).collect::<Vec<_>>(), ["altitude", "accuracy", "vertical_accuracy"]);
+    /// assert_eq!(found[0].sentinel, Sentinel::Magic(-9999.0));
+    /// assert_eq!((aml.altitude, aml.accuracy, aml.latitude), (None, None, Some(48.82639)));
+    /// ```
+    pub fn apply_sentinels(&mut self, policy: &SentinelPolicy) -> Vec<SentinelValue> {
+        let remove = policy.action == SentinelAction::Remove;
+        let mut found = Vec::new();
+
+        let mut fields: [(&'static str, &mut Option<f64>); 8] = [
+            ("latitude", &mut self.latitude),
+            ("longitude", &mut self.longitude),
+            ("altitude", &mut self.altitude),
+            ("accuracy", &mut self.accuracy),
+            ("vertical_accuracy", &mut self.vertical_accuracy),
+            ("confidence", &mut self.confidence),
+            ("bearing", &mut self.bearing),
+            ("speed", &mut self.speed),
+        ];
+        for (field, value) in fields.iter_mut() {
+            if let Some(magic) = value.filter(|value| policy.magic_values.contains(value)) {
+                found.push(SentinelValue { field, sentinel: Sentinel::Magic(magic) });
+                if remove {
+                    **value = None;
+                }
+            }
+        }
+
+        if policy.null_island && self.latitude == Some(0.0) && self.longitude == Some(0.0) {
+            found.push(SentinelValue { field: "position", sentinel: Sentinel::NullIsland });
+            if remove {
+                self.latitude = None;
+                self.longitude = None;
+            }
+        }
+
+        if policy.zero_accuracy {
+            for (field, value) in [("accuracy", &mut self.accuracy), ("vertical_accuracy", &mut self.vertical_accuracy)] {
+                if *value == Some(0.0) {
+                    found.push(SentinelValue { field, sentinel: Sentinel::ZeroAccuracy });
+                    if remove {
+                        *value = None;
+                    }
+                }
+            }
+        }
+
+        found
+    }
+
+    /// Parse a SMS text and apply `policy`. See [`AmlData::apply_sentinels`].
+    pub fn from_text_sms_with_sentinels<S: AsRef<str>>(text_sms: S, policy: &SentinelPolicy) -> Result<(Self, Vec<SentinelValue>), AmlError> {
+        let mut aml = AmlData::from_text_sms(text_sms)?;
+        let found = aml.apply_sentinels(policy);
+        Ok((aml, found))
+    }
+
+    /// Parse a HTTPS AML message and apply `policy`. See [`AmlData::apply_sentinels`].
+    pub fn from_https_with_sentinels(payload: &str, policy: &SentinelPolicy) -> Result<(Self, Vec<SentinelValue>), AmlError> {
+        let mut aml = AmlData::from_https(payload)?;
+        let found = aml.apply_sentinels(policy);
+        Ok((aml, found))
+    }
+}
@@ -0,0 +1,351 @@
+//! A small HTTP/1.1 receiver for HTTPS AML, built on hyper and tokio.
+//!
+//! Each connection is served by its own task, up to [`Server::max_connections`]: the
+//! `POST` body is authenticated and parsed (see [`AmlPayload::from_body_with`]) and the
+//! callback is invoked with the data. The request line, the headers and the body must
+//! arrive within [`Server::header_timeout`]. TLS is expected to be terminated in front
+//! of the server (e.g. by a reverse proxy).
+//!
+//! # Example
+//!
+//! ```no_run
+//! use aml_lib::server::Server;
+//! use aml_lib::web::KeyRing;
+//!
+//! let mut keys = KeyRing::new();
+//! keys.add("operator-a", "AML");
+//!
+//! let server = Server::bind("0.0.0.0:8080", keys).unwrap();
+//! server.serve(|aml| println!("{:?} {:?}", aml.latitude, aml.longitude)).unwrap();
+//! ```
+//!
+//! [`AmlReceiver`] adds the grouping of the messages by call (see [`SessionAggregator`])
+//! and the removal of duplicates, for a receiver which only has to store the results.
+
+use crate::web::{AmlPayload, KeyRing, Strictness};
+use crate::{timestamp, AmlData, Limits, Deduplicator, Session, SessionAggregator, Transition};
+use ::http_body_util::{BodyExt, Full};
+use ::hyper::body::{Bytes, Incoming};
+use ::hyper::header::CONTENT_LENGTH;
+use ::hyper::server::conn::http1;
+use ::hyper::service::service_fn;
+use ::hyper::{Method, Request, Response, StatusCode};
+use ::hyper_util::rt::TokioIo;
+use ::tokio::io::AsyncWriteExt;
+use ::tokio::net::TcpStream;
+use ::tokio::sync::Semaphore;
+use ::tokio::time::{timeout, timeout_at, Instant};
+use std::convert::Infallible;
+use std::io;
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+/// Largest accepted request body.
+pub const MAX_BODY: usize = 64 * 1024;
+
+/// Largest accepted request line and headers.
+const MAX_HEAD: usize = 64 * 1024;
+
+/// Most header lines of a request.
+const MAX_HEADERS: usize = 64;
+
+/// How long the answer to a refused connection may take.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A listening AML receiver.
+pub struct Server {
+    listener: TcpListener,
+
+    /// Keys authenticating the messages.
+    pub keys: KeyRing,
+
+    /// Whether unauthenticated messages are accepted. Strict by default.
+    pub strictness: Strictness,
+
+    /// Limits of the bodies, larger ones being refused (413) before authentication.
+    pub limits: Limits,
+
+    /// Most connections served at the same time, 64 by default. The connections over
+    /// the limit are answered 503 at once.
+    pub max_connections: usize,
+
+    /// Time given to a client to send the request line, all the headers and the body, 10
+    /// seconds by default. Slower requests are answered 408.
+    pub header_timeout: Duration,
+}
+
+impl Server {
+    /// Listen on `addr`.
+    pub fn bind<A: ToSocketAddrs>(addr: A, keys: KeyRing) -> io::Result<Self> {
+        Ok(Server {
+            listener: TcpListener::bind(addr)?,
+            keys,
+            strictness: Strictness::default(),
+            limits: Limits::default(),
+            max_connections: 64,
+            header_timeout: Duration::from_secs(10),
+        })
+    }
+
+    /// Address the server listens on, useful when bound to port 0.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accept connections forever, calling `callback` for each accepted message.
+    /// Returns only if the listener fails.
+    ///
+    /// The connections are served by a tokio runtime of its own, and `callback` is called
+    /// on its blocking threads: it may store the message before returning.
+    pub fn serve<F>(self, callback: F) -> io::Result<()>
+    where
+        F: Fn(AmlData) + Send + Sync + 'static,
+    {
+        let runtime = ::tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
+        let listener = self.listener;
+        listener.set_nonblocking(true)?;
+        let shared = Arc::new(Shared {
+            keys: self.keys,
+            strictness: self.strictness,
+            limits: self.limits,
+            callback,
+        });
+        let header_timeout = self.header_timeout;
+        let active = Arc::new(Semaphore::new(self.max_connections));
+
+        runtime.block_on(async move {
+            let listener = ::tokio::net::TcpListener::from_std(listener)?;
+            loop {
+                let (mut stream, _) = listener.accept().await?;
+                let slot = match Arc::clone(&active).try_acquire_owned() {
+                    Ok(slot) => slot,
+                    Err(_) => {
+                        // Answered without a task, the client may retry later
+                        let _ = timeout(WRITE_TIMEOUT, respond(&mut stream, StatusCode::SERVICE_UNAVAILABLE)).await;
+                        continue;
+                    }
+                };
+
+                let shared = Arc::clone(&shared);
+                ::tokio::spawn(async move {
+                    // Connection errors only concern the sender
+                    let _ = connection(stream, shared, header_timeout).await;
+                    // Released once the connection is closed
+                    drop(slot);
+                });
+            }
+        })
+    }
+}
+
+/// What the connections of [`Server::serve`] share.
+struct Shared<F> {
+    keys: KeyRing,
+    strictness: Strictness,
+    limits: Limits,
+    callback: F,
+}
+
+/// Configuration of an [`AmlReceiver`].
+#[derive(Debug, Clone)]
+pub struct ReceiverConfig {
+    /// Address to listen on, `0.0.0.0:8080` by default.
+    pub addr: String,
+
+    /// Keys authenticating the messages.
+    pub keys: KeyRing,
+
+    /// Whether unauthenticated messages are accepted. Strict by default.
+    pub strictness: Strictness,
+
+    /// Maximum gap between the beginnings of call of the messages of a session, 30
+    /// minutes by default. See [`SessionAggregator::window`].
+    pub session_window: crate::Duration,
+
+    /// Window in which a message received again is ignored, 5 minutes by default.
+    /// `None` keeps the duplicates. See [`Deduplicator`].
+    pub duplicate_window: Option<crate::Duration>,
+
+    /// Limits of the bodies. See [`Server::limits`].
+    pub limits: Limits,
+
+    /// Most connections served at the same time. See [`Server::max_connections`].
+    pub max_connections: usize,
+
+    /// Time given to send the request. See [`Server::header_timeout`].
+    pub header_timeout: Duration,
+}
+
+impl Default for ReceiverConfig {
+    fn default() -> Self {
+        ReceiverConfig {
+            addr: "0.0.0.0:8080".to_string(),
+            keys: KeyRing::new(),
+            strictness: Strictness::default(),
+            session_window: crate::Duration::minutes(30),
+            duplicate_window: Some(crate::Duration::minutes(5)),
+            limits: Limits::default(),
+            max_connections: 64,
+            header_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Number of messages remembered to detect the duplicates.
+const DUPLICATE_CAPACITY: usize = 10_000;
+
+/// A ready-to-run receiver: the messages accepted by a [`Server`] are grouped by call,
+/// and handed with their session to the storage callback of [`AmlReceiver::serve`].
+///
+/// # Example
+///
+/// ```no_run
+/// use aml_lib::server::{AmlReceiver, ReceiverConfig};
+/// use aml_lib::Transition;
+///
+/// let mut config = ReceiverConfig::default();
+/// config.keys.add("operator-a", "AML");
+///
+/// let receiver = AmlReceiver::bind(config).unwrap();
+/// receiver
+///     .serve(|aml, session, transitions| {
+///         if transitions.iter().any(|t| matches!(t, Transition::LocationImproved { .. })) {
+///             println!("{:?}: {:?}", session.device, session.best_location().map(|best| best.position()));
+///         }
+///         println!("{}", aml.to_json());
+///     })
+///     .unwrap();
+/// ```
+pub struct AmlReceiver {
+    server: Server,
+    sessions: Arc<Mutex<SessionAggregator>>,
+}
+
+impl AmlReceiver {
+    /// Listen on the address of `config`.
+    pub fn bind(config: ReceiverConfig) -> io::Result<Self> {
+        let mut server = Server::bind(config.addr.as_str(), config.keys)?;
+        server.strictness = config.strictness;
+        server.limits = config.limits;
+        server.max_connections = config.max_connections;
+        server.header_timeout = config.header_timeout;
+
+        let mut sessions = SessionAggregator::new(config.session_window);
+        sessions.deduplicator = config.duplicate_window.map(|window| Deduplicator::new(window, DUPLICATE_CAPACITY));
+
+        Ok(AmlReceiver {
+            server,
+            sessions: Arc::new(Mutex::new(sessions)),
+        })
+    }
+
+    /// Address the receiver listens on, useful when bound to port 0.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.server.local_addr()
+    }
+
+    /// The sessions, shared with the receiver. The sessions idle for longer than
+    /// [`ReceiverConfig::session_window`] are closed by the receiver.
+    pub fn sessions(&self) -> Arc<Mutex<SessionAggregator>> {
+        Arc::clone(&self.sessions)
+    }
+
+    /// Accept connections forever, calling `store` for each accepted message with its
+    /// session and the [`Transition`]s it has caused. `store` is called with a copy of
+    /// the session, the sessions being released, so that slow storage does not hold
+    /// the other connections. Returns only if the listener fails.
+    pub fn serve<F>(self, store: F) -> io::Result<()>
+    where
+        F: Fn(&AmlData, &Session, &[Transition]) + Send + Sync + 'static,
+    {
+        let sessions = self.sessions;
+        self.server.serve(move |aml| {
+            let received_at = timestamp::now();
+            let (session, transitions) = {
+                let mut sessions = sessions.lock().unwrap_or_else(PoisonError::into_inner);
+                sessions.close_idle(received_at);
+                let (session, transitions) = sessions.ingest(aml.clone(), received_at);
+                (session.clone(), transitions)
+            };
+            store(&aml, &session, &transitions);
+        })
+    }
+}
+
+async fn connection<F>(stream: TcpStream, shared: Arc<Shared<F>>, header_timeout: Duration) -> io::Result<()>
+where
+    F: Fn(AmlData) + Send + Sync + 'static,
+{
+    // The request line, the headers and the body share one deadline, however they are split
+    let deadline = Instant::now() + header_timeout;
+    let started = Arc::new(AtomicBool::new(false));
+    let service = {
+        let started = Arc::clone(&started);
+        service_fn(move |request| {
+            started.store(true, Ordering::Release);
+            let shared = Arc::clone(&shared);
+            async move { Ok::<_, Infallible>(handle(request, shared, deadline).await) }
+        })
+    };
+    let mut connection = http1::Builder::new()
+        .keep_alive(false)
+        .max_headers(MAX_HEADERS)
+        .max_buf_size(MAX_HEAD)
+        .serve_connection(TokioIo::new(stream), service);
+
+    match timeout_at(deadline, &mut connection).await {
+        Ok(served) => return served.map_err(io::Error::other),
+        // The body is read under the same deadline by handle, which answers 408
+        Err(_) if started.load(Ordering::Acquire) => return connection.await.map_err(io::Error::other),
+        Err(_) => (),
+    }
+    let mut stream = connection.into_parts().io.into_inner();
+    respond(&mut stream, StatusCode::REQUEST_TIMEOUT).await
+}
+
+async fn handle<F>(request: Request<Incoming>, shared: Arc<Shared<F>>, deadline: Instant) -> Response<Full<Bytes>>
+where
+    F: Fn(AmlData) + Send + Sync + 'static,
+{
+    if request.method() != Method::POST {
+        return status(StatusCode::METHOD_NOT_ALLOWED);
+    }
+    let content_length = request.headers().get(CONTENT_LENGTH).and_then(|value| value.to_str().ok()?.trim().parse::<usize>().ok());
+    match content_length {
+        Some(length) if length > MAX_BODY.min(shared.limits.max_payload_length) => return status(StatusCode::PAYLOAD_TOO_LARGE),
+        Some(_) => (),
+        None => return status(StatusCode::LENGTH_REQUIRED),
+    }
+
+    let body = match timeout_at(deadline, request.into_body().collect()).await {
+        Ok(Ok(body)) => body.to_bytes(),
+        Ok(Err(_)) => return status(StatusCode::BAD_REQUEST),
+        Err(_) => return status(StatusCode::REQUEST_TIMEOUT),
+    };
+
+    let data = match AmlPayload::from_body_limited(&body, &shared.keys, shared.strictness, &shared.limits) {
+        Ok(payload) => AmlData::from(payload.data),
+        Err(rejection) => return status(StatusCode::from_u16(rejection.status_code()).unwrap_or(StatusCode::BAD_REQUEST)),
+    };
+    match ::tokio::task::spawn_blocking(move || (shared.callback)(data)).await {
+        Ok(()) => status(StatusCode::OK),
+        Err(_) => status(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// An empty response, the connection being closed after it.
+fn status(status: StatusCode) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::default());
+    *response.status_mut() = status;
+    response
+}
+
+/// Answer a connection not handed to hyper, and close it.
+async fn respond(stream: &mut TcpStream, status: StatusCode) -> io::Result<()> {
+    let reason = status.canonical_reason().unwrap_or_default();
+    let head = format!("HTTP/1.1 {} {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status.as_u16(), reason);
+    stream.write_all(head.as_bytes()).await?;
+    stream.shutdown().await
+}
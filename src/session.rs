@@ -0,0 +1,289 @@
+use crate::{timestamp, AmlData, Deduplicator, Duration, Timestamp};
+
+/// Change observed on a session when a message is added.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transition {
+    /// First message of a new session.
+    Started,
+
+    /// A message has been added without changing the current location.
+    Updated,
+
+    /// The message has already been received and has been ignored.
+    Duplicate,
+
+    /// The current location has been replaced by a more accurate one.
+    LocationImproved { previous_accuracy: Option<f64>, accuracy: Option<f64> },
+
+    /// The current location has moved by `distance` meters.
+    LocationMoved { distance: f64 },
+
+    /// The positioning method of the current location has changed.
+    MethodChanged { previous: Option<String>, method: Option<String> },
+}
+
+/// Bearing and speed computed from two fixes of a [`Session`]. These are estimates,
+/// not values reported by the handset (see [`AmlData::bearing`] and [`AmlData::speed`]).
+#[derive(Debug, Clone, PartialEq)]
+pub struct EstimatedMotion {
+    /// Estimated direction of travel in degrees clockwise from true north.
+    pub bearing: f64,
+
+    /// Estimated speed in meters per second.
+    pub speed: f64,
+
+    /// Distance between the two fixes in meters.
+    pub distance: f64,
+
+    /// Times of positioning of the two fixes.
+    pub from: Timestamp,
+    pub to: Timestamp,
+}
+
+/// All the AML messages received for one emergency call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    /// Device identifier grouping the messages: IMEI, else IMSI, else device number, else
+    /// the originating number of the [`ReceiveContext`](crate::ReceiveContext).
+    pub device: Option<String>,
+
+    /// The data of all the messages merged with [`AmlData::merge`].
+    pub current: AmlData,
+
+    /// The messages carrying a location, ordered by time of positioning.
+    pub history: Vec<AmlData>,
+
+    /// Whether the first message has been added.
+    started: bool,
+}
+
+impl Session {
+    /// Start a session with its first message.
+    pub fn new(first: AmlData) -> Self {
+        let mut session = Session {
+            device: device_of(&first),
+            current: AmlData::default(),
+            history: Vec::new(),
+            started: false,
+        };
+        session.push(first);
+        session
+    }
+
+    /// The current best location. See [`AmlData::merge`] for the rules.
+    pub fn best_location(&self) -> Option<&AmlData> {
+        self.current.position().map(|_| &self.current)
+    }
+
+    /// True if `aml` belongs to this call: same device and beginnings of call
+    /// at most `window` apart (an unknown beginning of call always matches).
+    pub fn accepts(&self, aml: &AmlData, window: Duration) -> bool {
+        if self.device.is_none() || self.device != device_of(aml) {
+            return false;
+        }
+
+        match (self.current.beginning_of_call, aml.beginning_of_call) {
+            (Some(current), Some(other)) => timestamp::whole_millis(current - other).abs() <= timestamp::whole_millis(window),
+            _ => true,
+        }
+    }
+
+    /// Add a message and return what has changed.
+    pub fn push(&mut self, aml: AmlData) -> Vec<Transition> {
+        let mut transitions = Vec::new();
+        let previous = self.best_location().cloned();
+
+        if aml.position().is_some() {
+            let at = self
+                .history
+                .partition_point(|h| h.time_of_positioning <= aml.time_of_positioning);
+            self.history.insert(at, aml.clone());
+        }
+        self.current.merge(aml);
+
+        match (previous, self.best_location()) {
+            _ if !self.started => {
+                self.started = true;
+                transitions.push(Transition::Started);
+            }
+            (Some(previous), Some(current)) => {
+                if previous.positioning_method != current.positioning_method {
+                    transitions.push(Transition::MethodChanged {
+                        previous: previous.positioning_method.clone(),
+                        method: current.positioning_method.clone(),
+                    });
+                }
+                if previous.position() != current.position() {
+                    if previous.time_of_positioning == current.time_of_positioning {
+                        transitions.push(Transition::LocationImproved {
+                            previous_accuracy: previous.accuracy,
+                            accuracy: current.accuracy,
+                        });
+                    } else if let Some(distance) = previous.distance_to(current) {
+                        transitions.push(Transition::LocationMoved { distance });
+                    }
+                }
+            }
+            _ => (),
+        }
+        if transitions.is_empty() {
+            transitions.push(Transition::Updated);
+        }
+
+        transitions
+    }
+
+    /// Bearing and speed from the latest fix and the latest earlier one it is
+    /// significantly away from: further than the combined accuracy radius of both. Fixes
+    /// without accuracy or time of positioning are not used, and `None` is returned if
+    /// no pair of fixes qualifies, e.g. for a handset standing still.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{AmlData, Session};
+    ///
+    /// let mut session = Session::new(AmlData::from_text_sms(r#"A"ML=2;et=1593187189;lo=48.80000,2.30000,10;lt=5;ei=358239059042542"#).unwrap());
+    /// session.push(AmlData::from_text_sms(r#"A"ML=2;et=1593187189;lo=48.80090,2.30000,10;lt=25;ei=358239059042542"#).unwrap());
+    ///
+    /// let motion = session.estimated_motion().unwrap();
+    /// assert!(motion.bearing < 1.0 || motion.bearing > 359.0);
+    /// assert!((motion.speed - 5.0).abs() < 0.1);
+    /// ```
+    pub fn estimated_motion(&self) -> Option<EstimatedMotion> {
+        let fixes: Vec<(&AmlData, Timestamp, f64)> = self
+            .history
+            .iter()
+            .filter_map(|aml| Some((aml, aml.time_of_positioning?, aml.accuracy.filter(|accuracy| *accuracy > 0.0)?)))
+            .collect();
+        let (last, to, last_accuracy) = *fixes.last()?;
+
+        fixes.iter().rev().filter(|(_, from, _)| *from < to).find_map(|(fix, from, accuracy)| {
+            let distance = fix.distance_to(last)?;
+            if distance <= accuracy.hypot(last_accuracy) {
+                return None;
+            }
+            let elapsed = timestamp::whole_millis(to - *from) as f64 / 1000.0;
+            Some(EstimatedMotion {
+                bearing: fix.bearing_to(last)?,
+                speed: distance / elapsed,
+                distance,
+                from: *from,
+                to,
+            })
+        })
+    }
+
+    /// Time of the last location received.
+    pub fn last_update(&self) -> Option<Timestamp> {
+        self.history.last().and_then(|aml| aml.time_of_positioning)
+    }
+}
+
+/// Groups incoming AML messages into [`Session`]s.
+///
+/// # Example
+///
+/// ```
+/// use aml_lib::{timestamp, AmlData, Duration, SessionAggregator, Transition};
+///
+/// let mut sessions = SessionAggregator::new(Duration::minutes(5));
+/// let received_at = timestamp::from_unix(1593187200).unwrap();
+///
+/// let first = AmlData::from_text_sms(r#"A"ML=2;et=1593187189;lo=48.80,2.30,1500;lt=5;ls=C;ei=358239059042542"#).unwrap();
+/// let (_, transitions) = sessions.ingest(first, received_at);
+/// assert_eq!(transitions, vec![Transition::Started]);
+///
+/// let second = AmlData::from_text_sms(r#"A"ML=2;et=1593187189;lo=48.80,2.30,12;lt=5;ls=G;ei=358239059042542"#).unwrap();
+/// let (session, _) = sessions.ingest(second, received_at + Duration::seconds(20));
+/// assert_eq!(session.best_location().unwrap().accuracy, Some(12.0));
+/// assert_eq!(sessions.sessions().len(), 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SessionAggregator {
+    /// Maximum gap between the beginnings of call of messages of the same session.
+    pub window: Duration,
+
+    /// When set, the messages already received are ignored.
+    pub deduplicator: Option<Deduplicator>,
+
+    sessions: Vec<Session>,
+
+    /// Receive time of the last message of each session.
+    received: Vec<Timestamp>,
+}
+
+impl SessionAggregator {
+    pub fn new(window: Duration) -> Self {
+        SessionAggregator {
+            window,
+            deduplicator: None,
+            sessions: Vec::new(),
+            received: Vec::new(),
+        }
+    }
+
+    /// Add a message received at `received_at` (e.g. [`AmlData::received_at`]) to its
+    /// session (created if needed) and return the session with its transitions.
+    pub fn ingest(&mut self, aml: AmlData, received_at: Timestamp) -> (&Session, Vec<Transition>) {
+        let window = self.window;
+        let index = self.sessions.iter().position(|s| s.accepts(&aml, window));
+        let duplicate = match self.deduplicator.as_mut() {
+            Some(dedup) => dedup.is_duplicate(&aml, received_at),
+            None => false,
+        };
+
+        match index {
+            Some(index) if duplicate => (&self.sessions[index], vec![Transition::Duplicate]),
+            Some(index) => {
+                let transitions = self.sessions[index].push(aml);
+                self.received[index] = self.received[index].max(received_at);
+                (&self.sessions[index], transitions)
+            }
+            None => {
+                self.sessions.push(Session::new(aml));
+                self.received.push(received_at);
+                (self.sessions.last().unwrap(), vec![Transition::Started])
+            }
+        }
+    }
+
+    /// All the open sessions.
+    pub fn sessions(&self) -> &[Session] {
+        &self.sessions
+    }
+
+    /// Close and return the sessions whose call started before `before`.
+    pub fn close_before(&mut self, before: Timestamp) -> Vec<Session> {
+        self.close_where(|session, _| session.current.beginning_of_call.is_some_and(|bc| bc < before))
+    }
+
+    /// Close and return the sessions which have received no message in the `window`
+    /// before `now`, whether their beginning of call is known or not.
+    pub fn close_idle(&mut self, now: Timestamp) -> Vec<Session> {
+        let window = self.window;
+        self.close_where(|_, received| now - received > window)
+    }
+
+    fn close_where<F: Fn(&Session, Timestamp) -> bool>(&mut self, closes: F) -> Vec<Session> {
+        let mut closed = Vec::new();
+        let mut index = 0;
+        while index < self.sessions.len() {
+            if closes(&self.sessions[index], self.received[index]) {
+                self.received.remove(index);
+                closed.push(self.sessions.remove(index));
+            } else {
+                index += 1;
+            }
+        }
+        closed
+    }
+}
+
+pub(crate) fn device_of(aml: &AmlData) -> Option<String> {
+    aml.imei
+        .clone()
+        .or_else(|| aml.imsi.clone())
+        .or_else(|| aml.device_number.clone())
+        .or_else(|| aml.context.as_ref().and_then(|context| context.source_msisdn.clone()))
+}
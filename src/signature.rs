@@ -0,0 +1,132 @@
+use crate::hmac::hmac_sha1;
+use crate::SmsData;
+use subtle::ConstantTimeEq;
+
+/// Which part of a SMS AML message is signed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Canonicalization {
+    /// The text before the `;` of the signature attribute, as received. The signature is
+    /// the last attribute, like the HTTPS `hmac` field.
+    #[default]
+    Preceding,
+
+    /// All the other `key=value` attributes (the `A"ML=` header included), trimmed, sorted
+    /// by key and joined with `;`. Insensitive to the order of the attributes.
+    SortedAttributes,
+}
+
+/// Check of the signature of a message.
+pub trait SignatureVerifier {
+    /// Whether `signature` (the value of the signature attribute) signs `message`
+    /// (the canonicalized message).
+    fn verify(&self, message: &[u8], signature: &str) -> bool;
+}
+
+impl<F: Fn(&[u8], &str) -> bool> SignatureVerifier for F {
+    fn verify(&self, message: &[u8], signature: &str) -> bool {
+        self(message, signature)
+    }
+}
+
+/// Hexadecimal HMAC-SHA1 keyed with the given key, as in HTTPS AML, compared in constant
+/// time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HmacSha1(pub Vec<u8>);
+
+impl SignatureVerifier for HmacSha1 {
+    fn verify(&self, message: &[u8], signature: &str) -> bool {
+        hex::decode(signature).is_ok_and(|signature| signature.as_slice().ct_eq(&hmac_sha1(&self.0, message)).into())
+    }
+}
+
+/// Signature scheme of an operator: where the signature is and how to check it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmsSignature<V> {
+    /// Name of the attribute holding the signature, e.g. `sg`.
+    pub attribute: String,
+
+    /// What is signed.
+    pub canonicalization: Canonicalization,
+
+    /// Check of the signature.
+    pub verifier: V,
+}
+
+impl<V: SignatureVerifier> SmsSignature<V> {
+    /// The signature and the canonicalized message, or `None` if the message has no
+    /// signature attribute or several ones, or (with [`Canonicalization::Preceding`])
+    /// attributes after the signature, which would be parsed without being signed.
+    fn split<'a>(&self, text: &'a str) -> Option<(String, &'a str)> {
+        let mut signatures = text
+            .split(';')
+            .enumerate()
+            .filter(|(_, element)| element.split_once('=').map(|(key, _)| key.trim()) == Some(self.attribute.as_str()));
+        let (index, element) = signatures.next()?;
+        if signatures.next().is_some() {
+            return None;
+        }
+        let signature = element.split_once('=')?.1.trim();
+
+        let message = match self.canonicalization {
+            Canonicalization::Preceding => {
+                if text.split(';').skip(index + 1).any(|element| !element.trim().is_empty()) {
+                    return None;
+                }
+                let end: usize = text.split(';').take(index).map(|element| element.len() + 1).sum();
+                text[..end.saturating_sub(1)].to_string()
+            }
+            Canonicalization::SortedAttributes => {
+                let mut attributes: Vec<(&str, &str)> = text
+                    .split(';')
+                    .enumerate()
+                    .filter(|(i, _)| *i != index)
+                    .filter_map(|(_, element)| element.split_once('='))
+                    .map(|(key, value)| (key.trim(), value.trim()))
+                    .collect();
+                attributes.sort();
+                attributes.iter().map(|(key, value)| format!("{}={}", key, value)).collect::<Vec<_>>().join(";")
+            }
+        };
+
+        Some((message, signature))
+    }
+
+    /// Whether the text SMS `text` carries exactly one valid signature, covering all
+    /// its attributes.
+    pub fn verify<S: AsRef<str>>(&self, text: S) -> bool {
+        match self.split(text.as_ref().trim_end()) {
+            Some((message, signature)) => self.verifier.verify(message.as_bytes(), signature),
+            None => false,
+        }
+    }
+}
+
+impl SmsData {
+    /// Verify the signature an operator appended to a text SMS, the counterpart of
+    /// [`HttpsData::is_authenticated`](crate::HttpsData::is_authenticated).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{Canonicalization, HmacSha1, SmsData, SmsSignature};
+    ///
+    /// let signature = SmsSignature {
+    ///     attribute: "sg".to_string(),
+    ///     canonicalization: Canonicalization::Preceding,
+    ///     verifier: HmacSha1(b"KEY".to_vec()),
+    /// };
+    /// let text = r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;ls=G;sg=0000"#;
+    /// assert!(!SmsData::is_signed(text, &signature));
+    ///
+    /// // Any check can be plugged in
+    /// let signature = SmsSignature {
+    ///     attribute: "sg".to_string(),
+    ///     canonicalization: Canonicalization::SortedAttributes,
+    ///     verifier: |message: &[u8], sg: &str| message.starts_with(br#"A"ML=2;en=112"#) && sg == "0000",
+    /// };
+    /// assert!(SmsData::is_signed(text, &signature));
+    /// ```
+    pub fn is_signed<S: AsRef<str>, V: SignatureVerifier>(text: S, signature: &SmsSignature<V>) -> bool {
+        signature.verify(text)
+    }
+}
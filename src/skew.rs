@@ -0,0 +1,118 @@
+use crate::{AmlData, Duration, Timestamp};
+
+/// Bounds of plausible handset timestamps. See [`AmlData::clock_skew`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkewPolicy {
+    /// A timestamp ahead of the receive time by more than this is in the future.
+    pub tolerance: Duration,
+
+    /// A call which began longer than this before the receive time is implausible.
+    pub max_call_age: Duration,
+}
+
+impl Default for SkewPolicy {
+    /// 30 seconds of tolerance, calls up to 2 hours old.
+    fn default() -> Self {
+        SkewPolicy {
+            tolerance: Duration::seconds(30),
+            max_call_age: Duration::hours(2),
+        }
+    }
+}
+
+/// An implausible handset timestamp found by [`AmlData::clock_skew`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkewIssue {
+    /// The time of positioning is ahead of the receive time.
+    PositionInFuture { ahead: Duration },
+
+    /// The beginning of call is ahead of the receive time.
+    CallInFuture { ahead: Duration },
+
+    /// The beginning of call is older than [`SkewPolicy::max_call_age`].
+    CallTooOld { age: Duration },
+}
+
+/// Skew between the handset clock and the receiver clock.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClockSkew {
+    /// Latest handset timestamp (time of positioning, else beginning of call) minus the
+    /// receive time. As the message is sent after both, a positive offset is the handset
+    /// clock running ahead; a negative one mixes a late clock with the transport delay.
+    /// `None` without any timestamp.
+    pub offset: Option<Duration>,
+
+    /// Implausible timestamps, empty if all are plausible.
+    pub issues: Vec<SkewIssue>,
+}
+
+impl ClockSkew {
+    /// True if no timestamp is implausible.
+    pub fn is_plausible(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl AmlData {
+    /// Skew between the handset timestamps (`et`/`time` and `top`/`location_time`) and
+    /// `received_at`, with the timestamps which are implausible according to `policy`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{timestamp, AmlData, Duration, SkewIssue, SkewPolicy};
+    ///
+    /// let aml = AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;ls=G"#).unwrap();
+    /// let skew = aml.clock_skew(timestamp::from_unix(1593187159).unwrap(), &SkewPolicy::default());
+    ///
+    /// assert_eq!(skew.offset, Some(Duration::minutes(1)));
+    /// assert_eq!(skew.issues, [SkewIssue::PositionInFuture { ahead: Duration::minutes(1) }]);
+    /// ```
+    pub fn clock_skew(&self, received_at: Timestamp, policy: &SkewPolicy) -> ClockSkew {
+        let mut issues = Vec::new();
+
+        if let Some(top) = self.time_of_positioning {
+            let ahead = top - received_at;
+            if ahead > policy.tolerance {
+                issues.push(SkewIssue::PositionInFuture { ahead });
+            }
+        }
+        if let Some(et) = self.beginning_of_call {
+            let age = received_at - et;
+            if -age > policy.tolerance {
+                issues.push(SkewIssue::CallInFuture { ahead: -age });
+            } else if age > policy.max_call_age {
+                issues.push(SkewIssue::CallTooOld { age });
+            }
+        }
+
+        ClockSkew {
+            offset: self.time_of_positioning.or(self.beginning_of_call).map(|latest| latest - received_at),
+            issues,
+        }
+    }
+
+    /// Bring the handset timestamps back to the receiver clock when the handset clock is
+    /// ahead of `received_at` by more than the tolerance of `policy`. Both the time of
+    /// positioning and the beginning of call are shifted, as they come from the same
+    /// clock and the delay between them is kept. Returns the correction applied.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{timestamp, AmlData, Duration, SkewPolicy};
+    ///
+    /// let mut aml = AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;ls=G"#).unwrap();
+    /// let received_at = timestamp::from_unix(1593187159).unwrap();
+    ///
+    /// assert_eq!(aml.correct_clock_skew(received_at, &SkewPolicy::default()), Some(Duration::minutes(1)));
+    /// assert_eq!(aml.time_of_positioning, Some(received_at));
+    /// ```
+    pub fn correct_clock_skew(&mut self, received_at: Timestamp, policy: &SkewPolicy) -> Option<Duration> {
+        let offset = self.clock_skew(received_at, policy).offset.filter(|offset| *offset > policy.tolerance)?;
+
+        self.time_of_positioning = self.time_of_positioning.map(|top| top - offset);
+        self.beginning_of_call = self.beginning_of_call.map(|et| et - offset);
+        Some(offset)
+    }
+}
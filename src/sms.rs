@@ -1,10 +1,10 @@
 use std::collections::HashMap;
-use chrono::{DateTime, LocalResult, NaiveDateTime, TimeZone, Utc};
-use crate::{seconds_to_utc, valid_list, AmlError};
+use crate::{AmlError, LengthMode, VersionPolicy, MessageLengths, Profile, Timestamp, ValidationFailure, ValidationReport};
+use crate::validation::{check_location, clean_text};
+use crate::model::{Properties, SmsDataV1, SmsDataV2};
 
-const DATETIME_FORMAT: &str = "%Y%m%d%H%M%S";
-
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct  SmsData {
     /// The header shall appear at the beginning of the SMS message.
     /// This is the version of AML.
@@ -27,7 +27,14 @@ pub struct  SmsData {
 
     /// The date and time that the handset determined the location area specified in UTC.
     /// This field may be ignored if location or beginning of call fields are valued to None.
-    pub time_of_positioning: Option<DateTime<Utc>>,
+    /// (v2) The beginning of call plus the positioning delay, `None` if the delay is negative.
+    #[cfg_attr(feature = "serde", serde(with = "crate::timestamp::rfc3339"))]
+    pub time_of_positioning: Option<Timestamp>,
+
+    /// (v2) The `lt` attribute as received: seconds from the beginning of the call to the
+    /// time of positioning. A negative value, such as the `-9999` of the specification
+    /// examples, is a sentinel for an unknown time of positioning.
+    pub positioning_delay: Option<i64>,
 
     /// The Level of Confidence is a percentage probability that the mobile handset is within the area being communicated.
     pub level_of_confidence: Option<f64>,
@@ -70,9 +77,17 @@ pub struct  SmsData {
     /// (v1) The length of the entire SMS message including the header and the length attribute.
     pub message_length: Option<i32>,    
 
-    /// SMS AML is validated for v1 if message length is equal to message_length.
-    /// For v2, SMS AML is always validated. 
-    pub is_validated: bool,
+    /// Phone number the SMS has been sent from, the number to call back. Not part of the
+    /// text: set from the envelope by [`SmsData::from_pdu_hex`].
+    pub originating_address: Option<String>,
+
+    /// Details of the checks made on the message (header position, message length
+    /// for v1 and consistency of the values). See [`SmsData::is_valid`]. Not serialized.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub validation: ValidationReport,
+
+    /// Conventions the message has been parsed with. See [`SmsData::from_text_with`].
+    pub profile: Profile,
 }
 
 impl SmsData {
@@ -92,11 +107,31 @@ impl SmsData {
     /// }
     /// ```
     pub fn from_data(bin_sms: &[u8]) -> Result<Self, AmlError> {
-        let raw_sms: Vec<u8>;
-        let text_sms: &str;
+        Self::from_data_with(bin_sms, None, 0)
+    }
 
-        raw_sms = Self::decode_7to8(bin_sms);
-        text_sms = std::str::from_utf8(&raw_sms).unwrap_or_default();
+    /// Parse a SMS data given as hexadecimal digits. Whitespace between the digits
+    /// (spaces, tabs, line breaks) is ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::SmsData;
+    ///
+    /// let sms = SmsData::from_hex("415193D98BED D8F4DEECE6A2C962B7DA8E7DEEB56232990B86A3D9623B39B92783EDE86F784F068BD560B6D80C1683E568B81D7BDCB3E176F076EFB89BA77B39DCCD56A3C966B15D39DD9BD570B2590E56CBC168B21A4DB66B8FC7BD590CB66BBBC73D990DB66BB37B31D90C").unwrap();
+    /// assert_eq!(sms.latitude, Some(37.42175));
+    /// ```
+    pub fn from_hex<S: AsRef<str>>(hex_sms: S) -> Result<Self, AmlError> {
+        let digits: String = hex_sms.as_ref().chars().filter(|c| !c.is_whitespace()).collect();
+        let bin_sms = hex::decode(digits)?;
+        Self::from_data(&bin_sms)
+    }
+
+    /// Parse a SMS data of `septets` septets which followed a user data header of
+    /// `udh_length` bytes. See [`SmsData::decode_7to8`].
+    pub fn from_data_with(bin_sms: &[u8], septets: Option<usize>, udh_length: usize) -> Result<Self, AmlError> {
+        let raw_sms = Self::decode_7to8(bin_sms, septets, udh_length);
+        let text_sms = std::str::from_utf8(&raw_sms).unwrap_or_default();
         Self::from_text(text_sms)
     }
 
@@ -115,138 +150,250 @@ impl SmsData {
     /// }
     /// ```
     pub fn from_text<S: AsRef<str>>(text_sms: S) -> Result<Self, AmlError> {
-        let properties = Self::get_properties(text_sms.as_ref());
-
-        match properties.get(r#"A"ML"#) {
-            Some(&"1") => {
-                let mut sms_data = Self::from_text_v1(properties);
-                if let Some(len) = sms_data.message_length {
-                    sms_data.is_validated = len == (text_sms.as_ref().len() as i32);
-                };
-                Ok(sms_data)
-            },
-            Some(&"2") => {
-                let mut sms_data = Self::from_text_v2(properties);
-                // By default AML SMS v2 is validate
-                sms_data.is_validated = true;
-                Ok(sms_data)
-            },
-            _ => Err(AmlError::UnimplementedVersion),
-        }
+        Self::parse_text(text_sms.as_ref())
     }
 
-    fn from_text_v1(properties: HashMap<&str, &str>) -> Self {
-        let mut sms: SmsData = Default::default();
-
-        for (key, value) in properties {
-            match (key, value) {
-                (r#"A"ML"#, _) => sms.header = Some(value.to_string()),
-                ("lg", _) => sms.longitude = value.parse::<f64>().ok(),
-                ("lt", _) => sms.latitude = value.parse::<f64>().ok(),
-                ("rd", _) => sms.accuracy = value.parse::<f64>().ok(),
-                ("top", _) => {
-                    if let Ok(ndt) = NaiveDateTime::parse_from_str(&value, DATETIME_FORMAT) {
-                        sms.time_of_positioning = Some(DateTime::<Utc>::from_utc(ndt, Utc));
-                    }
-                }
-                ("lc", _) => sms.level_of_confidence = value.parse::<f64>().ok(),
-                ("pm", _) => {
-                    sms.positioning_method =
-                        valid_list!(value.to_uppercase(), "G", "W", "C", "U")
-                }
-                ("si", _) => sms.imsi = Some(value.to_string()),
-                ("ei", _) => sms.imei = Some(value.to_string()),
-                ("mcc", _) => sms.network_mcc = value.parse::<i32>().ok(),
-                ("mnc", _) => sms.network_mnc = value.parse::<i32>().ok(), 
-                ("ml", _) => sms.message_length = value.parse::<i32>().ok(),
-                (_, _) => (),
-            }
+    fn parse_text(text_sms: &str) -> Result<Self, AmlError> {
+        Self::parse_text_with(text_sms, VersionPolicy::Reject, false)
+    }
+
+    pub(crate) fn parse_text_with(text_sms: &str, policy: VersionPolicy, best_effort: bool) -> Result<Self, AmlError> {
+        #[cfg(feature = "trace")]
+        let span = crate::trace::parse_span("sms").entered();
+        let result = Self::parse_properties(text_sms, policy, best_effort);
+        #[cfg(feature = "trace")]
+        crate::trace::sms_parsed(&span, &result);
+        result
+    }
+
+    fn parse_properties(text_sms: &str, policy: VersionPolicy, best_effort: bool) -> Result<Self, AmlError> {
+        let properties = Properties::of(clean_text(text_sms).0);
+        let header = properties.header.ok_or(AmlError::UnimplementedVersion)?;
+
+        let v1 = match policy.select(header) {
+            Some(major) => major == 1,
+            None if best_effort => false,
+            None => return Err(AmlError::UnimplementedVersion),
+        };
+        #[cfg(feature = "trace")]
+        properties.unread(v1).into_iter().for_each(crate::trace::dropped_key);
+        let sms_data = match v1 {
+            true => SmsData::from(SmsDataV1::from_properties(&properties)),
+            false => SmsData::from(SmsDataV2::from_properties(&properties)),
+        };
+        let mut sms_data = SmsData {
+            header: Some(header.to_string()),
+            ..sms_data
+        };
+        sms_data.validation = sms_data.validate(text_sms);
+
+        Ok(sms_data)
+    }
+
+    /// Parse a SMS text like [`SmsData::from_text`], a version of major 1 or 2 being
+    /// decoded as such (see [`VersionPolicy::MajorCompatible`]) and any other version
+    /// with the attributes of v2 instead of failing: a degraded location beats
+    /// none. The version is kept in `header` and the validation report has a
+    /// [`ValidationFailure::PartialVersion`]. Fails only without `A"ML` attribute.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::SmsData;
+    ///
+    /// let text = r#"A"ML=3;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;xx=new"#;
+    /// assert!(SmsData::from_text(text).is_err());
+    ///
+    /// let sms = SmsData::from_text_best_effort(text).unwrap();
+    /// assert_eq!((sms.header.as_deref(), sms.latitude), (Some("3"), Some(48.82639)));
+    /// assert!(sms.is_partial());
+    /// ```
+    pub fn from_text_best_effort<S: AsRef<str>>(text_sms: S) -> Result<Self, AmlError> {
+        Self::parse_text_with(text_sms.as_ref(), VersionPolicy::MajorCompatible, true)
+    }
+
+    /// True if the message has been decoded by [`SmsData::from_text_best_effort`] from
+    /// an unimplemented version.
+    pub fn is_partial(&self) -> bool {
+        self.validation.failures.iter().any(|failure| matches!(failure, ValidationFailure::PartialVersion(_)))
+    }
+
+    /// True if the message has passed all the checks of [`SmsData::validation`].
+    /// For v1, that implies the message length is equal to `message_length`.
+    pub fn is_valid(&self) -> bool {
+        self.validation.is_valid()
+    }
+
+    /// Parse a SMS text like [`SmsData::from_text`], counting the length of v1 messages
+    /// in `mode` as operators do not all count bytes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{LengthMode, SmsData};
+    ///
+    /// // 63 characters, but 64 bytes in UTF-8
+    /// let text = r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;ü;ml=63"#;
+    /// assert!(!SmsData::from_text(text).unwrap().is_valid());
+    ///
+    /// let sms = SmsData::from_text_with_length(text, LengthMode::Any).unwrap();
+    /// assert!(sms.is_valid());
+    /// assert_eq!(sms.validation.lengths.unwrap().bytes, 64);
+    /// ```
+    pub fn from_text_with_length<S: AsRef<str>>(text_sms: S, mode: LengthMode) -> Result<Self, AmlError> {
+        let mut sms_data = Self::from_text(text_sms.as_ref())?;
+        sms_data.validation = sms_data.validate_with(text_sms.as_ref(), mode);
+        Ok(sms_data)
+    }
+
+    pub(crate) fn validate(&self, text_sms: &str) -> ValidationReport {
+        self.validate_with(text_sms, LengthMode::Bytes)
+    }
+
+    fn validate_with(&self, text_sms: &str, mode: LengthMode) -> ValidationReport {
+        let (text_sms, cleanup) = clean_text(text_sms);
+        let mut report = ValidationReport {
+            header_first: text_sms.starts_with(r#"A"ML="#),
+            cleanup,
+            ..Default::default()
+        };
+
+        if !report.header_first {
+            report.failures.push(ValidationFailure::HeaderNotFirst);
         }
 
-        sms
-    }
-
-    fn from_text_v2(properties: HashMap<&str, &str>) -> Self {
-        let mut sms: SmsData = Default::default();
-        let (mut et_opt, mut lt_opt): (Option<i64>, Option<i64>) = Default::default();
-
-        for (key, value) in properties {
-            match (key, value) {
-                (r#"A"ML"#, _) => sms.header = Some(value.to_string()),
-                ("en", _) => sms.emergency_number = Some(value.to_string()),
-                ("et", _) => et_opt = value.parse::<i64>().ok(),
-                ("lo", _) => {
-                    let mut values: Vec<Option<f64>> =
-                        value.split(',').map(|i| i.parse::<f64>().ok()).collect();
-                    values.resize(3, None);
-                    sms.latitude = values[0];
-                    sms.longitude = values[1];
-                    sms.accuracy = values[2];
-                }
-                ("lt", _) => lt_opt = value.parse::<i64>().ok(),
-                ("lc", _) => sms.level_of_confidence = value.parse::<f64>().ok(),
-                ("lz", _) => {
-                    let mut values: Vec<Option<f64>> =
-                        value.split(',').map(|i| i.parse::<f64>().ok()).collect();
-                    values.resize(2, None);
-                    sms.altitude = values[0];
-                    sms.vertical_accuracy = values[1];
-                }
-                ("ls", _) => {
-                    sms.positioning_method =
-                        valid_list!(value.to_uppercase(), "G", "W", "C", "U", "F")
-                }
-                ("ei", _) => sms.imei = Some(value.to_string()),
-                ("nc", _) => {
-                    sms.network_mcc = value.get(..3).and_then(|s| s.parse::<i32>().ok());
-                    sms.network_mnc = value.get(3..).and_then(|s| s.parse::<i32>().ok()); 
-                }
-                ("hc", _) => {
-                    sms.home_mcc = value.get(..3).and_then(|s| s.parse::<i32>().ok());
-                    sms.home_mnc = value.get(3..).and_then(|s| s.parse::<i32>().ok());
-                }
-                ("lg", _) => sms.languages = Some(value.to_string()),
-                (_, _) => (),
+        match self.header.as_deref() {
+            Some(version) if VersionPolicy::MajorCompatible.select(version).is_none() => {
+                report.failures.push(ValidationFailure::PartialVersion(version.to_string()))
             }
+            _ => (),
         }
 
-        if let Some(et) = et_opt {
-            sms.beginning_of_call = Some(et); //seconds_to_utc!(et);
-            if let Some(lt) = lt_opt {
-                sms.time_of_positioning = seconds_to_utc!(et + lt);
+        if self.version().map(|version| version.major) == Some(1) {
+            let lengths = MessageLengths::of(text_sms);
+            let computed = lengths.get(mode, self.message_length);
+            report.length_mode = mode;
+            report.lengths = Some(lengths);
+            report.declared_length = self.message_length;
+            report.computed_length = Some(computed);
+
+            match self.message_length {
+                Some(declared) if declared != computed => report
+                    .failures
+                    .push(ValidationFailure::LengthMismatch { declared, computed }),
+                Some(_) => (),
+                None => report.failures.push(ValidationFailure::MissingLength),
             }
         }
 
-        sms
+        check_location(&mut report, self.latitude, self.longitude, self.level_of_confidence);
+
+        report
     }
 
-    fn get_properties(s: &str) -> HashMap<&str, &str> {
+    /// All the attributes of a SMS text, for the hooks which need the unknown ones too.
+    /// The parser itself goes through [`Properties`].
+    pub(crate) fn get_properties(s: &str) -> HashMap<&str, &str> {
         s.split(';')
-            .map(|property| {
-                let key_value: Vec<&str> = property.split('=').collect();
-                (key_value[0].trim(), key_value[1].trim())
-            })
+            .filter_map(|property| property.split_once('='))
+            .map(|(key, value)| (key.trim(), value.trim()))
             .filter(|key_val| !key_val.0.is_empty() && !key_val.1.is_empty())
             .collect()
     }
 
-    // The definition of the 7 bit encoding can be found in ETSI TS 123 038 (see clause 6.1.2.1.1 specifically)
-    fn decode_7to8(raw_bytes: &[u8]) -> Vec<u8> {
-        let (mut bits_len, mut bits) = (0_u8, 0_u8);
-        let mut out = Vec::<u8>::with_capacity(raw_bytes.len() << 1);
+    /// Pack `text` into GSM 7 bit septets, the inverse of the decoding of [`SmsData::from_data`].
+    /// Each byte is taken as a septet value (bit 8 is ignored).
+    ///
+    /// `udh_length` is the length in bytes of the user data header sent before the text
+    /// (0 without header): fill bits are inserted so that the first septet starts on a septet
+    /// boundary. The returned bytes do not include the header. When the last byte has 7 spare
+    /// bits, they are set to `<CR>` so that they cannot be read as `@` (TS 123 038 6.1.2.3.1).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::SmsData;
+    ///
+    /// assert_eq!(SmsData::encode_8to7(b"hellohello", 0), hex::decode("E8329BFD4697D9EC37").unwrap());
+    ///
+    /// let sms_text = r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;ml=62"#;
+    /// let sms = SmsData::from_data(&SmsData::encode_8to7(sms_text.as_bytes(), 0)).unwrap();
+    /// assert_eq!(sms.latitude, Some(48.82639));
+    /// ```
+    pub fn encode_8to7(text: &[u8], udh_length: usize) -> Vec<u8> {
+        let fill_bits = (7 - (udh_length * 8) % 7) % 7;
+        let (mut bits, mut bits_len) = (0_u32, fill_bits as u32);
+        let mut out = Vec::<u8>::with_capacity((fill_bits + text.len() * 7).div_ceil(8));
+
+        for septet in text {
+            bits |= u32::from(septet & 0x7F) << bits_len;
+            bits_len += 7;
+            while bits_len >= 8 {
+                out.push(bits as u8);
+                bits >>= 8;
+                bits_len -= 8;
+            }
+        }
+
+        match bits_len {
+            0 => (),
+            1 => out.push(bits as u8 | (0x0D << 1)),
+            _ => out.push(bits as u8),
+        }
 
-        for byte in raw_bytes {
-            out.push(((byte << bits_len) | bits) & 0x7F);
-            bits = byte >> (7 - bits_len);
-            bits_len += 1;
+        out
+    }
 
-            if bits_len == 7 {
-                out.push(bits);
-                bits = 0;
-                bits_len = 0;
+    /// Unpack GSM 7 bit septets (ETSI TS 123 038 6.1.2.1.1), one byte per septet.
+    ///
+    /// `udh_length` is the length in bytes of the user data header which preceded `packed`
+    /// (0 without header), so that its fill bits are skipped. `septets` is the number of
+    /// septets given by the TP-User-Data-Length; without it, a last septet made of the
+    /// 7 spare bits of the last byte (`@` or `<CR>`) is dropped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::SmsData;
+    ///
+    /// let packed = SmsData::encode_8to7(b"1234567", 6);
+    /// assert_eq!(SmsData::decode_7to8(&packed, None, 6), b"1234567");
+    /// assert_eq!(SmsData::decode_7to8(&packed, Some(3), 6), b"123");
+    /// ```
+    pub fn decode_7to8(packed: &[u8], septets: Option<usize>, udh_length: usize) -> Vec<u8> {
+        let fill_bits = (7 - (udh_length * 8) % 7) % 7;
+        let bits = (packed.len() * 8).saturating_sub(fill_bits);
+        let available = bits / 7;
+        let count = septets.map_or(available, |septets| septets.min(available));
+
+        // 8 septets at a time from 7 bytes (plus one for the fill bits), then one by one
+        let mut out = Vec::with_capacity(count);
+        let mut chunk = 0;
+        while (chunk + 1) * 8 <= count && chunk * 7 + 8 <= packed.len() {
+            let mut word = [0; 8];
+            word.copy_from_slice(&packed[chunk * 7..chunk * 7 + 8]);
+            out.extend_from_slice(&unpack_septets(u64::from_le_bytes(word) >> fill_bits).to_le_bytes());
+            chunk += 1;
+        }
+        out.extend((out.len()..count).map(|i| {
+            let bit = fill_bits + i * 7;
+            let (byte, shift) = (bit / 8, bit % 8);
+            let mut value = u16::from(packed[byte]) >> shift;
+            if shift > 1 {
+                value |= u16::from(packed[byte + 1]) << (8 - shift);
             }
+            (value & 0x7F) as u8
+        }));
+
+        if septets.is_none() && count > 0 && bits.is_multiple_of(7) && matches!(out.last(), Some(0x00) | Some(0x0D)) {
+            out.pop();
         }
 
         out
     }
 }
+
+/// Spread the 8 septets of the low 56 bits of `word` into the 8 bytes of the result.
+fn unpack_septets(word: u64) -> u64 {
+    (0..8).fold(0, |bytes, i| bytes | ((word >> (7 * i)) & 0x7F) << (8 * i))
+}
@@ -0,0 +1,252 @@
+//! Storage of the parsed messages in a SQL database, independent of the driver.
+//!
+//! [`create_table`] gives the PostgreSQL table, [`insert_statement`] the statement
+//! whose parameters are [`AmlData::to_sql_values`], in the order of [`COLUMNS`]. Each
+//! value is bound with the driver in use. With the `sqlx` feature, [`sqlx`](self::sqlx)
+//! creates the table, binds the values and reads the rows back.
+//!
+//! For Diesel, [`diesel_schema`] gives the matching `table!` declaration. With the
+//! `diesel` feature, [`aml_table!`](crate::aml_table) declares it and [`AmlData`] is
+//! `Insertable` and `Queryable`, see [`diesel`](self::diesel).
+//!
+//! The `document` column holds the whole data as JSON: rows are read back from it
+//! with [`AmlData::from_sql_document`], without loss.
+
+use crate::json::JsonValue;
+use crate::{AmlData, Timestamp};
+
+#[cfg(feature = "diesel")]
+pub mod diesel;
+#[cfg(feature = "sqlx")]
+pub mod sqlx;
+
+/// Type of a column, mapped to the PostgreSQL types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SqlType {
+    Text,
+    Float,
+    Integer,
+    Bool,
+    Timestamp,
+    Json,
+}
+
+impl SqlType {
+    /// The PostgreSQL type.
+    pub fn postgres(self) -> &'static str {
+        match self {
+            SqlType::Text => "TEXT",
+            SqlType::Float => "DOUBLE PRECISION",
+            SqlType::Integer => "INTEGER",
+            SqlType::Bool => "BOOLEAN",
+            SqlType::Timestamp => "TIMESTAMPTZ",
+            SqlType::Json => "JSONB",
+        }
+    }
+
+    /// The Diesel SQL type of the PostgreSQL backend.
+    pub fn diesel(self) -> &'static str {
+        match self {
+            SqlType::Text => "Text",
+            SqlType::Float => "Float8",
+            SqlType::Integer => "Int4",
+            SqlType::Bool => "Bool",
+            SqlType::Timestamp => "Timestamptz",
+            SqlType::Json => "Jsonb",
+        }
+    }
+}
+
+/// A value to bind. `Null` carries the type of its column, for the drivers which need it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlValue {
+    Null(SqlType),
+    Text(String),
+    Float(f64),
+    Integer(i32),
+    Bool(bool),
+    Timestamp(Timestamp),
+}
+
+/// The columns of the table, in the order of [`AmlData::to_sql_values`]. The JSON
+/// `document` is bound as text.
+pub const COLUMNS: &[(&str, SqlType)] = &[
+    ("version", SqlType::Text),
+    ("emergency_number", SqlType::Text),
+    ("source_of_activation", SqlType::Text),
+    ("beginning_of_call", SqlType::Timestamp),
+    ("latitude", SqlType::Float),
+    ("longitude", SqlType::Float),
+    ("time_of_positioning", SqlType::Timestamp),
+    ("altitude", SqlType::Float),
+    ("floor", SqlType::Text),
+    ("positioning_method", SqlType::Text),
+    ("accuracy", SqlType::Float),
+    ("vertical_accuracy", SqlType::Float),
+    ("confidence", SqlType::Float),
+    ("bearing", SqlType::Float),
+    ("speed", SqlType::Float),
+    ("device_number", SqlType::Text),
+    ("model", SqlType::Text),
+    ("imsi", SqlType::Text),
+    ("imei", SqlType::Text),
+    ("iccid", SqlType::Text),
+    ("home_mcc", SqlType::Integer),
+    ("home_mnc", SqlType::Integer),
+    ("network_mcc", SqlType::Integer),
+    ("network_mnc", SqlType::Integer),
+    ("languages", SqlType::Text),
+    ("transport", SqlType::Text),
+    ("via", SqlType::Text),
+    ("test", SqlType::Bool),
+    ("received_at", SqlType::Timestamp),
+    ("source_msisdn", SqlType::Text),
+    ("gateway_id", SqlType::Text),
+    ("document", SqlType::Json),
+];
+
+/// The columns which always have a value.
+pub(crate) const NOT_NULL: &[&str] = &["transport", "test", "document"];
+
+/// `CREATE TABLE` statement of a PostgreSQL table `table`, with an `id` primary key.
+/// With `postgis`, a `location` geography column is computed from the latitude and
+/// longitude, with a spatial index.
+///
+/// # Example
+///
+/// ```
+/// use aml_lib::sql;
+///
+/// let ddl = sql::create_table("aml_messages", true);
+/// assert!(ddl.starts_with("CREATE TABLE IF NOT EXISTS aml_messages (\n    id BIGSERIAL PRIMARY KEY,\n    version TEXT,"));
+/// assert!(ddl.contains("location GEOGRAPHY(POINT, 4326) GENERATED ALWAYS AS"));
+/// ```
+pub fn create_table(table: &str, postgis: bool) -> String {
+    let mut columns = vec!["    id BIGSERIAL PRIMARY KEY".to_string()];
+    for (name, kind) in COLUMNS {
+        let constraint = if NOT_NULL.contains(name) { " NOT NULL" } else { "" };
+        columns.push(format!("    {} {}{}", name, kind.postgres(), constraint));
+    }
+    if postgis {
+        columns.push(
+            "    location GEOGRAPHY(POINT, 4326) GENERATED ALWAYS AS \
+             (ST_SetSRID(ST_MakePoint(longitude, latitude), 4326)::GEOGRAPHY) STORED"
+                .to_string(),
+        );
+    }
+
+    let mut ddl = format!("CREATE TABLE IF NOT EXISTS {} (\n{}\n);\n", table, columns.join(",\n"));
+    ddl.push_str(&format!(
+        "CREATE INDEX IF NOT EXISTS {0}_call ON {0} (imei, beginning_of_call);\n",
+        table
+    ));
+    if postgis {
+        ddl.push_str(&format!("CREATE INDEX IF NOT EXISTS {0}_location ON {0} USING GIST (location);\n", table));
+    }
+    ddl
+}
+
+/// Source of the Diesel `table!` declaration of the table of [`create_table`], to be
+/// pasted in the `schema.rs` of the application (the `location` column of PostGIS is
+/// left out, Diesel having no type for it).
+///
+/// # Example
+///
+/// ```
+/// use aml_lib::sql;
+///
+/// let schema = sql::diesel_schema("aml_messages");
+/// assert!(schema.starts_with("diesel::table! {\n    aml_messages (id) {\n        id -> Int8,\n        version -> Nullable<Text>,"));
+/// assert!(schema.contains("        document -> Jsonb,\n"));
+/// ```
+pub fn diesel_schema(table: &str) -> String {
+    let mut schema = format!("diesel::table! {{\n    {} (id) {{\n        id -> Int8,\n", table);
+    for (name, kind) in COLUMNS {
+        let kind = if NOT_NULL.contains(name) {
+            kind.diesel().to_string()
+        } else {
+            format!("Nullable<{}>", kind.diesel())
+        };
+        schema.push_str(&format!("        {} -> {},\n", name, kind));
+    }
+    schema.push_str("    }\n}\n");
+    schema
+}
+
+/// `INSERT` statement of a row in `table`, with PostgreSQL placeholders (`$1`, ...) in
+/// the order of [`COLUMNS`]. The document is cast to `JSONB`.
+pub fn insert_statement(table: &str) -> String {
+    let names: Vec<&str> = COLUMNS.iter().map(|(name, _)| *name).collect();
+    let placeholders: Vec<String> = COLUMNS
+        .iter()
+        .enumerate()
+        .map(|(i, (_, kind))| match kind {
+            SqlType::Json => format!("${}::JSONB", i + 1),
+            _ => format!("${}", i + 1),
+        })
+        .collect();
+    format!("INSERT INTO {} ({}) VALUES ({})", table, names.join(", "), placeholders.join(", "))
+}
+
+impl AmlData {
+    /// The values of a row, in the order of [`COLUMNS`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::sql::{SqlValue, COLUMNS};
+    /// use aml_lib::AmlData;
+    ///
+    /// let aml = AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20"#).unwrap();
+    /// let values = aml.to_sql_values();
+    /// assert_eq!(values.len(), COLUMNS.len());
+    /// assert_eq!(values[4], SqlValue::Float(48.82639));
+    /// ```
+    pub fn to_sql_values(&self) -> Vec<SqlValue> {
+        let text = |value: &Option<String>| value.clone().map_or(SqlValue::Null(SqlType::Text), SqlValue::Text);
+        let float = |value: Option<f64>| value.map_or(SqlValue::Null(SqlType::Float), SqlValue::Float);
+        let integer = |value: Option<i32>| value.map_or(SqlValue::Null(SqlType::Integer), SqlValue::Integer);
+        let timestamp = |value: Option<Timestamp>| value.map_or(SqlValue::Null(SqlType::Timestamp), SqlValue::Timestamp);
+        let context = self.context.clone().unwrap_or_default();
+
+        vec![
+            text(&self.version),
+            text(&self.emergency_number),
+            text(&self.source_of_activation),
+            timestamp(self.beginning_of_call),
+            float(self.latitude),
+            float(self.longitude),
+            timestamp(self.time_of_positioning),
+            float(self.altitude),
+            text(&self.floor),
+            text(&self.positioning_method),
+            float(self.accuracy),
+            float(self.vertical_accuracy),
+            float(self.confidence),
+            float(self.bearing),
+            float(self.speed),
+            text(&self.device_number),
+            text(&self.model),
+            text(&self.imsi),
+            text(&self.imei),
+            text(&self.iccid),
+            integer(self.home_mcc),
+            integer(self.home_mnc),
+            integer(self.network_mcc),
+            integer(self.network_mnc),
+            text(&self.languages),
+            SqlValue::Text(self.transport.clone()),
+            text(&self.via),
+            SqlValue::Bool(self.test),
+            timestamp(context.received_at),
+            text(&context.source_msisdn),
+            text(&context.gateway_id),
+            SqlValue::Text(self.to_json()),
+        ]
+    }
+
+    /// Read back the `document` column of a row. `None` if it is not a JSON object.
+    pub fn from_sql_document(document: &str) -> Option<AmlData> {
+        AmlData::from_json_value(&JsonValue::parse(document)?)
+    }
+}
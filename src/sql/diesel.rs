@@ -0,0 +1,207 @@
+//! [`diesel`] support of the PostgreSQL table of [`create_table`](super::create_table).
+//!
+//! [`aml_table!`](crate::aml_table) declares the table, as [`diesel_schema`](super::diesel_schema)
+//! but without code generation, and makes `&AmlData` [`Insertable`](::diesel::Insertable)
+//! into it. [`AmlData`] is [`Queryable`] from the `document` column, without loss.
+//!
+//! ```
+//! use aml_lib::AmlData;
+//! use diesel::connection::LoadConnection;
+//! use diesel::pg::Pg;
+//! use diesel::prelude::*;
+//!
+//! aml_lib::aml_table!(aml_messages);
+//!
+//! // With the `postgres` feature of diesel, `C` is a `PgConnection`
+//! fn store<C: LoadConnection<Backend = Pg>>(connection: &mut C, aml: &AmlData) -> QueryResult<Vec<AmlData>> {
+//!     diesel::insert_into(aml_messages::table).values(aml).execute(connection)?;
+//!     aml_messages::table.select(aml_messages::document).order(aml_messages::id).load(connection)
+//! }
+//! # fn main() {}
+//! ```
+
+use crate::{AmlData, Timestamp};
+use ::diesel::deserialize::{self, FromSql, Queryable};
+use ::diesel::pg::{Pg, PgValue};
+use ::diesel::serialize::{self, IsNull, Output, ToSql};
+use ::diesel::sql_types::Jsonb;
+use ::diesel::expression::AsExpression;
+use std::io::Write;
+
+/// Declare the Diesel table `$table` of [`create_table`](crate::sql::create_table) (the
+/// `location` column of PostGIS is left out), and implement `Insertable` for `&AmlData`.
+#[macro_export]
+macro_rules! aml_table {
+    ($table:ident) => {
+        $crate::__aml_table! {
+            $table:
+            version: Nullable<Text> = Option<String>,
+            emergency_number: Nullable<Text> = Option<String>,
+            source_of_activation: Nullable<Text> = Option<String>,
+            beginning_of_call: Nullable<Timestamptz> = Option<$crate::Timestamp>,
+            latitude: Nullable<Float8> = Option<f64>,
+            longitude: Nullable<Float8> = Option<f64>,
+            time_of_positioning: Nullable<Timestamptz> = Option<$crate::Timestamp>,
+            altitude: Nullable<Float8> = Option<f64>,
+            floor: Nullable<Text> = Option<String>,
+            positioning_method: Nullable<Text> = Option<String>,
+            accuracy: Nullable<Float8> = Option<f64>,
+            vertical_accuracy: Nullable<Float8> = Option<f64>,
+            confidence: Nullable<Float8> = Option<f64>,
+            bearing: Nullable<Float8> = Option<f64>,
+            speed: Nullable<Float8> = Option<f64>,
+            device_number: Nullable<Text> = Option<String>,
+            model: Nullable<Text> = Option<String>,
+            imsi: Nullable<Text> = Option<String>,
+            imei: Nullable<Text> = Option<String>,
+            iccid: Nullable<Text> = Option<String>,
+            home_mcc: Nullable<Int4> = Option<i32>,
+            home_mnc: Nullable<Int4> = Option<i32>,
+            network_mcc: Nullable<Int4> = Option<i32>,
+            network_mnc: Nullable<Int4> = Option<i32>,
+            languages: Nullable<Text> = Option<String>,
+            transport: Text = String,
+            via: Nullable<Text> = Option<String>,
+            test: Bool = bool,
+            received_at: Nullable<Timestamptz> = Option<$crate::Timestamp>,
+            source_msisdn: Nullable<Text> = Option<String>,
+            gateway_id: Nullable<Text> = Option<String>,
+            document: Jsonb = $crate::sql::diesel::Document,
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __aml_table {
+    ($table:ident: $($column:ident: $sql:ty = $rust:ty,)*) => {
+        ::diesel::table! {
+            $table (id) {
+                id -> Int8,
+                $($column -> $sql,)*
+            }
+        }
+
+        impl<'a> ::diesel::Insertable<$table::table> for &'a $crate::AmlData {
+            type Values = <($(::diesel::dsl::Eq<$table::$column, $rust>,)*) as ::diesel::Insertable<$table::table>>::Values;
+
+            fn values(self) -> Self::Values {
+                use ::diesel::ExpressionMethods;
+
+                let ($($column,)*) = $crate::sql::diesel::values(self);
+                ($($table::$column.eq($column),)*).values()
+            }
+        }
+    };
+}
+
+/// The `document` column: the data as JSON, see [`AmlData::to_json`].
+#[derive(Debug, Clone, PartialEq, AsExpression)]
+#[diesel(sql_type = Jsonb)]
+pub struct Document(pub String);
+
+impl ToSql<Jsonb, Pg> for Document {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        // Version of the binary format of JSONB
+        out.write_all(&[1])?;
+        out.write_all(self.0.as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+/// The data of the `document` column.
+impl FromSql<Jsonb, Pg> for AmlData {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        match value.as_bytes().split_first() {
+            Some((1, document)) => std::str::from_utf8(document)
+                .ok()
+                .and_then(AmlData::from_sql_document)
+                .ok_or_else(|| "not a JSON object".into()),
+            _ => Err("unsupported JSONB format".into()),
+        }
+    }
+}
+
+impl Queryable<Jsonb, Pg> for AmlData {
+    type Row = AmlData;
+
+    fn build(row: AmlData) -> deserialize::Result<Self> {
+        Ok(row)
+    }
+}
+
+/// The values of the columns of [`aml_table!`](crate::aml_table) but `id`, in their order.
+#[doc(hidden)]
+#[allow(clippy::type_complexity)]
+pub fn values(
+    aml: &AmlData,
+) -> (
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<Timestamp>,
+    Option<f64>,
+    Option<f64>,
+    Option<Timestamp>,
+    Option<f64>,
+    Option<String>,
+    Option<String>,
+    Option<f64>,
+    Option<f64>,
+    Option<f64>,
+    Option<f64>,
+    Option<f64>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<i32>,
+    Option<i32>,
+    Option<i32>,
+    Option<i32>,
+    Option<String>,
+    String,
+    Option<String>,
+    bool,
+    Option<Timestamp>,
+    Option<String>,
+    Option<String>,
+    Document,
+) {
+    let context = aml.context.clone().unwrap_or_default();
+    (
+        aml.version.clone(),
+        aml.emergency_number.clone(),
+        aml.source_of_activation.clone(),
+        aml.beginning_of_call,
+        aml.latitude,
+        aml.longitude,
+        aml.time_of_positioning,
+        aml.altitude,
+        aml.floor.clone(),
+        aml.positioning_method.clone(),
+        aml.accuracy,
+        aml.vertical_accuracy,
+        aml.confidence,
+        aml.bearing,
+        aml.speed,
+        aml.device_number.clone(),
+        aml.model.clone(),
+        aml.imsi.clone(),
+        aml.imei.clone(),
+        aml.iccid.clone(),
+        aml.home_mcc,
+        aml.home_mnc,
+        aml.network_mcc,
+        aml.network_mnc,
+        aml.languages.clone(),
+        aml.transport.clone(),
+        aml.via.clone(),
+        aml.test,
+        context.received_at,
+        context.source_msisdn,
+        context.gateway_id,
+        Document(aml.to_json()),
+    )
+}
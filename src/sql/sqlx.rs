@@ -0,0 +1,70 @@
+//! [`sqlx`] support of the PostgreSQL table of [`create_table`](super::create_table):
+//! rows are read back as [`AmlData`] with [`FromRow`], from the `document` column, and
+//! written with [`insert`], or [`bind`] for the statements of the application.
+//!
+//! ```no_run
+//! use aml_lib::sql::sqlx::{create_table, insert};
+//! use aml_lib::AmlData;
+//! use sqlx::PgPool;
+//!
+//! async fn store(pool: &PgPool, aml: &AmlData) -> Result<Vec<AmlData>, sqlx::Error> {
+//!     create_table(pool, "aml_messages", true).await?;
+//!     insert(pool, "aml_messages", aml).await?;
+//!     sqlx::query_as("SELECT document FROM aml_messages ORDER BY id").fetch_all(pool).await
+//! }
+//! ```
+
+use super::{insert_statement, SqlType, SqlValue};
+use crate::AmlData;
+use ::sqlx::postgres::{PgArguments, PgQueryResult, PgRow};
+use ::sqlx::query::Query;
+use ::sqlx::types::JsonRawValue;
+use ::sqlx::{Acquire, Executor, FromRow, Postgres, Row};
+
+/// Bind the values of `aml` ([`AmlData::to_sql_values`]) to the parameters of `query`,
+/// in the order of [`COLUMNS`](super::COLUMNS).
+pub fn bind<'q>(query: Query<'q, Postgres, PgArguments>, aml: &AmlData) -> Query<'q, Postgres, PgArguments> {
+    aml.to_sql_values().into_iter().fold(query, |query, value| match value {
+        SqlValue::Null(SqlType::Float) => query.bind(None::<f64>),
+        SqlValue::Null(SqlType::Integer) => query.bind(None::<i32>),
+        SqlValue::Null(SqlType::Bool) => query.bind(None::<bool>),
+        SqlValue::Null(SqlType::Timestamp) => query.bind(None::<crate::Timestamp>),
+        SqlValue::Null(SqlType::Text) | SqlValue::Null(SqlType::Json) => query.bind(None::<String>),
+        SqlValue::Text(text) => query.bind(text),
+        SqlValue::Float(value) => query.bind(value),
+        SqlValue::Integer(value) => query.bind(value),
+        SqlValue::Bool(value) => query.bind(value),
+        SqlValue::Timestamp(value) => query.bind(value),
+    })
+}
+
+/// Insert a row of `aml` in `table`. See [`insert_statement`].
+pub async fn insert<'c, E>(executor: E, table: &str, aml: &AmlData) -> Result<PgQueryResult, ::sqlx::Error>
+where
+    E: Executor<'c, Database = Postgres>,
+{
+    let statement = insert_statement(table);
+    bind(::sqlx::query(&statement), aml).execute(executor).await
+}
+
+/// Create `table` and its indexes, if they do not exist. See [`create_table`](super::create_table).
+pub async fn create_table<'a, A>(connection: A, table: &str, postgis: bool) -> Result<(), ::sqlx::Error>
+where
+    A: Acquire<'a, Database = Postgres>,
+{
+    let ddl = super::create_table(table, postgis);
+    let mut connection = connection.acquire().await?;
+    ::sqlx::raw_sql(&ddl).execute(&mut *connection).await?;
+    Ok(())
+}
+
+/// The data of the `document` column, the other columns being ignored.
+impl<'r> FromRow<'r, PgRow> for AmlData {
+    fn from_row(row: &'r PgRow) -> Result<Self, ::sqlx::Error> {
+        let document: &JsonRawValue = row.try_get("document")?;
+        AmlData::from_sql_document(document.get()).ok_or_else(|| ::sqlx::Error::ColumnDecode {
+            index: "document".to_string(),
+            source: "not a JSON object".into(),
+        })
+    }
+}
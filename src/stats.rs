@@ -0,0 +1,193 @@
+use crate::json::JsonValue;
+use crate::{AmlData, AmlError, DropObserver, DropReason, DroppedField, HttpsData, SmsData, ValidationReport};
+use std::collections::BTreeMap;
+
+/// Upper bounds, in meters, of the buckets of [`Stats::accuracy`]. The last bucket
+/// holds the accuracies above the last bound.
+pub const ACCURACY_BUCKETS: [f64; 6] = [10.0, 25.0, 50.0, 100.0, 500.0, 1000.0];
+
+/// Most distinct keys counted in [`Stats::by_version`], [`Stats::unknown_keys`] and
+/// [`Stats::invalid_values`], which are sent by the handsets. Once full, the new keys
+/// of a transport are counted under [`OTHER_KEY`].
+pub const MAX_DISTINCT_KEYS: usize = 64;
+
+/// The key counting what no longer fits into a full map, e.g. `("sms", "other")`.
+pub const OTHER_KEY: &str = "other";
+
+/// Increment the count of `key`, or of `(transport, OTHER_KEY)` if `map` is full.
+fn count(map: &mut BTreeMap<(String, String), u64>, (transport, key): (&str, &str)) {
+    let mut key = (transport.to_string(), key.to_string());
+    if map.len() >= MAX_DISTINCT_KEYS && !map.contains_key(&key) {
+        key.1 = OTHER_KEY.to_string();
+    }
+    *map.entry(key).or_default() += 1;
+}
+
+/// Health view of an AML feed: what has been received, what has been rejected and how
+/// good the locations are. Feed it with [`Stats::parse_sms`] and [`Stats::parse_https`],
+/// or with [`Stats::record`] and [`Stats::record_validation`] after parsing, and as the
+/// [`DropObserver`] of the parsers to count the unknown keys.
+///
+/// # Example
+///
+/// ```
+/// use aml_lib::Stats;
+///
+/// let mut stats = Stats::default();
+/// stats.parse_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;ls=G;bt=87"#).unwrap();
+/// stats.parse_sms("hello").unwrap_err();
+///
+/// let snapshot = stats.snapshot();
+/// assert_eq!(snapshot.messages, 2);
+/// assert_eq!(snapshot.by_version[&("sms".to_string(), "2".to_string())], 1);
+/// assert_eq!(snapshot.errors["unimplemented_version"], 1);
+/// assert_eq!(snapshot.unknown_keys[&("sms".to_string(), "bt".to_string())], 1);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Stats {
+    /// Messages recorded, parsed or not.
+    pub messages: u64,
+
+    /// Parsed messages by transport and version (empty if unknown), at most
+    /// [`MAX_DISTINCT_KEYS`] versions.
+    pub by_version: BTreeMap<(String, String), u64>,
+
+    /// Rejected messages by [`AmlError::name`].
+    pub errors: BTreeMap<&'static str, u64>,
+
+    /// Messages validated, see [`Stats::record_validation`].
+    pub validated: u64,
+
+    /// Validated messages with at least one failure.
+    pub invalid: u64,
+
+    /// Validation failures by [`ValidationFailure::name`](crate::ValidationFailure::name).
+    pub validation_failures: BTreeMap<&'static str, u64>,
+
+    /// Unknown keys by transport and key, at most [`MAX_DISTINCT_KEYS`] keys.
+    pub unknown_keys: BTreeMap<(String, String), u64>,
+
+    /// Values which could not be converted, by transport and key, at most
+    /// [`MAX_DISTINCT_KEYS`] keys.
+    pub invalid_values: BTreeMap<(String, String), u64>,
+
+    /// Parsed messages with a location by accuracy, see [`ACCURACY_BUCKETS`].
+    pub accuracy: [u64; ACCURACY_BUCKETS.len() + 1],
+
+    /// Parsed messages with a location but no accuracy.
+    pub unknown_accuracy: u64,
+}
+
+impl Stats {
+    /// Count a parsed or rejected message.
+    pub fn record(&mut self, result: &Result<AmlData, AmlError>) {
+        self.messages += 1;
+        let aml = match result {
+            Ok(aml) => aml,
+            Err(error) => {
+                *self.errors.entry(error.name()).or_default() += 1;
+                return;
+            }
+        };
+
+        count(&mut self.by_version, (&aml.transport, aml.version.as_deref().unwrap_or_default()));
+
+        if aml.position().is_some() {
+            match aml.accuracy {
+                Some(accuracy) => {
+                    let bucket = ACCURACY_BUCKETS.iter().position(|bound| accuracy <= *bound).unwrap_or(ACCURACY_BUCKETS.len());
+                    self.accuracy[bucket] += 1;
+                }
+                None => self.unknown_accuracy += 1,
+            }
+        }
+    }
+
+    /// Count the outcome of the validation of a message.
+    pub fn record_validation(&mut self, report: &ValidationReport) {
+        self.validated += 1;
+        if !report.is_valid() {
+            self.invalid += 1;
+        }
+        for failure in &report.failures {
+            *self.validation_failures.entry(failure.name()).or_default() += 1;
+        }
+    }
+
+    /// Parse a SMS text and record it. See [`SmsData::from_text_observed`].
+    pub fn parse_sms(&mut self, text_sms: &str) -> Result<AmlData, AmlError> {
+        let result = SmsData::from_text_observed(text_sms, self).map(|sms| {
+            self.record_validation(&sms.validation);
+            AmlData::from(sms)
+        });
+        self.record(&result);
+        result
+    }
+
+    /// Parse a HTTPS AML message and record it. See [`HttpsData::from_urlencoded_observed`].
+    pub fn parse_https(&mut self, payload: &str) -> Result<AmlData, AmlError> {
+        let https = HttpsData::from_urlencoded_observed(payload, self);
+        self.record_validation(&https.validate());
+        let result = Ok(AmlData::from(https));
+        self.record(&result);
+        result
+    }
+
+    /// Share of the validated messages which are invalid, `None` before any validation.
+    pub fn invalid_rate(&self) -> Option<f64> {
+        Some(self.invalid as f64 / self.validated as f64).filter(|_| self.validated > 0)
+    }
+
+    /// A copy of the counters, e.g. to export them while the feed goes on.
+    pub fn snapshot(&self) -> Stats {
+        self.clone()
+    }
+
+    /// The counters, reset for the next period.
+    pub fn take(&mut self) -> Stats {
+        std::mem::take(self)
+    }
+
+    /// The counters as a JSON object, keys being joined with `/` (e.g. `sms/2`) and the
+    /// accuracy buckets named after their upper bound.
+    pub fn to_json_value(&self) -> JsonValue {
+        let counts = |map: &BTreeMap<(String, String), u64>| {
+            let members = map.iter().map(|((a, b), count)| (format!("{}/{}", a, b), JsonValue::Number(*count as f64)));
+            JsonValue::Object(members.collect())
+        };
+        let names = |map: &BTreeMap<&'static str, u64>| {
+            let members = map.iter().map(|(name, count)| (name.to_string(), JsonValue::Number(*count as f64)));
+            JsonValue::Object(members.collect())
+        };
+        let mut accuracy: Vec<(String, JsonValue)> = ACCURACY_BUCKETS
+            .iter()
+            .map(|bound| format!("<={}", bound))
+            .chain(std::iter::once(format!(">{}", ACCURACY_BUCKETS[ACCURACY_BUCKETS.len() - 1])))
+            .zip(self.accuracy.iter().map(|count| JsonValue::Number(*count as f64)))
+            .collect();
+        accuracy.push(("unknown".to_string(), JsonValue::Number(self.unknown_accuracy as f64)));
+
+        JsonValue::Object(vec![
+            ("messages".to_string(), JsonValue::Number(self.messages as f64)),
+            ("by_version".to_string(), counts(&self.by_version)),
+            ("errors".to_string(), names(&self.errors)),
+            ("validated".to_string(), JsonValue::Number(self.validated as f64)),
+            ("invalid".to_string(), JsonValue::Number(self.invalid as f64)),
+            ("validation_failures".to_string(), names(&self.validation_failures)),
+            ("unknown_keys".to_string(), counts(&self.unknown_keys)),
+            ("invalid_values".to_string(), counts(&self.invalid_values)),
+            ("accuracy".to_string(), JsonValue::Object(accuracy)),
+        ])
+    }
+}
+
+impl DropObserver for Stats {
+    fn dropped(&mut self, field: &DroppedField) {
+        let counts = match field.reason {
+            DropReason::Unknown => &mut self.unknown_keys,
+            DropReason::Invalid => &mut self.invalid_values,
+            DropReason::Repeated => return,
+        };
+        count(counts, (field.transport, field.key));
+    }
+}
@@ -0,0 +1,181 @@
+//! Embedded store of the parsed messages, for the small PSAPs without a database.
+//!
+//! [`Store`] keeps the messages in a SQLite database, a file or in memory, with the
+//! columns indexed by the queries: device, time and geohash of the location. Each
+//! message is stored whole as JSON (see [`AmlData::to_json`]) and read back without
+//! loss. To store the messages in a database server, see the [`sql`](crate::sql) feature.
+
+use crate::geo::geohash;
+use crate::json::JsonValue;
+use crate::session::device_of;
+use crate::{timestamp, AmlData, Timestamp};
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OptionalExtension, Params, Result, Row};
+use std::path::Path;
+
+/// Length of the geohashes of the index, about 5 meters.
+const GEOHASH_LENGTH: usize = 9;
+
+/// Table and indexes of the messages. The timestamps are milliseconds since the epoch.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS aml_messages (
+    id INTEGER PRIMARY KEY,
+    device TEXT,
+    time INTEGER,
+    beginning_of_call INTEGER,
+    time_of_positioning INTEGER,
+    geohash TEXT,
+    document TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS aml_messages_device ON aml_messages (device, beginning_of_call);
+CREATE INDEX IF NOT EXISTS aml_messages_time ON aml_messages (time);
+CREATE INDEX IF NOT EXISTS aml_messages_geohash ON aml_messages (geohash);
+";
+
+/// Identifier of a message in a [`Store`], increasing in insertion order.
+pub type MessageId = i64;
+
+/// Messages indexed by device (IMEI, else IMSI, else phone number), by time (time of
+/// positioning, else beginning of call, else reception) and by geohash of the location.
+#[derive(Debug)]
+pub struct Store {
+    connection: Connection,
+}
+
+impl Store {
+    /// A store in memory, lost when dropped.
+    pub fn in_memory() -> Result<Self> {
+        Store::with_connection(Connection::open_in_memory()?)
+    }
+
+    /// Open the store of the database `path`, created if needed, with the messages
+    /// already recorded.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Store::with_connection(Connection::open(path)?)
+    }
+
+    /// The store of the database of `connection`, whose table and indexes are created
+    /// if needed.
+    pub fn with_connection(connection: Connection) -> Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Store { connection })
+    }
+
+    /// The connection to the database, e.g. for other queries.
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Record `aml`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::store::Store;
+    /// use aml_lib::AmlData;
+    ///
+    /// let store = Store::in_memory().unwrap();
+    /// store.insert(&AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lt=11;lo=48.82639,-2.36619,40;ei=358239059042542"#).unwrap()).unwrap();
+    /// store.insert(&AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lt=41;lo=48.82641,-2.36620,12;ei=358239059042542"#).unwrap()).unwrap();
+    ///
+    /// assert_eq!(store.latest_location("358239059042542").unwrap().unwrap().accuracy, Some(12.0));
+    /// assert_eq!(store.near("gbwmf0").unwrap().len(), 2);
+    /// ```
+    pub fn insert(&self, aml: &AmlData) -> Result<MessageId> {
+        self.connection.execute(
+            "INSERT INTO aml_messages (device, time, beginning_of_call, time_of_positioning, geohash, document) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                device_of(aml),
+                time_of(aml).map(timestamp::unix_millis),
+                aml.beginning_of_call.map(timestamp::unix_millis),
+                aml.time_of_positioning.map(timestamp::unix_millis),
+                aml.position().map(|position| geohash(position, GEOHASH_LENGTH)),
+                aml.to_json(),
+            ],
+        )?;
+        Ok(self.connection.last_insert_rowid())
+    }
+
+    pub fn get(&self, id: MessageId) -> Result<Option<AmlData>> {
+        self.connection
+            .query_row("SELECT document FROM aml_messages WHERE id = ?1", [id], document)
+            .optional()
+    }
+
+    pub fn len(&self) -> Result<usize> {
+        self.connection.query_row("SELECT COUNT(*) FROM aml_messages", [], |row| row.get(0))
+    }
+
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// The messages of `device`, in insertion order.
+    pub fn device(&self, device: &str) -> Result<Vec<AmlData>> {
+        self.documents("SELECT document FROM aml_messages WHERE device = ?1 ORDER BY id", [device])
+    }
+
+    /// The last location of `device`, by time of positioning (then insertion order).
+    pub fn latest_location(&self, device: &str) -> Result<Option<AmlData>> {
+        self.connection
+            .query_row(
+                "SELECT document FROM aml_messages WHERE device = ?1 AND geohash IS NOT NULL \
+                 ORDER BY time_of_positioning IS NULL, time_of_positioning DESC, id DESC LIMIT 1",
+                [device],
+                document,
+            )
+            .optional()
+    }
+
+    /// The last location of each call (device and beginning of call), by device.
+    pub fn latest_locations(&self) -> Result<Vec<AmlData>> {
+        self.documents(
+            "SELECT document FROM (
+                 SELECT document, device, beginning_of_call, ROW_NUMBER() OVER (
+                     PARTITION BY device, beginning_of_call
+                     ORDER BY time_of_positioning IS NULL, time_of_positioning DESC, id DESC
+                 ) AS rank
+                 FROM aml_messages WHERE device IS NOT NULL AND geohash IS NOT NULL
+             ) WHERE rank = 1 ORDER BY device, beginning_of_call",
+            [],
+        )
+    }
+
+    /// The messages whose time is in `from..to`, by time.
+    pub fn between(&self, from: Timestamp, to: Timestamp) -> Result<Vec<AmlData>> {
+        self.documents(
+            "SELECT document FROM aml_messages WHERE time >= ?1 AND time < ?2 ORDER BY time, id",
+            [timestamp::unix_millis(from), timestamp::unix_millis(to)],
+        )
+    }
+
+    /// The messages located in the geohash cell `prefix` (any length), by geohash.
+    pub fn near(&self, prefix: &str) -> Result<Vec<AmlData>> {
+        // `~` sorts after the characters of the geohashes
+        self.documents(
+            "SELECT document FROM aml_messages WHERE geohash >= ?1 AND geohash < ?1 || '~' ORDER BY geohash, id",
+            [prefix],
+        )
+    }
+
+    fn documents<P: Params>(&self, query: &str, params: P) -> Result<Vec<AmlData>> {
+        self.connection.prepare_cached(query)?.query_map(params, document)?.collect()
+    }
+}
+
+/// The message of the `document` of a row.
+fn document(row: &Row) -> Result<AmlData> {
+    let json: String = row.get(0)?;
+    JsonValue::parse(&json)
+        .as_ref()
+        .and_then(AmlData::from_json_value)
+        .ok_or_else(|| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, "not a message".into()))
+}
+
+/// Time of a message in the index.
+fn time_of(aml: &AmlData) -> Option<Timestamp> {
+    aml.time_of_positioning
+        .or(aml.beginning_of_call)
+        .or_else(|| aml.context.as_ref().and_then(|context| context.received_at))
+}
@@ -0,0 +1,36 @@
+use crate::AmlData;
+
+/// Emergency numbers reserved for tests: 933 is the 9-1-1 address test line in the US.
+pub const TEST_EMERGENCY_NUMBERS: &[&str] = &["933"];
+
+impl AmlData {
+    /// True if the message comes from a test or a drill rather than a real emergency:
+    /// it carries a ground truth location (only sent by test handsets), is flagged as a
+    /// test by the operator or the vehicle, or the number dialed is one of
+    /// [`TEST_EMERGENCY_NUMBERS`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::AmlData;
+    ///
+    /// let drill = "v=1&emergency_number=112&location_latitude=48.85&location_longitude=2.35&gt_location_latitude=48.8501&gt_location_longitude=2.3502";
+    /// assert!(AmlData::from_https(drill).unwrap().is_test_message());
+    ///
+    /// let call = "v=1&emergency_number=112&location_latitude=48.85&location_longitude=2.35";
+    /// assert!(!AmlData::from_https(call).unwrap().is_test_message());
+    /// ```
+    pub fn is_test_message(&self) -> bool {
+        self.is_test_message_with(TEST_EMERGENCY_NUMBERS)
+    }
+
+    /// Like [`AmlData::is_test_message`], with the test numbers of the deployment.
+    pub fn is_test_message_with(&self, test_numbers: &[&str]) -> bool {
+        self.test
+            || self.ground_truth.is_some()
+            || self
+                .emergency_number
+                .as_deref()
+                .is_some_and(|number| test_numbers.contains(&number.trim()))
+    }
+}
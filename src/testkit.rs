@@ -0,0 +1,337 @@
+//! A corpus of AML payloads shaped like the ones received in production (all versions,
+//! both transports, known handset quirks, broken messages) with the data expected from
+//! each, to check an ingestion pipeline end to end.
+//!
+//! # Example
+//!
+//! ```
+//! use aml_lib::testkit;
+//!
+//! // The pipeline under test: here the parser alone, usually the whole ingestion chain
+//! let failures = testkit::run(|vector| vector.parse());
+//! assert!(failures.is_empty(), "{:?}", failures);
+//! ```
+
+use crate::{batch, timestamp, AmlData, AmlError};
+
+/// What a payload must give. `None` members are not checked.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Expected {
+    pub version: Option<&'static str>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub accuracy: Option<f64>,
+
+    /// Unix time in seconds.
+    pub time_of_positioning: Option<i64>,
+
+    pub imei: Option<&'static str>,
+
+    /// [`AmlError::name`] of the expected error. The other members are then ignored.
+    pub error: Option<&'static str>,
+}
+
+const UNCHECKED: Expected = Expected {
+    version: None,
+    latitude: None,
+    longitude: None,
+    accuracy: None,
+    time_of_positioning: None,
+    imei: None,
+    error: None,
+};
+
+/// A payload of the corpus.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vector {
+    /// Unique and stable.
+    pub name: &'static str,
+
+    pub description: &'static str,
+
+    /// Format of the payload, as in [`batch::parse_payload`] (`None` when it has to be
+    /// detected).
+    pub transport: Option<&'static str>,
+
+    pub payload: &'static str,
+
+    pub expected: Expected,
+}
+
+impl Vector {
+    /// Parse the payload with the parsers of the crate. See [`batch::parse_payload`].
+    pub fn parse(&self) -> Result<AmlData, AmlError> {
+        batch::parse_payload(self.transport, self.payload)
+    }
+
+    /// The differences between `result` and what is expected, empty if it matches.
+    pub fn check(&self, result: &Result<AmlData, AmlError>) -> Vec<String> {
+        let aml = match (result, self.expected.error) {
+            (Err(error), Some(expected)) if error.name() == expected => return Vec::new(),
+            (Err(error), _) => return vec![format!("unexpected error {}", error.name())],
+            (Ok(_), Some(expected)) => return vec![format!("expected error {}", expected)],
+            (Ok(aml), None) => aml,
+        };
+
+        let mut differences = Vec::new();
+        let mut compare = |name: &str, expected: Option<String>, actual: Option<String>| {
+            if expected.is_some() && expected != actual {
+                differences.push(format!("{}: expected {:?}, got {:?}", name, expected, actual));
+            }
+        };
+        let expected = &self.expected;
+        compare("version", expected.version.map(str::to_string), aml.version.clone());
+        compare("latitude", expected.latitude.map(|v| v.to_string()), aml.latitude.map(|v| v.to_string()));
+        compare("longitude", expected.longitude.map(|v| v.to_string()), aml.longitude.map(|v| v.to_string()));
+        compare("accuracy", expected.accuracy.map(|v| v.to_string()), aml.accuracy.map(|v| v.to_string()));
+        compare(
+            "time_of_positioning",
+            expected.time_of_positioning.map(|v| v.to_string()),
+            aml.time_of_positioning.map(|v| timestamp::unix(v).to_string()),
+        );
+        compare("imei", expected.imei.map(str::to_string), aml.imei.clone());
+        differences
+    }
+}
+
+/// The whole corpus.
+pub fn vectors() -> &'static [Vector] {
+    VECTORS
+}
+
+/// Run `pipeline` on each vector, returning the names of the vectors which do not give
+/// the expected data with the differences.
+pub fn run<F: FnMut(&Vector) -> Result<AmlData, AmlError>>(mut pipeline: F) -> Vec<(&'static str, Vec<String>)> {
+    VECTORS
+        .iter()
+        .filter_map(|vector| {
+            let differences = vector.check(&pipeline(vector));
+            Some((vector.name, differences)).filter(|(_, differences)| !differences.is_empty())
+        })
+        .collect()
+}
+
+const VECTORS: &[Vector] = &[
+    Vector {
+        name: "sms-v1-text",
+        description: "Text SMS of version 1, as sent by iPhones",
+        transport: Some("sms"),
+        payload: r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;lc=68;pm=G;si=208201771948415;ei=353472104343540;mcc=208;mnc=20;ml=126"#,
+        expected: Expected {
+            version: Some("1"),
+            latitude: Some(48.82639),
+            longitude: Some(-2.36619),
+            accuracy: Some(52.0),
+            time_of_positioning: Some(1573558168),
+            imei: Some("353472104343540"),
+            ..UNCHECKED
+        },
+    },
+    Vector {
+        name: "sms-v1-hex",
+        description: "Data SMS of version 1 in GSM 7 bit, as hexadecimal",
+        transport: Some("sms_hex"),
+        payload: "415193D98BEDD8F4DEECE6A2C962B7DA8E7DEEB56232990B86A3D9623B39B92783EDE86F784F068BD560B6D80C1683E568B81D7BDCB3E176F076EFB89BA77B39DCCD56A3C966B15D39DD9BD570B2590E56CBC168B21A4DB66B8FC7BD590CB66BBBC73D990DB66BB37B31D90C",
+        expected: Expected {
+            version: Some("1"),
+            latitude: Some(37.42175),
+            longitude: Some(-122.08461),
+            accuracy: Some(20.0),
+            time_of_positioning: Some(1434157788),
+            imei: Some("358239059042542"),
+            ..UNCHECKED
+        },
+    },
+    Vector {
+        name: "sms-v1-base64",
+        description: "Data SMS of version 1 in GSM 7 bit, as base64",
+        transport: Some("sms_base64"),
+        payload: "QVGT2Yvt2PTe7OaiyWK32o597rViMpkLhqPZYjs5uSeD7ehveE8Gi9VgttgMFoPlaLgde9yz4Xbwdu+4m6d7OdzNVqPJZrFdOd2b1XCyWQ5Wy8FoshpNtmuPx71ZDLZru8c9mQ22a7N7MdkM",
+        expected: Expected {
+            version: Some("1"),
+            latitude: Some(37.42175),
+            longitude: Some(-122.08461),
+            time_of_positioning: Some(1434157788),
+            ..UNCHECKED
+        },
+    },
+    Vector {
+        name: "sms-v2-text",
+        description: "Text SMS of version 2, positioning time relative to the beginning of call",
+        transport: Some("sms"),
+        payload: r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=11;lc=68;ls=G;ei=358239059042542;nc=20801"#,
+        expected: Expected {
+            version: Some("2"),
+            latitude: Some(48.82639),
+            longitude: Some(-2.36619),
+            accuracy: Some(20.0),
+            time_of_positioning: Some(1593187200),
+            imei: Some("358239059042542"),
+            ..UNCHECKED
+        },
+    },
+    Vector {
+        name: "sms-v2-unknown-keys",
+        description: "Text SMS of version 2 with vendor keys, to be ignored",
+        transport: Some("sms"),
+        payload: r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=0;ls=W;bt=87;xv=1.2"#,
+        expected: Expected {
+            version: Some("2"),
+            latitude: Some(48.82639),
+            time_of_positioning: Some(1593187189),
+            ..UNCHECKED
+        },
+    },
+    Vector {
+        name: "sms-byte-order-mark",
+        description: "Text SMS relayed with a byte order mark, a trailing NUL and a line break",
+        transport: Some("sms"),
+        payload: "\u{feff}A\"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;lc=68;pm=G;ml=79\0\r\n",
+        expected: Expected {
+            version: Some("1"),
+            latitude: Some(48.82639),
+            longitude: Some(-2.36619),
+            accuracy: Some(52.0),
+            ..UNCHECKED
+        },
+    },
+    Vector {
+        name: "sms-trailing-semicolon",
+        description: "Text SMS ending with a separator and with spaces around the values",
+        transport: Some("sms"),
+        payload: r#"A"ML=1; lt=48.82639; lg=-2.36619; rd=52; top=20191112112928;"#,
+        expected: Expected {
+            version: Some("1"),
+            latitude: Some(48.82639),
+            longitude: Some(-2.36619),
+            ..UNCHECKED
+        },
+    },
+    Vector {
+        name: "sms-detected",
+        description: "Text SMS without transport, the format being detected",
+        transport: None,
+        payload: r#"A"ML=2;en=112;et=1593187189;lo=45.76400,4.83566,8;lt=3;ls=G"#,
+        expected: Expected {
+            version: Some("2"),
+            latitude: Some(45.764),
+            longitude: Some(4.83566),
+            accuracy: Some(8.0),
+            ..UNCHECKED
+        },
+    },
+    Vector {
+        name: "https-v1-hmac",
+        description: "HTTPS message authenticated with the key AML",
+        transport: Some("https"),
+        payload: "v=1&device_number=%2B33611223344&location_latitude=0.85732&location_longitude=-4.26325&location_time=1604912121000&location_accuracy=10.4&location_source=GPS&location_certainty=83&hmac=f64c70eb238bb239e00e8ac8c023bf2b5d3c41dd",
+        expected: Expected {
+            version: Some("1"),
+            latitude: Some(0.85732),
+            longitude: Some(-4.26325),
+            accuracy: Some(10.4),
+            time_of_positioning: Some(1604912121),
+            ..UNCHECKED
+        },
+    },
+    Vector {
+        name: "https-v3-full",
+        description: "HTTPS message with the ellipse, the device and the network",
+        transport: Some("https"),
+        payload: "v=3&emergency_number=112&source=call&time=1604912100000&location_latitude=55.85732&location_longitude=-4.26325\
+                  &location_time=1604912121000&location_altitude=12.5&location_floor=3&location_source=fused&location_accuracy=15\
+                  &location_vertical_accuracy=4&location_semi_major_axis=20&location_semi_minor_axis=10&location_orientation=45\
+                  &location_confidence=68&location_bearing=270&location_speed=1.5&device_number=447700900123&device_model=Pixel\
+                  &device_imei=490154203237518&device_imsi=234150999999999&cell_home_mcc=234&cell_home_mnc=15\
+                  &cell_network_mcc=234&cell_network_mnc=15&device_languages=en-GB",
+        expected: Expected {
+            version: Some("3"),
+            latitude: Some(55.85732),
+            longitude: Some(-4.26325),
+            accuracy: Some(15.0),
+            time_of_positioning: Some(1604912121),
+            imei: Some("490154203237518"),
+            ..UNCHECKED
+        },
+    },
+    Vector {
+        name: "https-els",
+        description: "HTTPS message of the Android Emergency Location Service",
+        transport: Some("https"),
+        payload: "v=4&emergency_number=999&thunderbird_version=1.0&time=1604912100000&location_latitude=51.50101\
+                  &location_longitude=-0.14189&location_time=1604912110000&location_accuracy=8&location_source=gps\
+                  &location_certainty=83",
+        expected: Expected {
+            version: Some("4"),
+            latitude: Some(51.50101),
+            longitude: Some(-0.14189),
+            accuracy: Some(8.0),
+            ..UNCHECKED
+        },
+    },
+    Vector {
+        name: "https-car-crash",
+        description: "HTTPS message of a vehicle with a crash notification",
+        transport: Some("https"),
+        payload: "v=1&emergency_number=112&time=1604912100000&location_latitude=48.85&location_longitude=2.35\
+                  &location_accuracy=5&adr_carcrash_time=1604912098000&adr_severity=3&adr_rollover=true&adr_occupants=2",
+        expected: Expected {
+            version: Some("1"),
+            latitude: Some(48.85),
+            accuracy: Some(5.0),
+            ..UNCHECKED
+        },
+    },
+    Vector {
+        name: "https-tunneled-sms",
+        description: "Text SMS posted in a HTTPS body by a SMS gateway",
+        transport: None,
+        payload: "from=%2B33611223344&body=A%22ML%3D2%3Ben%3D112%3Bet%3D1593187189%3Blo%3D48.82639%2C-2.36619%2C20",
+        expected: Expected {
+            version: Some("2"),
+            latitude: Some(48.82639),
+            accuracy: Some(20.0),
+            ..UNCHECKED
+        },
+    },
+    Vector {
+        name: "sms-unknown-version",
+        description: "Text SMS of a version which does not exist",
+        transport: Some("sms"),
+        payload: r#"A"ML=9;lt=48.82639;lg=-2.36619"#,
+        expected: Expected {
+            error: Some("unimplemented_version"),
+            ..UNCHECKED
+        },
+    },
+    Vector {
+        name: "sms-not-aml",
+        description: "Ordinary text SMS",
+        transport: Some("sms"),
+        payload: "Call me back",
+        expected: Expected {
+            error: Some("unimplemented_version"),
+            ..UNCHECKED
+        },
+    },
+    Vector {
+        name: "sms-hex-corrupted",
+        description: "Data SMS with a character which is not hexadecimal",
+        transport: Some("sms_hex"),
+        payload: "4151Z3",
+        expected: Expected {
+            error: Some("invalid_hex"),
+            ..UNCHECKED
+        },
+    },
+    Vector {
+        name: "sms-base64-corrupted",
+        description: "Data SMS with a character which is not base64",
+        transport: Some("sms_base64"),
+        payload: "QU1M=*",
+        expected: Expected {
+            error: Some("invalid_base64"),
+            ..UNCHECKED
+        },
+    },
+];
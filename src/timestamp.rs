@@ -0,0 +1,258 @@
+//! Date and time backend of the data structures: chrono (`chrono` feature, the default) or
+//! the `time` crate (`time` feature, without the default features).
+//!
+//! [`Timestamp`] and [`Duration`] are the types of the selected backend, and the functions
+//! of this module create and read them the same way with both.
+//!
+//! # The features are not additive
+//!
+//! When both features are enabled, e.g. because another crate of the build depends on
+//! this one with the default features, chrono is used and code written for the `time`
+//! types no longer compiles. Code meant to build with either backend, libraries in
+//! particular, should only use [`Timestamp`], [`Duration`] and the functions of this
+//! module, and leave the choice of the backend to the final application.
+//!
+//! # Example
+//!
+//! ```
+//! use aml_lib::timestamp::{self, Duration};
+//!
+//! let time = timestamp::from_unix(1604912121).unwrap();
+//! assert_eq!(timestamp::to_rfc3339(time), "2020-11-09T08:55:21+00:00");
+//! assert_eq!(timestamp::unix_millis(time + Duration::milliseconds(250)), 1604912121250);
+//! assert_eq!(timestamp::parse_rfc3339("2020-11-09T09:55:21+01:00"), Some(time));
+//! ```
+
+#[cfg(not(any(feature = "chrono", feature = "time")))]
+compile_error!("either the `chrono` or the `time` feature must be enabled");
+
+/// A date and time in UTC.
+#[cfg(feature = "chrono")]
+pub type Timestamp = chrono::DateTime<chrono::Utc>;
+
+/// A date and time, always in UTC.
+#[cfg(all(feature = "time", not(feature = "chrono")))]
+pub type Timestamp = ::time::OffsetDateTime;
+
+/// A signed span of time, e.g. the difference of two [`Timestamp`]s.
+#[cfg(feature = "chrono")]
+pub type Duration = chrono::Duration;
+
+/// A signed span of time, e.g. the difference of two [`Timestamp`]s.
+#[cfg(all(feature = "time", not(feature = "chrono")))]
+pub type Duration = ::time::Duration;
+
+#[cfg(feature = "chrono")]
+mod backend {
+    use super::Timestamp;
+    use chrono::{Datelike, LocalResult, NaiveDate, TimeZone, Timelike, Utc};
+
+    pub fn from_unix_nanos(seconds: i64, nanos: u32) -> Option<Timestamp> {
+        match Utc.timestamp_opt(seconds, nanos) {
+            LocalResult::Single(time) => Some(time),
+            _ => None,
+        }
+    }
+
+    pub fn unix(time: Timestamp) -> i64 {
+        time.timestamp()
+    }
+
+    pub fn subsec_nanos(time: Timestamp) -> u32 {
+        time.timestamp_subsec_nanos()
+    }
+
+    pub fn now() -> Timestamp {
+        Utc::now()
+    }
+
+    pub fn from_utc(date: (i32, u32, u32), time: (u32, u32, u32)) -> Option<Timestamp> {
+        let naive = NaiveDate::from_ymd_opt(date.0, date.1, date.2)?.and_hms_opt(time.0, time.1, time.2)?;
+        Some(Utc.from_utc_datetime(&naive))
+    }
+
+    pub fn parse_rfc3339(text: &str) -> Option<Timestamp> {
+        chrono::DateTime::parse_from_rfc3339(text).ok().map(|time| time.with_timezone(&Utc))
+    }
+
+    pub fn fields(time: Timestamp) -> (i32, u32, u32, u32, u32, u32) {
+        (time.year(), time.month(), time.day(), time.hour(), time.minute(), time.second())
+    }
+
+    pub fn whole_millis(duration: super::Duration) -> i64 {
+        duration.num_milliseconds()
+    }
+}
+
+#[cfg(all(feature = "time", not(feature = "chrono")))]
+mod backend {
+    use super::Timestamp;
+    use ::time::format_description::well_known::Rfc3339;
+    use ::time::{Date, Month, OffsetDateTime, Time, UtcOffset};
+    use std::convert::TryFrom;
+
+    pub fn from_unix_nanos(seconds: i64, nanos: u32) -> Option<Timestamp> {
+        if nanos >= 1_000_000_000 {
+            return None;
+        }
+        OffsetDateTime::from_unix_timestamp_nanos(i128::from(seconds) * 1_000_000_000 + i128::from(nanos)).ok()
+    }
+
+    pub fn unix(time: Timestamp) -> i64 {
+        time.unix_timestamp()
+    }
+
+    pub fn subsec_nanos(time: Timestamp) -> u32 {
+        time.nanosecond()
+    }
+
+    pub fn now() -> Timestamp {
+        OffsetDateTime::now_utc()
+    }
+
+    pub fn from_utc(date: (i32, u32, u32), time: (u32, u32, u32)) -> Option<Timestamp> {
+        let month = Month::try_from(u8::try_from(date.1).ok()?).ok()?;
+        let date = Date::from_calendar_date(date.0, month, u8::try_from(date.2).ok()?).ok()?;
+        let time = Time::from_hms(u8::try_from(time.0).ok()?, u8::try_from(time.1).ok()?, u8::try_from(time.2).ok()?).ok()?;
+        Some(date.with_time(time).assume_utc())
+    }
+
+    pub fn parse_rfc3339(text: &str) -> Option<Timestamp> {
+        OffsetDateTime::parse(text, &Rfc3339).ok().map(|time| time.to_offset(UtcOffset::UTC))
+    }
+
+    pub fn fields(time: Timestamp) -> (i32, u32, u32, u32, u32, u32) {
+        let time = time.to_offset(UtcOffset::UTC);
+        let (hour, minute, second) = time.to_hms();
+        (time.year(), u8::from(time.month()).into(), time.day().into(), hour.into(), minute.into(), second.into())
+    }
+
+    pub fn whole_millis(duration: super::Duration) -> i64 {
+        duration.whole_milliseconds().clamp(i64::MIN.into(), i64::MAX.into()) as i64
+    }
+}
+
+/// The time `seconds` after the Unix epoch, if representable.
+pub fn from_unix(seconds: i64) -> Option<Timestamp> {
+    backend::from_unix_nanos(seconds, 0)
+}
+
+/// The time `millis` milliseconds after the Unix epoch, if representable.
+pub fn from_unix_millis(millis: i64) -> Option<Timestamp> {
+    backend::from_unix_nanos(millis.div_euclid(1000), (millis.rem_euclid(1000) * 1_000_000) as u32)
+}
+
+/// The time `seconds` and `nanos` (less than a second) after the Unix epoch, if representable.
+pub fn from_unix_nanos(seconds: i64, nanos: u32) -> Option<Timestamp> {
+    backend::from_unix_nanos(seconds, nanos)
+}
+
+/// The whole seconds since the Unix epoch (rounded down).
+pub fn unix(time: Timestamp) -> i64 {
+    backend::unix(time)
+}
+
+/// The whole milliseconds since the Unix epoch (rounded down).
+pub fn unix_millis(time: Timestamp) -> i64 {
+    unix(time) * 1000 + i64::from(subsec_nanos(time) / 1_000_000)
+}
+
+/// The nanoseconds past the second of [`unix`].
+pub fn subsec_nanos(time: Timestamp) -> u32 {
+    backend::subsec_nanos(time)
+}
+
+/// The current time.
+pub fn now() -> Timestamp {
+    backend::now()
+}
+
+/// The time of a date (year, month, day) and a time of day (hour, minute, second) in UTC,
+/// if valid.
+pub fn from_utc(date: (i32, u32, u32), time: (u32, u32, u32)) -> Option<Timestamp> {
+    backend::from_utc(date, time)
+}
+
+/// Parse a RFC 3339 date and time, in any offset.
+pub fn parse_rfc3339(text: &str) -> Option<Timestamp> {
+    backend::parse_rfc3339(text)
+}
+
+/// The RFC 3339 form of `time`, with the fraction of second if any (e.g.
+/// `2020-11-09T08:55:21.250+00:00`).
+pub fn to_rfc3339(time: Timestamp) -> String {
+    let nanos = subsec_nanos(time);
+    let fraction = if nanos == 0 {
+        String::new()
+    } else if nanos.is_multiple_of(1_000_000) {
+        format!(".{:03}", nanos / 1_000_000)
+    } else if nanos.is_multiple_of(1_000) {
+        format!(".{:06}", nanos / 1_000)
+    } else {
+        format!(".{:09}", nanos)
+    };
+    format!("{}{}+00:00", format(time, "T"), fraction)
+}
+
+/// The whole seconds of `duration` (rounded toward zero).
+pub fn whole_seconds(duration: Duration) -> i64 {
+    whole_millis(duration) / 1000
+}
+
+/// The whole milliseconds of `duration` (rounded toward zero).
+pub fn whole_millis(duration: Duration) -> i64 {
+    backend::whole_millis(duration)
+}
+
+/// serde form of an optional [`Timestamp`]: the RFC 3339 form of [`to_rfc3339_millis`], as
+/// in [`AmlData::to_json_value`](crate::AmlData::to_json_value).
+#[cfg(feature = "serde")]
+pub(crate) mod rfc3339 {
+    use super::Timestamp;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(time: &Option<Timestamp>, serializer: S) -> Result<S::Ok, S::Error> {
+        match time {
+            Some(time) => serializer.serialize_str(&super::to_rfc3339_millis(*time)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Timestamp>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(text) => super::parse_rfc3339(&text).map(Some).ok_or_else(|| D::Error::custom("invalid RFC 3339 date and time")),
+            None => Ok(None),
+        }
+    }
+}
+
+/// The RFC 3339 form of `time` with milliseconds, in Zulu time (e.g.
+/// `2020-11-09T08:55:21.000Z`).
+pub(crate) fn to_rfc3339_millis(time: Timestamp) -> String {
+    format!("{}.{:03}Z", format(time, "T"), subsec_nanos(time) / 1_000_000)
+}
+
+/// Date and time of `time` as `YYYY-MM-DD<separator>hh:mm:ss`.
+pub(crate) fn format(time: Timestamp, separator: &str) -> String {
+    let (year, month, day, hour, minute, second) = backend::fields(time);
+    format!("{:04}-{:02}-{:02}{}{:02}:{:02}:{:02}", year, month, day, separator, hour, minute, second)
+}
+
+/// Date and time of `time` as `YYYYMMDDhhmmss`, the form of SMS AML.
+pub(crate) fn format_compact(time: Timestamp) -> String {
+    let (year, month, day, hour, minute, second) = backend::fields(time);
+    format!("{:04}{:02}{:02}{:02}{:02}{:02}", year, month, day, hour, minute, second)
+}
+
+/// Parse a date and time in UTC as `YYYYMMDDhhmmss`, the form of SMS AML.
+pub(crate) fn parse_compact(text: &str) -> Option<Timestamp> {
+    if text.len() != 14 || !text.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let number = |range: std::ops::Range<usize>| text[range].parse::<u32>().ok();
+    from_utc(
+        (number(0..4)? as i32, number(4..6)?, number(6..8)?),
+        (number(8..10)?, number(10..12)?, number(12..14)?),
+    )
+}
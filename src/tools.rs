@@ -2,11 +2,7 @@
 #[macro_export]
 macro_rules! char_millis_to_utc {
     ($ts: expr) => {
-        $ts.parse::<i64>()
-            .map_or(None, |lt| match Utc.timestamp_millis_opt(lt) {
-                LocalResult::Single(dt) => Some(dt),
-                _ => None,
-            })
+        $ts.parse::<i64>().map_or(None, $crate::timestamp::from_unix_millis)
     };
 }
 
@@ -14,10 +10,7 @@ macro_rules! char_millis_to_utc {
 #[macro_export]
 macro_rules! seconds_to_utc {
     ($ts: expr) => {
-        match Utc.timestamp_opt($ts, 0_u32) {
-            LocalResult::Single(dt) => Some(dt),
-            _ => None,
-        }
+        $crate::timestamp::from_unix($ts)
     };
 }
 
@@ -25,10 +18,7 @@ macro_rules! seconds_to_utc {
 #[macro_export]
 macro_rules! millis_to_utc {
     ($ts: expr) => {
-        match Utc.timestamp_millis_opt($ts) {
-            LocalResult::Single(dt) => Some(dt),
-            _ => None,
-        }
+        $crate::timestamp::from_unix_millis($ts)
     };
 }
 
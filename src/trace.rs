@@ -0,0 +1,63 @@
+//! [`tracing`] instrumentation of the parsers.
+//!
+//! Each text SMS and HTTPS message is parsed in an `aml_parse` span, at debug level,
+//! with the fields:
+//! - `transport`: `sms` or `https`,
+//! - `version`: the `A"ML` header or the `v` parameter,
+//! - `valid`: the validation outcome of a SMS (see [`SmsData::is_valid`]),
+//! - `error`: why the message has been rejected.
+//!
+//! Within the span, debug events with a `key` field report the keys unknown to the
+//! parser and the keys of the other SMS version, which are ignored, and a last event
+//! the outcome of the parse. The events are emitted as the message is read, the
+//! payload is not parsed a second time.
+//!
+//! ```ignore
+//! tracing_subscriber::fmt().with_max_level(tracing::Level::DEBUG).init();
+//!
+//! // DEBUG aml_parse{transport="sms" version="2" valid=true}: aml_lib::trace: AML parsed
+//! let sms = aml_lib::SmsData::from_text(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;ls=G"#);
+//! ```
+
+use crate::{AmlError, SmsData};
+use tracing::{field, Span};
+
+/// The span of the parse of a message.
+pub(crate) fn parse_span(transport: &'static str) -> Span {
+    tracing::debug_span!(
+        "aml_parse",
+        transport,
+        version = field::Empty,
+        valid = field::Empty,
+        error = field::Empty
+    )
+}
+
+/// A key unknown to the parser has been ignored.
+pub(crate) fn unknown_key(key: &str) {
+    tracing::debug!(key, "unknown key ignored");
+}
+
+/// A key of the other SMS version has been ignored.
+pub(crate) fn dropped_key(key: &str) {
+    tracing::debug!(key, "key of the other version ignored");
+}
+
+pub(crate) fn sms_parsed(span: &Span, result: &Result<SmsData, AmlError>) {
+    match result {
+        Ok(sms) => {
+            span.record("version", sms.header.as_deref());
+            span.record("valid", sms.is_valid());
+            tracing::debug!("AML parsed");
+        }
+        Err(error) => {
+            span.record("error", field::display(error));
+            tracing::debug!(%error, "AML rejected");
+        }
+    }
+}
+
+pub(crate) fn https_parsed(span: &Span, version: Option<&str>) {
+    span.record("version", version);
+    tracing::debug!("AML parsed");
+}
@@ -0,0 +1,98 @@
+use crate::{AmlData, AmlError, SmsData};
+use std::borrow::Cow;
+
+/// Value of a hexadecimal digit.
+fn hex_digit(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|digit| digit as u8)
+}
+
+/// `text` with the `%XX` escapes decoded, `None` if the result is not UTF-8.
+fn percent_decoded(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = match bytes.get(i..i + 3) {
+            Some([b'%', high, low]) => hex_digit(*high).zip(hex_digit(*low)),
+            _ => None,
+        };
+        match escaped {
+            Some((high, low)) => {
+                decoded.push(high << 4 | low);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// `text` with `\"` and `\\` replaced by the escaped character, other backslashes kept.
+fn backslash_unescaped(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.clone().next() {
+            Some(next @ ('"' | '\\')) => {
+                unescaped.push(next);
+                chars.next();
+            }
+            _ => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+impl SmsData {
+    /// Undo the escaping of SMS texts found in operators' log exports: URL encoding
+    /// (`A%22ML%3D1%3Blt%3D...`), backslash-escaped quotes (`A\"ML=1;...`) and quotes
+    /// around the whole text. A text without escapes is returned as is.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::SmsData;
+    ///
+    /// assert_eq!(SmsData::unescape(r#"A%22ML%3D2%3Ben%3D112"#), r#"A"ML=2;en=112"#);
+    /// assert_eq!(SmsData::unescape(r#""A\"ML=2;en=112""#), r#"A"ML=2;en=112"#);
+    /// assert_eq!(SmsData::unescape(r#"A"ML=2;en=112"#), r#"A"ML=2;en=112"#);
+    /// ```
+    pub fn unescape(text_sms: &str) -> Cow<'_, str> {
+        let mut text = Cow::Borrowed(text_sms);
+
+        if text.contains('%') {
+            if let Some(decoded) = percent_decoded(&text) {
+                text = Cow::Owned(decoded);
+            }
+        }
+        if text.contains(r#"\""#) {
+            text = Cow::Owned(backslash_unescaped(&text));
+        }
+
+        let trimmed = text.trim();
+        match trimmed.strip_prefix('"').and_then(|inner| inner.strip_suffix('"')) {
+            Some(inner) if inner.starts_with(r#"A"ML="#) => Cow::Owned(inner.to_string()),
+            _ => text,
+        }
+    }
+
+    /// Parse a SMS text which may be escaped. See [`SmsData::unescape`] and
+    /// [`SmsData::from_text`]; the message is validated once unescaped.
+    pub fn from_escaped_text<S: AsRef<str>>(text_sms: S) -> Result<Self, AmlError> {
+        Self::from_text(Self::unescape(text_sms.as_ref()))
+    }
+}
+
+impl AmlData {
+    /// Parse a SMS text which may be escaped. See [`SmsData::from_escaped_text`].
+    pub fn from_escaped_text_sms<S: AsRef<str>>(text_sms: S) -> Result<Self, AmlError> {
+        Ok(SmsData::from_escaped_text(text_sms)?.into())
+    }
+}
@@ -0,0 +1,170 @@
+use crate::AmlData;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+macro_rules! unit {
+    ($(#[$doc: meta])* $name: ident, $symbol: expr) => {
+        $(#[$doc])*
+        #[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+        pub struct $name(pub f64);
+
+        impl $name {
+            /// The raw value.
+            pub fn value(self) -> f64 {
+                self.0
+            }
+        }
+
+        impl From<f64> for $name {
+            fn from(value: f64) -> Self {
+                $name(value)
+            }
+        }
+
+        impl From<$name> for f64 {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl Add for $name {
+            type Output = $name;
+
+            fn add(self, rhs: $name) -> $name {
+                $name(self.0 + rhs.0)
+            }
+        }
+
+        impl Sub for $name {
+            type Output = $name;
+
+            fn sub(self, rhs: $name) -> $name {
+                $name(self.0 - rhs.0)
+            }
+        }
+
+        impl Neg for $name {
+            type Output = $name;
+
+            fn neg(self) -> $name {
+                $name(-self.0)
+            }
+        }
+
+        impl Mul<f64> for $name {
+            type Output = $name;
+
+            fn mul(self, rhs: f64) -> $name {
+                $name(self.0 * rhs)
+            }
+        }
+
+        impl Div<f64> for $name {
+            type Output = $name;
+
+            fn div(self, rhs: f64) -> $name {
+                $name(self.0 / rhs)
+            }
+        }
+
+        /// Ratio of two values of the same unit.
+        impl Div for $name {
+            type Output = f64;
+
+            fn div(self, rhs: $name) -> f64 {
+                self.0 / rhs.0
+            }
+        }
+
+        /// The value and its unit, e.g. `20 m`. The precision of the formatter is honored.
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                match f.precision() {
+                    Some(precision) => write!(f, "{:.*}{}", precision, self.0, $symbol),
+                    None => write!(f, "{}{}", self.0, $symbol),
+                }
+            }
+        }
+    };
+}
+
+unit!(
+    /// An angle in degrees: WGS84 latitude or longitude, bearing.
+    Degrees,
+    "°"
+);
+
+unit!(
+    /// A distance in meters: accuracy, altitude.
+    Meters,
+    " m"
+);
+
+unit!(
+    /// A speed in meters per second.
+    MetersPerSecond,
+    " m/s"
+);
+
+unit!(
+    /// A percentage: level of confidence.
+    Percent,
+    " %"
+);
+
+impl Meters {
+    /// Meters from feet.
+    pub fn from_feet(feet: f64) -> Self {
+        Meters(feet * 0.3048)
+    }
+
+    /// The value in feet.
+    pub fn feet(self) -> f64 {
+        self.0 / 0.3048
+    }
+}
+
+/// Typed views of the measures. The fields stay raw `f64` values.
+///
+/// # Example
+///
+/// ```
+/// use aml_lib::{AmlData, Meters};
+///
+/// let aml = AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;lc=68;ls=G"#).unwrap();
+/// assert_eq!(aml.accuracy_meters(), Some(Meters(20.0)));
+/// assert_eq!(format!("{:.1}", aml.latitude_degrees().unwrap()), "48.8°");
+/// assert_eq!(aml.confidence_percent().unwrap().to_string(), "68 %");
+/// ```
+impl AmlData {
+    pub fn latitude_degrees(&self) -> Option<Degrees> {
+        self.latitude.map(Degrees)
+    }
+
+    pub fn longitude_degrees(&self) -> Option<Degrees> {
+        self.longitude.map(Degrees)
+    }
+
+    pub fn accuracy_meters(&self) -> Option<Meters> {
+        self.accuracy.map(Meters)
+    }
+
+    pub fn altitude_meters(&self) -> Option<Meters> {
+        self.altitude.map(Meters)
+    }
+
+    pub fn vertical_accuracy_meters(&self) -> Option<Meters> {
+        self.vertical_accuracy.map(Meters)
+    }
+
+    pub fn bearing_degrees(&self) -> Option<Degrees> {
+        self.bearing.map(Degrees)
+    }
+
+    pub fn speed_meters_per_second(&self) -> Option<MetersPerSecond> {
+        self.speed.map(MetersPerSecond)
+    }
+
+    pub fn confidence_percent(&self) -> Option<Percent> {
+        self.confidence.map(Percent)
+    }
+}
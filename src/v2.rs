@@ -0,0 +1,267 @@
+//! Semver-stable view of the decoded data.
+//!
+//! [`AmlData`] has public fields so that every new attribute is a breaking change for
+//! code building it with a struct literal, and nothing prevents an out-of-range value.
+//! [`Aml`] wraps it with getters only: it is built by parsing or through an
+//! [`AmlBuilder`] which checks the values. New attributes only add getters.
+//!
+//! # Example
+//!
+//! ```
+//! use aml_lib::v2::Aml;
+//!
+//! let aml = Aml::from_sms_text(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;lc=68;ls=G"#).unwrap();
+//! assert_eq!(aml.position(), Some((48.82639, -2.36619)));
+//! assert_eq!(aml.emergency_number(), Some("112"));
+//!
+//! let built = Aml::builder().position(48.82639, -2.36619).accuracy(20.0).transport("sms").build().unwrap();
+//! assert_eq!(built.accuracy(), Some(20.0));
+//! assert!(Aml::builder().position(91.0, 0.0).build().is_err());
+//! ```
+
+use crate::{AmlData, AmlError, Timestamp};
+
+/// Why an [`AmlBuilder`] cannot build an [`Aml`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BuildError {
+    /// The latitude is not in [-90, 90].
+    LatitudeOutOfRange,
+
+    /// The longitude is not in [-180, 180].
+    LongitudeOutOfRange,
+
+    /// The accuracy or the vertical accuracy is negative or not a number.
+    NegativeAccuracy,
+
+    /// The level of confidence is not in [0, 100].
+    ConfidenceOutOfRange,
+}
+
+impl std::error::Error for BuildError {}
+
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let text = match self {
+            BuildError::LatitudeOutOfRange => "The latitude is not in [-90, 90]",
+            BuildError::LongitudeOutOfRange => "The longitude is not in [-180, 180]",
+            BuildError::NegativeAccuracy => "The accuracy is negative",
+            BuildError::ConfidenceOutOfRange => "The level of confidence is not in [0, 100]",
+        };
+        write!(f, "Error: {}", text)
+    }
+}
+
+macro_rules! getters {
+    ($($(#[$doc: meta])* $name: ident: $field: ident -> f64;)*) => {
+        $(
+            $(#[$doc])*
+            pub fn $name(&self) -> Option<f64> {
+                self.data.$field
+            }
+        )*
+    };
+    ($($(#[$doc: meta])* $name: ident: $field: ident -> str;)*) => {
+        $(
+            $(#[$doc])*
+            pub fn $name(&self) -> Option<&str> {
+                self.data.$field.as_deref()
+            }
+        )*
+    };
+}
+
+/// Decoded AML data with read-only access. See the [module documentation](self).
+#[derive(Debug, Default, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Aml {
+    data: AmlData,
+}
+
+impl Aml {
+    /// Start building an `Aml` from scratch.
+    pub fn builder() -> AmlBuilder {
+        AmlBuilder::default()
+    }
+
+    /// Parse a SMS text. See [`AmlData::from_text_sms`].
+    pub fn from_sms_text(text: &str) -> Result<Self, AmlError> {
+        AmlData::from_text_sms(text).map(Self::from)
+    }
+
+    /// Parse a HTTPS AML message. See [`AmlData::from_https`].
+    pub fn from_https(payload: &str) -> Result<Self, AmlError> {
+        AmlData::from_https(payload).map(Self::from)
+    }
+
+    /// The underlying data, for the functions of the crate taking an [`AmlData`].
+    pub fn data(&self) -> &AmlData {
+        &self.data
+    }
+
+    /// The underlying data, by value.
+    pub fn into_data(self) -> AmlData {
+        self.data
+    }
+
+    /// The (latitude, longitude) of the location, if both are known.
+    pub fn position(&self) -> Option<(f64, f64)> {
+        self.data.position()
+    }
+
+    /// See [`AmlData::beginning_of_call`].
+    pub fn beginning_of_call(&self) -> Option<Timestamp> {
+        self.data.beginning_of_call
+    }
+
+    /// See [`AmlData::time_of_positioning`].
+    pub fn time_of_positioning(&self) -> Option<Timestamp> {
+        self.data.time_of_positioning
+    }
+
+    /// See [`AmlData::transport`].
+    pub fn transport(&self) -> &str {
+        &self.data.transport
+    }
+
+    /// See [`AmlData::is_test_message`].
+    pub fn is_test(&self) -> bool {
+        self.data.is_test_message()
+    }
+
+    getters! {
+        /// See [`AmlData::latitude`].
+        latitude: latitude -> f64;
+        /// See [`AmlData::longitude`].
+        longitude: longitude -> f64;
+        /// See [`AmlData::altitude`].
+        altitude: altitude -> f64;
+        /// See [`AmlData::accuracy`].
+        accuracy: accuracy -> f64;
+        /// See [`AmlData::vertical_accuracy`].
+        vertical_accuracy: vertical_accuracy -> f64;
+        /// See [`AmlData::confidence`].
+        confidence: confidence -> f64;
+        /// See [`AmlData::bearing`].
+        bearing: bearing -> f64;
+        /// See [`AmlData::speed`].
+        speed: speed -> f64;
+    }
+
+    getters! {
+        /// See [`AmlData::version`].
+        version: version -> str;
+        /// See [`AmlData::emergency_number`].
+        emergency_number: emergency_number -> str;
+        /// See [`AmlData::positioning_method`].
+        positioning_method: positioning_method -> str;
+        /// See [`AmlData::device_number`].
+        device_number: device_number -> str;
+        /// See [`AmlData::imsi`].
+        imsi: imsi -> str;
+        /// See [`AmlData::imei`].
+        imei: imei -> str;
+        /// See [`AmlData::languages`].
+        languages: languages -> str;
+    }
+}
+
+impl From<AmlData> for Aml {
+    fn from(data: AmlData) -> Self {
+        Aml { data }
+    }
+}
+
+impl From<Aml> for AmlData {
+    fn from(aml: Aml) -> Self {
+        aml.data
+    }
+}
+
+/// Builder of an [`Aml`], checking the values in [`AmlBuilder::build`].
+#[derive(Debug, Default, Clone)]
+pub struct AmlBuilder {
+    data: AmlData,
+}
+
+impl AmlBuilder {
+    pub fn version(mut self, version: &str) -> Self {
+        self.data.version = Some(version.to_string());
+        self
+    }
+
+    pub fn emergency_number(mut self, emergency_number: &str) -> Self {
+        self.data.emergency_number = Some(emergency_number.to_string());
+        self
+    }
+
+    pub fn beginning_of_call(mut self, beginning_of_call: Timestamp) -> Self {
+        self.data.beginning_of_call = Some(beginning_of_call);
+        self
+    }
+
+    /// Latitude and longitude in WGS84 degrees.
+    pub fn position(mut self, latitude: f64, longitude: f64) -> Self {
+        self.data.latitude = Some(latitude);
+        self.data.longitude = Some(longitude);
+        self
+    }
+
+    pub fn time_of_positioning(mut self, time_of_positioning: Timestamp) -> Self {
+        self.data.time_of_positioning = Some(time_of_positioning);
+        self
+    }
+
+    /// Altitude in meters.
+    pub fn altitude(mut self, altitude: f64) -> Self {
+        self.data.altitude = Some(altitude);
+        self
+    }
+
+    /// Radius of the uncertainty circle in meters.
+    pub fn accuracy(mut self, accuracy: f64) -> Self {
+        self.data.accuracy = Some(accuracy);
+        self
+    }
+
+    /// Vertical uncertainty in meters.
+    pub fn vertical_accuracy(mut self, vertical_accuracy: f64) -> Self {
+        self.data.vertical_accuracy = Some(vertical_accuracy);
+        self
+    }
+
+    /// Level of confidence in percent.
+    pub fn confidence(mut self, confidence: f64) -> Self {
+        self.data.confidence = Some(confidence);
+        self
+    }
+
+    pub fn positioning_method(mut self, positioning_method: &str) -> Self {
+        self.data.positioning_method = Some(positioning_method.to_string());
+        self
+    }
+
+    /// `sms` or `https`.
+    pub fn transport(mut self, transport: &str) -> Self {
+        self.data.transport = transport.to_string();
+        self
+    }
+
+    /// Check the values and build the `Aml`.
+    pub fn build(self) -> Result<Aml, BuildError> {
+        let data = self.data;
+        if data.latitude.is_some_and(|latitude| !(-90.0..=90.0).contains(&latitude)) {
+            return Err(BuildError::LatitudeOutOfRange);
+        }
+        if data.longitude.is_some_and(|longitude| !(-180.0..=180.0).contains(&longitude)) {
+            return Err(BuildError::LongitudeOutOfRange);
+        }
+        if [data.accuracy, data.vertical_accuracy].iter().flatten().any(|accuracy| accuracy.is_nan() || *accuracy < 0.0) {
+            return Err(BuildError::NegativeAccuracy);
+        }
+        if data.confidence.is_some_and(|confidence| !(0.0..=100.0).contains(&confidence)) {
+            return Err(BuildError::ConfidenceOutOfRange);
+        }
+        Ok(Aml { data })
+    }
+}
@@ -0,0 +1,204 @@
+use crate::HeaderDeviation;
+
+/// How the length of an AML SMS is counted when it is compared to the `ml` attribute.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LengthMode {
+    /// Length of the UTF-8 text in bytes.
+    #[default]
+    Bytes,
+
+    /// Number of characters of the text.
+    Chars,
+
+    /// Number of GSM 7-bit septets (extension table characters count twice).
+    Septets,
+
+    /// Any of the above: the length is valid if one of the counts matches.
+    Any,
+}
+
+/// Length of an AML SMS counted in each [`LengthMode`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MessageLengths {
+    pub bytes: i32,
+    pub chars: i32,
+    pub septets: i32,
+}
+
+/// Characters of the GSM 7-bit extension table, sent as an escape and a septet.
+const GSM_EXTENSION: &str = "\u{c}^{}\\[~]|\u{20ac}";
+
+impl MessageLengths {
+    /// The counts of `text`.
+    pub fn of(text: &str) -> Self {
+        MessageLengths {
+            bytes: text.len() as i32,
+            chars: text.chars().count() as i32,
+            septets: text.chars().map(|c| if GSM_EXTENSION.contains(c) { 2 } else { 1 }).sum(),
+        }
+    }
+
+    /// The count in `mode`. With [`LengthMode::Any`], the count equal to `declared`
+    /// if any, the length in bytes otherwise.
+    pub fn get(&self, mode: LengthMode, declared: Option<i32>) -> i32 {
+        match mode {
+            LengthMode::Bytes => self.bytes,
+            LengthMode::Chars => self.chars,
+            LengthMode::Septets => self.septets,
+            LengthMode::Any => [self.bytes, self.chars, self.septets]
+                .iter()
+                .copied()
+                .find(|count| Some(*count) == declared)
+                .unwrap_or(self.bytes),
+        }
+    }
+}
+
+/// A single reason why an AML message is not considered valid.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationFailure {
+    /// The header is not the first attribute of the message.
+    HeaderNotFirst,
+
+    /// (v1) The message length attribute `ml` is missing or is not a number.
+    MissingLength,
+
+    /// (v1) The declared message length differs from the computed one.
+    LengthMismatch { declared: i32, computed: i32 },
+
+    /// Only one of latitude and longitude is present.
+    IncompleteLocation,
+
+    /// Latitude is outside of [-90, 90].
+    LatitudeOutOfRange(f64),
+
+    /// Longitude is outside of [-180, 180].
+    LongitudeOutOfRange(f64),
+
+    /// Level of confidence is outside of [0, 100].
+    ConfidenceOutOfRange(f64),
+
+    /// A field required by the profile of the message is missing.
+    MissingField(&'static str),
+
+    /// The version is not supported by the profile of the message.
+    UnsupportedVersion(String),
+
+    /// The version is not implemented: only the attributes known in v2 have been
+    /// decoded. See [`SmsData::from_text_best_effort`](crate::SmsData::from_text_best_effort).
+    PartialVersion(String),
+}
+
+impl ValidationFailure {
+    /// Short snake case name of the kind of failure, e.g. `length_mismatch`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ValidationFailure::HeaderNotFirst => "header_not_first",
+            ValidationFailure::MissingLength => "missing_length",
+            ValidationFailure::LengthMismatch { .. } => "length_mismatch",
+            ValidationFailure::IncompleteLocation => "incomplete_location",
+            ValidationFailure::LatitudeOutOfRange(_) => "latitude_out_of_range",
+            ValidationFailure::LongitudeOutOfRange(_) => "longitude_out_of_range",
+            ValidationFailure::ConfidenceOutOfRange(_) => "confidence_out_of_range",
+            ValidationFailure::MissingField(_) => "missing_field",
+            ValidationFailure::UnsupportedVersion(_) => "unsupported_version",
+            ValidationFailure::PartialVersion(_) => "partial_version",
+        }
+    }
+}
+
+/// Noise removed at either end of a SMS text before it is parsed. See [`ValidationReport::cleanup`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TextCleanup {
+    /// A byte order mark (U+FEFF), e.g. from a UTF-8 file export.
+    ByteOrderMark,
+
+    /// Whitespace, including CR/LF line endings.
+    Whitespace,
+
+    /// Trailing NUL characters, the padding of fixed-size buffers.
+    TrailingNul,
+
+    /// Other control characters.
+    ControlCharacters,
+}
+
+/// `text` without the BOM, whitespace and control characters at either end, with what
+/// has been removed.
+pub(crate) fn clean_text(text: &str) -> (&str, Vec<TextCleanup>) {
+    let mut cleanup = Vec::new();
+    let kind = |c: char, trailing: bool| match c {
+        '\u{feff}' => Some(TextCleanup::ByteOrderMark),
+        '\0' if trailing => Some(TextCleanup::TrailingNul),
+        c if c.is_whitespace() => Some(TextCleanup::Whitespace),
+        c if c.is_control() => Some(TextCleanup::ControlCharacters),
+        _ => None,
+    };
+
+    let trimmed = text.trim_start_matches(|c| kind(c, false).is_some());
+    cleanup.extend(text[..text.len() - trimmed.len()].chars().filter_map(|c| kind(c, false)));
+    let text = trimmed.trim_end_matches(|c| kind(c, true).is_some());
+    cleanup.extend(trimmed[text.len()..].chars().filter_map(|c| kind(c, true)));
+
+    cleanup.sort();
+    cleanup.dedup();
+    (text, cleanup)
+}
+
+/// Outcome of the validation of an AML message.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ValidationReport {
+    /// (v1) The length declared by the `ml` attribute.
+    pub declared_length: Option<i32>,
+
+    /// (v1) The length computed from the received message.
+    pub computed_length: Option<i32>,
+
+    /// The way `computed_length` has been counted.
+    pub length_mode: LengthMode,
+
+    /// (v1) The length of the received message in all the modes.
+    pub lengths: Option<MessageLengths>,
+
+    /// The header has been found at the beginning of the message.
+    pub header_first: bool,
+
+    /// Noise removed at either end of the text before parsing, which is not a failure.
+    pub cleanup: Vec<TextCleanup>,
+
+    /// Variants of the header normalized before parsing, which are not failures. See
+    /// [`SmsData::from_text_with_header`](crate::SmsData::from_text_with_header).
+    pub header_deviations: Vec<HeaderDeviation>,
+
+    /// All the checks that have failed.
+    pub failures: Vec<ValidationFailure>,
+}
+
+impl ValidationReport {
+    /// True when no check has failed.
+    pub fn is_valid(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Checks of the location values, common to all the message kinds.
+pub(crate) fn check_location(report: &mut ValidationReport, latitude: Option<f64>, longitude: Option<f64>, confidence: Option<f64>) {
+    match (latitude, longitude) {
+        (Some(lat), Some(lon)) => {
+            if !(-90.0..=90.0).contains(&lat) {
+                report.failures.push(ValidationFailure::LatitudeOutOfRange(lat));
+            }
+            if !(-180.0..=180.0).contains(&lon) {
+                report.failures.push(ValidationFailure::LongitudeOutOfRange(lon));
+            }
+        }
+        (None, None) => (),
+        _ => report.failures.push(ValidationFailure::IncompleteLocation),
+    }
+
+    if let Some(lc) = confidence {
+        if !(0.0..=100.0).contains(&lc) {
+            report.failures.push(ValidationFailure::ConfidenceOutOfRange(lc));
+        }
+    }
+}
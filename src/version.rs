@@ -0,0 +1,123 @@
+use crate::{AmlData, AmlError, SmsData};
+
+/// Components of the version of an AML message, e.g. `1.1` for `A"ML=1.1`.
+///
+/// # Example
+///
+/// ```
+/// use aml_lib::AmlVersion;
+///
+/// let version = AmlVersion::parse("1.1").unwrap();
+/// assert_eq!((version.major, version.minor, version.patch), (1, Some(1), None));
+/// assert_eq!(version.to_string(), "1.1");
+/// assert!(AmlVersion::parse("1.x").is_none());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AmlVersion {
+    pub major: u32,
+    pub minor: Option<u32>,
+    pub patch: Option<u32>,
+}
+
+impl AmlVersion {
+    /// Parse `major[.minor[.patch]]`, surrounding spaces ignored.
+    pub fn parse(version: &str) -> Option<Self> {
+        let mut components = version.trim().split('.');
+        let mut component = || components.next().map(|c| c.parse::<u32>().ok());
+
+        let major = component()??;
+        let minor = component().map_or(Some(None), |c| c.map(Some))?;
+        let patch = minor.and(component()).map_or(Some(None), |c| c.map(Some))?;
+        if components.next().is_some() {
+            return None;
+        }
+        Some(AmlVersion { major, minor, patch })
+    }
+
+    /// True if the version is `major` itself, with zero minor and patch if any.
+    pub fn is_exactly(&self, major: u32) -> bool {
+        self.major == major && self.minor.unwrap_or(0) == 0 && self.patch.unwrap_or(0) == 0
+    }
+}
+
+impl std::fmt::Display for AmlVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.major)?;
+        if let Some(minor) = self.minor {
+            write!(f, ".{}", minor)?;
+        }
+        if let Some(patch) = self.patch {
+            write!(f, ".{}", patch)?;
+        }
+        Ok(())
+    }
+}
+
+/// How the version of a SMS header selects the v1 or v2 parser.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum VersionPolicy {
+    /// Only `1` and `2`, as [`SmsData::from_text`].
+    #[default]
+    Reject,
+
+    /// Versions equal to `1` or `2`: `1.0` and `2.0.0` are accepted, `1.1` is not.
+    Exact,
+
+    /// Any version of major 1 or 2: `1.1` is parsed as v1.
+    MajorCompatible,
+}
+
+impl VersionPolicy {
+    /// The major version whose parser reads `header`, if any.
+    pub(crate) fn select(&self, header: &str) -> Option<u32> {
+        let version = match self {
+            VersionPolicy::Reject => return ["1", "2"].iter().position(|v| *v == header).map(|i| i as u32 + 1),
+            _ => AmlVersion::parse(header)?,
+        };
+        let accepted = match self {
+            VersionPolicy::Exact => version.is_exactly(version.major),
+            _ => true,
+        };
+        Some(version.major).filter(|major| accepted && (*major == 1 || *major == 2))
+    }
+}
+
+impl SmsData {
+    /// Parse a SMS text like [`SmsData::from_text`], the header selecting the parser
+    /// according to `policy`. The header is kept as received in [`SmsData::header`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::{SmsData, VersionPolicy};
+    ///
+    /// let text = r#"A"ML=1.1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;lc=68;pm=G;ml=74"#;
+    /// assert!(SmsData::from_text(text).is_err());
+    /// assert!(SmsData::from_text_with_version(text, VersionPolicy::Exact).is_err());
+    ///
+    /// let sms = SmsData::from_text_with_version(text, VersionPolicy::MajorCompatible).unwrap();
+    /// assert_eq!((sms.header.as_deref(), sms.latitude), (Some("1.1"), Some(48.82639)));
+    /// assert_eq!(sms.version().unwrap().minor, Some(1));
+    /// assert!(sms.is_valid());
+    /// ```
+    pub fn from_text_with_version<S: AsRef<str>>(text_sms: S, policy: VersionPolicy) -> Result<Self, AmlError> {
+        Self::parse_text_with(text_sms.as_ref(), policy, false)
+    }
+
+    /// The components of [`SmsData::header`], if it is a version number.
+    pub fn version(&self) -> Option<AmlVersion> {
+        self.header.as_deref().and_then(AmlVersion::parse)
+    }
+}
+
+impl AmlData {
+    /// Parse a SMS text with a version `policy`. See [`SmsData::from_text_with_version`].
+    pub fn from_text_sms_with_version<S: AsRef<str>>(text_sms: S, policy: VersionPolicy) -> Result<Self, AmlError> {
+        Ok(SmsData::from_text_with_version(text_sms, policy)?.into())
+    }
+
+    /// The components of [`AmlData::version`], if it is a version number.
+    pub fn version_components(&self) -> Option<AmlVersion> {
+        self.version.as_deref().and_then(AmlVersion::parse)
+    }
+}
@@ -0,0 +1,68 @@
+//! WebAssembly bindings, with [`wasm_bindgen`], to decode AML payloads in a browser.
+//!
+//! The decoders return plain JS objects with the members of [`AmlData::to_json`], and
+//! throw an `Error` carrying the message of the [`AmlError`] if the payload is rejected.
+//!
+//! ```js
+//! import init, { fromTextSms, fromHttps } from "./pkg/aml_lib.js";
+//!
+//! await init();
+//! const aml = fromTextSms('A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;ls=G');
+//! console.log(aml.latitude, aml.transport);
+//! try {
+//!     fromHttps(pasted);
+//! } catch (error) {
+//!     console.error(error.message);
+//! }
+//! ```
+//!
+//! Build the module and its JS glue with:
+//!
+//! ```sh
+//! cargo rustc --release --lib --target wasm32-unknown-unknown --features wasm --crate-type cdylib
+//! wasm-bindgen --target web --out-dir pkg target/wasm32-unknown-unknown/release/aml_lib.wasm
+//! ```
+
+use crate::{json::JsonValue, AmlData, AmlError};
+use ::js_sys::{Array, Object, Reflect};
+use ::wasm_bindgen::prelude::*;
+
+/// Decode a text SMS. See [`AmlData::from_text_sms`].
+#[wasm_bindgen(js_name = fromTextSms)]
+pub fn from_text_sms(text_sms: &str) -> Result<JsValue, JsError> {
+    respond(AmlData::from_text_sms(text_sms))
+}
+
+/// Decode a base64 encoded binary SMS. See [`AmlData::from_base64_sms`].
+#[wasm_bindgen(js_name = fromBase64Sms)]
+pub fn from_base64_sms(base64_sms: &str) -> Result<JsValue, JsError> {
+    respond(AmlData::from_base64_sms(base64_sms.trim()))
+}
+
+/// Decode a HTTPS message. See [`AmlData::from_https`].
+#[wasm_bindgen(js_name = fromHttps)]
+pub fn from_https(payload: &str) -> Result<JsValue, JsError> {
+    respond(AmlData::from_https(payload))
+}
+
+fn respond(result: Result<AmlData, AmlError>) -> Result<JsValue, JsError> {
+    result.map(|aml| to_js(&aml.to_json_value())).map_err(|err| JsError::new(&err.to_string()))
+}
+
+fn to_js(value: &JsonValue) -> JsValue {
+    match value {
+        JsonValue::Null => JsValue::NULL,
+        JsonValue::Bool(b) => JsValue::from_bool(*b),
+        JsonValue::Number(n) => JsValue::from_f64(*n),
+        JsonValue::String(s) => JsValue::from_str(s),
+        JsonValue::Array(items) => items.iter().map(to_js).collect::<Array>().into(),
+        JsonValue::Object(members) => {
+            let object = Object::new();
+            for (name, member) in members {
+                // Setting a data property of a plain object cannot fail
+                let _ = Reflect::set(&object, &JsValue::from_str(name), &to_js(member));
+            }
+            object.into()
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! Building blocks for HTTPS AML endpoints, independent of the web framework:
+//! a [`KeyRing`] holding the HMAC keys of the senders and [`AmlPayload::from_body`]
+//! which authenticates and parses a request body, or tells which status to answer.
+//! An [`Endpoint`] gathers the keys with the strictness and the limits of the bodies.
+//!
+//! # axum
+//!
+//! With the `axum` feature, [`AmlPayload`] is an extractor. See [`axum`](self::axum).
+//!
+//! # actix-web
+//!
+//! With the `actix` feature, [`AmlPayload`] is an extractor and a middleware verifies
+//! the HMAC. See [`actix`].
+//!
+//! # warp
+//!
+//! With the `warp` feature, a filter yields [`AmlPayload`]. See [`warp`](self::warp).
+//!
+//! # Rocket
+//!
+//! With the `rocket` feature, [`AmlPayload`] and [`HttpsData`] are data guards. See
+//! [`rocket`](self::rocket).
+
+#[cfg(feature = "actix")]
+pub mod actix;
+#[cfg(feature = "axum")]
+pub mod axum;
+#[cfg(feature = "rocket")]
+pub mod rocket;
+#[cfg(feature = "warp")]
+pub mod warp;
+
+pub use crate::openapi::openapi;
+use crate::{AmlError, HttpsData, Limits};
+
+/// The HMAC keys accepted by an endpoint, each one identified (e.g. by operator).
+#[derive(Debug, Clone, Default)]
+pub struct KeyRing {
+    keys: Vec<(String, Vec<u8>)>,
+}
+
+impl KeyRing {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add a key identified by `id`.
+    pub fn add<S: Into<String>, K: Into<Vec<u8>>>(&mut self, id: S, key: K) -> &mut Self {
+        self.keys.push((id.into(), key.into()));
+        self
+    }
+
+    /// True if no key has been added.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Identifier of the first key authenticating `payload`. See [`HttpsData::is_authenticated`].
+    pub fn verify<S: AsRef<str>>(&self, payload: S) -> Option<&str> {
+        self.keys
+            .iter()
+            .find(|(_, key)| HttpsData::is_authenticated(payload.as_ref(), key))
+            .map(|(id, _)| id.as_str())
+    }
+}
+
+/// How unauthenticated messages are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strictness {
+    /// Messages must be authenticated by a key of the key ring.
+    #[default]
+    Strict,
+
+    /// Messages without `hmac` field are accepted, without key identifier. A message
+    /// with a `hmac` must still be authenticated by a key of the key ring.
+    /// Meant for test platforms and for senders which do not sign their messages yet.
+    Lenient,
+}
+
+/// What an endpoint accepts, as used by the extractors of the web frameworks.
+#[derive(Debug, Clone, Default)]
+pub struct Endpoint {
+    /// Keys authenticating the messages.
+    pub keys: KeyRing,
+
+    /// Whether unauthenticated messages are accepted. Strict by default.
+    pub strictness: Strictness,
+
+    /// Limits of the bodies, larger ones being refused (413) before authentication.
+    pub limits: Limits,
+}
+
+impl Endpoint {
+    /// Accept the messages authenticated by `keys`, with the default limits.
+    pub fn new(keys: KeyRing) -> Self {
+        Endpoint { keys, ..Default::default() }
+    }
+
+    /// Authenticate and parse `body`. See [`AmlPayload::from_body_limited`].
+    pub fn accept(&self, body: &[u8]) -> Result<AmlPayload, Rejection> {
+        AmlPayload::from_body_limited(body, &self.keys, self.strictness, &self.limits)
+    }
+}
+
+/// Why a request body has been refused, with the HTTP status to answer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rejection {
+    /// The body is empty (400).
+    EmptyBody,
+
+    /// The body is not UTF-8 text (400).
+    InvalidBody,
+
+    /// The body has no `hmac` field or no key of the key ring authenticates it (401).
+    Unauthenticated,
+
+    /// The body exceeds the [`Limits`] of the endpoint (413).
+    TooLarge,
+}
+
+impl Rejection {
+    /// HTTP status code to answer.
+    pub fn status_code(&self) -> u16 {
+        match self {
+            Rejection::EmptyBody | Rejection::InvalidBody => 400,
+            Rejection::Unauthenticated => 401,
+            Rejection::TooLarge => 413,
+        }
+    }
+}
+
+impl std::error::Error for Rejection {}
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let text = match self {
+            Rejection::EmptyBody => "The request body is empty",
+            Rejection::InvalidBody => "The request body is not UTF-8 text",
+            Rejection::Unauthenticated => "The request is not authenticated",
+            Rejection::TooLarge => "The request body exceeds the limits of the endpoint",
+        };
+        write!(f, "Error: {}", text)
+    }
+}
+
+/// An authenticated and parsed HTTPS AML message, as produced by web framework extractors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AmlPayload {
+    /// The parsed message.
+    pub data: HttpsData,
+
+    /// Identifier of the key which has authenticated the message,
+    /// `None` for a message accepted with [`Strictness::Lenient`].
+    pub key_id: Option<String>,
+}
+
+impl AmlPayload {
+    /// Authenticate `body` with `keys` and parse it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::web::{AmlPayload, KeyRing, Rejection};
+    ///
+    /// let mut keys = KeyRing::new();
+    /// keys.add("operator-a", "AML");
+    ///
+    /// let body = b"v=1&device_number=%2B33611223344&location_latitude=0.85732&location_longitude=-4.26325&location_time=1604912121000&location_accuracy=10.4&location_source=GPS&location_certainty=83&hmac=f64c70eb238bb239e00e8ac8c023bf2b5d3c41dd";
+    /// let payload = AmlPayload::from_body(body, &keys).unwrap();
+    /// assert_eq!(payload.key_id.as_deref(), Some("operator-a"));
+    ///
+    /// let rejection = AmlPayload::from_body(b"v=1&hmac=00", &keys).unwrap_err();
+    /// assert_eq!(rejection.status_code(), 401);
+    /// ```
+    pub fn from_body(body: &[u8], keys: &KeyRing) -> Result<Self, Rejection> {
+        Self::from_body_with(body, keys, Strictness::Strict)
+    }
+
+    /// Same as [`AmlPayload::from_body`] with the given `strictness`.
+    pub fn from_body_with(body: &[u8], keys: &KeyRing, strictness: Strictness) -> Result<Self, Rejection> {
+        let unlimited = Limits {
+            max_payload_length: usize::MAX,
+            max_properties: usize::MAX,
+            max_value_length: usize::MAX,
+        };
+        Self::from_body_limited(body, keys, strictness, &unlimited)
+    }
+
+    /// Same as [`AmlPayload::from_body_with`], the body being refused before being
+    /// authenticated if it exceeds `limits`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aml_lib::web::{AmlPayload, KeyRing, Rejection, Strictness};
+    /// use aml_lib::Limits;
+    ///
+    /// let body = format!("v=1&location_latitude=0.85732&device_model={}", "x".repeat(1000));
+    /// let rejection = AmlPayload::from_body_limited(body.as_bytes(), &KeyRing::new(), Strictness::Lenient, &Limits::default());
+    /// assert_eq!(rejection, Err(Rejection::TooLarge));
+    /// ```
+    pub fn from_body_limited(body: &[u8], keys: &KeyRing, strictness: Strictness, limits: &Limits) -> Result<Self, Rejection> {
+        if body.is_empty() {
+            return Err(Rejection::EmptyBody);
+        }
+        let payload = std::str::from_utf8(body).map_err(|_| Rejection::InvalidBody)?.trim();
+        limits.check(payload, b'&').map_err(|_: AmlError| Rejection::TooLarge)?;
+        let key_id = keys.verify(payload).map(str::to_string);
+        let data = HttpsData::from_urlencoded(payload);
+        if key_id.is_none() && (strictness == Strictness::Strict || data.hmac.is_some()) {
+            return Err(Rejection::Unauthenticated);
+        }
+
+        Ok(AmlPayload { key_id, data })
+    }
+}
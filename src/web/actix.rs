@@ -0,0 +1,87 @@
+//! actix-web support, the [`Endpoint`] being in the app data as `web::Data<Endpoint>`:
+//! [`AmlPayload`] is an extractor and [`verify_hmac`] a middleware refusing the
+//! requests whose body is not accepted by the endpoint.
+//!
+//! # Example
+//!
+//! ```
+//! use actix_web::{middleware, web, App, HttpResponse};
+//! use aml_lib::web::{actix::verify_hmac, AmlPayload, Endpoint, KeyRing};
+//!
+//! async fn receive(payload: AmlPayload) -> HttpResponse {
+//!     println!("{:?} {:?}", payload.key_id, payload.data.location_latitude);
+//!     HttpResponse::Ok().finish()
+//! }
+//!
+//! let mut keys = KeyRing::new();
+//! keys.add("operator-a", "AML");
+//! let endpoint = web::Data::new(Endpoint::new(keys));
+//!
+//! let app = App::new().app_data(endpoint).service(
+//!     web::resource("/aml")
+//!         .wrap(middleware::from_fn(verify_hmac))
+//!         .route(web::post().to(receive)),
+//! );
+//! ```
+
+use super::{AmlPayload, Endpoint, Rejection};
+use ::actix_web::body::{EitherBody, MessageBody};
+use ::actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use ::actix_web::error::ErrorInternalServerError;
+use ::actix_web::http::StatusCode;
+use ::actix_web::middleware::Next;
+use ::actix_web::web::{self, Bytes};
+use ::actix_web::{Error, FromRequest, HttpMessage, HttpRequest, ResponseError};
+use std::future::Future;
+use std::pin::Pin;
+
+/// The status of the rejection, with its text.
+impl ResponseError for Rejection {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(Rejection::status_code(self)).unwrap_or(StatusCode::BAD_REQUEST)
+    }
+}
+
+/// Read the body, up to the payload length of the [`Endpoint::limits`], and accept it.
+async fn accept(req: HttpRequest, mut payload: Payload) -> Result<(AmlPayload, Bytes), Error> {
+    let endpoint = req
+        .app_data::<web::Data<Endpoint>>()
+        .cloned()
+        .ok_or_else(|| ErrorInternalServerError("No aml_lib::web::Endpoint in the app data"))?;
+    let body = web::Payload::from_request(&req, &mut payload)
+        .await?
+        .to_bytes_limited(endpoint.limits.max_payload_length)
+        .await
+        .map_err(|_| Rejection::TooLarge)??;
+    Ok((endpoint.accept(&body)?, body))
+}
+
+impl FromRequest for AmlPayload {
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    /// The message accepted by [`verify_hmac`], or else the body accepted by the
+    /// [`Endpoint`]. See [`Endpoint::accept`].
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        if let Some(accepted) = req.extensions_mut().remove::<AmlPayload>() {
+            return Box::pin(async move { Ok(accepted) });
+        }
+        let accepted = accept(req.clone(), payload.take());
+        Box::pin(async move { Ok(accepted.await?.0) })
+    }
+}
+
+/// Middleware answering the status of the [`Rejection`] to the requests whose body is
+/// not accepted by the [`Endpoint`], to be wrapped with `actix_web::middleware::from_fn`.
+/// The body is handed to the service, and the accepted message is kept for the
+/// [`AmlPayload`] extractor.
+pub async fn verify_hmac<B: MessageBody + 'static>(mut req: ServiceRequest, next: Next<B>) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    match accept(req.request().clone(), req.take_payload()).await {
+        Ok((accepted, body)) => {
+            req.set_payload(Payload::from(body));
+            req.extensions_mut().insert(accepted);
+            next.call(req).await.map(ServiceResponse::map_into_left_body)
+        }
+        Err(err) => Ok(req.error_response(err).map_into_right_body()),
+    }
+}
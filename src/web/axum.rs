@@ -0,0 +1,55 @@
+//! [`AmlPayload`] as an axum extractor, the [`Endpoint`] being in the state of the router.
+//!
+//! # Example
+//!
+//! ```
+//! use aml_lib::web::{AmlPayload, Endpoint, KeyRing};
+//! use axum::{routing::post, Router};
+//! use std::sync::Arc;
+//!
+//! async fn receive(payload: AmlPayload) -> &'static str {
+//!     println!("{:?} {:?}", payload.key_id, payload.data.location_latitude);
+//!     "OK"
+//! }
+//!
+//! let mut keys = KeyRing::new();
+//! keys.add("operator-a", "AML");
+//!
+//! let app: Router = Router::new()
+//!     .route("/aml", post(receive))
+//!     .with_state(Arc::new(Endpoint::new(keys)));
+//! ```
+
+use super::{AmlPayload, Endpoint, Rejection};
+use ::axum::body;
+use ::axum::extract::{FromRef, FromRequest, Request};
+use ::axum::http::StatusCode;
+use ::axum::response::{IntoResponse, Response};
+use std::sync::Arc;
+
+impl<S> FromRequest<S> for AmlPayload
+where
+    Arc<Endpoint>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Rejection;
+
+    /// Read the body, up to the payload length of the [`Endpoint::limits`], and accept it.
+    /// See [`Endpoint::accept`].
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let endpoint = Arc::<Endpoint>::from_ref(state);
+        // The body is only read up to the limit, a longer one is an error
+        let body = body::to_bytes(req.into_body(), endpoint.limits.max_payload_length)
+            .await
+            .map_err(|_| Rejection::TooLarge)?;
+        endpoint.accept(&body)
+    }
+}
+
+/// The status of the rejection, with its text.
+impl IntoResponse for Rejection {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.status_code()).unwrap_or(StatusCode::BAD_REQUEST);
+        (status, self.to_string()).into_response()
+    }
+}
@@ -0,0 +1,76 @@
+//! Rocket data guards: [`AmlPayload`], and [`HttpsData`] for the routes which do not
+//! need the key identifier. The [`Endpoint`] must be managed by the instance, the
+//! launch being aborted otherwise.
+//!
+//! # Example
+//!
+//! ```
+//! use aml_lib::web::{AmlPayload, Endpoint, KeyRing};
+//! use rocket::{post, routes};
+//!
+//! #[post("/aml", data = "<payload>")]
+//! fn receive(payload: AmlPayload) -> &'static str {
+//!     println!("{:?} {:?}", payload.key_id, payload.data.location_latitude);
+//!     "OK"
+//! }
+//!
+//! let mut keys = KeyRing::new();
+//! keys.add("operator-a", "AML");
+//!
+//! let rocket = rocket::build().manage(Endpoint::new(keys)).mount("/", routes![receive]);
+//! ```
+
+use super::{AmlPayload, Endpoint, Rejection};
+use crate::HttpsData;
+use ::rocket::data::{self, Data, FromData, ToByteUnit};
+use ::rocket::http::Status;
+use ::rocket::outcome::Outcome;
+use ::rocket::request::Request;
+use ::rocket::{Ignite, Rocket, Sentinel};
+
+#[::rocket::async_trait]
+impl<'r> FromData<'r> for AmlPayload {
+    type Error = Rejection;
+
+    /// Read the body, up to the payload length of the [`Endpoint::limits`], and accept it.
+    /// See [`Endpoint::accept`].
+    async fn from_data(req: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        let endpoint = match req.rocket().state::<Endpoint>() {
+            Some(endpoint) => endpoint,
+            None => return Outcome::Forward((data, Status::InternalServerError)),
+        };
+        let body = match data.open(endpoint.limits.max_payload_length.bytes()).into_bytes().await {
+            Ok(body) if body.is_complete() => body.into_inner(),
+            Ok(_) => return Outcome::Error((Status::PayloadTooLarge, Rejection::TooLarge)),
+            Err(_) => return Outcome::Error((Status::BadRequest, Rejection::InvalidBody)),
+        };
+        match endpoint.accept(&body) {
+            Ok(payload) => Outcome::Success(payload),
+            Err(rejection) => Outcome::Error((Status::new(rejection.status_code()), rejection)),
+        }
+    }
+}
+
+#[::rocket::async_trait]
+impl<'r> FromData<'r> for HttpsData {
+    type Error = Rejection;
+
+    /// The data of the [`AmlPayload`] guard.
+    async fn from_data(req: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        AmlPayload::from_data(req, data).await.map(|payload| payload.data)
+    }
+}
+
+/// The launch is aborted if no [`Endpoint`] is managed.
+impl Sentinel for AmlPayload {
+    fn abort(rocket: &Rocket<Ignite>) -> bool {
+        rocket.state::<Endpoint>().is_none()
+    }
+}
+
+/// The launch is aborted if no [`Endpoint`] is managed.
+impl Sentinel for HttpsData {
+    fn abort(rocket: &Rocket<Ignite>) -> bool {
+        AmlPayload::abort(rocket)
+    }
+}
@@ -0,0 +1,74 @@
+//! A warp filter extracting [`AmlPayload`] from the request body, with the keys, the
+//! strictness and the limits of an [`Endpoint`].
+//!
+//! # Example
+//!
+//! ```
+//! use aml_lib::web::warp::{payload, recover};
+//! use aml_lib::web::{AmlPayload, Endpoint, KeyRing, Strictness};
+//! use warp::Filter;
+//!
+//! let mut keys = KeyRing::new();
+//! keys.add("operator-a", "AML");
+//! let endpoint = Endpoint { strictness: Strictness::Lenient, ..Endpoint::new(keys) };
+//!
+//! let routes = warp::post()
+//!     .and(warp::path("aml"))
+//!     .and(payload(endpoint))
+//!     .map(|payload: AmlPayload| {
+//!         println!("{:?} {:?}", payload.key_id, payload.data.location_latitude);
+//!         "OK"
+//!     })
+//!     .recover(recover);
+//! ```
+
+use super::{AmlPayload, Endpoint, Rejection};
+use ::warp::http::StatusCode;
+use ::warp::{reject, reply, Buf, Filter, Reply, Stream};
+use std::sync::Arc;
+
+impl reject::Reject for Rejection {}
+
+/// Read the body, up to the payload length of the [`Endpoint::limits`], and accept it.
+/// See [`Endpoint::accept`]. The refused bodies are rejected with a [`Rejection`],
+/// answered by [`recover`].
+pub fn payload(endpoint: Endpoint) -> impl Filter<Extract = (AmlPayload,), Error = ::warp::Rejection> + Clone {
+    let endpoint = Arc::new(endpoint);
+    ::warp::body::stream().and_then(move |body| {
+        let endpoint = endpoint.clone();
+        async move {
+            let body = read(body, endpoint.limits.max_payload_length).await.map_err(reject::custom)?;
+            endpoint.accept(&body).map_err(reject::custom)
+        }
+    })
+}
+
+/// The body, only read up to `limit` bytes: a longer one is an error.
+async fn read<S: Stream<Item = Result<B, ::warp::Error>>, B: Buf>(body: S, limit: usize) -> Result<Vec<u8>, Rejection> {
+    let mut body = Box::pin(body);
+    let mut bytes = Vec::new();
+    while let Some(chunk) = std::future::poll_fn(|cx| body.as_mut().poll_next(cx)).await {
+        let mut chunk = chunk.map_err(|_| Rejection::InvalidBody)?;
+        if bytes.len() + chunk.remaining() > limit {
+            return Err(Rejection::TooLarge);
+        }
+        while chunk.has_remaining() {
+            let len = chunk.chunk().len();
+            bytes.extend_from_slice(chunk.chunk());
+            chunk.advance(len);
+        }
+    }
+    Ok(bytes)
+}
+
+/// Answer a [`Rejection`] of [`payload`] with its status and text, for `Filter::recover`.
+/// Other rejections are passed on.
+pub async fn recover(rejection: ::warp::Rejection) -> Result<impl Reply, ::warp::Rejection> {
+    match rejection.find::<Rejection>() {
+        Some(refused) => {
+            let status = StatusCode::from_u16(refused.status_code()).unwrap_or(StatusCode::BAD_REQUEST);
+            Ok(reply::with_status(refused.to_string(), status))
+        }
+        None => Err(rejection),
+    }
+}
@@ -1,4 +1,4 @@
-use aml_lib::{AmlData, SmsData, HttpsData};
+use aml_lib::{timestamp, AmlData, Deduplicator, Duration, ExpiryPolicy, HttpsData, MapProvider, Profile, Redaction, RedactionPolicy, RetentionPolicy, SessionAggregator, SmsData, Tolerance, Transition, ValidationFailure};
 
 #[test]
 fn from_text_sms() {
@@ -29,12 +29,35 @@ fn sms_is_validate() {
 
     let sms_data = SmsData::from_text(&sms_text);
     if let Ok(sms) = sms_data {
-        assert!(sms.is_validated, "SMS is not valid : {:?}", sms);
+        assert!(sms.is_valid(), "SMS is not valid : {:?}", sms);
+        assert_eq!(sms.validation.declared_length, Some(126));
+        assert_eq!(sms.validation.computed_length, Some(126));
     } else {
         panic!("Error text SMS");
     }
 }
 
+#[test]
+fn sms_validation_report() {
+    let sms_text = r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;lc=68;pm=G;si=208201771948415;ei=353472104343540;mcc=208;mnc=20;ml=130"#;
+
+    let sms = SmsData::from_text(sms_text).unwrap();
+    assert!(!sms.is_valid());
+    assert_eq!(
+        sms.validation.failures,
+        vec![ValidationFailure::LengthMismatch { declared: 130, computed: 126 }]
+    );
+
+    let sms = SmsData::from_text(r#"lo=91.5,2.0;A"ML=2"#).unwrap();
+    assert!(!sms.validation.header_first);
+    assert!(sms.validation.failures.contains(&ValidationFailure::HeaderNotFirst));
+    assert!(sms.validation.failures.contains(&ValidationFailure::LatitudeOutOfRange(91.5)));
+
+    // Elements are split on their first '=' only, elements without '=' are ignored
+    let sms = SmsData::from_text(r#"A"ML=2;en=11=2;ls;lo=48.82639,-2.36619,20"#).unwrap();
+    assert_eq!((sms.emergency_number.as_deref(), sms.latitude), (Some("11=2"), Some(48.82639)));
+}
+
 #[test]
 fn from_text_sms_v2() {
     let sms_text = String::from(
@@ -77,7 +100,7 @@ fn from_data_sms() {
 fn from_https() {
     let https = r#"v=1&device_number=%2B447477593102&location_latitude=55.85732&location_longitude=-4.26325&location_time=1476189444435&location_accuracy=10.4&location_source=GPS&location_certainty=83&location_altitude=0.0&location_floor=5&device_model=ABC+ABC+Detente+530&device_imei=354773072099116&device_imsi=234159176307582&device_os=AOS&cell_carrier=&cell_home_mcc=234&cell_home_mnc=15&cell_network_mcc=234&cell_network_mnc=15&cell_id=0213454321"#;
 
-    let aml = AmlData::from_https(&https).unwrap();
+    let aml = AmlData::from_https(https).unwrap();
     assert!(
         aml.positioning_method == Some("gps".to_string()),
         "Parsing failed : {:?}",
@@ -90,4 +113,2673 @@ fn authenticate() {
     let https = String::from(r#"v=1&device_number=%2B33611223344&location_latitude=0.85732&location_longitude=-4.26325&location_time=1604912121000&location_accuracy=10.4&location_source=GPS&location_certainty=83&hmac=f64c70eb238bb239e00e8ac8c023bf2b5d3c41dd"#);
 
     assert!(HttpsData::is_authenticated(https, "AML".as_bytes()));
+}
+
+#[test]
+fn authentication_failures() {
+    use aml_lib::AuthError;
+
+    let message = "v=1&location_latitude=0.85732&location_longitude=-4.26325";
+    let hmac = "d2cfe0a9a3b8b6e8f3b42a6b2b7f0e1a5c7d9e11";
+
+    assert_eq!(HttpsData::authenticate(message, b"AML"), Err(AuthError::MissingHmac));
+    assert_eq!(HttpsData::authenticate(format!("{}&hmac=xyz", message), b"AML"), Err(AuthError::MalformedHmac));
+    assert_eq!(
+        HttpsData::authenticate(format!("{}&hmac={}&hmac={}", message, hmac, hmac), b"AML"),
+        Err(AuthError::MultipleHmacFields)
+    );
+    assert_eq!(
+        HttpsData::authenticate(format!("{}&hmac={}", message, hmac), b"KEY"),
+        Err(AuthError::Mismatch { expected_len: 40 })
+    );
+}
+#[test]
+fn location_expiry() {
+    let sms_text = r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;ls=G"#;
+    let aml = AmlData::from_text_sms(sms_text).unwrap();
+    let call = timestamp::from_unix(1593187189).unwrap();
+
+    assert_eq!(aml.location_age(call + Duration::minutes(5)), Some(Duration::seconds(270)));
+
+    let policy = ExpiryPolicy {
+        max_age: Duration::minutes(10),
+        ..Default::default()
+    };
+    assert!(!aml.is_expired_at(call + Duration::minutes(5), &policy));
+    assert!(aml.is_expired_at(call + Duration::minutes(15), &policy));
+    assert!(AmlData::default().is_expired_at(call, &policy));
+}
+
+#[test]
+fn geodesic_distance() {
+    use aml_lib::geo;
+
+    // Flinders Peak -> Buninyong, reference value of Vincenty's paper
+    let d = geo::vincenty_distance((-37.951_033_42, 144.424_867_89), (-37.652_821_14, 143.926_495_53)).unwrap();
+    assert!((d - 54_972.271).abs() < 0.01, "distance: {}", d);
+    assert!((geo::haversine_distance((-37.951_033_42, 144.424_867_89), (-37.652_821_14, 143.926_495_53)) - d).abs() < 200.0);
+
+    let a = AmlData::from_https("location_latitude=48.85341&location_longitude=2.3488").unwrap();
+    let b = AmlData::from_https("location_latitude=48.86341&location_longitude=2.3488").unwrap();
+    assert!((a.distance_to(&b).unwrap() - 1111.0).abs() < 5.0);
+    assert!(a.bearing_to(&b).unwrap() < 1e-9);
+    assert_eq!(a.distance_to(&AmlData::default()), None);
+
+    let https = HttpsData::from_urlencoded("location_latitude=48.85341&location_longitude=2.3488&gt_location_latitude=48.85341&gt_location_longitude=2.3488");
+    assert_eq!(https.ground_truth_error(), Some(0.0));
+}
+
+#[test]
+fn uncertainty_polygon() {
+    use aml_lib::geo;
+
+    let aml = AmlData::from_text_sms(r#"A"ML=2;lo=48.85341,2.3488,100;lc=68"#).unwrap();
+    let center = aml.position().unwrap();
+
+    let polygon = aml.uncertainty_polygon(16).unwrap();
+    assert_eq!(polygon.len(), 17);
+    assert_eq!(polygon.first(), polygon.last());
+    for vertex in &polygon {
+        assert!((geo::haversine_distance(center, *vertex) - 100.0).abs() < 0.01);
+    }
+
+    let wider = aml.uncertainty_polygon_at(16, 0.95).unwrap();
+    assert!(geo::haversine_distance(center, wider[0]) > 150.0);
+    assert_eq!(AmlData::default().uncertainty_polygon(16), None);
+    for target in [1.0, 1.5, 0.0, f64::NAN] {
+        assert_eq!(aml.uncertainty_polygon_at(16, target), None);
+    }
+}
+
+#[test]
+fn geofence_containment() {
+    use aml_lib::geo::{self, Containment};
+
+    // Roughly 11 km x 7 km around (48.5, 2.5)
+    let area = vec![(48.45, 2.45), (48.55, 2.45), (48.55, 2.55), (48.45, 2.55)];
+    assert!(geo::contains(&area, (48.5, 2.5)));
+    assert!(!geo::contains(&area, (48.6, 2.5)));
+
+    let at = |lat: f64, lon: f64, acc: f64| {
+        AmlData::from_https(&format!("location_latitude={}&location_longitude={}&location_accuracy={}", lat, lon, acc)).unwrap()
+    };
+    assert_eq!(at(48.5, 2.5, 100.0).is_within(&area), Some(Containment::Within));
+    assert_eq!(at(48.5, 2.5, 5000.0).is_within(&area), Some(Containment::Partial { center_inside: true }));
+    assert_eq!(at(48.551, 2.5, 500.0).is_within(&area), Some(Containment::Partial { center_inside: false }));
+    assert_eq!(at(48.6, 2.5, 500.0).is_within(&area), Some(Containment::Outside));
+    assert_eq!(AmlData::default().is_within(&area), None);
+}
+
+#[test]
+fn utm_and_mgrs() {
+    let aml = AmlData::from_https("location_latitude=48.8583&location_longitude=2.2945").unwrap();
+    let utm = aml.utm().unwrap();
+    assert_eq!((utm.zone, utm.band), (31, 'U'));
+    assert!((utm.easting - 448_251.0).abs() < 2.0, "{}", utm);
+    assert!((utm.northing - 5_411_943.0).abs() < 2.0, "{}", utm);
+    assert_eq!(aml.mgrs(5).unwrap(), "31U DQ 48251 11943");
+    assert_eq!(aml.mgrs(1).unwrap(), "31U DQ 4 1");
+
+    // Southern hemisphere, even zone
+    let aml = AmlData::from_https("location_latitude=-33.8568&location_longitude=151.2153").unwrap();
+    assert_eq!(aml.mgrs(2).unwrap(), "56H LH 34 52");
+    assert_eq!(AmlData::from_https("location_latitude=85.0&location_longitude=0.0").unwrap().utm(), None);
+}
+
+#[test]
+fn geohash_from_accuracy() {
+    use aml_lib::geo;
+
+    assert_eq!(geo::geohash_precision(Some(20.0)), 8);
+    assert_eq!(geo::geohash_precision(Some(2000.0)), 5);
+    assert_eq!(geo::geohash_precision(None), 9);
+
+    let aml = AmlData::from_https("location_latitude=48.8583&location_longitude=2.2945&location_accuracy=100").unwrap();
+    assert_eq!(aml.geohash().unwrap(), "u09tunq");
+    assert_eq!(aml.geohash_with_precision(5).unwrap(), "u09tu");
+    assert_eq!(AmlData::default().geohash(), None);
+}
+
+#[test]
+fn reverse_geocoding() {
+    use aml_lib::{AsyncReverseGeocoder, CivicAddress};
+    use std::future::Future;
+    use std::task::{Context, Poll, Waker};
+
+    struct Lookup;
+
+    impl AsyncReverseGeocoder for Lookup {
+        type Error = String;
+
+        async fn reverse_geocode(&self, latitude: f64, _: f64) -> Result<Option<CivicAddress>, String> {
+            if latitude > 0.0 {
+                Ok(Some(CivicAddress { country: Some("FR".to_string()), ..Default::default() }))
+            } else {
+                Err("out of coverage".to_string())
+            }
+        }
+    }
+
+    let mut aml = AmlData::from_https("location_latitude=48.8583&location_longitude=2.2945").unwrap();
+    let mut future = Box::pin(aml.resolve_address_async(&Lookup));
+    match future.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(address) => assert_eq!(address.unwrap().unwrap().country.as_deref(), Some("FR")),
+        Poll::Pending => panic!("Lookup is not expected to be pending"),
+    }
+}
+
+#[test]
+fn map_links() {
+    let precise = AmlData::from_https("location_latitude=48.8583&location_longitude=2.2945&location_accuracy=10").unwrap();
+    let coarse = AmlData::from_https("location_latitude=48.8583&location_longitude=2.2945&location_accuracy=5000").unwrap();
+
+    assert_eq!(
+        precise.map_url(MapProvider::Bing).unwrap(),
+        "https://www.bing.com/maps?cp=48.8583~2.2945&lvl=19&sp=point.48.8583_2.2945_AML"
+    );
+    assert!(coarse.map_url(MapProvider::Bing).unwrap().contains("&lvl=12&"));
+    assert_eq!(
+        coarse.map_url(MapProvider::Google).unwrap(),
+        "https://www.google.com/maps/search/48.8583,2.2945/@48.8583,2.2945,20000m"
+    );
+    assert_eq!(AmlData::default().map_url(MapProvider::OpenStreetMap), None);
+}
+
+#[cfg(feature = "geoid")]
+#[test]
+fn altitude_above_sea_level() {
+    use aml_lib::geoid::GeoidGrid;
+
+    let grid = GeoidGrid::parse_grd("48 49 358 359 1 1\n 44.0 45.0\n 46.0 47.0").unwrap();
+    let aml = AmlData::from_text_sms(r#"A"ML=2;lo=48.5,-1.5,10;lz=145.5,3"#).unwrap();
+    assert_eq!(aml.altitude_msl(&grid), Some(100.0));
+    assert_eq!(grid.undulation(50.0, -1.5), None);
+    assert_eq!(GeoidGrid::parse_grd("48 49 2 3 1 1\n 44.0 45.0"), None);
+    assert_eq!(GeoidGrid::parse_grd("48 inf 2 3 1 1\n 44.0 45.0\n 46.0 47.0"), None);
+
+    // Non-finite coordinates are accepted by the SMS parser
+    let aml = AmlData::from_text_sms(r#"A"ML=2;lo=48.5,-inf,10;lz=145.5,3"#).unwrap();
+    assert_eq!(aml.longitude, Some(f64::NEG_INFINITY));
+    assert_eq!(aml.altitude_msl(&grid), None);
+    assert_eq!(grid.undulation(f64::NAN, -1.5), None);
+    assert_eq!(aml.altitude_msl_egm96(), None);
+
+    // Bundled EGM96 model, about -28.4 m around Houston
+    let aml = AmlData::from_text_sms(r#"A"ML=2;lo=29.7604,-95.3698,10;lz=-15.0,5"#).unwrap();
+    let egm96 = GeoidGrid::egm96(29.0, 30.0, -96.0, -95.0, 0.25).unwrap();
+    let (direct, gridded) = (aml.altitude_msl_egm96().unwrap(), aml.altitude_msl(&egm96).unwrap());
+    assert!((direct - 13.4).abs() < 0.2 && (direct - gridded).abs() < 0.1);
+    assert_eq!(GeoidGrid::egm96(30.0, 29.0, -96.0, -95.0, 0.25), None);
+}
+
+#[test]
+fn merge_successive_messages() {
+    let first = r#"A"ML=2;en=112;et=1593187189;lo=48.82639,2.36619,12;lt=20;ls=G;ei=358239059042542;nc=20820"#;
+    let older_fix = r#"A"ML=2;et=1593187100;lo=48.80,2.30,1500;lt=10;ls=C"#;
+
+    let mut aml = AmlData::from_text_sms(first).unwrap();
+    aml.merge(AmlData::from_text_sms(older_fix).unwrap());
+    assert_eq!(aml.latitude, Some(48.82639));
+    assert_eq!(aml.beginning_of_call, Some(timestamp::from_unix(1593187100).unwrap()));
+    assert_eq!(aml.emergency_number.as_deref(), Some("112"));
+    assert_eq!(aml.network_mcc, Some(208));
+
+    // Same time of positioning: the best accuracy wins
+    let mut aml = AmlData::from_text_sms(r#"A"ML=2;et=1593187189;lo=48.80,2.30,1500;lt=20"#).unwrap();
+    aml.merge(AmlData::from_text_sms(first).unwrap());
+    assert_eq!(aml.accuracy, Some(12.0));
+
+    // No location in the newer message
+    aml.merge(AmlData::from_https("v=1&device_number=%2B33611223344").unwrap());
+    assert_eq!(aml.accuracy, Some(12.0));
+    assert_eq!(aml.device_number.as_deref(), Some("+33611223344"));
+    // The transport is the one of the location
+    assert_eq!((aml.transport.as_str(), aml.version.as_deref()), ("sms", Some("2")));
+
+    let mut aml = AmlData::from_text_sms(first).unwrap();
+    aml.merge(AmlData::from_https("v=1&location_latitude=48.85&location_longitude=2.35&location_time=1593187250000&location_source=gps").unwrap());
+    assert_eq!((aml.latitude, aml.transport.as_str(), aml.version.as_deref()), (Some(48.85), "https", Some("1")));
+    aml.merge(AmlData::from_text_sms(older_fix).unwrap());
+    assert_eq!((aml.latitude, aml.transport.as_str()), (Some(48.85), "https"));
+}
+
+#[test]
+fn call_sessions() {
+    let mut sessions = SessionAggregator::new(Duration::minutes(10));
+    let sms = |imei: &str, et: i64, lo: &str, lt: i64, ls: &str| {
+        AmlData::from_text_sms(format!(r#"A"ML=2;et={};lo={};lt={};ls={};ei={}"#, et, lo, lt, ls, imei)).unwrap()
+    };
+
+    sessions.ingest(sms("358239059042542", 1593187189, "48.80,2.30,1500", 5, "C"), timestamp::from_unix(1593187200).unwrap());
+    let (_, transitions) = sessions.ingest(sms("358239059042542", 1593187189, "48.81,2.30,10", 30, "G"), timestamp::from_unix(1593187200).unwrap());
+    assert_eq!(transitions.len(), 2);
+    assert_eq!(transitions[0], Transition::MethodChanged { previous: Some("C".to_string()), method: Some("G".to_string()) });
+    assert!(matches!(transitions[1], Transition::LocationMoved { distance } if (distance - 1112.0).abs() < 5.0));
+
+    // Another device and another call of the same device
+    sessions.ingest(sms("353472104343540", 1593187189, "48.80,2.30,20", 5, "G"), timestamp::from_unix(1593187200).unwrap());
+    sessions.ingest(sms("358239059042542", 1593197189, "48.80,2.30,20", 5, "G"), timestamp::from_unix(1593197200).unwrap());
+    assert_eq!(sessions.sessions().len(), 3);
+    assert_eq!(sessions.sessions()[0].history.len(), 2);
+
+    let closed = sessions.close_before(timestamp::from_unix(1593190000).unwrap());
+    assert_eq!(closed.len(), 2);
+    assert_eq!(sessions.sessions().len(), 1);
+
+    // Idle sessions are closed, even without beginning of call
+    let https = AmlData::from_https("v=1&location_latitude=48.85&location_longitude=2.35&device_imei=353472104343540").unwrap();
+    sessions.ingest(https, timestamp::from_unix(1593197300).unwrap());
+    assert_eq!(sessions.close_idle(timestamp::from_unix(1593197800).unwrap()).len(), 0);
+    let closed = sessions.close_idle(timestamp::from_unix(1593197900).unwrap());
+    assert_eq!((closed.len(), closed[0].device.as_deref()), (1, Some("358239059042542")));
+    assert_eq!(sessions.close_idle(timestamp::from_unix(1593198000).unwrap()).len(), 1);
+    assert!(sessions.sessions().is_empty());
+}
+
+#[test]
+fn session_started_once() {
+    use aml_lib::Session;
+
+    let mut session = Session::new(AmlData::from_https("device_imei=1&v=1").unwrap());
+    assert_eq!(session.push(AmlData::from_https("device_imei=1&device_model=FP3").unwrap()), vec![Transition::Updated]);
+    assert_eq!(session.push(AmlData::from_https("device_imei=1&location_latitude=48.8&location_longitude=2.3").unwrap()), vec![Transition::Updated]);
+    assert_eq!(session.current.model.as_deref(), Some("FP3"));
+}
+
+#[test]
+fn duplicate_messages() {
+    let text = r#"A"ML=2;en=112;et=1593187189;lo=48.82639,2.36619,12;lt=20;ls=G;ei=358239059042542"#;
+    let reordered = r#"A"ML=2;ei=358239059042542;ls=G;lt=20;lo=48.82639,2.36619,12;et=1593187189;en=112"#;
+    let first = AmlData::from_text_sms(text).unwrap();
+    assert_eq!(first.fingerprint(), AmlData::from_text_sms(reordered).unwrap().fingerprint());
+
+    let mut dedup = Deduplicator::new(Duration::minutes(1), 10);
+    let at = timestamp::from_unix(1593187300).unwrap();
+    assert!(!dedup.is_duplicate(&first, at));
+    assert!(dedup.is_duplicate(&first, at + Duration::seconds(59)));
+    assert!(!dedup.is_duplicate(&first, at + Duration::seconds(200)));
+
+    let mut sessions = SessionAggregator::new(Duration::minutes(10));
+    sessions.deduplicator = Some(Deduplicator::new(Duration::minutes(1), 10));
+    sessions.ingest(first.clone(), at);
+    let (session, transitions) = sessions.ingest(first.clone(), at + Duration::seconds(30));
+    assert_eq!(transitions, vec![Transition::Duplicate]);
+    assert_eq!(session.history.len(), 1);
+
+    // The receive times are the ones of the caller, replayed logs included
+    let (session, transitions) = sessions.ingest(first, at + Duration::seconds(90));
+    assert_ne!(transitions, vec![Transition::Duplicate]);
+    assert_eq!(session.history.len(), 2);
+}
+
+#[test]
+fn fuse_locations() {
+    let sms = AmlData::from_text_sms(r#"A"ML=2;lo=48.80000,2.30000,10;lc=68;ei=358239059042542"#).unwrap();
+    let https = AmlData::from_https("location_latitude=48.80100&location_longitude=2.30000&location_accuracy=30&device_number=%2B33611223344").unwrap();
+    let no_accuracy = AmlData::from_https("location_latitude=10.0&location_longitude=10.0").unwrap();
+
+    let fused = AmlData::fuse(&[sms, https, no_accuracy]).unwrap();
+    assert_eq!(fused.inputs.len(), 2);
+    assert_eq!(fused.inputs[1].index, 1);
+    assert!((fused.inputs[0].weight - 0.9).abs() < 1e-9);
+    assert!((fused.data.latitude.unwrap() - 48.8001).abs() < 1e-9);
+    assert!((fused.data.accuracy.unwrap() - 30.0 / 10.0_f64.sqrt()).abs() < 1e-9);
+    assert_eq!(fused.data.imei.as_deref(), Some("358239059042542"));
+    assert_eq!(fused.data.device_number.as_deref(), Some("+33611223344"));
+
+    assert!(AmlData::fuse(&[AmlData::default()]).is_none());
+}
+
+#[test]
+fn batch_parsing() {
+    use aml_lib::batch;
+
+    let log = [
+        r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;lc=68;pm=G;si=208201771948415;ei=353472104343540;mcc=208;mnc=20;ml=126"#,
+        "415193D98BEDD8F4DEECE6A2C962B7DA8E7DEEB56232990B86A3D9623B39B92783EDE86F784F068BD560B6D80C1683E568B81D7BDCB3E176F076EFB89BA77B39DCCD56A3C966B15D39DD9BD570B2590E56CBC168B21A4DB66B8FC7BD590CB66BBBC73D990DB66BB37B31D90C",
+        "",
+        "QVGT2Yvt2PTe7OaiyWK32o597rViMpkLhqPZYjs5uSeD7ehveE8Gi9VgttgMFoPlaLgde9yz4Xbwdu+4m6d7OdzNVqPJZrFdOd2b1XCyWQ5Wy8FoshpNtmuPx71ZDLZru8c9mQ22a7N7MdkM",
+        "v=1&location_latitude=55.85732&location_longitude=-4.26325",
+        "not an AML message",
+    ]
+    .join("\r\n");
+
+    let parsed: Vec<_> = batch::parse_lines(std::io::Cursor::new(log)).collect();
+    let lines: Vec<usize> = parsed.iter().map(|(line, _)| *line).collect();
+    assert_eq!(lines, vec![1, 2, 4, 5, 6]);
+    assert_eq!(parsed[0].1.as_ref().unwrap().imei.as_deref(), Some("353472104343540"));
+    assert_eq!(parsed[1].1.as_ref().unwrap().latitude, Some(37.42175));
+    assert_eq!(parsed[2].1.as_ref().unwrap().latitude, Some(37.42175));
+    assert_eq!(parsed[3].1.as_ref().unwrap().latitude, Some(55.85732));
+    assert!(parsed[4].1.is_err());
+
+    let invalid = b"\xff\xfe\nv=1&location_latitude=55.85732&location_longitude=-4.26325\n";
+    let parsed: Vec<_> = batch::parse_lines(&invalid[..]).collect();
+    assert!(matches!(parsed[0].1, Err(aml_lib::AmlError::Io(_))));
+    assert_eq!(parsed[1].1.as_ref().unwrap().latitude, Some(55.85732));
+
+    struct Broken;
+    impl std::io::Read for Broken {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disk failure"))
+        }
+    }
+    let parsed: Vec<_> = batch::parse_lines(std::io::BufReader::new(Broken)).take(3).collect();
+    assert_eq!(parsed.len(), 1);
+    assert!(matches!(parsed[0].1, Err(aml_lib::AmlError::Io(_))));
+}
+
+#[test]
+fn ndjson_input_and_output() {
+    use aml_lib::{batch, json::JsonValue};
+
+    let input = [
+        r#"{"transport":"sms","payload":"A\"ML=2;en=112;lo=48.82639,-2.36619,20;ei=358239059042542"}"#,
+        r#"{"transport":"sms_base64","payload":"QVGT2Yvt2PTe7OaiyWK32o597rViMpkLhqPZYjs5uSeD7ehveE8Gi9VgttgMFoPlaLgde9yz4Xbwdu+4m6d7OdzNVqPJZrFdOd2b1XCyWQ5Wy8FoshpNtmuPx71ZDLZru8c9mQ22a7N7MdkM"}"#,
+        r#"{"transport":"fax","payload":"A\"ML=1"}"#,
+        r#"{"transport":"https""#,
+    ]
+    .join("\n");
+
+    let parsed: Vec<_> = batch::parse_ndjson(input.as_bytes()).collect();
+    assert_eq!(parsed.len(), 4);
+    assert!(matches!(parsed[2].1, Err(aml_lib::AmlError::InvalidRecord)));
+    assert!(matches!(parsed[3].1, Err(aml_lib::AmlError::InvalidRecord)));
+
+    let messages: Vec<AmlData> = parsed.into_iter().filter_map(|(_, aml)| aml.ok()).collect();
+    let mut output = Vec::new();
+    batch::write_ndjson(&mut output, &messages).unwrap();
+
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(
+        lines[0],
+        r#"{"version":"2","emergency_number":"112","latitude":48.82639,"longitude":-2.36619,"accuracy":20,"imei":"358239059042542","transport":"sms"}"#
+    );
+    let second = JsonValue::parse(lines[1]).unwrap();
+    assert_eq!(second.get("latitude").and_then(JsonValue::as_f64), Some(37.42175));
+}
+
+#[test]
+fn strict_json_parser() {
+    use aml_lib::json::JsonValue;
+
+    let valid = [("0", 0.0), ("-0.5", -0.5), ("1e3", 1000.0), ("12.5E-1", 1.25), (" \r\n\t7 ", 7.0)];
+    for (text, number) in valid {
+        assert_eq!(JsonValue::parse(text), Some(JsonValue::Number(number)), "{}", text);
+    }
+    let invalid = ["+1", ".5", "1.", "01", "-", "1e", "0x10", "1e999", "\u{c}1", "\"a\u{1}b\"", r#""\udc00""#, r#""\ud800\ue000""#, r#""\u+041""#];
+    for text in invalid {
+        assert_eq!(JsonValue::parse(text), None, "{:?}", text);
+    }
+    assert_eq!(JsonValue::parse(r#""\ud83d\ude91""#), Some(JsonValue::String("\u{1f691}".to_string())));
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn cli_authenticates_detected_https() {
+    use std::process::Command;
+
+    let decode = |payload: &str| Command::new(env!("CARGO_BIN_EXE_aml")).args(["--key", "AML", payload]).output().unwrap().status.success();
+
+    let signed = "v=1&device_number=%2B33611223344&location_latitude=0.85732&location_longitude=-4.26325&location_time=1604912121000&location_accuracy=10.4&location_source=GPS&location_certainty=83&hmac=f64c70eb238bb239e00e8ac8c023bf2b5d3c41dd";
+    assert!(decode(signed));
+    assert!(!decode(&signed.replace("hmac=f", "hmac=0")));
+
+    // Detected as HTTPS without any `&`, or tunneling a SMS
+    assert!(!decode("location_latitude=1"));
+    assert!(!decode("body=A%22ML%3D2%3Ben%3D112%3Bet%3D1593187189%3Blo%3D48.82639%2C-2.36619%2C20"));
+    assert!(decode(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30"#));
+}
+
+// Run with `cargo test --target wasm32-unknown-unknown --features wasm --test verify wasm`,
+// `wasm-bindgen-test-runner` being the runner of the target
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+#[wasm_bindgen_test::wasm_bindgen_test]
+fn wasm_decoders() {
+    use aml_lib::wasm::*;
+    use js_sys::Reflect;
+    use wasm_bindgen::{JsCast, JsValue};
+
+    let aml = from_https("v=1&location_latitude=55.85732&location_longitude=-4.26325").unwrap();
+    assert!(aml.is_object());
+    assert_eq!(Reflect::get(&aml, &"latitude".into()).unwrap().as_f64(), Some(55.85732));
+    assert_eq!(Reflect::get(&aml, &"transport".into()).unwrap().as_string().as_deref(), Some("https"));
+    assert_eq!(Reflect::get(&aml, &"altitude".into()).unwrap(), JsValue::UNDEFINED);
+
+    let sms = from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;ls=G"#).unwrap();
+    assert_eq!(Reflect::get(&sms, &"version".into()).unwrap().as_string().as_deref(), Some("2"));
+
+    let error: js_sys::Error = JsValue::from(from_text_sms(r#"A"ML=9"#).unwrap_err()).dyn_into().unwrap();
+    assert!(!String::from(error.message()).is_empty());
+}
+
+#[cfg(feature = "ffi")]
+#[test]
+fn c_interface() {
+    use aml_lib::ffi::*;
+    use std::ffi::{CStr, CString};
+
+    let sms = CString::new(r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;ei=353472104343540;mcc=208"#).unwrap();
+    unsafe {
+        let record = aml_parse_record(sms.as_ptr(), AML_FORMAT_AUTO);
+        assert!(!record.is_null());
+        assert_eq!((*record).latitude, 48.82639);
+        assert!((*record).altitude.is_nan());
+        assert_eq!((*record).network_mcc, 208);
+        assert_eq!((*record).network_mnc, -1);
+        assert_eq!(CStr::from_ptr((*record).imei).to_str(), Ok("353472104343540"));
+        assert!((*record).iccid.is_null());
+        aml_record_free(record);
+
+        let json = aml_parse_json(sms.as_ptr(), AML_FORMAT_HTTPS);
+        assert!(CStr::from_ptr(json).to_str().unwrap().starts_with("{"));
+        aml_string_free(json);
+
+        assert!(aml_parse_record(sms.as_ptr(), 42).is_null());
+
+        let hex = CString::new("0x3G").unwrap();
+        let json = aml_parse_json(hex.as_ptr(), AML_FORMAT_SMS_HEX);
+        assert!(CStr::from_ptr(json).to_str().unwrap().contains("not valid hexadecimal"));
+        aml_string_free(json);
+
+        let https = CString::new("v=1&device_number=%2B33611223344&location_latitude=0.85732&location_longitude=-4.26325&location_time=1604912121000&location_accuracy=10.4&location_source=GPS&location_certainty=83&hmac=f64c70eb238bb239e00e8ac8c023bf2b5d3c41dd").unwrap();
+        assert_eq!(aml_https_is_authenticated(https.as_ptr(), b"AML".as_ptr(), 3), 1);
+        assert_eq!(aml_https_is_authenticated(https.as_ptr(), b"KEY".as_ptr(), 3), 0);
+    }
+}
+
+#[cfg(feature = "axum")]
+#[test]
+fn axum_extractor() {
+    use aml_lib::web::{AmlPayload, Endpoint, KeyRing, Rejection};
+    use axum::body::Body;
+    use axum::extract::{FromRequest, Request};
+    use axum::response::IntoResponse;
+    use std::future::Future;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Waker};
+
+    let mut keys = KeyRing::new();
+    keys.add("operator-a", "AML");
+    let state = Arc::new(Endpoint::new(keys));
+
+    // The bodies are in memory, the extraction never waits
+    let extract = |body: &str| {
+        let request = Request::post("/aml").body(Body::from(body.to_string())).unwrap();
+        let mut future = Box::pin(AmlPayload::from_request(request, &state));
+        match future.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(result) => result,
+            Poll::Pending => panic!("the body is not expected to be pending"),
+        }
+    };
+
+    let body = "v=1&device_number=%2B33611223344&location_latitude=0.85732&location_longitude=-4.26325&location_time=1604912121000&location_accuracy=10.4&location_source=GPS&location_certainty=83&hmac=f64c70eb238bb239e00e8ac8c023bf2b5d3c41dd";
+    let payload = extract(body).unwrap();
+    assert_eq!((payload.key_id.as_deref(), payload.data.location_latitude), (Some("operator-a"), Some(0.85732)));
+
+    let rejection = extract("v=1&location_latitude=1.5&hmac=00").unwrap_err();
+    assert_eq!(rejection.into_response().status(), 401);
+    assert_eq!(extract(&"v=1&".repeat(2000)).unwrap_err(), Rejection::TooLarge);
+}
+
+#[cfg(feature = "actix")]
+#[test]
+fn actix_extractor_and_middleware() {
+    use actix_web::{middleware, test, web, App, HttpResponse};
+    use aml_lib::web::{actix::verify_hmac, AmlPayload, Endpoint, KeyRing};
+    use std::future::Future;
+    use std::task::{Context, Poll, Waker};
+
+    // The requests are in memory, the services never wait
+    fn ready<F: Future>(future: F) -> F::Output {
+        let mut future = Box::pin(future);
+        match future.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("the service is not expected to be pending"),
+        }
+    }
+
+    async fn receive(payload: AmlPayload) -> HttpResponse {
+        HttpResponse::Ok().body(payload.key_id.unwrap_or_default())
+    }
+
+    let mut keys = KeyRing::new();
+    keys.add("operator-a", "AML");
+    let app = ready(test::init_service(
+        App::new()
+            .app_data(web::Data::new(Endpoint::new(keys)))
+            .service(web::resource("/checked").wrap(middleware::from_fn(verify_hmac)).route(web::post().to(receive)))
+            .route("/aml", web::post().to(receive)),
+    ));
+
+    let body = "v=1&device_number=%2B33611223344&location_latitude=0.85732&location_longitude=-4.26325&location_time=1604912121000&location_accuracy=10.4&location_source=GPS&location_certainty=83&hmac=f64c70eb238bb239e00e8ac8c023bf2b5d3c41dd";
+    for path in ["/aml", "/checked"] {
+        let post = |body: &str| test::TestRequest::post().uri(path).set_payload(body.to_string()).to_request();
+        let response = ready(test::call_service(&app, post(body)));
+        assert_eq!(response.status(), 200);
+        assert_eq!(ready(test::read_body(response)), "operator-a");
+
+        assert_eq!(ready(test::call_service(&app, post("v=1&location_latitude=1.5&hmac=00"))).status(), 401);
+        assert_eq!(ready(test::call_service(&app, post(&"v=1&".repeat(2000)))).status(), 413);
+    }
+}
+
+#[cfg(feature = "warp")]
+#[test]
+fn warp_filter() {
+    use aml_lib::web::warp::{payload, recover};
+    use aml_lib::web::{AmlPayload, Endpoint, KeyRing, Strictness};
+    use std::future::Future;
+    use std::task::{Context, Poll, Waker};
+    use warp::Filter;
+
+    // The requests are in memory, the filters never wait
+    fn ready<F: Future>(future: F) -> F::Output {
+        let mut future = Box::pin(future);
+        match future.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("the filter is not expected to be pending"),
+        }
+    }
+
+    let mut keys = KeyRing::new();
+    keys.add("operator-a", "AML");
+    let routes = |strictness: Strictness| {
+        warp::post()
+            .and(payload(Endpoint { strictness, ..Endpoint::new(keys.clone()) }))
+            .map(|payload: AmlPayload| format!("{} {:?}", payload.key_id.unwrap_or_default(), payload.data.location_latitude))
+            .recover(recover)
+    };
+    let post = |strictness: Strictness, body: &str| {
+        let response = ready(warp::test::request().method("POST").body(body).reply(&routes(strictness)));
+        (response.status().as_u16(), String::from_utf8_lossy(response.body()).into_owned())
+    };
+
+    let body = "v=1&device_number=%2B33611223344&location_latitude=0.85732&location_longitude=-4.26325&location_time=1604912121000&location_accuracy=10.4&location_source=GPS&location_certainty=83&hmac=f64c70eb238bb239e00e8ac8c023bf2b5d3c41dd";
+    assert_eq!(post(Strictness::Strict, body), (200, "operator-a Some(0.85732)".to_string()));
+    assert_eq!(post(Strictness::Strict, "v=1&location_latitude=1.5").0, 401);
+    assert_eq!(post(Strictness::Lenient, "v=1&location_latitude=1.5"), (200, " Some(1.5)".to_string()));
+    assert_eq!(post(Strictness::Lenient, "v=1&location_latitude=1.5&hmac=00").0, 401);
+    assert_eq!(post(Strictness::Strict, &"v=1&".repeat(2000)).0, 413);
+    assert_eq!(post(Strictness::Strict, "").0, 400);
+
+    // The rejection is the one of the endpoint
+    let rejection = ready(warp::test::request().method("POST").body("v=1").filter(&payload(Endpoint::new(keys.clone())))).unwrap_err();
+    assert_eq!(rejection.find::<aml_lib::web::Rejection>(), Some(&aml_lib::web::Rejection::Unauthenticated));
+}
+
+// The routes are items of a module, as the macros of rocket expect
+#[cfg(feature = "rocket")]
+mod rocket_routes {
+    use aml_lib::web::AmlPayload;
+    use aml_lib::HttpsData;
+
+    #[rocket::post("/aml", data = "<payload>")]
+    pub fn receive(payload: AmlPayload) -> String {
+        format!("{} {}", payload.key_id.unwrap_or_default(), payload.data.location_latitude.unwrap_or_default())
+    }
+
+    #[rocket::post("/data", data = "<data>")]
+    pub fn receive_data(data: HttpsData) -> String {
+        data.location_source.unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "rocket")]
+#[test]
+fn rocket_data_guard() {
+    use aml_lib::web::{Endpoint, KeyRing};
+    use rocket::error::ErrorKind;
+    use rocket::http::Status;
+    use rocket::local::blocking::Client;
+
+    let mut keys = KeyRing::new();
+    keys.add("operator-a", "AML");
+    let rocket = rocket::build().manage(Endpoint::new(keys)).mount("/", rocket::routes![rocket_routes::receive, rocket_routes::receive_data]);
+    let client = Client::tracked(rocket).unwrap();
+
+    let body = "v=1&device_number=%2B33611223344&location_latitude=0.85732&location_longitude=-4.26325&location_time=1604912121000&location_accuracy=10.4&location_source=GPS&location_certainty=83&hmac=f64c70eb238bb239e00e8ac8c023bf2b5d3c41dd";
+    let response = client.post("/aml").body(body).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_string().as_deref(), Some("operator-a 0.85732"));
+    assert_eq!(client.post("/data").body(body).dispatch().into_string().as_deref(), Some("gps"));
+
+    assert_eq!(client.post("/aml").body("v=1&location_latitude=1.5&hmac=00").dispatch().status(), Status::Unauthorized);
+    assert_eq!(client.post("/aml").body("v=1&".repeat(2000)).dispatch().status(), Status::PayloadTooLarge);
+
+    // Without a managed endpoint, the launch is aborted
+    let error = Client::tracked(rocket::build().mount("/", rocket::routes![rocket_routes::receive])).unwrap_err();
+    assert!(matches!(error.kind(), ErrorKind::SentinelAborts(_)));
+}
+
+#[test]
+fn web_payload_authentication() {
+    use aml_lib::web::{AmlPayload, KeyRing, Rejection, Strictness};
+
+    let body = "v=1&device_number=%2B33611223344&location_latitude=0.85732&location_longitude=-4.26325&location_time=1604912121000&location_accuracy=10.4&location_source=GPS&location_certainty=83&hmac=f64c70eb238bb239e00e8ac8c023bf2b5d3c41dd";
+    let mut keys = KeyRing::new();
+    keys.add("operator-a", "KEY").add("operator-b", "AML");
+
+    let payload = AmlPayload::from_body(body.as_bytes(), &keys).unwrap();
+    assert_eq!(payload.key_id.as_deref(), Some("operator-b"));
+    assert_eq!(payload.data.location_latitude, Some(0.85732));
+
+    assert_eq!(AmlPayload::from_body(b"", &keys).unwrap_err(), Rejection::EmptyBody);
+    assert_eq!(AmlPayload::from_body(&[0xff, 0xfe], &keys).unwrap_err().status_code(), 400);
+    assert_eq!(AmlPayload::from_body(body.as_bytes(), &KeyRing::new()).unwrap_err(), Rejection::Unauthenticated);
+
+    let lenient = AmlPayload::from_body_with(b"v=1&location_latitude=1.5", &keys, Strictness::Lenient).unwrap();
+    assert_eq!(lenient.key_id, None);
+    assert_eq!(lenient.data.location_latitude, Some(1.5));
+
+    // A wrong hmac is refused, lenient or not
+    let forged = body.replace("hmac=f", "hmac=0");
+    assert_eq!(AmlPayload::from_body_with(forged.as_bytes(), &keys, Strictness::Lenient).unwrap_err(), Rejection::Unauthenticated);
+    assert_eq!(AmlPayload::from_body_with(b"v=1&location_latitude=1.5&hmac=", &keys, Strictness::Lenient).unwrap_err(), Rejection::Unauthenticated);
+}
+
+#[cfg(feature = "server")]
+#[test]
+fn server_receives_https_aml() {
+    use aml_lib::server::Server;
+    use aml_lib::web::KeyRing;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::sync::mpsc;
+
+    let mut keys = KeyRing::new();
+    keys.add("operator-a", "AML");
+    let server = Server::bind("127.0.0.1:0", keys).unwrap();
+    let addr = server.local_addr().unwrap();
+    let (sender, received) = mpsc::channel();
+    std::thread::spawn(move || server.serve(move |aml| sender.send(aml).unwrap()));
+
+    let post = |body: &str| {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "POST /aml HTTP/1.1\r\nHost: psap\r\nContent-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+
+    let body = "v=1&device_number=%2B33611223344&location_latitude=0.85732&location_longitude=-4.26325&location_time=1604912121000&location_accuracy=10.4&location_source=GPS&location_certainty=83&hmac=f64c70eb238bb239e00e8ac8c023bf2b5d3c41dd";
+    assert!(post(body).starts_with("HTTP/1.1 200"));
+    assert_eq!(received.recv().unwrap().latitude, Some(0.85732));
+
+    assert!(post("v=1&location_latitude=1.5&hmac=00").starts_with("HTTP/1.1 401"));
+
+    let mut headers = String::from("POST /aml HTTP/1.1\r\n");
+    (0..100).for_each(|i| headers.push_str(&format!("X-Probe-{}: 1\r\n", i)));
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(stream, "{}\r\n", headers).unwrap();
+    // The connection may be reset after the response, the headers left unread
+    let mut response = Vec::new();
+    let _ = stream.read_to_end(&mut response);
+    assert!(response.starts_with(b"HTTP/1.1 431"));
+}
+
+#[cfg(feature = "server")]
+#[test]
+fn server_bounds_connections_and_header_time() {
+    use aml_lib::server::Server;
+    use aml_lib::web::KeyRing;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::time::{Duration, Instant};
+
+    let mut server = Server::bind("127.0.0.1:0", KeyRing::new()).unwrap();
+    server.max_connections = 1;
+    server.header_timeout = Duration::from_millis(300);
+    let addr = server.local_addr().unwrap();
+    std::thread::spawn(move || server.serve(|_| ()));
+
+    let response = |stream: &mut TcpStream| {
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+
+    // A client sending its headers a line at a time holds the only connection
+    let start = Instant::now();
+    let mut slow = TcpStream::connect(addr).unwrap();
+    write!(slow, "POST /aml HTTP/1.1\r\n").unwrap();
+    std::thread::sleep(Duration::from_millis(100));
+    let mut other = TcpStream::connect(addr).unwrap();
+    assert!(response(&mut other).starts_with("HTTP/1.1 503"));
+
+    write!(slow, "X-Slow: 1\r\n").unwrap();
+    assert!(response(&mut slow).starts_with("HTTP/1.1 408"));
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    let mut next = TcpStream::connect(addr).unwrap();
+    write!(next, "GET / HTTP/1.1\r\n\r\n").unwrap();
+    assert!(response(&mut next).starts_with("HTTP/1.1 405"));
+
+    // The body shares the deadline of the headers
+    let start = Instant::now();
+    let mut slow = TcpStream::connect(addr).unwrap();
+    write!(slow, "POST /aml HTTP/1.1\r\nContent-Length: 10\r\n\r\nv=1").unwrap();
+    assert!(response(&mut slow).starts_with("HTTP/1.1 408"));
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn https_els_profile() {
+    let https = "v=4&time=1604912100000&location_latitude=0.85732&location_longitude=-4.26325&location_certainty=83";
+
+    let generic = HttpsData::from_urlencoded(https);
+    assert_eq!(generic.profile, Profile::Generic);
+    assert_eq!(generic.location_confidence, None);
+    assert!(generic.validate().is_valid());
+
+    let els = HttpsData::from_urlencoded_with(https, Profile::Els);
+    assert_eq!(els.location_confidence, Some(0.83));
+    assert_eq!(
+        els.validate().failures,
+        vec![
+            ValidationFailure::UnsupportedVersion("4".to_string()),
+            ValidationFailure::MissingField("emergency_number"),
+        ]
+    );
+
+    let aml = AmlData::from_https_with(https, Profile::Els).unwrap();
+    assert_eq!(aml.profile, Profile::Els);
+    assert_eq!(aml.confidence, Some(0.83));
+    assert_eq!(aml.confidence_ratio(), Some(0.83));
+}
+
+#[test]
+fn sms_apple_profile() {
+    let sms_text = r#"A"ML=2;en=112;et=1593187189000;lo=48.82639,-2.36619,52;lt=3;lc=0.68;ls=G"#;
+    assert_eq!(Profile::detect(sms_text), Profile::Apple);
+
+    let generic = SmsData::from_text(sms_text).unwrap();
+    assert_eq!(generic.level_of_confidence, Some(0.68));
+
+    let apple = AmlData::from_text_sms_with(sms_text, Profile::detect(sms_text)).unwrap();
+    assert_eq!(apple.profile, Profile::Apple);
+    assert_eq!(apple.confidence, Some(68.0));
+    assert_eq!(apple.beginning_of_call, Some(timestamp::from_unix(1593187189).unwrap()));
+    assert_eq!(apple.time_of_positioning, Some(timestamp::from_unix(1593187192).unwrap()));
+
+    // A generic SMS stays generic, its lc=1 is 1%
+    let generic = r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,52;lt=3;lc=1;ls=G"#;
+    assert_eq!(Profile::detect(generic), Profile::Generic);
+    assert_eq!(AmlData::from_text_sms_with(generic, Profile::detect(generic)).unwrap().confidence, Some(1.0));
+
+    let overflow = format!(r#"A"ML=2;et={};lo=48.82639,-2.36619,52;lt=3"#, i64::MAX);
+    assert_eq!(AmlData::from_text_sms_with(&overflow, Profile::Apple).unwrap().time_of_positioning, None);
+}
+
+#[test]
+fn pemea_envelope_round_trip() {
+    use aml_lib::pemea::{Content, Envelope};
+
+    let aml = AmlData::from_https("v=1&emergency_number=112&location_latitude=55.85732&location_longitude=-4.26325&location_source=gps").unwrap();
+    let envelope = Envelope {
+        id: "b7e3<&>".to_string(),
+        origin: "ap.example.fr".to_string(),
+        destination: "ap.example.be".to_string(),
+        created: timestamp::from_unix(1604912121).unwrap(),
+        content: Content::Aml(Box::new(aml)),
+    };
+
+    let xml = Envelope::from_xml(&envelope.to_xml()).unwrap();
+    assert_eq!(xml.id, "b7e3<&>");
+    assert_eq!(xml.created, envelope.created);
+    assert_eq!(xml.aml_data().unwrap().latitude, Some(55.85732));
+
+    let json = Envelope::from_json(&envelope.to_json()).unwrap();
+    assert_eq!(json.destination, "ap.example.be");
+    assert_eq!(json.aml_data().unwrap().positioning_method.as_deref(), Some("gps"));
+
+    let foreign = format!(
+        r#"<e:envelope xmlns:e="{}" xmlns:x="urn:example"><x:trace>ap.example.de</x:trace><e:id>7</e:id><e:origin>a</e:origin>
+        <e:destination>b</e:destination><e:created>2020-11-09T08:55:21Z</e:created><!-- raw -->
+        <e:content type='text/plain; charset=utf-8; transport="sms"'><![CDATA[A"ML=1;lt=48.82639;lg=-2.36619]]></e:content></e:envelope>"#,
+        aml_lib::pemea::NAMESPACE
+    );
+    let foreign = Envelope::from_xml(&foreign).unwrap();
+    match foreign.content {
+        Content::Raw { transport, payload } => assert_eq!((transport.as_str(), payload.as_str()), ("sms", r#"A"ML=1;lt=48.82639;lg=-2.36619"#)),
+        Content::Aml(_) => panic!("raw content expected"),
+    }
+
+    assert!(Envelope::from_xml("<pemea:envelope/>").is_err());
+    assert!(Envelope::from_xml(r#"<envelope xmlns="urn:eena:pemea:aml"><id>7</id></envelope>"#).is_err());
+    assert!(Envelope::from_xml(&envelope.to_xml().replace("application/json", "application/vnd.aml+json")).is_err());
+}
+
+#[test]
+fn conformance_reports() {
+    use aml_lib::conformance::{self, Severity};
+
+    let report = conformance::check_sms(r#"A"ML=2;en=+15555555555;et=1593187189;lo=-37.42175,-122.08461,2000.1;lt=-9999;lc=68;lz=-100.1,100.1;ls=G;ei=358239059042542;nc=310260;hc=310260;lg=en-US"#);
+    assert!(report.is_conformant(), "{:?}", report.findings);
+    assert_eq!(report.version.as_deref(), Some("2"));
+
+    let report = conformance::check_sms(r#"lt=48.82639;A"ML=1;lt=48.8;xx=1;pm=Q"#);
+    assert!(!report.is_conformant());
+    let rules: Vec<_> = report.findings.iter().map(|f| f.rule).collect();
+    for rule in ["SMS-01", "SMS-03", "SMS-04", "SMS-05", "SMS-06"] {
+        assert!(rules.contains(&rule), "{} not in {:?}", rule, rules);
+    }
+
+    let https = "v=1&emergency_number=112&time=1604912100000&location_latitude=0.85732&location_longitude=-4.26325&location_time=1604912121000&location_accuracy=10.4&location_source=GPS";
+    let report = conformance::check_https(https);
+    assert!(report.is_conformant());
+    assert_eq!(report.findings.len(), 1);
+    assert_eq!((report.findings[0].rule, report.findings[0].severity), ("HTTPS-06", Severity::Warning));
+    assert_eq!(report.to_json_value().get("conformant").and_then(|c| c.as_bool()), Some(true));
+}
+
+#[test]
+fn redacted_identifiers() {
+    let aml = AmlData::from_text_sms(r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;lc=68;pm=G;si=208201771948415;ei=353472104343540;mcc=208;mnc=20;ml=126"#).unwrap();
+
+    let removed = aml.redacted(&RedactionPolicy::default());
+    assert_eq!((removed.imsi, removed.imei), (None, None));
+
+    let hashed = aml.redacted(&RedactionPolicy::all(Redaction::Hash(b"salt".to_vec())));
+    let other = aml.redacted(&RedactionPolicy::all(Redaction::Hash(b"pepper".to_vec())));
+    assert_eq!(hashed.imsi.as_ref().map(String::len), Some(40));
+    assert_ne!(hashed.imsi, other.imsi);
+    assert_eq!(hashed.imsi, aml.redacted(&RedactionPolicy::all(Redaction::Hash(b"salt".to_vec()))).imsi);
+
+    let kept = aml.redacted(&RedactionPolicy::all(Redaction::Keep));
+    assert_eq!(kept.imei, aml.imei);
+}
+
+#[test]
+fn retention_stages() {
+    let aml = AmlData::from_https("v=1&time=1604912100000&device_imei=353472104343540&location_latitude=55.85732&location_longitude=-4.26325&location_accuracy=10.4&location_floor=3").unwrap();
+    let call = timestamp::from_unix(1604912100).unwrap();
+    let policy = RetentionPolicy::default();
+
+    let (fresh, stage) = aml.retained(&policy, call + Duration::hours(1));
+    assert!(!stage.identifiers_stripped && !stage.location_coarsened);
+    assert_eq!(fresh.imei, aml.imei);
+
+    let (day_old, stage) = aml.retained(&policy, call + Duration::hours(25));
+    assert!(stage.identifiers_stripped && !stage.location_coarsened);
+    assert_eq!(day_old.imei, None);
+    assert_eq!(day_old.latitude, Some(55.85732));
+
+    let (old, _) = aml.retained(&policy, call + Duration::days(31));
+    assert_eq!((old.latitude, old.longitude), (Some(55.86), Some(-4.26)));
+    assert_eq!(old.floor, None);
+
+    let unknown_age = AmlData { latitude: Some(1.23456), ..Default::default() };
+    assert!(policy.stage(&unknown_age, call).location_coarsened);
+}
+
+#[cfg(feature = "trace")]
+#[test]
+fn tracing_spans_and_events() {
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    // Fields of the spans and events, as `name=value`, in the order they are seen
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl Visit for Recorder {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0.lock().unwrap().push(format!("{}={:?}", field.name(), value));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+        fn new_span(&self, span: &Attributes) -> Id {
+            span.record(&mut self.clone());
+            Id::from_u64(1)
+        }
+        fn record(&self, _: &Id, values: &Record) {
+            values.record(&mut self.clone());
+        }
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, event: &Event) {
+            event.record(&mut self.clone());
+        }
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    let recorder = Recorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        HttpsData::from_urlencoded("v=1&location_latitude=1.5&zz_trace_probe=1");
+        let _ = SmsData::from_text(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;ls=G;ml=64"#);
+        let _ = SmsData::from_text(r#"A"ML=9;zz_trace_probe=1"#);
+    });
+
+    let fields = recorder.0.lock().unwrap().join(" ");
+    for expected in [
+        r#"transport="https" message=unknown key ignored key="zz_trace_probe" version="1" message=AML parsed"#,
+        r#"transport="sms" message=key of the other version ignored key="ml" version="2" valid=true message=AML parsed"#,
+        r#"transport="sms" message=unknown key ignored key="zz_trace_probe" error="#,
+        "message=AML rejected",
+    ] {
+        assert!(fields.contains(expected), "{} not in {}", expected, fields);
+    }
+}
+
+#[test]
+fn display_summary_and_table() {
+    let aml = AmlData::from_https("v=1&emergency_number=112&time=1604912100000&device_number=%2B33611223344&location_latitude=55.85732&location_longitude=-4.26325&location_time=1604912121000&location_accuracy=10.4&location_source=wifi&location_floor=3&cell_network_mcc=234&cell_network_mnc=15").unwrap();
+    let now = timestamp::from_unix(1604912121 + 7500).unwrap();
+
+    let summary = aml.summary_at(now);
+    let lines: Vec<&str> = summary.lines().collect();
+    assert_eq!(lines[0], "Location  55.85732, -4.26325 ±10.4 m Wi-Fi");
+    assert_eq!(lines[1], "Altitude  floor 3");
+    assert_eq!(lines[2], "Fixed     2020-11-09 08:55:21 UTC (2 h 5 min ago)");
+    assert_eq!(lines[3], "Call      112 at 2020-11-09 08:55:00 UTC via https");
+    assert_eq!(lines[4], "Device    +33611223344");
+    assert_eq!(lines[5], "Carrier   network 234-15");
+    assert!(!aml.to_string().is_empty());
+
+    let table = aml.to_table();
+    assert!(table.lines().any(|line| line == "latitude              55.85732"));
+}
+
+#[test]
+fn clone_and_approx_eq() {
+    let sms = SmsData::from_text(r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;ml=62"#).unwrap();
+    assert_eq!(sms.clone(), sms);
+    let https = HttpsData::from_urlencoded("v=1&location_latitude=0.85732");
+    assert_eq!(https.clone(), https);
+
+    let aml = AmlData::from(sms);
+    let mut moved = aml.clone();
+    moved.longitude = moved.longitude.map(|lon| lon + 1e-4);
+    assert!(!aml.approx_eq(&moved, Tolerance::default()));
+    assert!(aml.approx_eq(&moved, Tolerance { epsilon: 1e-3, ..Default::default() }));
+
+    moved.imei = Some("353472104343540".to_string());
+    assert!(!aml.approx_eq(&moved, Tolerance { epsilon: 1e-3, ..Default::default() }));
+}
+
+#[cfg(feature = "fuzz")]
+#[test]
+fn parsers_survive_corrupted_payloads() {
+    use aml_lib::{batch, fuzz::Corruptor};
+
+    let payloads: [&[u8]; 3] = [
+        br#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;lc=68;pm=G;si=208201771948415;ei=353472104343540;mcc=208;mnc=20;ml=126"#,
+        br#"A"ML=2;en=+15555555555;et=1593187189;lo=-37.42175,-122.08461,2000.1;lt=-9999;lc=68;lz=-100.1,100.1;ls=G;ei=358239059042542;nc=310260;hc=310260;lg=en-US"#,
+        b"v=1&device_number=%2B33611223344&location_latitude=0.85732&location_longitude=-4.26325&location_time=1604912121000&location_accuracy=10.4&location_source=GPS&hmac=f64c70eb238bb239e00e8ac8c023bf2b5d3c41dd",
+    ];
+
+    let mut corruptor = Corruptor::new(7);
+    for payload in payloads.iter().cycle().take(3000) {
+        let corrupted = corruptor.corrupt(payload);
+        let _ = batch::parse_line(&String::from_utf8_lossy(&corrupted));
+        let _ = AmlData::from_data_sms(&corrupted);
+        let _ = aml_lib::conformance::check_sms(&String::from_utf8_lossy(&corrupted));
+    }
+}
+
+#[cfg(feature = "fuzz")]
+#[test]
+fn arbitrary_data_round_trips() {
+    use aml_lib::fuzz::Corruptor;
+    use aml_lib::json::JsonValue;
+    use arbitrary::{Arbitrary, Unstructured};
+
+    let mut corruptor = Corruptor::new(11);
+    let mut bytes: Vec<u8> = (0..=255).collect();
+    for _ in 0..500 {
+        bytes = corruptor.corrupt(&bytes);
+        bytes.resize(512, 0x5a);
+
+        let sms = SmsData::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+        let _ = sms.to_text();
+        let _ = AmlData::from(sms);
+        let https = HttpsData::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+        let _ = AmlData::from(https);
+
+        let aml = AmlData::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+        let _ = aml.to_sms_v2().0.to_text();
+        let back = AmlData::from_json_value(&JsonValue::parse(&aml.to_json()).unwrap()).unwrap();
+        if aml.latitude.is_none_or(f64::is_finite) && aml.accuracy.is_none_or(f64::is_finite) {
+            assert_eq!(back.latitude, aml.latitude);
+            assert_eq!((back.beginning_of_call, back.address), (aml.beginning_of_call, aml.address));
+        }
+    }
+}
+
+#[test]
+fn generated_messages_are_conformant() {
+    use aml_lib::conformance;
+    use aml_lib::generator::{Generator, GeneratorConfig};
+
+    let config = GeneratorConfig {
+        bounding_box: (48.0, 2.0, 49.0, 3.0),
+        ..Default::default()
+    };
+    let mut generator = Generator::new(config, 3);
+
+    for _ in 0..200 {
+        let sms = generator.sms_v1();
+        let report = conformance::check_sms(&sms);
+        assert!(report.is_conformant(), "{} {:?}", sms, report.findings);
+        assert!(SmsData::from_text(&sms).unwrap().is_valid());
+        let lat = AmlData::from_text_sms(&sms).unwrap().latitude.unwrap();
+        assert!((48.0..=49.0).contains(&lat));
+
+        let sms = generator.sms_v2();
+        assert!(conformance::check_sms(&sms).is_conformant(), "{}", sms);
+
+        let https = generator.https(Some(b"AML"));
+        assert!(conformance::check_https(&https).findings.is_empty(), "{}", https);
+    }
+
+    let mut again = Generator::new(GeneratorConfig::default(), 9);
+    assert_eq!(Generator::new(GeneratorConfig::default(), 9).https(None), again.https(None));
+}
+
+#[test]
+fn septet_packing() {
+    // 7 septets leave 7 spare bits, filled with <CR>
+    assert_eq!(SmsData::encode_8to7(b"1234567", 0), hex::decode("31D98C56B3DD1A").unwrap());
+    // A 6 byte UDH is followed by 1 fill bit
+    assert_eq!(SmsData::encode_8to7(b"A", 6), vec![0x82]);
+
+    let mut generator = aml_lib::generator::Generator::new(Default::default(), 5);
+    for _ in 0..20 {
+        let text = generator.sms_v2();
+        let sms = SmsData::from_data(&SmsData::encode_8to7(text.as_bytes(), 0)).unwrap();
+        assert_eq!(sms, SmsData::from_text(&text).unwrap());
+    }
+}
+
+#[test]
+fn septet_unpacking_with_fill_bits() {
+    let mut generator = aml_lib::generator::Generator::new(Default::default(), 11);
+    let text = std::iter::repeat_with(|| generator.sms_v1()).find(|text| text.len() % 8 == 7).unwrap();
+
+    // The 7 spare bits of the last byte are not read as an extra septet
+    let packed = SmsData::encode_8to7(text.as_bytes(), 0);
+    assert!(SmsData::from_data(&packed).unwrap().is_valid());
+
+    let text = r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;ml=61"#;
+    let packed = SmsData::encode_8to7(text.as_bytes(), 5);
+    let sms = SmsData::from_data_with(&packed, Some(text.len()), 5).unwrap();
+    assert!(sms.is_valid());
+    assert_eq!(sms.longitude, Some(-2.36619));
+}
+
+#[test]
+fn from_hex_sms() {
+    let input = "415193D98BEDD8F4DEECE6A2C962B7DA 8E7DEEB56232990B86A3D9623B39B92783EDE86F784F068BD560B6D80C1683E568B81D7BDCB3E176F076EFB89BA77B39DCCD56A3C966B15D39DD9BD570B2590E56CBC168B21A4DB66B8FC7BD590CB66BBBC73D990DB66BB37B31D90C\n";
+    assert_eq!(AmlData::from_hex_sms(input).unwrap().latitude, Some(37.42175));
+    assert!(matches!(SmsData::from_hex("4151Z3"), Err(aml_lib::AmlError::Hex(_))));
+    assert!(matches!(
+        aml_lib::batch::parse_record(r#"{"transport":"sms_hex","payload":"415"}"#),
+        Err(aml_lib::AmlError::Hex(_))
+    ));
+}
+
+#[test]
+fn from_pdu_hex() {
+    let text = r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;ml=61"#;
+
+    // No SMSC, UDHI set, +33611223344, SCTS 2020-11-12 15:21:00 +01:00, GSM 7 bit, concatenation header
+    let header = "050003010101";
+    let user_data = SmsData::encode_8to7(text.as_bytes(), 6);
+    let pdu = format!(
+        "0044 0B91 3316213243F4 00 00 02112151120040 {:02X} {}{}",
+        text.len() + 7,
+        header,
+        hex::encode(&user_data)
+    );
+    let (sms, envelope) = SmsData::from_pdu_hex(&pdu).unwrap();
+    assert!(sms.is_valid());
+    assert_eq!(sms.longitude, Some(-2.36619));
+    assert_eq!(envelope.smsc, None);
+    assert_eq!(envelope.sender, "+33611223344");
+    assert_eq!(sms.originating_address.as_deref(), Some("+33611223344"));
+    assert_eq!(AmlData::from(sms.clone()).device_number.as_deref(), Some("+33611223344"));
+    assert_eq!(hex::encode(&envelope.user_data_header), header);
+    assert_eq!(envelope.service_center_time, timestamp::parse_rfc3339("2020-11-12T14:21:00Z"));
+
+    // UCS-2
+    let ucs2: String = text.encode_utf16().map(|unit| format!("{:04X}", unit)).collect();
+    let pdu = format!("00040B913316213243F4000802112151120040{:02X}{}", text.len() * 2, ucs2);
+    assert_eq!(SmsData::from_pdu_hex(&pdu).unwrap().0, sms);
+
+    assert!(matches!(SmsData::pdu_text("0004"), Err(aml_lib::AmlError::InvalidPdu)));
+}
+
+#[test]
+fn sms_signature() {
+    use aml_lib::{Canonicalization, HmacSha1, SmsSignature};
+
+    // RFC 2202 test case 2
+    let signature = SmsSignature {
+        attribute: "sg".to_string(),
+        canonicalization: Canonicalization::Preceding,
+        verifier: HmacSha1(b"Jefe".to_vec()),
+    };
+    assert!(SmsData::is_signed("what do ya want for nothing?;sg=effcdf6ae5eb2fa2d27416d5f184df9c259a7c79\n", &signature));
+    assert!(!SmsData::is_signed("what do ya want for nothing!;sg=effcdf6ae5eb2fa2d27416d5f184df9c259a7c79", &signature));
+    assert!(!SmsData::is_signed("what do ya want for nothing?", &signature));
+
+    let text = r#"A"ML=2;lt=30;en=112;sg=1;ls=G"#;
+    let canonical = |expected: &'static str| move |message: &[u8], sg: &str| message == expected.as_bytes() && sg == "1";
+    let preceding = SmsSignature { attribute: "sg".to_string(), canonicalization: Canonicalization::Preceding, verifier: canonical(r#"A"ML=2;lt=30;en=112"#) };
+    assert!(SmsData::is_signed(r#"A"ML=2;lt=30;en=112;sg=1;"#, &preceding));
+    // An attribute after the signature is not signed: it could replace a signed one
+    assert!(!SmsData::is_signed(text, &preceding));
+    assert!(!SmsData::is_signed(r#"A"ML=2;lt=30;en=112;sg=1;lo=0,0,1"#, &preceding));
+    let sorted = SmsSignature { attribute: "sg".to_string(), canonicalization: Canonicalization::SortedAttributes, verifier: canonical(r#"A"ML=2;en=112;ls=G;lt=30"#) };
+    assert!(SmsData::is_signed(text, &sorted));
+    assert!(!SmsData::is_signed(format!("{};sg=1", text), &sorted));
+
+    // The signature attribute does not disturb the parsing
+    assert_eq!(SmsData::from_text(text).unwrap().emergency_number.as_deref(), Some("112"));
+}
+
+#[test]
+fn json_round_trip_keeps_unknown_members() {
+    use aml_lib::json::JsonValue;
+
+    let https = r#"v=1&device_number=%2B33611223344&location_latitude=0.85732&location_longitude=-4.26325&location_time=1604912121000&location_accuracy=10.4&location_source=GPS&location_certainty=83"#;
+    let mut json = AmlData::from_https(https).unwrap().to_json_value();
+    if let JsonValue::Object(members) = &mut json {
+        members.insert(2, ("network_slice".to_string(), JsonValue::Object(vec![("sst".to_string(), JsonValue::Number(1.0))])));
+        members.push(("vendor_flags".to_string(), JsonValue::Array(vec![JsonValue::Bool(true), JsonValue::Null])));
+    }
+
+    let aml = AmlData::from_json_value(&json).unwrap();
+    assert_eq!(aml.latitude, Some(0.85732));
+    assert_eq!(aml.extras.len(), 2);
+    assert_eq!(aml.extras[0].0, "network_slice");
+
+    // Known members first, then the extras in their original order
+    let again = AmlData::from_json_value(&JsonValue::parse(&aml.to_json()).unwrap()).unwrap();
+    assert_eq!(again, aml);
+    assert_eq!(again.to_json(), aml.to_json());
+
+    // Every field of the data is written, the resolved address and the profile included
+    let mut els = AmlData::from_https_with(https, aml_lib::Profile::Els).unwrap();
+    els.address = Some(aml_lib::CivicAddress {
+        country: Some("GB".to_string()),
+        city: Some("Glasgow".to_string()),
+        ..Default::default()
+    });
+    let stored = AmlData::from_json_value(&JsonValue::parse(&els.to_json()).unwrap()).unwrap();
+    assert_eq!(stored, els);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trip() {
+    use aml_lib::json::JsonValue;
+    use aml_lib::ReceiveContext;
+
+    let https = "v=1&emergency_number=112&time=1604912121123&location_latitude=48.85&location_longitude=2.35&location_time=1604912121000\
+                 &location_floor=2&location_semi_major_axis=30&location_semi_minor_axis=10&gt_location_latitude=48.851\
+                 &gt_location_longitude=2.351&adr_carcrash_time=1604912100000&adr_rollover=true&location_certainty=83";
+    let mut aml = AmlData::from_https_with(https, Profile::Els).unwrap().with_context(ReceiveContext {
+        received_at: Some(timestamp::from_unix(1604912122).unwrap()),
+        remote_ip: Some("192.0.2.1".parse().unwrap()),
+        ..Default::default()
+    });
+    aml.extras = vec![("vendor_cell_id".to_string(), JsonValue::Array(vec![JsonValue::Number(208.0), JsonValue::Number(4242.0)]))];
+
+    let json = serde_json::to_string(&aml).unwrap();
+    assert!(json.contains(r#""beginning_of_call":"2020-11-09T08:55:21.123Z""#), "{}", json);
+    assert!(json.contains(r#""ground_truth_latitude":48.851,"ground_truth_longitude":2.351"#), "{}", json);
+    assert!(json.ends_with(r#""vendor_cell_id":[208,4242]}"#), "{}", json);
+    assert_eq!(serde_json::from_str::<AmlData>(&json).unwrap(), aml);
+
+    // Same members as the JSON form of the data, in both directions
+    assert_eq!(AmlData::from_json_value(&JsonValue::parse(&json).unwrap()).unwrap(), aml);
+    assert_eq!(serde_json::from_str::<AmlData>(&aml.to_json()).unwrap(), aml);
+
+    let https_data = HttpsData::from_urlencoded(https);
+    assert_eq!(serde_json::from_str::<HttpsData>(&serde_json::to_string(&https_data).unwrap()).unwrap(), https_data);
+
+    let sms = SmsData::from_text(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;ls=G"#).unwrap();
+    let json = serde_json::to_value(&sms).unwrap();
+    assert_eq!((json["time_of_positioning"].as_str(), json.get("validation")), (Some("2020-06-26T16:00:19.000Z"), None));
+    assert_eq!(serde_json::from_value::<SmsData>(json).unwrap(), SmsData { validation: Default::default(), ..sms });
+}
+
+#[test]
+fn version_exact_sms_models() {
+    use aml_lib::SmsMessage;
+
+    let v1 = r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;lc=68;pm=G;si=208201771948415;ei=353472104343540;mcc=208;mnc=20;ml=126"#;
+    let message = SmsMessage::from_text(v1).unwrap();
+    match &message {
+        SmsMessage::V1(v1) => {
+            assert_eq!(v1.radius, Some(52.0));
+            assert_eq!(v1.mcc, Some(208));
+        }
+        SmsMessage::V2(_) => panic!("v1 message parsed as v2"),
+    }
+
+    // Flattened like SmsData::from_text, except for the validation
+    let flattened = SmsData::from(message);
+    let parsed = SmsData::from_text(v1).unwrap();
+    assert_eq!(flattened, SmsData { validation: Default::default(), ..parsed });
+
+    let v2 = r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;ls=F;hc=20810"#;
+    let parsed = SmsData::from_text(v2).unwrap();
+    assert_eq!(SmsData::from(SmsMessage::from_text(v2).unwrap()), SmsData { validation: Default::default(), ..parsed });
+    assert!(matches!(SmsMessage::from_text(r#"A"ML=3;lt=1"#), Err(aml_lib::AmlError::UnimplementedVersion)));
+
+    // Cleaned up like SmsData::from_text
+    let bom = format!("\u{feff}{}", v2);
+    assert_eq!(SmsData::from(SmsMessage::from_text(&bom).unwrap()), SmsData { validation: Default::default(), ..SmsData::from_text(&bom).unwrap() });
+    assert!(SmsMessage::from_text(r#"A"ML=2.1;lt=1"#).is_err());
+}
+
+#[test]
+fn unit_newtypes() {
+    use aml_lib::{Degrees, Meters, MetersPerSecond, Percent};
+
+    let https = r#"v=1&location_latitude=0.85732&location_longitude=-4.26325&location_accuracy=10.4&location_altitude=35.2&location_speed=1.5&location_confidence=83"#;
+    let aml = AmlData::from_https(https).unwrap();
+
+    let accuracy = aml.accuracy_meters().unwrap();
+    assert_eq!(accuracy.value(), 10.4);
+    assert!((accuracy.feet() - 34.12).abs() < 0.01);
+    assert_eq!(accuracy.to_string(), "10.4 m");
+    assert_eq!(aml.altitude_meters().unwrap() + accuracy, Meters(45.6));
+    assert_eq!(aml.speed_meters_per_second().unwrap() * 2.0, MetersPerSecond(3.0));
+    assert_eq!(aml.confidence_percent(), Some(Percent(83.0)));
+    assert_eq!(-aml.longitude_degrees().unwrap(), Degrees(4.26325));
+    assert_eq!(f64::from(aml.latitude_degrees().unwrap()), 0.85732);
+    assert_eq!(Meters(30.0) / Meters(20.0), 1.5);
+    assert_eq!(format!("{:.2}", MetersPerSecond(1.0 / 3.0)), "0.33 m/s");
+}
+
+#[test]
+fn movement() {
+    use aml_lib::MovementIssue;
+
+    let base = "v=1&location_latitude=48.85&location_longitude=2.35&location_time=1604912121000";
+    let parse = |extra: &str| AmlData::from_https(&format!("{}&{}", base, extra)).unwrap().movement();
+
+    let movement = parse("location_source=gps&location_bearing=0&location_speed=10");
+    assert!(movement.is_usable());
+    let at = movement.time_of_positioning.unwrap() + Duration::seconds(30);
+    let (latitude, _) = movement.project(at, Duration::minutes(1)).unwrap();
+    assert!((latitude - 48.85 - 300.0 / 111_195.0).abs() < 1e-5);
+    assert_eq!(movement.project(at, Duration::seconds(10)), None);
+    assert_eq!(movement.project(at - Duration::minutes(1), Duration::minutes(1)), None);
+
+    let movement = parse("location_source=gps&location_bearing=360&location_speed=-1");
+    assert!(!movement.is_usable());
+    assert_eq!(movement.issues, vec![MovementIssue::BearingOutOfRange, MovementIssue::NegativeSpeed]);
+
+    let movement = parse("location_source=cell&location_bearing=90&location_speed=10");
+    assert_eq!((movement.bearing, movement.speed), (None, None));
+    assert_eq!(movement.issues, vec![MovementIssue::CellOnly]);
+}
+
+#[test]
+fn adr_crash_notification() {
+    use aml_lib::json::JsonValue;
+
+    let https = "v=1&location_latitude=48.85&location_longitude=2.35&adr_carcrash_time=1604912121000&adr_severity=high&adr_rollover=1&adr_impact_direction=270&adr_occupants=2";
+    let aml = AmlData::from_https(https).unwrap();
+    let adr = aml.adr.clone().unwrap();
+    assert_eq!(adr.carcrash_time, Some(timestamp::from_unix_millis(1604912121000).unwrap()));
+    assert_eq!(adr.severity.as_deref(), Some("high"));
+    assert_eq!(adr.rollover, Some(true));
+    assert_eq!(adr.impact_direction, Some(270.0));
+    assert_eq!(adr.occupants, Some(2));
+    let report = aml_lib::conformance::check_https(https);
+    assert!(!report.findings.iter().any(|f| f.attribute.as_deref().is_some_and(|a| a.starts_with("adr_"))));
+
+    assert_eq!(AmlData::from_json_value(&JsonValue::parse(&aml.to_json()).unwrap()).unwrap(), aml);
+    assert_eq!(AmlData::from_https("v=1&location_latitude=48.85").unwrap().adr, None);
+}
+
+#[cfg(feature = "ecall")]
+#[test]
+fn ecall_msd_decoding() {
+    use aml_lib::ecall::Msd;
+
+    // UPER fields of a version 2 ECallMessage, as (value, bits)
+    let vin: Vec<(u64, usize)> = "WVWZZZ1JZ3W386752"
+        .bytes()
+        .map(|c| (b"0123456789ABCDEFGHJKLMNPRSTUVWXYZ".iter().position(|a| *a == c).unwrap() as u64, 6))
+        .collect();
+    let mut fields = vec![(2, 8), (0, 1), (0, 1), (0, 1), (1, 1), (0, 1), (1, 1), (5, 8), (1, 1), (0, 1), (1, 1), (0, 1), (0, 4)];
+    fields.extend(vin);
+    fields.extend([(0, 1), (0b0100000, 7), (1, 1)]); // propulsion: diesel only, the others default
+    fields.extend([(1604912121, 32), ((175_860_000 + 324_000_000) as u64, 30), ((8_460_000 + 648_000_000) as u64, 31), (45, 8)]);
+    fields.extend([((10 + 512) as u64, 10), ((-20 + 512) as u64, 10), (3, 8)]);
+
+    let pack = |fields: &[(u64, usize)]| {
+        let mut bytes = Vec::new();
+        for (i, bit) in fields.iter().flat_map(|(value, bits)| (0..*bits).rev().map(move |b| value >> b & 1)).enumerate() {
+            if i % 8 == 0 {
+                bytes.push(0);
+            }
+            *bytes.last_mut().unwrap() |= (bit as u8) << (7 - i % 8);
+        }
+        bytes
+    };
+    let mut bytes = pack(&fields);
+
+    let msd = Msd::from_uper(&bytes).unwrap();
+    assert_eq!(msd.vin, "WVWZZZ1JZ3W386752");
+    assert_eq!(msd.message_identifier, 5);
+    assert!(msd.automatic_activation && !msd.test_call && msd.position_can_be_trusted);
+    assert_eq!(msd.vehicle_type, 1);
+    assert!(msd.propulsion.diesel && !msd.propulsion.gasoline);
+    assert_eq!(msd.recent_location_n1, Some((10, -20)));
+    assert_eq!(msd.recent_location_n2, None);
+
+    let aml = AmlData::from_ecall_msd(&bytes).unwrap();
+    assert_eq!(aml.transport, "ecall");
+    assert_eq!(aml.latitude, Some(48.85));
+    assert_eq!(aml.longitude, Some(2.35));
+    assert_eq!(aml.bearing, Some(90.0));
+    assert_eq!(aml.time_of_positioning, timestamp::from_unix(1604912121));
+    assert_eq!(aml.adr.unwrap().occupants, Some(3));
+
+    bytes[0] = 1;
+    assert!(matches!(AmlData::from_ecall_msd(&bytes), Err(aml_lib::AmlError::UnimplementedVersion)));
+    assert!(matches!(AmlData::from_ecall_msd(&[2, 0]), Err(aml_lib::AmlError::InvalidMsd)));
+
+    // The vehicle types of version 2 end with L7e motorcycles, the 13th
+    fields[12] = (12, 4);
+    assert_eq!(Msd::from_uper(&pack(&fields)).unwrap().vehicle_type, 13);
+    fields[12] = (13, 4);
+    assert!(matches!(Msd::from_uper(&pack(&fields)), Err(aml_lib::AmlError::InvalidMsd)));
+}
+
+#[cfg(feature = "ecall")]
+#[test]
+fn ecall_msd_encoding() {
+    use aml_lib::ecall::Msd;
+
+    let bytes = hex::decode("02141681d71d8208014a00dd0c818714220afd481fcbb9684409c832380b60a7b00c").unwrap();
+    let msd = Msd::from_uper(&bytes).unwrap();
+    assert_eq!(msd.to_uper(), bytes);
+    assert!(msd.to_xml().contains("<isowmi>WVW</isowmi>"));
+    assert!(msd.to_xml().contains("<dieselTankPresent><true/></dieselTankPresent>"));
+
+    let https = "v=1&location_latitude=48.85&location_longitude=2.35&location_time=1604912121000&location_accuracy=500&location_bearing=91&adr_occupants=2";
+    let aml = AmlData::from_https(https).unwrap();
+    let msd = Msd::from_aml(&aml).unwrap();
+    assert!(!msd.position_can_be_trusted);
+    assert_eq!(msd.direction, 46);
+    assert_eq!(msd.number_of_passengers, Some(2));
+
+    let decoded = AmlData::from_ecall_msd(&aml.to_ecall_msd().unwrap()).unwrap();
+    assert_eq!((decoded.latitude, decoded.longitude), (Some(48.85), Some(2.35)));
+    assert_eq!(decoded.time_of_positioning, aml.time_of_positioning);
+    assert_eq!(AmlData::from_https("v=1").unwrap().to_ecall_msd(), None);
+}
+
+#[test]
+fn sms_text_tunneled_in_https() {
+    let text = r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;ls=G"#;
+    let encoded: String = url::form_urlencoded::byte_serialize(text.as_bytes()).collect();
+
+    let aml = AmlData::from_https_tunnel(&format!("id=42&message={}", encoded)).unwrap();
+    assert_eq!(aml.transport, "sms");
+    assert_eq!(aml.via.as_deref(), Some("https"));
+    assert_eq!(aml.emergency_number.as_deref(), Some("112"));
+    assert_eq!(aml_lib::batch::parse_line(&format!("payload={}", encoded)).unwrap().via.as_deref(), Some("https"));
+
+    // Plain HTTPS AML
+    let aml = AmlData::from_https_tunnel("v=1&location_latitude=55.85732&location_longitude=-4.26325").unwrap();
+    assert_eq!((aml.transport.as_str(), aml.via.as_deref()), ("https", None));
+}
+
+#[test]
+fn https_from_url() {
+    let query = "v=1&location_latitude=0.85732&location_longitude=-4.26325&device_number=%2B33611223344";
+    let expected = HttpsData::from_urlencoded(query);
+
+    assert_eq!(HttpsData::from_url(format!("https://psap.example:8443/aml?{}", query)), expected);
+    assert_eq!(HttpsData::from_url(format!("/aml?{}#fragment?x=1", query)), expected);
+    assert_eq!(HttpsData::from_url(format!("  ?{}\n", query)), expected);
+    assert_eq!(HttpsData::from_url("https://psap.example/aml#v=1"), HttpsData::default());
+}
+
+#[cfg(feature = "multipart")]
+#[test]
+fn https_multipart_body() {
+    let urlencoded = "v=1&device_number=%2B33611223344&location_latitude=0.85732&location_longitude=-4.26325&location_source=GPS";
+    let body = "preamble\r\n\
+        --aml-7f3a\r\nContent-Disposition: form-data; name=\"v\"\r\n\r\n1\r\n\
+        --aml-7f3a\r\nContent-Disposition: form-data; name=\"device_number\"\r\nContent-Type: text/plain\r\n\r\n+33611223344\r\n\
+        --aml-7f3a\r\nContent-Disposition: form-data; name=location_latitude\r\n\r\n0.85732\r\n\
+        --aml-7f3a\nContent-Disposition: form-data; name=\"location_longitude\"\n\n-4.26325\n\
+        --aml-7f3a\r\ncontent-disposition: form-data; name=\"location_source\"\r\n\r\nGPS\r\n\
+        --aml-7f3a--\r\n";
+    let content_type = "multipart/form-data; charset=utf-8; boundary=\"aml-7f3a\"";
+
+    assert_eq!(HttpsData::from_multipart(body.as_bytes(), content_type).unwrap(), HttpsData::from_urlencoded(urlencoded));
+    assert!(matches!(HttpsData::from_multipart(body.as_bytes(), "text/plain"), Err(aml_lib::AmlError::InvalidMultipart)));
+    assert!(matches!(
+        HttpsData::from_multipart(&body.as_bytes()[..body.len() - 14], content_type),
+        Err(aml_lib::AmlError::InvalidMultipart)
+    ));
+}
+
+#[test]
+fn https_json_body() {
+    let urlencoded = "v=1&device_number=%2B33611223344&location_latitude=0.85732&location_longitude=-4.26325&location_time=1604912121000&location_accuracy=10.4&location_source=GPS&location_certainty=83&device_languages=en,fr&hmac=f64c70eb238bb239e00e8ac8c023bf2b5d3c41dd";
+    let body = r#"{"v":"1","device_number":"+33611223344","location_latitude":0.85732,"location_longitude":-4.26325,
+        "location_time":1604912121000,"location_accuracy":10.4,"location_source":"GPS","location_certainty":83,
+        "device_languages":["en","fr"],"adr_carcrash_time":null,"hmac":"f64c70eb238bb239e00e8ac8c023bf2b5d3c41dd"}"#;
+    assert_eq!(HttpsData::from_json(body).unwrap(), HttpsData::from_urlencoded(urlencoded));
+
+    let flat = r#"{"v":"1","device_number":"+33611223344","location_latitude":0.85732,"location_longitude":-4.26325,"location_time":1604912121000,"location_accuracy":10.4,"location_source":"GPS","location_certainty":83,"hmac":"f64c70eb238bb239e00e8ac8c023bf2b5d3c41dd"}"#;
+    assert!(HttpsData::authenticate_json(flat, b"AML").is_ok());
+    assert!(HttpsData::authenticate_json(flat, b"KEY").is_err());
+
+    // Signed over the canonical form, numbers as written in the body
+    let canonical = "v=1&location_accuracy=10.40&location_time=1604912121000&device_model=ABC+ABC&device_languages=en%2Cfr&test=true";
+    let body = r#"{"v": "1", "location": {"accuracy": 10.40, "time": 1604912121000}, "device_model": "ABC ABC",
+        "device_languages": ["en", "fr"], "test": true, "hmac": "542f3358bd296e6e137b6c5af8f89a1a60bf0d40"}"#;
+    assert_eq!(HttpsData::authenticate_json(body, b"AML"), Ok(()));
+    assert_eq!(HttpsData::authenticate(format!("{}&hmac=542f3358bd296e6e137b6c5af8f89a1a60bf0d40", canonical), b"AML"), Ok(()));
+    assert!(HttpsData::authenticate_json(&body.replace("10.40", "10.4"), b"AML").is_err());
+    assert_eq!(HttpsData::authenticate_json(r#"{"v": "1", "hmac": "#, b"AML"), Err(aml_lib::AuthError::MalformedBody));
+
+    assert!(matches!(HttpsData::from_json("[1, 2]"), Err(aml_lib::AmlError::InvalidJson)));
+}
+
+#[test]
+fn precision_normalization() {
+    use aml_lib::precision::truncate_decimals;
+
+    assert_eq!(truncate_decimals(0.999999, 5), 0.99999);
+    assert_eq!(truncate_decimals(-0.000009, 5), 0.0);
+    assert_eq!(truncate_decimals(35.29, 1), 35.2);
+
+    let https = "v=1&location_latitude=48.826398&location_longitude=-2.3661999&location_altitude=35.29&location_vertical_accuracy=4.06";
+    let mut aml = AmlData::from_https(https).unwrap();
+    aml.normalize_precision();
+    assert_eq!((aml.latitude, aml.longitude), (Some(48.82639), Some(-2.36619)));
+    assert_eq!((aml.altitude, aml.vertical_accuracy), (Some(35.2), Some(4.0)));
+
+    let normalized = aml.clone();
+    aml.normalize_precision();
+    assert_eq!(aml, normalized);
+
+    // The generated messages are at the precision of the specification
+    let mut generator = aml_lib::generator::Generator::new(Default::default(), 3);
+    let mut aml = AmlData::from_text_sms(generator.sms_v2()).unwrap();
+    let parsed = aml.clone();
+    aml.normalize_precision();
+    assert_eq!(aml, parsed);
+}
+
+#[test]
+fn length_counting_modes() {
+    use aml_lib::{LengthMode, MessageLengths};
+
+    // '€' is 3 bytes, 1 character and 2 septets
+    let lengths = MessageLengths::of("A€[b");
+    assert_eq!(lengths, MessageLengths { bytes: 6, chars: 4, septets: 6 });
+    assert_eq!(lengths.get(LengthMode::Any, Some(4)), 4);
+    assert_eq!(lengths.get(LengthMode::Any, Some(5)), 6);
+
+    let text = r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;x=~;ml=66"#;
+    let sms = SmsData::from_text(text).unwrap();
+    assert!(!sms.is_valid());
+    assert_eq!(sms.validation.lengths, Some(MessageLengths { bytes: 65, chars: 65, septets: 66 }));
+    assert!(SmsData::from_text_with_length(text, LengthMode::Septets).unwrap().is_valid());
+    assert!(!SmsData::from_text_with_length(text, LengthMode::Chars).unwrap().is_valid());
+
+    let report = SmsData::from_text_with_length(text, LengthMode::Any).unwrap().validation;
+    assert_eq!((report.length_mode, report.computed_length), (LengthMode::Any, Some(66)));
+}
+
+#[test]
+fn test_message_detection() {
+    use aml_lib::json::JsonValue;
+
+    let base = "v=1&location_latitude=48.85&location_longitude=2.35";
+    let parse = |extra: &str| AmlData::from_https(&format!("{}&{}", base, extra)).unwrap();
+
+    assert!(!parse("emergency_number=112").is_test_message());
+    assert!(parse("emergency_number=933").is_test_message());
+    assert!(parse("emergency_number=112&test=true").is_test_message());
+    assert!(!parse("emergency_number=112&test=0").is_test_message());
+    assert!(parse("emergency_number=1599").is_test_message_with(&["1599"]));
+
+    let drill = parse("emergency_number=112&gt_location_latitude=48.8501&gt_location_longitude=2.3502");
+    assert_eq!(drill.ground_truth, Some((48.8501, 2.3502)));
+    assert!(drill.is_test_message());
+    assert!(AmlData::from_json_value(&JsonValue::parse(&drill.to_json()).unwrap()).unwrap().is_test_message());
+}
+
+#[test]
+fn error_codes_and_sources() {
+    use aml_lib::AmlError;
+    use std::error::Error;
+
+    let err = AmlData::from_base64_sms("QU1M=*").unwrap_err();
+    assert_eq!((err.code(), err.name()), (2, "invalid_base64"));
+    assert!(err.source().is_some());
+
+    let err = SmsData::from_hex("4151Z3").unwrap_err();
+    assert_eq!((err.code(), err.name()), (3, "invalid_hex"));
+    assert!(err.source().unwrap().downcast_ref::<hex::FromHexError>().is_some());
+
+    // The unit variants of the previous versions keep their codes
+    #[allow(deprecated)]
+    let (base64, hex) = (AmlError::InvalidBase64, AmlError::InvalidHex);
+    assert_eq!((base64.code(), hex.name()), (2, "invalid_hex"));
+    assert_eq!(base64.to_string(), AmlData::from_base64_sms("QU1M=*").unwrap_err().to_string());
+
+    assert_eq!((AmlError::InvalidPdu.code(), AmlError::InvalidPdu.name()), (8, "invalid_pdu"));
+    assert!(AmlError::InvalidUtf8(None).source().is_none());
+}
+
+#[test]
+fn v2_builder_and_getters() {
+    use aml_lib::v2::{Aml, BuildError};
+
+    let https = "v=1&emergency_number=112&location_latitude=48.85&location_longitude=2.35&location_accuracy=12";
+    let aml = Aml::from_https(https).unwrap();
+    assert_eq!((aml.version(), aml.accuracy(), aml.transport()), (Some("1"), Some(12.0), "https"));
+    assert_eq!(aml.data(), &AmlData::from_https(https).unwrap());
+
+    assert_eq!(Aml::builder().position(0.0, 181.0).build(), Err(BuildError::LongitudeOutOfRange));
+    assert_eq!(Aml::builder().accuracy(f64::NAN).build(), Err(BuildError::NegativeAccuracy));
+    assert_eq!(Aml::builder().confidence(120.0).build(), Err(BuildError::ConfidenceOutOfRange));
+    let aml = Aml::builder().emergency_number("112").confidence(68.0).build().unwrap();
+    assert_eq!(AmlData::from(aml).confidence, Some(68.0));
+}
+
+#[cfg(feature = "heapless")]
+#[test]
+fn heapless_parsing() {
+    use aml_lib::heapless::FixedSms;
+
+    let text = r#"A"ML=1;lt=+55.74317;lg=+037.61930;rd=50;top=20130717141935;lc=90;pm=W;si=250999999999999;ei=1234567890123456;mcc=250;mnc=99;ml=124"#;
+    let fixed = FixedSms::from_text(text).unwrap();
+    let sms = SmsData::from_text(text).unwrap();
+    assert_eq!((fixed.latitude, fixed.accuracy, fixed.time_of_positioning), (sms.latitude, sms.accuracy, sms.time_of_positioning));
+    assert_eq!((fixed.positioning_method, fixed.imsi.as_deref()), (Some('W'), sms.imsi.as_deref()));
+
+    let fixed = FixedSms::from_text(r#"A"ML=2;en=112;lg=en-GB,fr-FR,de-DE,es-ES,it-IT,nl-NL,pt-PT"#).unwrap();
+    assert!(fixed.overflow && fixed.languages.is_none());
+    assert!(FixedSms::from_text("A\"ML=3;lt=1").is_err());
+}
+
+#[test]
+fn septet_unpacking_by_words() {
+    // Reference: one septet at a time, bit by bit
+    let reference = |packed: &[u8], fill_bits: usize, count: usize| -> Vec<u8> {
+        (0..count)
+            .map(|i| (0..7).fold(0, |septet, b| {
+                let bit = fill_bits + i * 7 + b;
+                septet | ((packed[bit / 8] >> (bit % 8)) & 1) << b
+            }))
+            .collect()
+    };
+
+    let mut seed = 0x2545_f491_u32;
+    let packed: Vec<u8> = (0..140)
+        .map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed as u8
+        })
+        .collect();
+
+    for udh_length in 0..7 {
+        let fill_bits = (7 - (udh_length * 8) % 7) % 7;
+        for len in [0, 1, 7, 8, 15, 64, 140].iter().copied() {
+            let count = (len * 8 - fill_bits.min(len * 8)) / 7;
+            let decoded = SmsData::decode_7to8(&packed[..len], Some(count), udh_length);
+            assert_eq!(decoded, reference(&packed[..len], fill_bits, count), "udh {} len {}", udh_length, len);
+        }
+    }
+}
+
+#[test]
+fn single_pass_parser_keeps_semantics() {
+    let sms = SmsData::from_text(r#"A"ML=2;en=112;lo=48.82639,-2.36619;lz=35.5;nc=20801;hc=20815;lt=5;lt=30;et=1593187189;zz=1"#).unwrap();
+    assert_eq!((sms.latitude, sms.longitude, sms.accuracy), (Some(48.82639), Some(-2.36619), None));
+    assert_eq!((sms.altitude, sms.vertical_accuracy), (Some(35.5), None));
+    assert_eq!((sms.network_mcc, sms.network_mnc, sms.home_mnc), (Some(208), Some(1), Some(15)));
+    // The last value of a repeated attribute wins
+    assert_eq!(timestamp::unix(sms.time_of_positioning.unwrap()), 1593187189 + 30);
+}
+
+#[test]
+fn interner_bounds() {
+    use aml_lib::Interner;
+    use std::sync::Arc;
+
+    let mut interner = Interner::new(2);
+    assert!(Arc::ptr_eq(&interner.intern("G"), &interner.intern("G")));
+    let long = "x".repeat(64);
+    assert!(!Arc::ptr_eq(&interner.intern(&long), &interner.intern(&long)));
+    interner.intern("W");
+    interner.intern("C");
+    assert_eq!(interner.len(), 2);
+
+    let tags = AmlData::from_https("v=1&location_source=gps&source=call").unwrap().tags(&mut interner);
+    assert_eq!((tags.version.as_deref(), tags.transport.as_deref()), (Some("1"), Some("https")));
+}
+
+#[test]
+fn diff_lists_changed_fields() {
+    use aml_lib::FieldChange;
+
+    let text = r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;ls=G"#;
+    let sms = AmlData::from_text_sms(text).unwrap();
+    assert!(sms.diff(&sms.clone()).is_empty());
+
+    let mut update = sms.clone();
+    update.positioning_method = None;
+    update.time_of_positioning = update.time_of_positioning.map(|top| top + Duration::seconds(5));
+    update.ground_truth = Some((48.8, -2.3));
+    assert_eq!(
+        sms.diff(&update),
+        [
+            FieldChange {
+                field: "time_of_positioning",
+                old: Some("2020-06-26T16:00:19+00:00".to_string()),
+                new: Some("2020-06-26T16:00:24+00:00".to_string()),
+            },
+            FieldChange { field: "positioning_method", old: Some("G".to_string()), new: None },
+            FieldChange { field: "ground_truth", old: Some("None".to_string()), new: Some("Some((48.8, -2.3))".to_string()) },
+        ]
+    );
+}
+
+#[test]
+fn aml_to_sms_v2() {
+    let https = "v=1&emergency_number=112&source=call&time=1604912121500&location_latitude=48.8512345&location_longitude=2.35&location_time=1604912130900&location_altitude=35.27&location_vertical_accuracy=3&location_source=wifi&device_imei=358239059042542&device_imsi=208201771948415&cell_network_mcc=208&cell_network_mnc=1&device_languages=fr-FR";
+    let aml = AmlData::from_https(https).unwrap();
+    let (sms, dropped) = aml.to_sms_v2();
+    assert_eq!(dropped, ["source_of_activation", "imsi"]);
+
+    let text = sms.to_text();
+    assert_eq!(text, r#"A"ML=2;en=112;et=1604912121;lo=48.85123,2.35;lt=9;lz=35.2,3;ls=W;ei=358239059042542;nc=20801;lg=fr-FR"#);
+    let parsed = SmsData::from_text(&text).unwrap();
+    assert_eq!((parsed.time_of_positioning, parsed.network_mnc), (sms.time_of_positioning, Some(1)));
+    assert!(sms.is_valid());
+
+    let v1 = r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;lc=68;pm=G;si=208201771948415;ei=353472104343540;mcc=208;mnc=20;ml=126"#;
+    assert_eq!(SmsData::from_text(v1).unwrap().to_text(), v1);
+
+    // Values which would inject attributes are dropped, not pasted
+    let https = "v=1&emergency_number=112%3Blo%3D0%2C0%2C1&time=1604912121000&location_latitude=48.85&location_longitude=2.35&device_imei=35823905904254%3D2&device_languages=fr&location_source=gps";
+    let (sms, dropped) = AmlData::from_https(https).unwrap().to_sms_v2();
+    assert_eq!(dropped, ["emergency_number", "imei"]);
+    assert_eq!(sms.to_text(), r#"A"ML=2;et=1604912121;lo=48.85,2.35;ls=G;lg=fr"#);
+
+    let mut sms = SmsData::from_text(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;ls=G"#).unwrap();
+    sms.positioning_method = Some("G;lo=0,0,1".to_string());
+    assert_eq!(sms.to_text(), r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30"#);
+}
+
+#[test]
+fn convert_with_loss_report() {
+    use aml_lib::TargetFormat;
+
+    let https = "v=1&emergency_number=112&source=call&time=1604912121500&location_latitude=48.8512345&location_longitude=2.35&location_time=1604912130000&location_source=fused&location_bearing=90&location_speed=3&device_imsi=208201771948415&cell_home_mcc=208&cell_home_mnc=1&adr_severity=high&test=1";
+    let aml = AmlData::from_https(https).unwrap();
+
+    let (encoded, loss) = aml.convert(TargetFormat::Https);
+    assert!(loss.is_lossless());
+    assert_eq!(AmlData::from_https(&encoded).unwrap(), aml);
+
+    let (v1, loss) = aml.convert(TargetFormat::SmsV1);
+    assert!(SmsData::from_text(&v1).unwrap().is_valid());
+    assert_eq!(
+        loss.dropped,
+        ["emergency_number", "source_of_activation", "beginning_of_call", "bearing", "speed", "home_mcc", "home_mnc", "test", "adr"]
+    );
+    assert_eq!(loss.degraded, ["latitude", "positioning_method"]);
+
+    let (_, loss) = aml.convert(TargetFormat::SmsV2);
+    assert!(loss.dropped.contains(&"imsi") && !loss.dropped.contains(&"home_mcc"));
+    assert_eq!(loss.degraded, ["beginning_of_call", "latitude"]);
+}
+
+#[test]
+fn uncertainty_ellipse() {
+    use aml_lib::geo::{haversine_distance as distance, Ellipse};
+    use aml_lib::TargetFormat;
+
+    let https = "v=1&location_latitude=48.85&location_longitude=2.35&location_accuracy=80&location_semi_major_axis=80&location_semi_minor_axis=15&location_orientation=0&location_confidence=68";
+    let aml = AmlData::from_https(https).unwrap();
+    assert_eq!(aml.ellipse, Some(Ellipse { semi_major: 80.0, semi_minor: 15.0, orientation: 0.0 }));
+    assert!(aml_lib::conformance::check_https(https).findings.iter().all(|finding| finding.attribute.as_deref() != Some("location_orientation")));
+
+    // North-south ellipse: the quarter points are 80 m north and 15 m east
+    let polygon = aml.uncertainty_polygon(4).unwrap();
+    assert!((distance((48.85, 2.35), polygon[0]) - 80.0).abs() < 0.01 && polygon[0].0 > 48.85);
+    assert!((distance((48.85, 2.35), polygon[1]) - 15.0).abs() < 0.01 && polygon[1].1 > 2.35);
+
+    let back = AmlData::from_json_value(&aml_lib::json::JsonValue::parse(&aml.to_json()).unwrap()).unwrap();
+    assert_eq!(back.ellipse, aml.ellipse);
+    let (encoded, _) = aml.convert(TargetFormat::Https);
+    assert_eq!(AmlData::from_https(&encoded).unwrap().ellipse, aml.ellipse);
+    assert!(aml.convert(TargetFormat::SmsV2).1.dropped.contains(&"ellipse"));
+}
+
+#[test]
+fn eena_accuracy_classes() {
+    use aml_lib::AccuracyClass;
+
+    let class = |query: &str| AmlData::from_https(&format!("v=1&location_latitude=48.85&location_longitude=2.35&{}", query)).unwrap().accuracy_class();
+    assert_eq!(class("location_source=gps&location_accuracy=30"), AccuracyClass::HandsetUnder50m);
+    // 60 m at 95 % is about 30 m at 68 %
+    assert_eq!(class("location_source=wifi&location_accuracy=60&location_confidence=0.95"), AccuracyClass::HandsetUnder50m);
+    assert_eq!(class("location_source=fused&location_accuracy=80"), AccuracyClass::HandsetUnder100m);
+    assert_eq!(class("location_source=gps&location_accuracy=250"), AccuracyClass::HandsetOver100m);
+    assert_eq!(class("location_source=unknown&location_accuracy=10"), AccuracyClass::Network);
+    assert_eq!(class("location_source=gps"), AccuracyClass::Unknown);
+    assert_eq!(AccuracyClass::HandsetUnder50m.label(), "<50 m handset");
+
+    // lc=1 is a 1 % confidence: 40 m at 1 % is far above 100 m at 68 %
+    let sms = AmlData::from_text_sms(r#"A"ML=2;lo=48.85,2.35,40;lc=1;ls=G"#).unwrap();
+    assert_eq!((sms.confidence_ratio(), sms.accuracy_class()), (Some(0.01), AccuracyClass::HandsetOver100m));
+}
+
+#[test]
+fn roaming_detection() {
+    use aml_lib::Roaming;
+
+    let roaming = |codes: &str| AmlData::from_text_sms(format!(r#"A"ML=2;en=112;lo=48.8,2.3,20;{}"#, codes)).unwrap().roaming();
+    assert_eq!(roaming("nc=20801;hc=20801"), Some(Roaming::Home));
+    assert_eq!(roaming("nc=310260;hc=311480"), Some(Roaming::National));
+    assert_eq!(roaming("nc=26201;hc=20810"), Some(Roaming::International));
+    assert_eq!(roaming("nc=20801"), None);
+
+    let mut aml = AmlData::from_text_sms(r#"A"ML=2;en=112;lo=48.8,2.3,20;nc=20801;hc=20810"#).unwrap();
+    aml.network_mnc = None;
+    assert_eq!(aml.roaming(), Some(Roaming::Home));
+}
+
+#[test]
+fn emergency_number_registry() {
+    use aml_lib::emergency::{country_of_mcc, emergency_numbers};
+    use aml_lib::EmergencyNumberCheck;
+
+    let check = |en: &str, nc: &str| AmlData::from_text_sms(format!(r#"A"ML=2;en={};lo=48.8,2.3,20;nc={}"#, en, nc)).unwrap().check_emergency_number();
+    assert_eq!(check("999", "23415"), EmergencyNumberCheck::Recognized);
+    assert_eq!(check("911", "20801"), EmergencyNumberCheck::Recognized);
+    assert_eq!(check("999", "20801"), EmergencyNumberCheck::Unrecognized { country: "FR" });
+    assert_eq!(check("911", "99901"), EmergencyNumberCheck::Recognized);
+    assert_eq!(check("113", "99901"), EmergencyNumberCheck::UnknownCountry);
+    assert_eq!(AmlData::from_text_sms(r#"A"ML=2;en=112;lo=48.8,2.3,20"#).unwrap().check_emergency_number(), EmergencyNumberCheck::Missing);
+
+    assert_eq!(country_of_mcc(311), Some("US"));
+    assert!(emergency_numbers("gb").unwrap().contains(&"999"));
+}
+
+#[test]
+fn dialed_number_service_type() {
+    use aml_lib::ServiceType;
+
+    let service = |en: &str, codes: &str| AmlData::from_text_sms(format!(r#"A"ML=2;en={};lo=48.8,2.3,20;{}"#, en, codes)).unwrap().service_type();
+    assert_eq!(service("17", "nc=20801"), Some(ServiceType::Police));
+    assert_eq!(service("18", "hc=20810"), Some(ServiceType::Rescue));
+    assert_eq!(service("112", "nc=24201"), Some(ServiceType::Police));
+    assert_eq!(service("999", "nc=26001"), Some(ServiceType::Ambulance));
+    assert_eq!(service("999", "nc=23415"), Some(ServiceType::General));
+    assert_eq!(service("911", "nc=99901"), Some(ServiceType::General));
+    assert_eq!(service("3615", "nc=20801"), None);
+}
+
+#[test]
+fn clock_skew() {
+    use aml_lib::{SkewIssue, SkewPolicy};
+
+    let policy = SkewPolicy::default();
+    let mut aml = AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;ls=G"#).unwrap();
+
+    let on_time = aml.clock_skew(timestamp::from_unix(1593187225).unwrap(), &policy);
+    assert_eq!(on_time.offset, Some(Duration::seconds(-6)));
+    assert!(on_time.is_plausible());
+
+    let late = aml.clock_skew(timestamp::from_unix(1593187189).unwrap() + Duration::hours(3), &policy);
+    assert_eq!(late.issues, [SkewIssue::CallTooOld { age: Duration::hours(3) }]);
+
+    let ahead = timestamp::from_unix(1593187189).unwrap() - Duration::hours(1);
+    assert_eq!(aml.correct_clock_skew(ahead + Duration::hours(2), &policy), None);
+    assert_eq!(aml.correct_clock_skew(ahead, &policy), Some(Duration::seconds(3630)));
+    assert_eq!(aml.time_of_positioning, Some(ahead));
+    assert_eq!(aml.beginning_of_call, Some(ahead - Duration::seconds(30)));
+    assert!(aml.clock_skew(ahead, &policy).is_plausible());
+}
+
+#[test]
+fn receive_context() {
+    use aml_lib::json::JsonValue;
+    use aml_lib::ReceiveContext;
+
+    let context = ReceiveContext {
+        received_at: Some(timestamp::from_unix(1593187225).unwrap()),
+        source_msisdn: Some("+33611223344".to_string()),
+        gateway_id: Some("smsc-1".to_string()),
+        remote_ip: Some("192.0.2.7".parse().unwrap()),
+    };
+    let aml = AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30"#)
+        .unwrap()
+        .with_context(context.clone());
+
+    let stored = AmlData::from_json_value(&JsonValue::parse(&aml.to_json()).unwrap()).unwrap();
+    assert_eq!(stored.context.as_ref(), Some(&context));
+
+    let mut merged = AmlData::default();
+    merged.merge(aml.clone());
+    assert_eq!(merged.context, Some(context));
+
+    let redacted = aml.redacted(&RedactionPolicy::default());
+    assert_eq!(redacted.context.and_then(|context| context.source_msisdn), None);
+
+    assert_eq!(aml_lib::Session::new(aml).device.as_deref(), Some("+33611223344"));
+}
+
+#[test]
+fn udh_application_ports() {
+    use aml_lib::ApplicationPorts;
+
+    let text = r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;ml=61"#;
+
+    // Concatenation then 8 bit ports, 8 bit data
+    let header = "09000301010104021A0B";
+    let pdu = format!("00440B913316213243F4000402112151120040{:02X}{}{}", text.len() + 10, header, hex::encode(text));
+    let envelope = SmsData::pdu_envelope(&pdu).unwrap();
+    assert_eq!(envelope.information_elements(), [(0x00, &[1, 1, 1][..]), (0x04, &[0x1A, 0x0B][..])]);
+    assert_eq!(envelope.ports(), Some(ApplicationPorts { destination: 26, source: 11 }));
+    assert_eq!(SmsData::from_pdu_hex(&pdu).unwrap().0.latitude, Some(48.82639));
+
+    let pdu = format!("00040B913316213243F4000402112151120040{:02X}{}", text.len(), hex::encode(text));
+    assert_eq!(SmsData::pdu_envelope(&pdu).unwrap().ports(), None);
+}
+
+#[test]
+fn escaped_sms_text() {
+    let text = r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;ml=61"#;
+    let sms = SmsData::from_text(text).unwrap();
+
+    let encoded = "A%22ML%3D1%3Blt%3D48.82639%3Blg%3D-2.36619%3Brd%3D52%3Btop%3D20191112112928%3Bml%3D61";
+    assert_eq!(SmsData::from_escaped_text(encoded).unwrap(), sms);
+    assert_eq!(SmsData::from_escaped_text(format!(r#""{}""#, text.replace('"', r#"\""#))).unwrap(), sms);
+    assert_eq!(SmsData::from_escaped_text(text).unwrap(), sms);
+    assert!(SmsData::from_text(encoded).is_err());
+    assert_eq!(AmlData::from_escaped_text_sms(encoded).unwrap().latitude, Some(48.82639));
+
+    // Backslashes are unescaped in one pass, NUL characters are kept
+    assert_eq!(SmsData::unescape(r#"A\"ML=2;en=112\\;\x\\\""#), r#"A"ML=2;en=112\;\x\""#);
+    assert_eq!(SmsData::unescape("A\\\"ML=2;en=\u{0}112"), "A\"ML=2;en=\u{0}112");
+}
+
+#[test]
+fn noisy_sms_text() {
+    use aml_lib::TextCleanup;
+
+    let text = r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;ml=61"#;
+    let sms = SmsData::from_text(text).unwrap();
+    assert!(sms.validation.cleanup.is_empty());
+
+    let noisy = SmsData::from_text(format!("\u{feff}{}\r\n\0\0\0", text)).unwrap();
+    assert!(noisy.is_valid());
+    assert_eq!(noisy.message_length, Some(61));
+    assert_eq!(
+        noisy.validation.cleanup,
+        [TextCleanup::ByteOrderMark, TextCleanup::Whitespace, TextCleanup::TrailingNul]
+    );
+    assert_eq!(SmsData { validation: sms.validation.clone(), ..noisy }, sms);
+}
+
+#[test]
+fn conformance_attribute_order() {
+    use aml_lib::conformance::{self, Severity};
+
+    let report = conformance::check_sms(r#"A"ML=2;et=1593187189;en=112;lo=48.82639,-2.36619,20;lc=68;lt=30;ls=G"#);
+    assert!(report.is_conformant());
+    let order: Vec<_> = report.findings.iter().map(|f| (f.rule, f.severity, f.attribute.as_deref())).collect();
+    assert_eq!(order, [("SMS-09", Severity::Warning, Some("en")), ("SMS-09", Severity::Warning, Some("lt"))]);
+    assert!(report.checked.contains(&"SMS-09"));
+    assert!(conformance::rule("SMS-09").is_some());
+    assert!(conformance::rule("SMS-99").is_none());
+}
+
+#[test]
+fn declarative_key_mapping() {
+    use aml_lib::KeyMapping;
+
+    let mapping = KeyMapping::from_json(
+        r#"{"acc": "accuracy", "tpos": {"field": "time_of_positioning", "unit": "ms"}, "alt_ft": {"field": "altitude", "scale": 0.3048}}"#,
+    )
+    .unwrap();
+    let aml = AmlData::from_text_sms_mapped(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619;acc=15;tpos=1593187200000;alt_ft=100"#, &mapping).unwrap();
+    assert_eq!(aml.accuracy, Some(15.0));
+    assert_eq!(aml.time_of_positioning, Some(timestamp::from_unix(1593187200).unwrap()));
+    assert!((aml.altitude.unwrap() - 30.48).abs() < 1e-9);
+
+    assert!(KeyMapping::from_json(r#"{"acc": {"field": "accuracy", "unit": "h"}}"#).is_err());
+    assert!(KeyMapping::from_json("[]").is_err());
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn toml_key_mapping() {
+    use aml_lib::KeyMapping;
+
+    let json = KeyMapping::from_json(
+        r#"{"acc": "accuracy", "tpos": {"field": "time_of_positioning", "unit": "ms"}, "alt_ft": {"field": "altitude", "scale": 0.3048}}"#,
+    )
+    .unwrap();
+    let toml = KeyMapping::from_toml(
+        r#"
+        acc = "accuracy"
+        tpos = { field = "time_of_positioning", unit = "ms" }
+
+        [alt_ft]
+        field = "altitude"
+        scale = 0.3048
+        "#,
+    )
+    .unwrap();
+    assert_eq!(toml.rules.len(), 3);
+    for rule in &json.rules {
+        assert!(toml.rules.contains(rule), "{:?}", rule);
+    }
+
+    let invalid = KeyMapping::from_toml(r#"acc = { field = "accuracy", unit = "h" }"#).unwrap_err();
+    assert_eq!(invalid.message(), "invalid key mapping");
+    assert!(KeyMapping::from_toml("acc = 1").is_err());
+    assert!(KeyMapping::from_toml("acc = ").unwrap_err().span().is_some());
+}
+
+#[test]
+fn dropped_field_observer() {
+    use aml_lib::{DropReason, DroppedField};
+
+    let mut dropped = Vec::new();
+    let mut observer = |field: &DroppedField| dropped.push((field.transport, field.key.to_string(), field.value.to_string(), field.reason));
+    let https = "v=1&location_latitude=48.85&location_latitude=48.86&location_longitude=east&location_accuracy=12&location_floor_label=3";
+    let aml = AmlData::from_https_observed(https, &mut observer).unwrap();
+
+    assert_eq!(aml.latitude, Some(48.86));
+    assert_eq!(
+        dropped,
+        [
+            ("https", "location_latitude".to_string(), "48.85".to_string(), DropReason::Repeated),
+            ("https", "location_longitude".to_string(), "east".to_string(), DropReason::Invalid),
+            ("https", "location_floor_label".to_string(), "3".to_string(), DropReason::Unknown),
+        ]
+    );
+
+    let mut count = 0;
+    let text = r#"A"ML=2;en=112;lo=48.82639,-2.36619,20;lt=30;ls=G"#;
+    AmlData::from_text_sms_observed(text, &mut |field: &DroppedField| {
+        assert_eq!((field.key, field.reason), ("lt", DropReason::Invalid));
+        count += 1;
+    })
+    .unwrap();
+    assert_eq!(count, 1);
+}
+
+#[test]
+fn parse_statistics() {
+    use aml_lib::Stats;
+
+    let mut stats = Stats::default();
+    stats.parse_sms(r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;ml=61"#).unwrap();
+    stats.parse_sms(r#"A"ML=1;lt=48.82639;lg=-2.36619;rd=8;top=20191112112928;ml=99"#).unwrap();
+    stats.parse_https("v=1&location_latitude=48.85&location_longitude=2.35&location_accuracy=east").unwrap();
+    stats.record(&Err(aml_lib::AmlError::InvalidPdu));
+
+    let snapshot = stats.take();
+    assert_eq!(stats, Stats::default());
+    assert_eq!(snapshot.messages, 4);
+    assert_eq!(snapshot.by_version[&("sms".to_string(), "1".to_string())], 2);
+    assert_eq!(snapshot.errors["invalid_pdu"], 1);
+    assert_eq!(snapshot.invalid_rate(), Some(1.0 / 3.0));
+    assert_eq!(snapshot.validation_failures["length_mismatch"], 1);
+    assert_eq!(snapshot.invalid_values[&("https".to_string(), "location_accuracy".to_string())], 1);
+    assert_eq!((snapshot.accuracy[0], snapshot.accuracy[3], snapshot.unknown_accuracy), (1, 1, 1));
+
+    let json = snapshot.to_json_value();
+    assert_eq!(json.get("accuracy").and_then(|accuracy| accuracy.get("<=100")).and_then(|count| count.as_f64()), Some(1.0));
+
+    // The keys sent by the handsets are bounded
+    use aml_lib::{MAX_DISTINCT_KEYS, OTHER_KEY};
+    for i in 0..MAX_DISTINCT_KEYS + 10 {
+        stats.parse_https(&format!("v={}&location_latitude=48.85&x{}=1", i, i)).unwrap();
+    }
+    stats.parse_https("v=0&x0=1").unwrap();
+    assert_eq!((stats.unknown_keys.len(), stats.by_version.len()), (MAX_DISTINCT_KEYS + 1, MAX_DISTINCT_KEYS + 1));
+    assert_eq!(stats.unknown_keys[&("https".to_string(), OTHER_KEY.to_string())], 10);
+    assert_eq!(stats.unknown_keys[&("https".to_string(), "x0".to_string())], 2);
+}
+
+#[cfg(feature = "kafka")]
+#[test]
+fn kafka_sink() {
+    use aml_lib::kafka::{KafkaProducer, KafkaRecord, KafkaSink};
+    use aml_lib::ReceiveContext;
+    use rdkafka::producer::{BaseProducer, Producer};
+
+    struct Recorder(Vec<KafkaRecord>);
+
+    impl KafkaProducer for Recorder {
+        type Error = ();
+
+        fn send(&mut self, record: KafkaRecord) -> Result<(), ()> {
+            self.0.push(record);
+            Ok(())
+        }
+    }
+
+    let context = ReceiveContext {
+        source_msisdn: Some("+33611223344".to_string()),
+        ..Default::default()
+    };
+    let aml = AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20"#).unwrap().with_context(context);
+
+    let mut sink = KafkaSink::new(Recorder(Vec::new()), "aml");
+    sink.publish(&aml).unwrap();
+    let record = &sink.producer().0[0];
+    assert_eq!((record.topic.as_str(), record.key.as_str()), ("aml", "+33611223344@1593187189"));
+    assert!(record.payload.contains(r#""source_msisdn":"+33611223344""#));
+    assert!(record.headers.contains(&("aml-version".to_string(), "2".to_string())));
+
+    // Without a broker, rdkafka queues the record, with the requests of the client,
+    // until they are purged on drop
+    let producer: BaseProducer = rdkafka::ClientConfig::new().set("bootstrap.servers", "127.0.0.1:1").set("log_level", "0").create().unwrap();
+    let mut sink = KafkaSink::new(producer, "aml");
+    sink.publish(&aml).unwrap();
+    assert!(sink.producer().in_flight_count() > 0);
+}
+
+#[cfg(feature = "mqtt")]
+#[test]
+fn mqtt_sink() {
+    use aml_lib::mqtt::{MqttClient, MqttSink, Qos, TopicTemplate};
+
+    struct Recorder(Vec<(String, Qos, bool)>);
+
+    impl MqttClient for Recorder {
+        type Error = ();
+
+        fn publish(&mut self, topic: &str, payload: &[u8], qos: Qos, retain: bool) -> Result<(), ()> {
+            assert!(payload.starts_with(b"{"));
+            self.0.push((topic.to_string(), qos, retain));
+            Ok(())
+        }
+    }
+
+    let aml = AmlData::from_text_sms(r#"A"ML=2;en=112;lo=48.8,2.3,20;nc=26201"#).unwrap();
+    let mut sink = MqttSink::new(Recorder(Vec::new()), TopicTemplate::new("aml/{country}/{psap}/{mnc}"))
+        .with_qos(Qos::ExactlyOnce)
+        .with_retain(true)
+        .with_resolver(|name, aml| (name == "psap" && aml.latitude.is_some()).then(|| "berlin".to_string()));
+
+    assert_eq!(sink.publish(&aml).unwrap(), "aml/DE/berlin/01");
+    assert_eq!(sink.client().0, [("aml/DE/berlin/01".to_string(), Qos::ExactlyOnce, true)]);
+    assert_eq!(TopicTemplate::new("aml/{imei}/{").render(&aml, &|_, _| None), "aml/unknown/{");
+
+    // The requests queued by rumqttc for its event loop
+    let (requests, queued) = flume::unbounded();
+    let mut sink = MqttSink::new(rumqttc::Client::from_sender(requests), TopicTemplate::new("aml/{country}")).with_retain(true);
+    sink.publish(&aml).unwrap();
+    match queued.try_recv().unwrap() {
+        rumqttc::Request::Publish(publish) => {
+            assert_eq!((publish.topic.as_str(), publish.qos, publish.retain), ("aml/DE", rumqttc::QoS::AtLeastOnce, true));
+            assert_eq!(&publish.payload[..], aml.to_json().as_bytes());
+        }
+        request => panic!("unexpected request {:?}", request),
+    }
+}
+
+#[cfg(feature = "sql")]
+#[test]
+fn sql_rows() {
+    use aml_lib::sql::{self, SqlType, SqlValue, COLUMNS};
+
+    let https = "v=1&emergency_number=112&time=1604912121000&location_latitude=48.85&location_longitude=2.35&location_semi_major_axis=30&location_semi_minor_axis=10";
+    let aml = AmlData::from_https(https).unwrap();
+    let values = aml.to_sql_values();
+
+    let column = |name: &str| COLUMNS.iter().position(|(column, _)| *column == name).unwrap();
+    assert_eq!(values[column("beginning_of_call")], SqlValue::Timestamp(timestamp::from_unix(1604912121).unwrap()));
+    assert_eq!(values[column("imei")], SqlValue::Null(SqlType::Text));
+    assert_eq!(values[column("test")], SqlValue::Bool(false));
+
+    match &values[column("document")] {
+        SqlValue::Text(document) => assert_eq!(AmlData::from_sql_document(document).unwrap(), aml),
+        other => panic!("{:?}", other),
+    }
+
+    let insert = sql::insert_statement("aml");
+    assert!(insert.starts_with("INSERT INTO aml (version, emergency_number,"));
+    assert!(insert.ends_with(&format!("${}::JSONB)", COLUMNS.len())));
+    assert!(!sql::create_table("aml", false).contains("GEOGRAPHY"));
+
+    let schema = sql::diesel_schema("aml");
+    assert_eq!(schema.lines().filter(|line| line.contains("->")).count(), COLUMNS.len() + 1);
+    assert!(schema.contains("        beginning_of_call -> Nullable<Timestamptz>,\n"));
+    assert!(schema.contains("        test -> Bool,\n"));
+}
+
+#[cfg(feature = "sqlx")]
+#[test]
+fn sqlx_bind() {
+    use aml_lib::sql::{self, COLUMNS};
+    use sqlx::{Arguments, Execute};
+
+    let aml = AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20"#).unwrap();
+    let statement = sql::insert_statement("aml_messages");
+    let mut query = sql::sqlx::bind(sqlx::query(&statement), &aml);
+    assert_eq!(query.take_arguments().unwrap().unwrap().len(), COLUMNS.len());
+}
+
+#[cfg(feature = "diesel")]
+aml_lib::aml_table!(aml_messages);
+
+#[cfg(feature = "diesel")]
+#[test]
+fn diesel_insertable() {
+    use aml_lib::sql::COLUMNS;
+
+    let aml = AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20"#).unwrap();
+    let insert = diesel::insert_into(aml_messages::table).values(&aml);
+    let statement = diesel::debug_query::<diesel::pg::Pg, _>(&insert).to_string();
+
+    let columns: Vec<String> = COLUMNS.iter().map(|(name, _)| format!("\"{}\"", name)).collect();
+    assert!(statement.starts_with(&format!("INSERT INTO \"aml_messages\" ({}) VALUES ($1, $2", columns.join(", "))));
+    assert!(statement.contains("Some(48.82639)"));
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn arrow_columns() {
+    use aml_lib::arrow::{self, ArrowType, Column, ColumnBatch};
+
+    let schema = arrow::schema();
+    assert_eq!(schema[0], ("version", ArrowType::Utf8, true));
+    assert!(schema.contains(&("test", ArrowType::Boolean, false)));
+    assert!(schema.contains(&("time_of_positioning", ArrowType::TimestampMillis, true)));
+
+    let messages = [
+        AmlData::from_https("v=1&emergency_number=112&location_latitude=48.85&location_longitude=2.35&cell_network_mcc=208&cell_network_mnc=01").unwrap(),
+        AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;ei=358239059042542"#).unwrap(),
+    ];
+    let batch = ColumnBatch::of(&messages);
+    assert_eq!(batch.columns.len(), schema.len());
+    assert!(batch.columns.iter().all(|(_, column)| column.len() == 2));
+    assert_eq!(batch.column("network_mcc"), Some(&Column::Integer(vec![Some(208), None])));
+    assert_eq!(batch.column("imei"), Some(&Column::Text(vec![None, Some("358239059042542".to_string())])));
+    assert_eq!(batch.column("transport"), Some(&Column::Text(vec![Some("https".to_string()), Some("sms".to_string())])));
+    assert_eq!(ColumnBatch::of(&[]).rows(), 0);
+
+    let record_batch = batch.to_record_batch().unwrap();
+    assert_eq!((record_batch.num_rows(), record_batch.num_columns()), (2, schema.len()));
+    assert_eq!(record_batch.schema(), arrow::arrow_schema());
+    assert_eq!(record_batch.column_by_name("imei").unwrap().null_count(), 1);
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn parquet_file() {
+    use aml_lib::arrow::{self, parquet::ParquetWriter};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    let messages = [
+        AmlData::from_https("v=1&emergency_number=112&location_latitude=48.85&location_longitude=2.35").unwrap(),
+        AmlData::from_text_sms(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;ei=358239059042542"#).unwrap(),
+    ];
+    let path = std::env::temp_dir().join(format!("aml-{}.parquet", std::process::id()));
+    let mut writer = ParquetWriter::new(std::fs::File::create(&path).unwrap()).unwrap();
+    writer.write(&messages[..1]).unwrap();
+    writer.write(&messages[1..]).unwrap();
+    writer.finish().unwrap();
+
+    let reader = ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path).unwrap()).unwrap().build().unwrap();
+    let batches: Vec<_> = reader.map(Result::unwrap).collect();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(batches, [arrow::record_batch(&messages).unwrap()]);
+}
+
+#[cfg(feature = "msgpack")]
+#[test]
+fn msgpack_round_trip() {
+    use aml_lib::json::JsonValue;
+    use aml_lib::{msgpack, AmlError, ReceiveContext};
+
+    let https = "v=1&emergency_number=112&time=1604912121123&location_latitude=48.85&location_longitude=2.35&location_altitude=-12&cell_network_mcc=208&cell_network_mnc=01&vendor_field=1";
+    let aml = AmlData::from_https(https).unwrap().with_context(ReceiveContext {
+        received_at: Some(timestamp::from_unix(1604912122).unwrap()),
+        gateway_id: Some("gw-1".to_string()),
+        ..Default::default()
+    });
+    let bytes = aml.to_msgpack();
+    assert!(bytes.windows(6).any(|window| window == [0xd6, 0xff, 0x5f, 0xa9, 0x03, 0xfa]));
+    assert_eq!(AmlData::from_msgpack(&bytes).unwrap(), aml);
+
+    let long = JsonValue::Array(vec![JsonValue::String("x".repeat(300)), JsonValue::Number(-70000.0), JsonValue::Number(1e20)]);
+    assert_eq!(msgpack::decode(&msgpack::encode(&long)), Some(long));
+
+    assert!(matches!(AmlData::from_msgpack(&bytes[..bytes.len() - 1]), Err(AmlError::InvalidMsgpack)));
+    assert!(matches!(AmlData::from_msgpack(&[0x91, 0xc0]), Err(AmlError::InvalidMsgpack)));
+    assert_eq!(msgpack::decode(&[0xdd, 0xff, 0xff, 0xff, 0xff]), None);
+}
+
+#[cfg(feature = "protobuf")]
+#[test]
+fn protobuf_round_trip() {
+    use aml_lib::{protobuf, AmlError, ReceiveContext};
+
+    let https = "v=1&emergency_number=112&time=1604912121123&location_latitude=48.85&location_longitude=2.35&location_altitude=-12\
+                 &location_semi_major_axis=30&location_semi_minor_axis=10&cell_network_mcc=208&cell_network_mnc=01&test=true\
+                 &adr_rollover=true&adr_occupants=2&vendor_field=1";
+    let mut aml = AmlData::from_https(https).unwrap().with_context(ReceiveContext {
+        received_at: Some(timestamp::from_unix(1604912122).unwrap()),
+        remote_ip: "10.0.0.1".parse().ok(),
+        ..Default::default()
+    });
+    aml.home_mnc = Some(-1);
+    aml.extras.push(("vendor".to_string(), aml_lib::json::JsonValue::parse(r#"{"cell":[208,1]}"#).unwrap()));
+
+    let bytes = aml.to_protobuf();
+    assert_eq!(AmlData::from_protobuf(&bytes).unwrap(), aml);
+    assert!(protobuf::PROTO.contains("message AmlData {"));
+
+    // Unknown fields of a newer writer are skipped.
+    let mut newer = bytes.clone();
+    newer.extend_from_slice(&[0xc8, 0x3e, 0x01, 0xd5, 0x3e, 0x00, 0x00, 0x00, 0x00]);
+    assert_eq!(AmlData::from_protobuf(&newer).unwrap(), aml);
+
+    assert!(matches!(AmlData::from_protobuf(&bytes[..bytes.len() - 1]), Err(AmlError::InvalidProtobuf)));
+    assert!(matches!(AmlData::from_protobuf(&[0x29, 0x01]), Err(AmlError::InvalidProtobuf)));
+    assert!(matches!(AmlData::from_protobuf(&[0x0d, 0, 0, 0, 0]), Err(AmlError::InvalidProtobuf)));
+}
+
+#[cfg(feature = "prost")]
+#[test]
+fn prost_conversions() {
+    use aml_lib::protobuf::v1;
+    use aml_lib::{AmlError, ReceiveContext};
+    use prost::Message;
+    use std::convert::TryFrom;
+
+    let https = "v=1&emergency_number=112&time=1604912121123&location_latitude=48.85&location_longitude=2.35\
+                 &location_semi_major_axis=30&location_semi_minor_axis=10&test=true&adr_rollover=true";
+    let mut aml = AmlData::from_https(https).unwrap().with_context(ReceiveContext {
+        remote_ip: "10.0.0.1".parse().ok(),
+        ..Default::default()
+    });
+    aml.extras.push(("vendor".to_string(), aml_lib::json::JsonValue::parse(r#"{"cell":[208,1]}"#).unwrap()));
+
+    // The prost types and the codec of the crate write the same bytes
+    let message = v1::AmlData::from(&aml);
+    assert_eq!(message.encode_to_vec(), aml.to_protobuf());
+    assert_eq!(v1::AmlData::decode(aml.to_protobuf().as_slice()).unwrap(), message);
+    assert_eq!(message.beginning_of_call.unwrap().nanos, 123_000_000);
+    assert_eq!(AmlData::try_from(message.clone()).unwrap(), aml);
+
+    let mut invalid = message;
+    invalid.extras[0].json = "{".to_string();
+    assert!(matches!(AmlData::try_from(invalid), Err(AmlError::InvalidProtobuf)));
+}
+
+#[cfg(feature = "store")]
+#[test]
+fn store_queries() {
+    use aml_lib::store::Store;
+
+    let path = std::env::temp_dir().join(format!("aml-store-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let store = Store::open(&path).unwrap();
+    assert!(store.is_empty().unwrap());
+    let messages = [
+        r#"A"ML=2;en=112;et=1593187189;lt=11;lo=48.82639,-2.36619,40;ei=358239059042542"#,
+        r#"A"ML=2;en=112;et=1593187189;lt=41;lo=48.82641,-2.36620,12;ei=358239059042542"#,
+        r#"A"ML=2;en=112;et=1593190000;lt=10;lo=45.76400,4.83566,8;ei=358239059042542"#,
+        r#"A"ML=2;en=112;et=1593187300;lt=10;lo=45.76401,4.83560,20;ei=490154203237518"#,
+    ];
+    let ids: Vec<_> = messages.iter().map(|message| store.insert(&AmlData::from_text_sms(message).unwrap()).unwrap()).collect();
+    drop(store);
+
+    let store = Store::open(&path).unwrap();
+    assert_eq!(store.len().unwrap(), 4);
+    assert_eq!(store.get(ids[0]).unwrap(), Some(AmlData::from_text_sms(messages[0]).unwrap()));
+    assert_eq!(store.device("358239059042542").unwrap().len(), 3);
+    assert_eq!(store.latest_location("358239059042542").unwrap().unwrap().latitude, Some(45.764));
+    assert_eq!(store.latest_location("000000000000000").unwrap(), None);
+
+    let latest: Vec<Option<f64>> = store.latest_locations().unwrap().iter().map(|aml| aml.accuracy).collect();
+    assert_eq!(latest, [Some(12.0), Some(8.0), Some(20.0)]);
+
+    let range = store.between(timestamp::from_unix(1593187200).unwrap(), timestamp::from_unix(1593187310).unwrap()).unwrap();
+    assert_eq!(range.iter().map(|aml| aml.accuracy).collect::<Vec<_>>(), [Some(40.0), Some(12.0)]);
+    let cell = store.get(ids[3]).unwrap().unwrap().geohash_with_precision(5).unwrap();
+    assert_eq!(store.near(&cell).unwrap().len(), 2);
+
+    store.connection().execute("UPDATE aml_messages SET document = 'not json' WHERE id = ?1", [ids[0]]).unwrap();
+    assert!(store.get(ids[0]).is_err());
+    drop(store);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn openapi_document() {
+    use aml_lib::json::JsonValue;
+
+    let document = aml_lib::web::openapi("/v1/aml");
+    assert_eq!(JsonValue::parse(&document.to_string()), Some(document.clone()));
+
+    let post = document.get("paths").and_then(|paths| paths.get("/v1/aml")).and_then(|path| path.get("post")).unwrap();
+    let responses = post.get("responses").unwrap();
+    assert!(["200", "400", "401"].iter().all(|status| responses.get(status).is_some()));
+
+    let schema = document.get("components").and_then(|c| c.get("schemas")).and_then(|s| s.get("AmlMessage")).unwrap();
+    let property = |key: &str| schema.get("properties").and_then(|properties| properties.get(key)).unwrap();
+    assert_eq!(property("location_latitude").get("maximum"), Some(&JsonValue::Number(90.0)));
+    assert_eq!(property("device_imei").get("pattern").and_then(JsonValue::as_str), Some("^[0-9]{14,16}$"));
+    assert!(property("hmac").get("description").and_then(JsonValue::as_str).unwrap().contains("last field"));
+    match schema.get("required") {
+        Some(JsonValue::Array(required)) => assert!(required.contains(&JsonValue::String("location_source".to_string()))),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[cfg(feature = "server")]
+#[test]
+fn receiver_groups_sessions() {
+    use aml_lib::server::{AmlReceiver, ReceiverConfig};
+    use aml_lib::web::Strictness;
+    use aml_lib::Transition;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::sync::mpsc;
+
+    let config = ReceiverConfig {
+        addr: "127.0.0.1:0".to_string(),
+        strictness: Strictness::Lenient,
+        ..Default::default()
+    };
+    let receiver = AmlReceiver::bind(config).unwrap();
+    let addr = receiver.local_addr().unwrap();
+    let sessions = receiver.sessions();
+    let (sender, received) = mpsc::channel();
+    std::thread::spawn(move || {
+        receiver.serve(move |aml, session, transitions| {
+            sender.send((aml.accuracy, session.history.len(), transitions.to_vec())).unwrap()
+        })
+    });
+
+    let post = |body: &str| {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "POST /aml HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"));
+    };
+
+    let first = "v=1&time=1604912100000&location_latitude=48.85&location_longitude=2.35&location_time=1604912110000&location_accuracy=50&device_imei=358239059042542";
+    post(first);
+    assert_eq!(received.recv().unwrap(), (Some(50.0), 1, vec![Transition::Started]));
+
+    post(&first.replace("location_accuracy=50", "location_accuracy=10").replace("1604912110000", "1604912120000").replace("48.85", "48.86"));
+    let (accuracy, history, transitions) = received.recv().unwrap();
+    assert_eq!((accuracy, history), (Some(10.0), 2));
+    assert!(matches!(transitions[0], Transition::LocationMoved { .. }));
+
+    post(first);
+    assert_eq!(received.recv().unwrap().2, vec![Transition::Duplicate]);
+    assert_eq!(sessions.lock().unwrap().sessions().len(), 1);
+}
+
+#[cfg(feature = "testkit")]
+#[test]
+fn testkit_vectors() {
+    use aml_lib::testkit::{self, Vector};
+
+    let vectors = testkit::vectors();
+    assert!(vectors.iter().any(|v| v.transport == Some("https")) && vectors.iter().any(|v| v.expected.error.is_some()));
+    let mut names: Vec<&str> = vectors.iter().map(|v| v.name).collect();
+    names.sort_unstable();
+    names.dedup();
+    assert_eq!(names.len(), vectors.len());
+
+    assert_eq!(testkit::run(Vector::parse), Vec::<(&str, Vec<String>)>::new());
+
+    // A pipeline dropping the positioning time of the HTTPS messages is caught
+    let failures = testkit::run(|vector| {
+        vector.parse().map(|mut aml| {
+            if aml.transport == "https" {
+                aml.time_of_positioning = None;
+            }
+            aml
+        })
+    });
+    assert_eq!(failures.iter().map(|(name, _)| *name).collect::<Vec<_>>(), ["https-v1-hmac", "https-v3-full"]);
+    assert_eq!(failures[0].1, ["time_of_positioning: expected Some(\"1604912121\"), got None"]);
+}
+
+#[test]
+fn limits_enforced() {
+    use aml_lib::{AmlError, Limit, Limits};
+
+    let limits = Limits::default();
+    let sms = r#"A"ML=2;en=112;et=1593187189;lt=11;lo=48.82639,-2.36619,40;ei=358239059042542"#;
+    assert_eq!(AmlData::from_text_sms_limited(sms, &limits).unwrap().imei, Some("358239059042542".to_string()));
+
+    let long_value = format!("{};dm={}", sms, "x".repeat(300));
+    assert!(matches!(AmlData::from_text_sms_limited(&long_value, &limits), Err(AmlError::LimitExceeded(Limit::ValueLength))));
+
+    let many = format!("{}{}", sms, ";x=1".repeat(64));
+    assert!(matches!(AmlData::from_text_sms_limited(&many, &limits), Err(AmlError::LimitExceeded(Limit::Properties))));
+
+    let huge = "v=1&".repeat(2000);
+    let error = AmlData::from_https_limited(&huge, &limits).unwrap_err();
+    assert_eq!(error.name(), "limit_exceeded");
+    assert!(matches!(error, AmlError::LimitExceeded(Limit::PayloadLength)));
+}
+
+#[cfg(feature = "heapless")]
+#[test]
+fn heapless_memory_audit() {
+    use aml_lib::heapless::{FixedSms, SCRATCH_CAPACITY};
+
+    // The second `ei` replaces the first one: the peak is reached before
+    let (sms, audit) = FixedSms::from_text_audited(r#"A"ML=2;en=112;ei=358239059042542;ei=35823905"#).unwrap();
+    assert_eq!(sms.scratch_used(), 3 + 8);
+    assert_eq!((audit.peak_scratch, audit.properties), (3 + 15, 4));
+
+    let hostile = format!(r#"A"ML=2;{}"#, "lg=fr-FR,en-GB;en=112112112112112;".repeat(10_000));
+    let (_, audit) = FixedSms::from_text_audited(&hostile).unwrap();
+    assert_eq!(audit.properties, 20_001);
+    assert!(audit.peak_scratch <= SCRATCH_CAPACITY);
+}
+
+#[test]
+fn best_effort_versions() {
+    let text = r#"A"ML=3;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;ls=G;ei=358239059042542;zz=future"#;
+    assert!(AmlData::from_text_sms(text).is_err());
+
+    let aml = AmlData::from_text_sms_best_effort(text).unwrap();
+    assert!(aml.is_partial());
+    assert_eq!(aml.version.as_deref(), Some("3"));
+    assert_eq!((aml.latitude, aml.accuracy), (Some(48.82639), Some(20.0)));
+    assert_eq!(aml.time_of_positioning, Some(timestamp::from_unix(1593187219).unwrap()));
+
+    let sms = SmsData::from_text_best_effort(text).unwrap();
+    assert!(!sms.is_valid());
+    assert_eq!(sms.validation.failures, vec![ValidationFailure::PartialVersion("3".to_string())]);
+
+    // Implemented versions are decoded as usual
+    let v2 = r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30"#;
+    assert_eq!(SmsData::from_text_best_effort(v2).unwrap(), SmsData::from_text(v2).unwrap());
+    assert!(!AmlData::from_text_sms(v2).unwrap().is_partial());
+    assert!(SmsData::from_text_best_effort("en=112;lo=48.82639,-2.36619,20").is_err());
+}
+
+#[test]
+fn tolerant_headers() {
+    use aml_lib::{HeaderDeviation, HeaderTolerance};
+
+    let v1 = r#"AML=1;lt=48.82639;lg=-2.36619;rd=52;top=20191112112928;lc=68;pm=G;ml=71"#;
+    let sms = SmsData::from_text_with_header(v1, HeaderTolerance::ALL).unwrap();
+    assert_eq!(sms.validation.header_deviations, vec![HeaderDeviation::Quoting]);
+    // The length is the one of the message as received
+    assert!(sms.is_valid(), "{:?}", sms.validation);
+
+    let spaced = r#"A"ML = 2;en=112;lo=48.82639,-2.36619,20"#;
+    let sms = SmsData::from_text_with_header(spaced, HeaderTolerance::ALL).unwrap();
+    assert!(sms.validation.header_first);
+    assert_eq!(sms.validation.header_deviations, vec![HeaderDeviation::Spaces]);
+    assert!(!SmsData::from_text(spaced).unwrap().validation.header_first);
+
+    let lowercase = r#"a"ml=2;en=112;lo=48.82639,-2.36619,20"#;
+    let only_case = HeaderTolerance { case: true, ..HeaderTolerance::NONE };
+    assert_eq!(AmlData::from_text_sms_with_header(lowercase, only_case).unwrap().version.as_deref(), Some("2"));
+    assert!(SmsData::from_text_with_header("a'ml=2;en=112", only_case).is_err());
+    assert!(SmsData::from_text_with_header(lowercase, HeaderTolerance::NONE).is_err());
+
+    // A strict header is left untouched
+    let strict = r#"A"ML=2;en=112;lo=48.82639,-2.36619,20"#;
+    assert_eq!(SmsData::from_text_with_header(strict, HeaderTolerance::ALL).unwrap(), SmsData::from_text(strict).unwrap());
+}
+
+#[test]
+fn minor_versions() {
+    use aml_lib::{AmlVersion, VersionPolicy};
+
+    assert_eq!(AmlVersion::parse("2.0.1"), Some(AmlVersion { major: 2, minor: Some(0), patch: Some(1) }));
+    assert_eq!(AmlVersion::parse(" 1 "), Some(AmlVersion { major: 1, minor: None, patch: None }));
+    assert!(AmlVersion::parse("").is_none() && AmlVersion::parse("1.").is_none() && AmlVersion::parse("1.2.3.4").is_none());
+
+    let v2 = r#"A"ML=2.0;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30"#;
+    assert!(AmlData::from_text_sms_with_version(v2, VersionPolicy::Reject).is_err());
+    let aml = AmlData::from_text_sms_with_version(v2, VersionPolicy::Exact).unwrap();
+    assert_eq!((aml.version.as_deref(), aml.accuracy), (Some("2.0"), Some(20.0)));
+    assert_eq!(aml.version_components().map(|v| v.major), Some(2));
+    assert!(!aml.is_partial());
+
+    let v2_3 = r#"A"ML=2.3;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30"#;
+    assert!(SmsData::from_text_with_version(v2_3, VersionPolicy::Exact).is_err());
+    let sms = SmsData::from_text_with_version(v2_3, VersionPolicy::MajorCompatible).unwrap();
+    assert!(sms.is_valid() && !sms.is_partial());
+    assert!(SmsData::from_text_with_version(r#"A"ML=3.0;en=112"#, VersionPolicy::MajorCompatible).is_err());
+
+    // Best effort reads the minor versions with their major parser
+    let v1_1 = r#"A"ML=1.1;lt=48.82639;lg=-2.36619;rd=52"#;
+    let sms = SmsData::from_text_best_effort(v1_1).unwrap();
+    assert_eq!((sms.latitude, sms.accuracy, sms.is_partial()), (Some(48.82639), Some(52.0), false));
+}
+
+#[test]
+fn negative_positioning_delay() {
+    let text = r#"A"ML=2;en=+15555555555;et=1593187189;lo=-37.42175,-122.08461,2000.1;lt=-9999;lc=68;ls=G"#;
+    let sms = SmsData::from_text(text).unwrap();
+    assert_eq!((sms.beginning_of_call, sms.positioning_delay), (Some(1593187189), Some(-9999)));
+    assert_eq!(sms.time_of_positioning, None);
+    assert_eq!(AmlData::from_text_sms(text).unwrap().time_of_positioning, None);
+    // The sentinel is sent again as received
+    assert_eq!(sms.to_text(), text);
+
+    let sms = SmsData::from_text(r#"A"ML=2;et=1593187189;lo=48.82639,-2.36619,20;lt=0"#).unwrap();
+    assert_eq!(sms.time_of_positioning, Some(timestamp::from_unix(1593187189).unwrap()));
+
+    // The sentinel is not reported as dropped, a delay without beginning of call is
+    let mut dropped = Vec::new();
+    SmsData::from_text_observed(text, &mut |field: &aml_lib::DroppedField| dropped.push(field.key.to_string())).unwrap();
+    SmsData::from_text_observed(r#"A"ML=2;lo=48.82639,-2.36619,20;lt=30"#, &mut |field: &aml_lib::DroppedField| dropped.push(field.key.to_string())).unwrap();
+    assert_eq!(dropped, ["lt"]);
+
+    // No overflow past the end of times
+    let sms = SmsData::from_text(format!(r#"A"ML=2;et={};lo=48.82639,-2.36619,20;lt=30"#, i64::MAX)).unwrap();
+    assert_eq!(sms.time_of_positioning, None);
+    #[cfg(feature = "heapless")]
+    {
+        let sms = aml_lib::heapless::FixedSms::from_text(&format!(r#"A"ML=2;et={};lt=30"#, i64::MAX)).unwrap();
+        assert_eq!(sms.time_of_positioning, None);
+    }
+}
+
+#[test]
+fn sentinel_policy() {
+    use aml_lib::{Sentinel, SentinelAction, SentinelPolicy};
+
+    let https = "v=1&location_latitude=0&location_longitude=0&location_accuracy=0&location_altitude=-9999&location_speed=3.5";
+    let (aml, found) = AmlData::from_https_with_sentinels(https, &SentinelPolicy::default()).unwrap();
+    assert_eq!(aml.position(), None);
+    assert_eq!((aml.accuracy, aml.altitude, aml.speed), (None, None, Some(3.5)));
+    let kinds: Vec<_> = found.iter().map(|s| (s.field, s.sentinel)).collect();
+    assert_eq!(kinds, vec![("altitude", Sentinel::Magic(-9999.0)), ("position", Sentinel::NullIsland), ("accuracy", Sentinel::ZeroAccuracy)]);
+
+    // Same handling for SMS
+    let sms = r#"A"ML=1;lt=0;lg=0;rd=0;top=20191112112928;lc=68;pm=G"#;
+    let (aml, found) = AmlData::from_text_sms_with_sentinels(sms, &SentinelPolicy::default()).unwrap();
+    assert_eq!((aml.position(), aml.accuracy, found.len()), (None, None, 2));
+
+    let flag = SentinelPolicy {
+        action: SentinelAction::Flag,
+        null_island: false,
+        ..Default::default()
+    };
+    let (aml, found) = AmlData::from_https_with_sentinels(https, &flag).unwrap();
+    assert_eq!((aml.position(), aml.altitude), (Some((0.0, 0.0)), Some(-9999.0)));
+    assert_eq!(found.len(), 2);
+}
+
+#[test]
+fn canonical_positioning_methods() {
+    use aml_lib::PositioningMethod;
+
+    let pairs = [("G", "gps"), ("W", "wifi"), ("C", "cell"), ("F", "fused"), ("U", "unknown")];
+    for (letter, source) in pairs {
+        let sms = AmlData::from_text_sms(format!(r#"A"ML=2;en=112;et=1593187189;lo=48.82639,-2.36619,20;lt=30;ls={}"#, letter)).unwrap();
+        let https = AmlData::from_https(&format!("v=1&location_latitude=0.85732&location_longitude=-4.26325&location_source={}", source.to_uppercase())).unwrap();
+        // The raw values are kept
+        assert_eq!(sms.positioning_method.as_deref(), Some(letter));
+        assert_eq!(https.positioning_method.as_deref(), Some(source));
+        assert_eq!(sms.method().map(|m| m.as_str()), Some(source));
+        assert_eq!(https.method().map(|m| m.letter()), Some(letter));
+    }
+    assert_eq!(AmlData::default().method(), None::<PositioningMethod>);
+}
+
+#[test]
+fn session_estimated_motion() {
+    use aml_lib::Session;
+
+    let fix = |lo: &str, lt: i64| AmlData::from_text_sms(format!(r#"A"ML=2;et=1593187189;lo={};lt={};ei=358239059042542"#, lo, lt)).unwrap();
+
+    let mut session = Session::new(fix("48.80000,2.30000,20", 0));
+    assert_eq!(session.estimated_motion(), None);
+
+    // Moved by about 14 meters, within the uncertainty of the fixes
+    session.push(fix("48.80000,2.30020,20", 10));
+    assert_eq!(session.estimated_motion(), None);
+
+    // About 220 meters east of the first fix in 40 seconds
+    session.push(fix("48.80000,2.30300,20", 40));
+    let motion = session.estimated_motion().unwrap();
+    assert!((motion.bearing - 90.0).abs() < 0.1, "{:?}", motion);
+    assert!((motion.speed - motion.distance / 30.0).abs() < 1e-9, "{:?}", motion);
+    assert_eq!(motion.from, timestamp::from_unix(1593187199).unwrap());
+
+    // The handset reports no speed: the estimate is not merged into the data
+    assert_eq!(session.current.speed, None);
 }
\ No newline at end of file